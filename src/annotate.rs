@@ -0,0 +1,340 @@
+use crate::config::Config;
+use crate::output::{CheckStatusJson, StatusItemJson};
+use crate::trailer::git;
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// GitHub rejects check runs with more annotations than this in one request
+const MAX_ANNOTATIONS: usize = 50;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Where to create the check run, from `verify annotate`'s options
+pub struct AnnotateOptions {
+    /// API token (default: $GITHUB_TOKEN)
+    pub token: Option<String>,
+    /// Repository as `owner/name` (default: $GITHUB_REPOSITORY, then the origin remote)
+    pub repo: Option<String>,
+    /// Name of the check run
+    pub name: String,
+    /// API base URL (default: $GITHUB_API_URL, then api.github.com)
+    pub api_url: Option<String>,
+}
+
+/// A check run created by `verify annotate`
+pub struct Annotation {
+    pub url: String,
+    pub head_sha: String,
+    pub verified: usize,
+    pub total: usize,
+}
+
+/// Create a check run on HEAD reporting which checks its Verified trailer covers, with an
+/// annotation on the config of each check that's unverified
+pub fn annotate(
+    project_root: &Path,
+    config_path: &Path,
+    config: &Config,
+    trailer_hashes: Option<&BTreeMap<String, String>>,
+    options: &AnnotateOptions,
+) -> Result<Annotation> {
+    let token = options
+        .token
+        .clone()
+        .or_else(|| env_var("GITHUB_TOKEN"))
+        .context("No GitHub token; pass --github-token or set GITHUB_TOKEN")?;
+    let repo = match options
+        .repo
+        .clone()
+        .or_else(|| env_var("GITHUB_REPOSITORY"))
+    {
+        Some(repo) => repo,
+        None => {
+            let remote = git(project_root, &["remote", "get-url", "origin"])
+                .context("No repository; pass --repo or set GITHUB_REPOSITORY")?;
+            parse_github_repo(&remote)
+                .with_context(|| format!("Can't tell the GitHub repository from '{}'", remote))?
+        }
+    };
+    let api_url = options
+        .api_url
+        .clone()
+        .or_else(|| env_var("GITHUB_API_URL"))
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+
+    let (mut status, has_unverified) =
+        crate::runner::collect_trailer_status(project_root, config, trailer_hashes)?;
+    status.flatten();
+    let checks: Vec<CheckStatusJson> = status
+        .checks
+        .into_iter()
+        .filter_map(|item| match item {
+            StatusItemJson::Check(check) => Some(check),
+            StatusItemJson::Subproject(_) => None,
+        })
+        .collect();
+
+    // Annotations point at the config file, by its path in the repository
+    let file_name = config_path.file_name().unwrap_or("verify.yaml".as_ref());
+    let config_file = format!(
+        "{}{}",
+        git(project_root, &["rev-parse", "--show-prefix"])?,
+        file_name.to_string_lossy()
+    );
+    let content = std::fs::read_to_string(config_path).unwrap_or_default();
+    let subprojects = config.subprojects();
+    let line_of = |check: &str| {
+        // A subproject's checks are annotated at the subproject's entry
+        let item = subprojects
+            .iter()
+            .find(|subproject| check.starts_with(&format!("{}/", subproject.prefix())))
+            .map_or(check, |subproject| subproject.name.as_str());
+        crate::configerror::check_line(&content, item)
+    };
+
+    let head_sha = git(project_root, &["rev-parse", "HEAD"])?;
+    let body = check_run(
+        &options.name,
+        &head_sha,
+        &checks,
+        has_unverified,
+        &config_file,
+        line_of,
+    );
+    let response = post(
+        &format!(
+            "{}/repos/{}/check-runs",
+            api_url.trim_end_matches('/'),
+            repo
+        ),
+        &token,
+        &body,
+    )?;
+
+    Ok(Annotation {
+        url: response["html_url"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        head_sha,
+        verified: checks.iter().filter(|c| c.status == "verified").count(),
+        total: checks.len(),
+    })
+}
+
+/// Request body of a completed check run for `checks`
+fn check_run(
+    name: &str,
+    head_sha: &str,
+    checks: &[CheckStatusJson],
+    has_unverified: bool,
+    config_file: &str,
+    line_of: impl Fn(&str) -> Option<usize>,
+) -> Value {
+    let unverified: Vec<&CheckStatusJson> =
+        checks.iter().filter(|c| c.status != "verified").collect();
+    let title = if checks.is_empty() {
+        "No checks to verify".to_string()
+    } else if unverified.is_empty() {
+        format!("All {} checks verified", checks.len())
+    } else {
+        format!(
+            "{} of {} checks verified",
+            checks.len() - unverified.len(),
+            checks.len()
+        )
+    };
+    let summary: Vec<String> = checks
+        .iter()
+        .map(|check| match check.status.as_str() {
+            "verified" => format!("- `{}`: verified", check.name),
+            _ => format!("- `{}`: unverified ({})", check.name, describe(check)),
+        })
+        .collect();
+    let annotations: Vec<Value> = unverified
+        .iter()
+        .take(MAX_ANNOTATIONS)
+        .map(|check| {
+            let line = line_of(&check.name).unwrap_or(1);
+            json!({
+                "path": config_file,
+                "start_line": line,
+                "end_line": line,
+                "annotation_level": "failure",
+                "title": format!("{} is unverified", check.name),
+                "message": describe(check),
+            })
+        })
+        .collect();
+
+    json!({
+        "name": name,
+        "head_sha": head_sha,
+        "status": "completed",
+        "conclusion": if has_unverified { "failure" } else { "success" },
+        "output": {
+            "title": title,
+            "summary": summary.join("\n"),
+            "annotations": annotations,
+        },
+    })
+}
+
+/// Why a check is unverified, for people reading the pull request
+fn describe(check: &CheckStatusJson) -> String {
+    match check.reason.as_deref() {
+        Some("never_run") => "not in the commit's Verified trailer".to_string(),
+        Some("files_changed") => "its files or config changed since it was verified".to_string(),
+        Some("dependency_unverified") => match &check.stale_dependency {
+            Some(dependency) => format!("depends on '{}', which is unverified", dependency),
+            None => "a dependency is unverified".to_string(),
+        },
+        Some(reason) => reason.replace('_', " "),
+        None => "unverified".to_string(),
+    }
+}
+
+/// `owner/name` from the URL of a GitHub remote, over https or ssh
+fn parse_github_repo(url: &str) -> Option<String> {
+    let path = url.trim().trim_end_matches('/').trim_end_matches(".git");
+    let mut segments = path.rsplit(['/', ':']);
+    let name = segments.next().filter(|s| !s.is_empty())?;
+    let owner = segments
+        .next()
+        .filter(|s| !s.is_empty() && !s.contains('@'))?;
+    Some(format!("{}/{}", owner, name))
+}
+
+/// POST `body` to the GitHub API, returning the parsed response
+fn post(url: &str, token: &str, body: &Value) -> Result<Value> {
+    // The token is read from a file so it doesn't show up in curl's arguments
+    let mut auth = tempfile::NamedTempFile::new()?;
+    writeln!(auth, "Authorization: Bearer {}", token)?;
+    let mut child = Command::new("curl")
+        .args([
+            "-sS",
+            "--fail-with-body",
+            "-X",
+            "POST",
+            "-H",
+            "Accept: application/vnd.github+json",
+            "-H",
+            "X-GitHub-Api-Version: 2022-11-28",
+            "-H",
+        ])
+        .arg(format!("@{}", auth.path().display()))
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run curl")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&serde_json::to_vec(body)?)?;
+    }
+    let output = child.wait_with_output().context("Failed to run curl")?;
+    if !output.status.success() {
+        let response = String::from_utf8_lossy(&output.stdout);
+        let message = serde_json::from_str::<Value>(&response)
+            .ok()
+            .and_then(|v| v["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| String::from_utf8_lossy(&output.stderr).trim().to_string());
+        anyhow::bail!("GitHub API request failed: {}", message);
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse the GitHub API response")
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{UnverifiedReason, VerificationStatus};
+
+    fn status(name: &str, reason: Option<UnverifiedReason>) -> CheckStatusJson {
+        let status = match reason {
+            Some(reason) => VerificationStatus::Unverified { reason },
+            None => VerificationStatus::Verified,
+        };
+        CheckStatusJson::from_status(name, &status, None)
+    }
+
+    #[test]
+    fn test_check_run_annotates_unverified_checks() {
+        let checks = vec![
+            status("build", None),
+            status(
+                "lint",
+                Some(UnverifiedReason::FilesChanged {
+                    changed_files: vec![],
+                }),
+            ),
+            status(
+                "all",
+                Some(UnverifiedReason::DependencyUnverified {
+                    dependency: "lint".to_string(),
+                }),
+            ),
+        ];
+        let body = check_run(
+            "verify",
+            "abc123",
+            &checks,
+            true,
+            "app/verify.yaml",
+            |name| (name == "lint").then_some(7),
+        );
+
+        assert_eq!(body["head_sha"], "abc123");
+        assert_eq!(body["status"], "completed");
+        assert_eq!(body["conclusion"], "failure");
+        assert_eq!(body["output"]["title"], "1 of 3 checks verified");
+        let summary = body["output"]["summary"].as_str().unwrap();
+        assert!(summary.contains("- `build`: verified"), "{}", summary);
+
+        let annotations = body["output"]["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0]["path"], "app/verify.yaml");
+        assert_eq!(annotations[0]["start_line"], 7);
+        assert_eq!(annotations[1]["start_line"], 1);
+        assert!(
+            annotations[1]["message"]
+                .as_str()
+                .unwrap()
+                .contains("'lint'")
+        );
+    }
+
+    #[test]
+    fn test_check_run_all_verified() {
+        let checks = vec![status("build", None)];
+        let body = check_run("verify", "abc123", &checks, false, "verify.yaml", |_| None);
+        assert_eq!(body["conclusion"], "success");
+        assert_eq!(body["output"]["title"], "All 1 checks verified");
+        assert!(body["output"]["annotations"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_github_repo() {
+        for url in [
+            "git@github.com:octo/app.git",
+            "https://github.com/octo/app.git",
+            "https://github.com/octo/app",
+            "ssh://git@github.com/octo/app.git",
+        ] {
+            assert_eq!(
+                parse_github_repo(url).as_deref(),
+                Some("octo/app"),
+                "{}",
+                url
+            );
+        }
+        assert_eq!(parse_github_repo("app"), None);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use blake3::Hasher;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Default audit log file name, in the root project's `.verify/`
+pub const AUDIT_FILE: &str = "audit.log";
+
+/// `prev` of the first entry
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One execution of a check's command
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuditRecord {
+    pub at: DateTime<Utc>,
+    pub check: String,
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Hash of the check's files when it ran, as in verify.lock
+    pub content_hash: String,
+    pub config_hash: String,
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// A line of the audit log: a record chained to the entry before it, so changing,
+/// removing or reordering entries breaks every hash after them
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    /// Hash of the previous entry
+    pub prev: String,
+    /// Hash of this entry's sequence number, `prev` and record
+    pub hash: String,
+}
+
+/// Result of checking the log's hash chain
+#[derive(Debug, Serialize)]
+pub struct ChainReport {
+    pub path: String,
+    pub entries: usize,
+    /// Hash of the last intact entry; record it elsewhere to detect truncation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    pub intact: bool,
+    /// Line number and description of the first broken entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn entry_hash(seq: u64, prev: &str, record: &AuditRecord) -> Result<String> {
+    let mut hasher = Hasher::new();
+    hasher.update(format!("{}:{}:", seq, prev).as_bytes());
+    hasher.update(&serde_json::to_vec(record)?);
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// The audit log check executions are appended to, from the root config's `audit_log`
+pub struct AuditLog<'a> {
+    /// Root project, which subproject checks are named relative to
+    pub root: &'a Path,
+    pub path: &'a Path,
+}
+
+impl AuditLog<'_> {
+    /// Append `record` to the log, naming its check relative to the root project. The log
+    /// file is locked while appending, so concurrent verify processes extend the chain one
+    /// at a time.
+    pub fn append(&self, project_root: &Path, mut record: AuditRecord) -> Result<()> {
+        if !statedir::writes_enabled() {
+            return Ok(());
+        }
+        if let Ok(relative) = project_root.strip_prefix(self.root)
+            && !relative.as_os_str().is_empty()
+        {
+            record.check = format!("{}/{}", relative.to_string_lossy(), record.check);
+        }
+
+        if let Some(dir) = self.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(self.path)
+            .with_context(|| format!("Failed to open audit log: {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock audit log: {}", self.path.display()))?;
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .with_context(|| format!("Failed to read audit log: {}", self.path.display()))?;
+        let (seq, prev) = match content.lines().rfind(|line| !line.trim().is_empty()) {
+            Some(line) => {
+                let last: AuditEntry = serde_json::from_str(line).with_context(|| {
+                    format!("Audit log's last entry is corrupt: {}", self.path.display())
+                })?;
+                (last.seq + 1, last.hash)
+            }
+            None => (1, GENESIS.to_string()),
+        };
+
+        let hash = entry_hash(seq, &prev, &record)?;
+        let entry = AuditEntry {
+            seq,
+            record,
+            prev,
+            hash,
+        };
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("Failed to write audit log: {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Check that every entry of the log at `path` is intact and chained to the one before
+pub fn verify_chain(path: &Path) -> Result<ChainReport> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read audit log: {}", path.display()))?;
+    let mut report = ChainReport {
+        path: path.display().to_string(),
+        entries: 0,
+        head: None,
+        intact: true,
+        error: None,
+    };
+
+    let mut prev = GENESIS.to_string();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let problem = match serde_json::from_str::<AuditEntry>(line) {
+            Err(e) => Some(format!("not a valid entry ({})", e)),
+            Ok(entry) if entry.seq != report.entries as u64 + 1 => Some(format!(
+                "expected entry {}, found {}",
+                report.entries + 1,
+                entry.seq
+            )),
+            Ok(entry) if entry.prev != prev => {
+                Some("doesn't follow the previous entry".to_string())
+            }
+            Ok(entry) if entry_hash(entry.seq, &entry.prev, &entry.record)? != entry.hash => {
+                Some("was modified after it was written".to_string())
+            }
+            Ok(entry) => {
+                prev = entry.hash;
+                None
+            }
+        };
+        if let Some(problem) = problem {
+            report.intact = false;
+            report.error = Some(format!("line {}: {}", index + 1, problem));
+            break;
+        }
+        report.entries += 1;
+        report.head = Some(prev.clone());
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(check: &str, passed: bool) -> AuditRecord {
+        AuditRecord {
+            at: Utc::now(),
+            check: check.to_string(),
+            user: "Test <test@test.com>".to_string(),
+            host: Some("ci-1".to_string()),
+            content_hash: "c".repeat(64),
+            config_hash: "f".repeat(64),
+            passed,
+            exit_code: (!passed).then_some(1),
+            duration_ms: 12,
+        }
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let log = AuditLog {
+            root: dir.path(),
+            path: &path,
+        };
+        log.append(dir.path(), record("build", true)).unwrap();
+        log.append(&dir.path().join("api"), record("test", false))
+            .unwrap();
+        log.append(dir.path(), record("lint", true)).unwrap();
+
+        let report = verify_chain(&path).unwrap();
+        assert!(report.intact, "{:?}", report.error);
+        assert_eq!(report.entries, 3);
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"check\":\"api/test\""));
+
+        // Flipping a result breaks that entry
+        fs::write(
+            &path,
+            content.replacen("\"passed\":false", "\"passed\":true", 1),
+        )
+        .unwrap();
+        let report = verify_chain(&path).unwrap();
+        assert!(!report.intact);
+        assert_eq!(report.entries, 1);
+        assert_eq!(
+            report.error.as_deref(),
+            Some("line 2: was modified after it was written")
+        );
+
+        // So does removing an entry
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let report = verify_chain(&path).unwrap();
+        assert_eq!(
+            report.error.as_deref(),
+            Some("line 2: expected entry 2, found 3")
+        );
+    }
+}
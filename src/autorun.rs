@@ -0,0 +1,163 @@
+use crate::cache::{CacheState, VerificationStatus};
+use crate::config::{Config, VerificationItem};
+use crate::hashcache;
+use crate::runner;
+use crate::ui::Ui;
+use crate::watch::FileWatcher;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long each wait for changes lasts before waiting again
+const POLL: Duration = Duration::from_secs(60);
+
+/// Run `verify watch` until interrupted: run the checks (`names`, or all of them) as
+/// `verify run` would, then whenever files change and have been quiet for `debounce`,
+/// run the ones that became unverified, showing the status of every check after each
+/// round. Errors, like a config that doesn't parse mid-edit, are shown and watching goes on.
+pub fn run_watch(
+    project_root: &Path,
+    config_path: &Path,
+    names: Vec<String>,
+    debounce: Duration,
+    verbose: bool,
+) -> Result<()> {
+    let watcher = FileWatcher::new(project_root)?;
+    let root = project_root.canonicalize()?;
+    let ui = Ui::new(verbose);
+    let mut to_run = Some(names.clone());
+
+    loop {
+        if let Some(run) = to_run.take() {
+            if let Err(e) = run_round(project_root, config_path, run, verbose) {
+                ui.print_error(&format!("{:#}", e));
+            }
+            show_status(project_root, config_path, &ui);
+            hashcache::save_all();
+        }
+
+        let changed = watcher.wait_settled(POLL, debounce);
+        if changed.is_empty() {
+            continue;
+        }
+        // Files the checks wrote while running are seen here too, and only run anything
+        // again if they're in a check's cache_paths. Changes that leave nothing to run
+        // (like files no check covers) don't change what's shown.
+        let stale = match stale_names(project_root, config_path, &names) {
+            Ok(stale) if stale.is_empty() => continue,
+            Ok(stale) => stale,
+            Err(e) => {
+                ui.print_error(&format!("{:#}", e));
+                continue;
+            }
+        };
+        ui.clear_screen();
+        let paths: Vec<String> = changed
+            .iter()
+            .filter_map(|path| path.strip_prefix(&root).ok())
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+            .collect();
+        ui.print_files_changed(&paths);
+        to_run = Some(stale);
+    }
+}
+
+/// Run `names` (everything when empty) with the current config and cache
+fn run_round(
+    project_root: &Path,
+    config_path: &Path,
+    names: Vec<String>,
+    verbose: bool,
+) -> Result<()> {
+    let config = Config::load(config_path)?;
+    let mut cache = CacheState::load(project_root, &Arc::new(config.settings(project_root)))?;
+    runner::run_checks(
+        project_root,
+        &config,
+        &mut cache,
+        names,
+        runner::RunOptions {
+            verbose,
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
+
+/// Print the status of every check, then that verify is waiting for changes
+fn show_status(project_root: &Path, config_path: &Path, ui: &Ui) {
+    let shown = Config::load(config_path).and_then(|config| {
+        let cache = CacheState::load(project_root, &Arc::new(config.settings(project_root)))?;
+        println!();
+        runner::run_status(project_root, &config, &cache, Default::default())
+    });
+    if let Err(e) = shown {
+        ui.print_error(&format!("{:#}", e));
+    }
+    ui.print_watching();
+}
+
+/// The watched checks and subprojects (`names`, or every item in the config) that need
+/// to run again. Failed checks whose files haven't changed since aren't, as running them
+/// again would fail the same way; nor are untracked checks, which nothing makes stale.
+fn stale_names(project_root: &Path, config_path: &Path, names: &[String]) -> Result<Vec<String>> {
+    let config = Config::load(config_path)?;
+    let cache = CacheState::load(project_root, &Arc::new(config.settings(project_root)))?;
+    let (statuses, subprojects_stale) = runner::compute_statuses(project_root, &config, &cache)?;
+
+    let watched: Vec<String> = if names.is_empty() {
+        config
+            .verifications
+            .iter()
+            .map(|item| match item {
+                VerificationItem::Verification(v) => v.name.clone(),
+                VerificationItem::Subproject(s) => s.name.clone(),
+            })
+            .collect()
+    } else {
+        names.to_vec()
+    };
+    Ok(watched
+        .into_iter()
+        .filter(|name| {
+            matches!(
+                statuses.get(name),
+                Some(VerificationStatus::Unverified { .. })
+            ) || subprojects_stale.get(name).copied().unwrap_or(false)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_stale_names_skips_untracked_and_unwatched_checks() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("verify.yaml");
+        fs::write(
+            &config_path,
+            r#"verifications:
+  - name: lint
+    command: "true"
+    cache_paths: ["*.txt"]
+  - name: build
+    command: "true"
+    cache_paths: ["*.rs"]
+  - name: e2e
+    command: "true"
+"#,
+        )
+        .unwrap();
+
+        let stale = stale_names(dir.path(), &config_path, &[]).unwrap();
+        assert_eq!(stale, vec!["lint", "build"]);
+
+        let stale = stale_names(dir.path(), &config_path, &["build".to_string()]).unwrap();
+        assert_eq!(stale, vec!["build"]);
+    }
+}
@@ -0,0 +1,586 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::cache::CacheState;
+use crate::config::Config;
+use crate::statedir;
+
+const LOCK_FILE: &str = "verify.lock";
+
+/// State directory files carried in bundles: the run history and cache stats. The rest
+/// of the state directory (hash cache, check locks, journals, ...) only describes the
+/// machine it was written on.
+const STATE_FILES: [&str; 2] = ["history.jsonl", "stats.jsonl"];
+
+/// Directory holding a project's state files in a bundle, wherever the state directory
+/// is on the machine that wrote it
+const STATE_ENTRY_DIR: &str = ".verify";
+
+/// Directory under `STATE_ENTRY_DIR` holding a project's artifact files in a bundle
+const ARTIFACT_ENTRY_DIR: &str = "artifacts";
+
+/// State files of each project, keyed by relative directory and then file name
+pub type StateFiles = BTreeMap<String, BTreeMap<String, Vec<u8>>>;
+
+/// Artifact files of each project, keyed by relative directory and then path relative
+/// to the project
+pub type ArtifactFiles = BTreeMap<String, BTreeMap<String, ArtifactFile>>;
+
+/// A file under a check's declared `artifacts`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactFile {
+    pub content: Vec<u8>,
+    pub executable: bool,
+}
+
+/// What `read_bundle` found in a bundle, keyed by relative directory
+pub struct Bundle {
+    /// Parsed lock file of each project
+    pub caches: BTreeMap<String, CacheState>,
+    /// State files of each project (see `STATE_FILES`)
+    pub state: StateFiles,
+    /// Artifact files of each project, when the bundle was exported with them
+    pub artifacts: ArtifactFiles,
+}
+
+/// Normalize a project-relative directory into the key used for bundle entries.
+/// Strips `.` components so `./packages/web` and `packages/web` map to the same entry.
+pub fn bundle_key(relative_dir: &Path) -> String {
+    let normalized: PathBuf = relative_dir
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+    normalized.to_string_lossy().replace('\\', "/")
+}
+
+/// Collect the lock file of the project and every subproject, keyed by relative directory.
+/// Projects without a verify.lock are skipped.
+pub fn collect_lock_files(
+    project_root: &Path,
+    config: &Config,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut locks = BTreeMap::new();
+    for_each_project(
+        project_root,
+        Path::new(""),
+        config,
+        &mut |relative_dir, _| {
+            let lock_path = project_root.join(relative_dir).join(LOCK_FILE);
+            if lock_path.exists() {
+                let content = fs::read(&lock_path).with_context(|| {
+                    format!("Failed to read lock file: {}", lock_path.display())
+                })?;
+                locks.insert(bundle_key(relative_dir), content);
+            }
+            Ok(())
+        },
+    )?;
+    Ok(locks)
+}
+
+/// Collect the state files (run history and cache stats) of the project and every
+/// subproject. Files a project doesn't have are skipped.
+pub fn collect_state_files(project_root: &Path, config: &Config) -> Result<StateFiles> {
+    let mut state = StateFiles::new();
+    for_each_project(
+        project_root,
+        Path::new(""),
+        config,
+        &mut |relative_dir, _| {
+            let state_dir = statedir::state_dir(&project_root.join(relative_dir));
+            for name in STATE_FILES {
+                let path = state_dir.join(name);
+                if path.exists() {
+                    let content = fs::read(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    state
+                        .entry(bundle_key(relative_dir))
+                        .or_default()
+                        .insert(name.to_string(), content);
+                }
+            }
+            Ok(())
+        },
+    )?;
+    Ok(state)
+}
+
+/// Collect the files under every check's declared `artifacts` in the project and every
+/// subproject, recursing into directories. Artifacts that don't exist, or are outside
+/// the project, are skipped.
+pub fn collect_artifact_files(project_root: &Path, config: &Config) -> Result<ArtifactFiles> {
+    let mut artifacts = ArtifactFiles::new();
+    for_each_project(
+        project_root,
+        Path::new(""),
+        config,
+        &mut |relative_dir, config| {
+            let project_dir = project_root.join(relative_dir);
+            let files = artifacts.entry(bundle_key(relative_dir)).or_default();
+            for check in config.verifications_only() {
+                for artifact in &check.artifacts {
+                    let outside = Path::new(artifact)
+                        .components()
+                        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+                    if outside {
+                        continue;
+                    }
+                    collect_artifact(&project_dir, &bundle_key(Path::new(artifact)), files)?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    artifacts.retain(|_, files| !files.is_empty());
+    Ok(artifacts)
+}
+
+/// Add the file at `relative` (or every file under it, for a directory) to `files`
+fn collect_artifact(
+    project_dir: &Path,
+    relative: &str,
+    files: &mut BTreeMap<String, ArtifactFile>,
+) -> Result<()> {
+    let path = project_dir.join(relative);
+    let Ok(metadata) = fs::metadata(&path) else {
+        return Ok(());
+    };
+
+    if metadata.is_dir() {
+        for entry in
+            fs::read_dir(&path).with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            let entry = entry.with_context(|| format!("Failed to read {}", path.display()))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            collect_artifact(project_dir, &format!("{}/{}", relative, name), files)?;
+        }
+        return Ok(());
+    }
+
+    let content = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    #[cfg(unix)]
+    let executable = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    };
+    #[cfg(not(unix))]
+    let executable = false;
+    files.insert(
+        relative.to_string(),
+        ArtifactFile {
+            content,
+            executable,
+        },
+    );
+    Ok(())
+}
+
+/// Write the bundled artifact files covered by `artifacts` (a check's declared artifact
+/// paths) into the project. Returns how many files were written.
+pub fn restore_artifacts(
+    project_root: &Path,
+    files: &BTreeMap<String, ArtifactFile>,
+    artifacts: &[String],
+) -> Result<usize> {
+    let declared: Vec<String> = artifacts
+        .iter()
+        .map(|artifact| bundle_key(Path::new(artifact)))
+        .collect();
+    let mut restored = 0;
+
+    for (relative, file) in files {
+        let covered = declared.iter().any(|artifact| {
+            relative == artifact || relative.starts_with(&format!("{}/", artifact))
+        });
+        if !covered {
+            continue;
+        }
+
+        let path = project_root.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, &file.content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        #[cfg(unix)]
+        if file.executable {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+        }
+        restored += 1;
+    }
+
+    Ok(restored)
+}
+
+/// Call `f` with the relative directory and config of the project and of every subproject
+fn for_each_project(
+    project_root: &Path,
+    relative_dir: &Path,
+    config: &Config,
+    f: &mut dyn FnMut(&Path, &Config) -> Result<()>,
+) -> Result<()> {
+    f(relative_dir, config)?;
+
+    for subproject in config.subprojects() {
+        let sub_relative = relative_dir.join(&subproject.path);
+        let subproject_dir = project_root.join(&sub_relative);
+        let sub_config_path = subproject_dir.join("verify.yaml");
+        let sub_config = Config::load_with_base(&sub_config_path, &subproject_dir)?;
+        for_each_project(project_root, &sub_relative, &sub_config, f)?;
+    }
+
+    Ok(())
+}
+
+/// Write lock files, state files and artifact files into a zstd-compressed tar bundle.
+/// Entries are stored at `<relative_dir>/verify.lock`, `<relative_dir>/.verify/<file>`
+/// and `<relative_dir>/.verify/artifacts/<path>`.
+pub fn write_bundle(
+    path: &Path,
+    locks: &BTreeMap<String, Vec<u8>>,
+    state: &StateFiles,
+    artifacts: &ArtifactFiles,
+) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create bundle: {}", path.display()))?;
+    let encoder = zstd::Encoder::new(file, 0)
+        .context("Failed to initialize zstd encoder")?
+        .auto_finish();
+    let mut builder = tar::Builder::new(encoder);
+
+    let lock_entries = locks
+        .iter()
+        .map(|(key, content)| (Path::new(key).join(LOCK_FILE), content, false));
+    let state_entries = state.iter().flat_map(|(key, files)| {
+        files.iter().map(move |(name, content)| {
+            (
+                Path::new(key).join(STATE_ENTRY_DIR).join(name),
+                content,
+                false,
+            )
+        })
+    });
+    let artifact_entries = artifacts.iter().flat_map(|(key, files)| {
+        files.iter().map(move |(relative, file)| {
+            let entry_path = Path::new(key)
+                .join(STATE_ENTRY_DIR)
+                .join(ARTIFACT_ENTRY_DIR)
+                .join(relative);
+            (entry_path, &file.content, file.executable)
+        })
+    });
+    for (entry_path, content, executable) in
+        lock_entries.chain(state_entries).chain(artifact_entries)
+    {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(if executable { 0o755 } else { 0o644 });
+        header.set_cksum();
+        builder
+            .append_data(&mut header, &entry_path, content.as_slice())
+            .with_context(|| format!("Failed to add {} to bundle", entry_path.display()))?;
+    }
+
+    builder
+        .into_inner()
+        .with_context(|| format!("Failed to write bundle: {}", path.display()))?;
+    Ok(())
+}
+
+/// Read a bundle written by `write_bundle`.
+/// Entries that aren't lock files, state files or artifact files, artifact files whose
+/// path leaves the project, and lock files that can't be parsed, are ignored.
+pub fn read_bundle(path: &Path) -> Result<Bundle> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open bundle: {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to initialize zstd decoder")?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut caches = BTreeMap::new();
+    let mut state = StateFiles::new();
+    let mut artifacts = ArtifactFiles::new();
+
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read bundle: {}", path.display()))?;
+    for entry in entries {
+        let mut entry =
+            entry.with_context(|| format!("Failed to read bundle: {}", path.display()))?;
+        let entry_path = entry.path()?.into_owned();
+        let parent = entry_path.parent().unwrap_or(Path::new(""));
+        let file_name = entry_path.file_name().and_then(|n| n.to_str());
+
+        if let Some((key, relative)) = split_artifact_entry(&entry_path) {
+            let Some(relative) = relative else {
+                continue;
+            };
+            let executable = entry.header().mode().is_ok_and(|mode| mode & 0o111 != 0);
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .with_context(|| format!("Failed to read {} from bundle", entry_path.display()))?;
+            artifacts.entry(key).or_default().insert(
+                relative,
+                ArtifactFile {
+                    content,
+                    executable,
+                },
+            );
+            continue;
+        }
+        if let Some(name) = file_name.filter(|name| STATE_FILES.contains(name))
+            && parent.file_name().and_then(|n| n.to_str()) == Some(STATE_ENTRY_DIR)
+        {
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .with_context(|| format!("Failed to read {} from bundle", entry_path.display()))?;
+            let key = bundle_key(parent.parent().unwrap_or(Path::new("")));
+            state
+                .entry(key)
+                .or_default()
+                .insert(name.to_string(), content);
+            continue;
+        }
+        if file_name != Some(LOCK_FILE) {
+            continue;
+        }
+
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .with_context(|| format!("Failed to read {} from bundle", entry_path.display()))?;
+
+        if let Ok(cache) = serde_json::from_str::<CacheState>(&content) {
+            caches.insert(bundle_key(parent), cache);
+        }
+    }
+
+    Ok(Bundle {
+        caches,
+        state,
+        artifacts,
+    })
+}
+
+/// Split an artifact entry path (`<relative_dir>/.verify/artifacts/<path>`) into its
+/// bundle key and path. The path is `None` when it isn't a plain relative path, so
+/// the entry can't write outside the project.
+fn split_artifact_entry(entry_path: &Path) -> Option<(String, Option<String>)> {
+    let components: Vec<Component> = entry_path.components().collect();
+    let position = components.windows(2).position(|pair| {
+        pair[0].as_os_str() == STATE_ENTRY_DIR && pair[1].as_os_str() == ARTIFACT_ENTRY_DIR
+    })?;
+
+    let key = bundle_key(&components[..position].iter().collect::<PathBuf>());
+    let rest = &components[position + 2..];
+    if rest.is_empty() || !rest.iter().all(|c| matches!(c, Component::Normal(_))) {
+        return Some((key, None));
+    }
+    let relative: PathBuf = rest.iter().collect();
+    Some((key, Some(relative.to_string_lossy().replace('\\', "/"))))
+}
+
+/// The timestamp every state file record starts with
+#[derive(Deserialize)]
+struct Timestamped {
+    timestamp: DateTime<Utc>,
+}
+
+/// Merge bundled state files into a project's state directory. Records the project
+/// doesn't have yet are added, and every record is kept in time order, so importing
+/// the same bundle twice changes nothing. Lines that can't be parsed are dropped.
+pub fn import_state(project_root: &Path, files: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+    if !statedir::writes_enabled() {
+        return Ok(());
+    }
+
+    for (name, content) in files {
+        let path = statedir::state_dir(project_root).join(name);
+        let existing = match fs::read_to_string(&path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        let bundled = String::from_utf8_lossy(content);
+
+        let known: HashSet<&str> = existing.lines().collect();
+        if bundled.lines().all(|line| known.contains(line)) {
+            continue;
+        }
+
+        let mut seen = HashSet::new();
+        let mut records: Vec<(DateTime<Utc>, &str)> = existing
+            .lines()
+            .chain(bundled.lines())
+            .filter(|line| seen.insert(*line))
+            .filter_map(|line| {
+                let record: Timestamped = serde_json::from_str(line).ok()?;
+                Some((record.timestamp, line))
+            })
+            .collect();
+        // Stable, so records with the same timestamp keep their order
+        records.sort_by_key(|(timestamp, _)| *timestamp);
+
+        let merged: String = records
+            .iter()
+            .map(|(_, line)| format!("{}\n", line))
+            .collect();
+        let dir = statedir::create(project_root)?;
+        let temp_path = dir.join(format!("{}.tmp", name));
+        fs::write(&temp_path, merged)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bundle_key_strips_current_dir() {
+        assert_eq!(bundle_key(Path::new("./packages/web")), "packages/web");
+        assert_eq!(bundle_key(Path::new("packages/web")), "packages/web");
+        assert_eq!(bundle_key(Path::new("")), "");
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = tempdir().unwrap();
+        let bundle_path = dir.path().join("bundle.tar.zst");
+
+        let mut root = CacheState::new();
+        root.update(
+            "build",
+            true,
+            "config".to_string(),
+            Some("content".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        let sub = CacheState::new();
+
+        let mut locks = BTreeMap::new();
+        locks.insert(String::new(), serde_json::to_vec(&root).unwrap());
+        locks.insert(
+            "packages/web".to_string(),
+            serde_json::to_vec(&sub).unwrap(),
+        );
+
+        let mut state = StateFiles::new();
+        state.entry("packages/web".to_string()).or_default().insert(
+            "history.jsonl".to_string(),
+            b"{\"timestamp\":\"2026-01-01T00:00:00Z\"}\n".to_vec(),
+        );
+
+        write_bundle(&bundle_path, &locks, &state, &ArtifactFiles::new()).unwrap();
+        let bundle = read_bundle(&bundle_path).unwrap();
+        let caches = bundle.caches;
+
+        assert_eq!(caches.len(), 2);
+        let build = caches[""].get("build").unwrap();
+        assert_eq!(build.content_hash.as_deref(), Some("content"));
+        assert!(caches["packages/web"].checks.is_empty());
+        assert_eq!(bundle.state, state);
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_with_artifacts() {
+        let dir = tempdir().unwrap();
+        let bundle_path = dir.path().join("bundle.tar.zst");
+        let file = |content: &str, executable| ArtifactFile {
+            content: content.as_bytes().to_vec(),
+            executable,
+        };
+
+        let mut artifacts = ArtifactFiles::new();
+        let root = artifacts.entry(String::new()).or_default();
+        root.insert("dist/app.js".to_string(), file("app", false));
+        root.insert("dist/bin/tool".to_string(), file("tool", true));
+        root.insert("distant.txt".to_string(), file("other", false));
+        artifacts
+            .entry("packages/web".to_string())
+            .or_default()
+            .insert("out.txt".to_string(), file("web", false));
+
+        write_bundle(
+            &bundle_path,
+            &BTreeMap::new(),
+            &StateFiles::new(),
+            &artifacts,
+        )
+        .unwrap();
+        let bundle = read_bundle(&bundle_path).unwrap();
+        assert_eq!(bundle.artifacts, artifacts);
+        assert!(bundle.state.is_empty());
+
+        // Only files under the declared paths are written
+        let project = dir.path().join("project");
+        let restored =
+            restore_artifacts(&project, &bundle.artifacts[""], &["./dist/".to_string()]).unwrap();
+        assert_eq!(restored, 2);
+        assert_eq!(
+            fs::read_to_string(project.join("dist/app.js")).unwrap(),
+            "app"
+        );
+        assert!(!project.join("distant.txt").exists());
+    }
+
+    #[test]
+    fn test_split_artifact_entry_rejects_paths_leaving_project() {
+        assert_eq!(
+            split_artifact_entry(Path::new("packages/web/.verify/artifacts/dist/app.js")),
+            Some(("packages/web".to_string(), Some("dist/app.js".to_string())))
+        );
+        assert_eq!(
+            split_artifact_entry(Path::new(".verify/artifacts/../../etc/passwd")),
+            Some((String::new(), None))
+        );
+        assert_eq!(
+            split_artifact_entry(Path::new(".verify/history.jsonl")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_import_state_merges_records_in_time_order() {
+        let dir = tempdir().unwrap();
+        let state_dir = dir.path().join(".verify");
+        fs::create_dir_all(&state_dir).unwrap();
+        let early = r#"{"timestamp":"2026-01-01T00:00:00Z","total":1}"#;
+        let middle = r#"{"timestamp":"2026-01-02T00:00:00Z","total":2}"#;
+        let late = r#"{"timestamp":"2026-01-03T00:00:00Z","total":3}"#;
+        fs::write(
+            state_dir.join("history.jsonl"),
+            format!("{early}\n{late}\n"),
+        )
+        .unwrap();
+
+        let mut files = BTreeMap::new();
+        files.insert(
+            "history.jsonl".to_string(),
+            format!("{early}\n{middle}\n").into_bytes(),
+        );
+        import_state(dir.path(), &files).unwrap();
+        import_state(dir.path(), &files).unwrap();
+
+        let merged = fs::read_to_string(state_dir.join("history.jsonl")).unwrap();
+        assert_eq!(merged, format!("{early}\n{middle}\n{late}\n"));
+    }
+}
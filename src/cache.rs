@@ -1,12 +1,20 @@
+use crate::checklock::FileLock;
+use crate::config::Settings;
+use crate::locksig::Signing;
 use crate::metadata::MetadataValue;
+use crate::migrations;
+use crate::statedir;
+use crate::update::CURRENT_VERSION;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File};
-use std::io::BufWriter;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-const CACHE_VERSION: u32 = 4;
+const CACHE_VERSION: u32 = migrations::LOCK_VERSION;
 const LOCK_FILE: &str = "verify.lock";
 
 /// Root cache structure stored in verify.lock
@@ -17,6 +25,25 @@ pub struct CacheState {
 
     /// Cache entry for each verification check
     pub checks: BTreeMap<String, CheckCache>,
+
+    /// Keyed hash of `version` and `checks` made with the `sign_lock` secret, so a
+    /// hand-edited lock file can be told apart from one written by verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+
+    /// Checks changed or removed since loading. Only these are written back on save,
+    /// so concurrent verify processes don't clobber each other's results.
+    #[serde(skip)]
+    touched: HashSet<String>,
+
+    /// Who runs in this process are attributed to (`record_user`), stamped on checks that pass
+    #[serde(skip)]
+    attribution: Option<Attribution>,
+
+    /// The root config's settings, which decide how the lock file is signed and whether
+    /// entries record the verify version
+    #[serde(skip)]
+    settings: Arc<Settings>,
 }
 
 /// Cache state for a single verification check
@@ -27,6 +54,11 @@ pub struct CheckCache {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config_hash: Option<String>,
 
+    /// Hashes of the parts of the configuration `config_hash` covers
+    /// (`Verification::config_components`), to tell which part changed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub config_components: BTreeMap<String, String>,
+
     /// Hash of all files matching cache_paths at time of last successful run
     /// None means the check needs to run (never passed or last run failed)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -40,6 +72,100 @@ pub struct CheckCache {
     /// Extracted metadata values from last successful run
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub metadata: BTreeMap<String, MetadataValue>,
+
+    /// How long the last run took, used to estimate remaining time in run progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+
+    /// Files that failed in the last per_file run, for `per_file_order: failed-first`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_files: Vec<String>,
+
+    /// Set when the check was marked verified with `verify mark-verified` instead of run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marked_verified: Option<MarkedVerified>,
+
+    /// Who ran the check when it last passed, when `record_user` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_by: Option<VerifiedBy>,
+
+    /// Set when the last run failed, so status can tell failed checks from stale ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<LastFailure>,
+
+    /// Version of verify that wrote the entry, with `invalidate_on_tool_upgrade`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_version: Option<String>,
+}
+
+impl CheckCache {
+    /// The config components that differ from those recorded, or none when the entry
+    /// didn't record them
+    pub fn changed_config(&self, current: &BTreeMap<String, String>) -> Vec<String> {
+        if self.config_components.is_empty() {
+            return vec![];
+        }
+        let names: std::collections::BTreeSet<&String> = self
+            .config_components
+            .keys()
+            .chain(current.keys())
+            .collect();
+        names
+            .into_iter()
+            .filter(|name| self.config_components.get(*name) != current.get(*name))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether a version of verify older than this one wrote the entry (or didn't say)
+    fn written_by_older_version(&self) -> bool {
+        let current = semver::Version::parse(CURRENT_VERSION).ok();
+        let recorded = self
+            .verify_version
+            .as_deref()
+            .and_then(|version| semver::Version::parse(version).ok());
+        match (recorded, current) {
+            (Some(recorded), Some(current)) => recorded < current,
+            _ => true,
+        }
+    }
+}
+
+/// Who marked a check verified by hand, when, and why
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MarkedVerified {
+    pub by: String,
+    pub at: DateTime<Utc>,
+    pub because: String,
+}
+
+/// How and when a check last failed, and the content it failed on
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LastFailure {
+    /// None when the command timed out or was killed by a signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// The command ran past `timeout_secs` and was stopped
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    pub at: DateTime<Utc>,
+    pub content_hash: String,
+}
+
+/// The user and machine that runs are attributed to
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Attribution {
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// Who ran a check when it last passed, and when
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VerifiedBy {
+    #[serde(flatten)]
+    pub attribution: Attribution,
+    pub at: DateTime<Utc>,
 }
 
 /// Computed verification status for a check
@@ -51,6 +177,26 @@ pub enum VerificationStatus {
     Unverified { reason: UnverifiedReason },
     /// Check has no cache_paths so changes can't be tracked
     Untracked,
+    /// Last run failed and nothing has changed since, so it needs fixing, not re-running
+    Failed {
+        exit_code: Option<i32>,
+        at: DateTime<Utc>,
+        timed_out: bool,
+    },
+    /// Check has no command variant for this platform, so it doesn't run here
+    Unsupported,
+}
+
+impl VerificationStatus {
+    /// Whether nothing is left to do for the check on this platform: it's verified, or it
+    /// doesn't run here. Unsupported checks don't hold up their dependents or
+    /// `status --verify`.
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self,
+            VerificationStatus::Verified | VerificationStatus::Unsupported
+        )
+    }
 }
 
 /// Reason why a check is unverified
@@ -60,10 +206,13 @@ pub enum UnverifiedReason {
     FilesChanged { changed_files: Vec<String> },
     /// A dependency is unverified
     DependencyUnverified { dependency: String },
-    /// The check definition changed in verify.yaml
-    ConfigChanged,
+    /// The check definition changed in verify.yaml, in the listed config components
+    /// (empty when the cache entry didn't record them)
+    ConfigChanged { changed: Vec<String> },
     /// Never run or no successful run recorded
     NeverRun,
+    /// Recorded by an older version of verify (`invalidate_on_tool_upgrade`)
+    ToolUpgraded { from: Option<String> },
 }
 
 impl CacheState {
@@ -72,51 +221,108 @@ impl CacheState {
         Self {
             version: CACHE_VERSION,
             checks: BTreeMap::new(),
+            signature: None,
+            touched: HashSet::new(),
+            attribution: None,
+            settings: Arc::default(),
         }
     }
 
-    /// Load cache from disk, returning empty cache if file doesn't exist or can't be parsed
-    pub fn load(project_root: &Path) -> Result<Self> {
+    /// Load cache from disk, returning empty cache if file doesn't exist or can't be parsed.
+    /// A lock file in an older format is migrated in memory (and rewritten by the next
+    /// save); one written by a newer verify is an error. Subprojects' caches are loaded
+    /// with their parent's `settings()`.
+    pub fn load(project_root: &Path, settings: &Arc<Settings>) -> Result<Self> {
+        let mut cache = Self::read(project_root, &settings.signing)?.0;
+        cache.settings = Arc::clone(settings);
+        Ok(cache)
+    }
+
+    /// The root config's settings this cache was loaded with
+    pub fn settings(&self) -> &Arc<Settings> {
+        &self.settings
+    }
+
+    /// Load the cache, along with the version and original contents of the lock file if
+    /// it had to be migrated
+    fn read(project_root: &Path, signing: &Signing) -> Result<(Self, Option<(u32, String)>)> {
         let lock_path = project_root.join(LOCK_FILE);
 
         if !lock_path.exists() {
-            return Ok(Self::new());
+            return Ok((Self::new(), None));
         }
 
         let content = match fs::read_to_string(&lock_path) {
             Ok(c) => c,
-            Err(_) => return Ok(Self::new()),
+            Err(_) => return Ok((Self::new(), None)),
         };
 
-        let cache: CacheState = match serde_json::from_str(&content) {
-            Ok(c) => c,
-            Err(_) => return Ok(Self::new()),
+        let mut value: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(v) => v,
+            Err(_) => return Ok((Self::new(), None)),
         };
+        let migrated_from = migrations::migrate(&mut value)
+            .with_context(|| format!("Can't read {}", lock_path.display()))?;
 
-        // Handle version migration - just return empty cache on version mismatch
+        let cache: CacheState = match serde_json::from_value(value) {
+            Ok(c) => c,
+            Err(_) => return Ok((Self::new(), None)),
+        };
         if cache.version != CACHE_VERSION {
-            return Ok(Self::new());
+            return Ok((Self::new(), None));
         }
 
-        Ok(cache)
+        // With `sign_lock`, results that weren't signed with the secret aren't trusted
+        if signing.enforced() && !cache.has_valid_signature(signing) {
+            return Ok((Self::new(), None));
+        }
+
+        Ok((cache, migrated_from.map(|from| (from, content))))
     }
 
-    /// Save cache to disk atomically
-    pub fn save(&self, project_root: &Path) -> Result<()> {
-        let lock_path = project_root.join(LOCK_FILE);
-        let temp_path = project_root.join("verify.lock.tmp");
+    /// Whether the lock file's signature matches its contents (false without the secret)
+    pub fn has_valid_signature(&self, signing: &Signing) -> bool {
+        signing.verify(&self.signed_contents(), self.signature.as_deref())
+    }
+
+    /// What the signature covers
+    fn signed_contents(&self) -> Vec<u8> {
+        serde_json::to_vec(&(self.version, &self.checks)).unwrap_or_default()
+    }
 
-        // Write to temp file
-        let file = File::create(&temp_path)
-            .with_context(|| format!("Failed to create temp lock file: {}", temp_path.display()))?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self).with_context(|| "Failed to serialize cache")?;
+    /// Save cache to disk atomically.
+    /// Entries this process changed are merged into the current lock file (under a lock),
+    /// so checks recorded by another verify process in the meantime are kept.
+    /// Does nothing when writes are disabled (`verify run --no-cache-write`).
+    pub fn save(&self, project_root: &Path) -> Result<()> {
+        if !statedir::writes_enabled() {
+            return Ok(());
+        }
+        let _lock = FileLock::lock_file(project_root)?;
+        let signing = &self.settings.signing;
+        let (mut merged, migrated) = Self::read(project_root, signing)?;
+        for name in &self.touched {
+            match self.checks.get(name) {
+                Some(entry) => merged.checks.insert(name.clone(), entry.clone()),
+                None => merged.checks.remove(name),
+            };
+        }
+        merged.signature = signing.sign(&merged.signed_contents());
 
-        // Atomic rename
-        fs::rename(&temp_path, &lock_path)
-            .with_context(|| format!("Failed to save lock file: {}", lock_path.display()))?;
+        // Serialize up front so a serialization failure can't leave a partial file behind
+        let contents =
+            serde_json::to_vec_pretty(&merged).with_context(|| "Failed to serialize cache")?;
 
-        Ok(())
+        if let Some((from, original)) = migrated {
+            let backup = back_up_lock_file(project_root, from, &original)?;
+            eprintln!(
+                "Migrated verify.lock from format {} to {} (previous file kept at {})",
+                from,
+                CACHE_VERSION,
+                backup.display()
+            );
+        }
+        replace_lock_file(project_root, &contents)
     }
 
     /// Determine verification status based on current content hash and config hash
@@ -141,16 +347,41 @@ impl CacheState {
                     Some(stored_config_hash) => {
                         if stored_config_hash != current_config_hash {
                             return VerificationStatus::Unverified {
-                                reason: UnverifiedReason::ConfigChanged,
+                                // Will be filled in by caller if needed
+                                reason: UnverifiedReason::ConfigChanged { changed: vec![] },
                             };
                         }
                     }
                 }
 
+                // Hashes from an older release may not be comparable with this one's
+                if self.settings.invalidate_on_tool_upgrade && cache.written_by_older_version() {
+                    return VerificationStatus::Unverified {
+                        reason: UnverifiedReason::ToolUpgraded {
+                            from: cache.verify_version.clone(),
+                        },
+                    };
+                }
+
                 // Then check content hash
                 match &cache.content_hash {
-                    None => VerificationStatus::Unverified {
-                        reason: UnverifiedReason::NeverRun,
+                    None => match &cache.last_failure {
+                        Some(failure) if failure.content_hash == current_content_hash => {
+                            VerificationStatus::Failed {
+                                exit_code: failure.exit_code,
+                                at: failure.at,
+                                timed_out: failure.timed_out,
+                            }
+                        }
+                        // Files changed since the failure, so a re-run may pass
+                        Some(_) => VerificationStatus::Unverified {
+                            reason: UnverifiedReason::FilesChanged {
+                                changed_files: vec![],
+                            },
+                        },
+                        None => VerificationStatus::Unverified {
+                            reason: UnverifiedReason::NeverRun,
+                        },
                     },
                     Some(stored_hash) => {
                         if stored_hash == current_content_hash {
@@ -181,9 +412,11 @@ impl CacheState {
         metadata: BTreeMap<String, MetadataValue>,
         per_file: bool,
     ) {
+        let duration_ms = self.checks.get(check_name).and_then(|c| c.duration_ms);
         let cache = if success {
             CheckCache {
                 config_hash: Some(config_hash),
+                config_components: BTreeMap::new(),
                 content_hash,
                 // Only store file_hashes for per_file checks
                 file_hashes: if per_file {
@@ -192,12 +425,19 @@ impl CacheState {
                     BTreeMap::new()
                 },
                 metadata,
+                duration_ms,
+                failed_files: Vec::new(),
+                marked_verified: None,
+                verified_by: self.verified_by(),
+                last_failure: None,
+                verify_version: None,
             }
         } else {
             // On failure, clear content_hash (will trigger re-run)
             // but keep file_hashes for per_file partial progress
             CheckCache {
                 config_hash: Some(config_hash),
+                config_components: BTreeMap::new(),
                 content_hash: None,
                 file_hashes: if per_file {
                     self.checks
@@ -208,9 +448,114 @@ impl CacheState {
                     BTreeMap::new()
                 },
                 metadata: BTreeMap::new(),
+                duration_ms,
+                failed_files: Vec::new(),
+                marked_verified: None,
+                verified_by: None,
+                last_failure: None,
+                verify_version: None,
             }
         };
-        self.checks.insert(check_name.to_string(), cache);
+        self.insert(check_name, cache);
+    }
+
+    /// Attribute checks that pass from now on to `attribution`
+    pub fn attribute_to(&mut self, attribution: Option<Attribution>) {
+        self.attribution = attribution;
+    }
+
+    /// Who checks that pass are attributed to, if anyone
+    pub fn attribution(&self) -> Option<&Attribution> {
+        self.attribution.as_ref()
+    }
+
+    /// The version to record in entries this process writes, with
+    /// `invalidate_on_tool_upgrade`
+    fn written_by(&self) -> Option<String> {
+        self.settings
+            .invalidate_on_tool_upgrade
+            .then(|| CURRENT_VERSION.to_string())
+    }
+
+    fn verified_by(&self) -> Option<VerifiedBy> {
+        self.attribution.clone().map(|attribution| VerifiedBy {
+            attribution,
+            at: Utc::now(),
+        })
+    }
+
+    /// Set the cache entry for a check
+    pub fn insert(&mut self, check_name: &str, mut entry: CheckCache) {
+        entry.verify_version = self.written_by();
+        self.checks.insert(check_name.to_string(), entry);
+        self.touched.insert(check_name.to_string());
+    }
+
+    /// Record a check as verified at the given hashes without running it
+    pub fn mark_verified(
+        &mut self,
+        check_name: &str,
+        config_hash: String,
+        content_hash: String,
+        file_hashes: BTreeMap<String, String>,
+        per_file: bool,
+        marked: MarkedVerified,
+    ) {
+        self.update(
+            check_name,
+            true,
+            config_hash,
+            Some(content_hash),
+            file_hashes,
+            BTreeMap::new(),
+            per_file,
+        );
+        if let Some(cache) = self.checks.get_mut(check_name) {
+            cache.marked_verified = Some(marked);
+            cache.verified_by = None;
+        }
+    }
+
+    /// Record the config components of the config hash a check's entry was written with
+    pub fn record_config_components(
+        &mut self,
+        check_name: &str,
+        components: BTreeMap<String, String>,
+    ) {
+        if let Some(cache) = self.checks.get_mut(check_name)
+            && cache.config_components != components
+        {
+            cache.config_components = components;
+            self.touched.insert(check_name.to_string());
+        }
+    }
+
+    /// Record how long a check took to run
+    pub fn record_duration(&mut self, check_name: &str, duration_ms: u64) {
+        if let Some(cache) = self.checks.get_mut(check_name) {
+            cache.duration_ms = Some(duration_ms);
+            self.touched.insert(check_name.to_string());
+        }
+    }
+
+    /// Record that a check's last run failed on the given content. Call after `update`
+    /// or `mark_per_file_failed`, which clear it.
+    pub fn record_failure(
+        &mut self,
+        check_name: &str,
+        exit_code: Option<i32>,
+        timed_out: bool,
+        content_hash: String,
+    ) {
+        if let Some(cache) = self.checks.get_mut(check_name) {
+            cache.last_failure = Some(LastFailure {
+                exit_code,
+                timed_out,
+                at: Utc::now(),
+                content_hash,
+            });
+            self.touched.insert(check_name.to_string());
+        }
     }
 
     /// Get cached info for a check
@@ -220,14 +565,26 @@ impl CacheState {
 
     /// Initialize or get mutable cache entry for per_file mode
     pub fn get_or_create_mut(&mut self, check_name: &str, config_hash: &str) -> &mut CheckCache {
-        self.checks
+        self.touched.insert(check_name.to_string());
+        let written_by = self.written_by();
+        let entry = self
+            .checks
             .entry(check_name.to_string())
             .or_insert_with(|| CheckCache {
                 config_hash: Some(config_hash.to_string()),
+                config_components: BTreeMap::new(),
                 content_hash: None,
                 file_hashes: BTreeMap::new(),
                 metadata: BTreeMap::new(),
-            })
+                duration_ms: None,
+                failed_files: Vec::new(),
+                marked_verified: None,
+                verified_by: None,
+                last_failure: None,
+                verify_version: None,
+            });
+        entry.verify_version = written_by;
+        entry
     }
 
     /// Update cache for a single file in per_file mode
@@ -251,47 +608,152 @@ impl CacheState {
         file_hashes: BTreeMap<String, String>,
         metadata: BTreeMap<String, MetadataValue>,
     ) {
+        let verified_by = self.verified_by();
         let cache = self.get_or_create_mut(check_name, config_hash);
         cache.config_hash = Some(config_hash.to_string());
         cache.content_hash = Some(combined_hash);
         cache.file_hashes = file_hashes;
         cache.metadata = metadata;
+        cache.failed_files.clear();
+        cache.marked_verified = None;
+        cache.verified_by = verified_by;
+        cache.last_failure = None;
     }
 
     /// Mark per_file check as failed (keeps partial file_hashes for progress)
-    pub fn mark_per_file_failed(&mut self, check_name: &str, config_hash: &str) {
+    pub fn mark_per_file_failed(
+        &mut self,
+        check_name: &str,
+        config_hash: &str,
+        failed_files: Vec<String>,
+    ) {
         let cache = self.get_or_create_mut(check_name, config_hash);
         cache.config_hash = Some(config_hash.to_string());
         cache.content_hash = None;
+        cache.failed_files = failed_files;
+        cache.marked_verified = None;
+        cache.verified_by = None;
+        cache.last_failure = None;
         // Keep existing file_hashes for partial progress
     }
 
     /// Remove cache entries for checks not in the valid set
     pub fn cleanup_orphaned(&mut self, valid_check_names: &HashSet<String>) {
-        self.checks
-            .retain(|name, _| valid_check_names.contains(name));
+        let touched = &mut self.touched;
+        self.checks.retain(|name, _| {
+            let keep = valid_check_names.contains(name);
+            if !keep {
+                touched.insert(name.clone());
+            }
+            keep
+        });
     }
 
     /// Clear cache for specific checks or all
     pub fn clear(&mut self, names: &[String]) {
         if names.is_empty() {
-            self.checks.clear();
+            self.touched
+                .extend(std::mem::take(&mut self.checks).into_keys());
         } else {
             for name in names {
                 self.checks.remove(name);
+                self.touched.insert(name.clone());
             }
         }
     }
 }
 
 /// Clean the cache file
-pub fn clean_cache(project_root: &Path, names: Vec<String>) -> Result<()> {
-    let mut cache = CacheState::load(project_root)?;
+pub fn clean_cache(
+    project_root: &Path,
+    settings: &Arc<Settings>,
+    names: Vec<String>,
+) -> Result<()> {
+    let mut cache = CacheState::load(project_root, settings)?;
     cache.clear(&names);
     cache.save(project_root)?;
     Ok(())
 }
 
+/// Replace verify.lock with `contents` as a whole, without merging, e.g. to restore a snapshot
+pub fn write_lock_file(project_root: &Path, contents: &[u8]) -> Result<()> {
+    let _lock = FileLock::lock_file(project_root)?;
+    replace_lock_file(project_root, contents)
+}
+
+/// Remove verify.lock, if there is one
+pub fn remove_lock_file(project_root: &Path) -> Result<()> {
+    let lock_path = project_root.join(LOCK_FILE);
+    let _lock = FileLock::lock_file(project_root)?;
+    match fs::remove_file(&lock_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove lock file: {}", lock_path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Keep a lock file about to be rewritten in a newer format as `verify.lock.v<version>` in
+/// the state directory. Returns the backup's path.
+fn back_up_lock_file(project_root: &Path, version: u32, contents: &str) -> Result<PathBuf> {
+    let path = statedir::create(project_root)?.join(format!("{}.v{}", LOCK_FILE, version));
+    fs::write(&path, contents)
+        .with_context(|| format!("Failed to back up lock file: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Atomically replace verify.lock. The caller holds the verify.lock lock.
+fn replace_lock_file(project_root: &Path, contents: &[u8]) -> Result<()> {
+    let lock_path = project_root.join(LOCK_FILE);
+    let temp_path = project_root.join("verify.lock.tmp");
+    if let Err(e) = write_atomically(&temp_path, &lock_path, contents) {
+        let _ = fs::remove_file(&temp_path);
+        let hint = write_error_hint(&e);
+        return Err(e)
+            .with_context(|| format!("Failed to save lock file: {}{}", lock_path.display(), hint));
+    }
+    Ok(())
+}
+
+/// Write `contents` to `temp_path`, flush it to disk, then rename it over `path`, so readers
+/// and interrupted runs only ever see the old or the new file. An existing file's
+/// permissions are kept; a new one gets the default (umask) permissions.
+fn write_atomically(temp_path: &Path, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    if let Ok(existing) = fs::metadata(path) {
+        fs::set_permissions(temp_path, existing.permissions())?;
+    }
+    drop(file);
+    fs::rename(temp_path, path)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        })
+    {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// A plain-language explanation for the write errors people can act on
+fn write_error_hint(e: &std::io::Error) -> &'static str {
+    match e.kind() {
+        std::io::ErrorKind::StorageFull => " (disk is full; the previous verify.lock is unchanged)",
+        std::io::ErrorKind::PermissionDenied => {
+            " (permission denied; check ownership of the project directory and verify.lock)"
+        }
+        std::io::ErrorKind::ReadOnlyFilesystem => " (the file system is read-only)",
+        _ => "",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,6 +788,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mark_verified_until_next_run() {
+        let mut cache = CacheState::new();
+        let marked = MarkedVerified {
+            by: "QA <qa@example.com>".to_string(),
+            at: Utc::now(),
+            because: "passed manual QA".to_string(),
+        };
+        cache.mark_verified(
+            "test",
+            "confighash".to_string(),
+            "abc123".to_string(),
+            BTreeMap::new(),
+            false,
+            marked.clone(),
+        );
+
+        assert_eq!(
+            cache.check_staleness("test", "abc123", "confighash"),
+            VerificationStatus::Verified
+        );
+        assert_eq!(cache.get("test").unwrap().marked_verified, Some(marked));
+
+        cache.update(
+            "test",
+            true,
+            "confighash".to_string(),
+            Some("abc123".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        assert_eq!(cache.get("test").unwrap().marked_verified, None);
+    }
+
+    #[test]
+    fn test_attribution_recorded_on_pass() {
+        let mut cache = CacheState::new();
+        let attribution = Attribution {
+            user: "Dev <dev@example.com>".to_string(),
+            host: Some("laptop".to_string()),
+        };
+        cache.attribute_to(Some(attribution.clone()));
+        let run = |cache: &mut CacheState, success: bool| {
+            cache.update(
+                "test",
+                success,
+                "confighash".to_string(),
+                Some("abc123".to_string()),
+                BTreeMap::new(),
+                BTreeMap::new(),
+                false,
+            )
+        };
+
+        run(&mut cache, true);
+        let verified_by = cache.get("test").unwrap().verified_by.clone().unwrap();
+        assert_eq!(verified_by.attribution, attribution);
+
+        run(&mut cache, false);
+        assert_eq!(cache.get("test").unwrap().verified_by, None);
+
+        // Without attribution nothing is recorded
+        cache.attribute_to(None);
+        run(&mut cache, true);
+        assert_eq!(cache.get("test").unwrap().verified_by, None);
+    }
+
     #[test]
     fn test_staleness_after_content_change() {
         let mut cache = CacheState::new();
@@ -362,7 +892,7 @@ mod tests {
 
         match cache.check_staleness("test", "abc123", "different_config") {
             VerificationStatus::Unverified {
-                reason: UnverifiedReason::ConfigChanged,
+                reason: UnverifiedReason::ConfigChanged { .. },
             } => {}
             other => panic!("Expected Unverified(ConfigChanged), got {:?}", other),
         }
@@ -390,6 +920,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_staleness_after_recorded_failure() {
+        let mut cache = CacheState::new();
+        cache.update(
+            "test",
+            false,
+            "confighash".to_string(),
+            Some("abc123".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.record_failure("test", Some(2), false, "abc123".to_string());
+
+        // Same content: needs fixing
+        match cache.check_staleness("test", "abc123", "confighash") {
+            VerificationStatus::Failed { exit_code, .. } => assert_eq!(exit_code, Some(2)),
+            other => panic!("Expected Failed, got {:?}", other),
+        }
+        // Changed content or config: needs running
+        assert!(matches!(
+            cache.check_staleness("test", "def456", "confighash"),
+            VerificationStatus::Unverified {
+                reason: UnverifiedReason::FilesChanged { .. }
+            }
+        ));
+        assert_eq!(
+            cache.check_staleness("test", "abc123", "newconfig"),
+            VerificationStatus::Unverified {
+                reason: UnverifiedReason::ConfigChanged { changed: vec![] }
+            }
+        );
+
+        // Passing clears the failure
+        cache.update(
+            "test",
+            true,
+            "confighash".to_string(),
+            Some("abc123".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        assert!(cache.get("test").unwrap().last_failure.is_none());
+    }
+
     #[test]
     fn test_cleanup_orphaned() {
         let mut cache = CacheState::new();
@@ -419,6 +995,85 @@ mod tests {
         assert!(cache.get("remove").is_none());
     }
 
+    #[test]
+    fn test_save_merges_with_concurrent_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = |cache: &mut CacheState, name: &str| {
+            cache.update(
+                name,
+                true,
+                "config".to_string(),
+                Some("hash".to_string()),
+                BTreeMap::new(),
+                BTreeMap::new(),
+                false,
+            );
+        };
+
+        let mut setup = CacheState::new();
+        record(&mut setup, "stale");
+        setup.save(dir.path()).unwrap();
+
+        // Two processes load the same state, then each records a different check
+        let mut first = CacheState::load(dir.path(), &Default::default()).unwrap();
+        let mut second = CacheState::load(dir.path(), &Default::default()).unwrap();
+        record(&mut first, "build");
+        first.save(dir.path()).unwrap();
+        record(&mut second, "lint");
+        second.clear(&["stale".to_string()]);
+        second.save(dir.path()).unwrap();
+
+        let merged = CacheState::load(dir.path(), &Default::default()).unwrap();
+        assert!(
+            merged.get("build").is_some(),
+            "First writer's result is kept"
+        );
+        assert!(merged.get("lint").is_some());
+        assert!(merged.get("stale").is_none(), "Removals are written back");
+    }
+
+    #[test]
+    fn test_failed_save_keeps_lock_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = CacheState::new();
+        cache.update(
+            "build",
+            true,
+            "config".to_string(),
+            Some("hash".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.save(dir.path()).unwrap();
+        let saved = fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+
+        // A directory in the temp file's place makes the write fail
+        fs::create_dir(dir.path().join("verify.lock.tmp")).unwrap();
+        cache.clear(&["build".to_string()]);
+        let err = cache.save(dir.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to save lock file"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap(),
+            saved
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_lock_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let lock_path = dir.path().join(LOCK_FILE);
+        CacheState::new().save(dir.path()).unwrap();
+        fs::set_permissions(&lock_path, fs::Permissions::from_mode(0o664)).unwrap();
+
+        CacheState::new().save(dir.path()).unwrap();
+        let mode = fs::metadata(&lock_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o664);
+    }
+
     #[test]
     fn test_file_hashes_only_stored_for_per_file() {
         let mut cache = CacheState::new();
@@ -449,4 +1104,37 @@ mod tests {
         );
         assert!(!cache.get("perfile").unwrap().file_hashes.is_empty());
     }
+
+    #[test]
+    fn test_duration_recorded_and_kept_across_updates() {
+        let mut cache = CacheState::new();
+
+        // Recording before the check has an entry is a no-op
+        cache.record_duration("build", 100);
+        assert!(cache.get("build").is_none());
+
+        cache.update(
+            "build",
+            true,
+            "config".to_string(),
+            Some("hash".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.record_duration("build", 1500);
+        assert_eq!(cache.get("build").unwrap().duration_ms, Some(1500));
+
+        // A later update (e.g. a sync or failure) keeps the last known duration
+        cache.update(
+            "build",
+            false,
+            "config".to_string(),
+            None,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        assert_eq!(cache.get("build").unwrap().duration_ms, Some(1500));
+    }
 }
@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::statedir;
+use crate::trailer;
+
+const CAPTURES_DIR: &str = "captures";
+const CAPTURE_EXTENSION: &str = ".tar.zst";
+
+/// Archive entry describing the capture, next to the captured files
+const MANIFEST_ENTRY: &str = ".verify-capture.json";
+
+/// What a capture holds: the check's hash and the hash of every file it covered
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptureManifest {
+    pub check: String,
+    /// Combined hash of the check's config and inputs, as `verify hash` prints it
+    pub hash: String,
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+    /// Hash of each archived file, keyed by project-relative path
+    pub files: BTreeMap<String, String>,
+    /// Inputs hashed without archiving them (submodule commits), keyed by path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+}
+
+/// Archive the files check `name`'s hash covers, with their contents, into
+/// `.verify/captures/<check>-<hash>.tar.zst`. The config file is added too (unless the
+/// check already covers it), so the check can be run again from the restored files.
+/// Returns the archive path and its manifest.
+pub fn capture(
+    project_root: &Path,
+    config_path: &Path,
+    config: &Config,
+    name: &str,
+) -> Result<(PathBuf, CaptureManifest)> {
+    let check = config.get(name).with_context(|| {
+        if name.contains('/') {
+            format!(
+                "Unknown check: {} (capture checks of a subproject from its directory)",
+                name
+            )
+        } else {
+            format!("Unknown check: {}", name)
+        }
+    })?;
+    if check.cache_paths.is_empty() {
+        anyhow::bail!(
+            "'{}' has no cache_paths, so its hash covers no files to capture",
+            name
+        );
+    }
+
+    let result = check.compute_hash(project_root)?;
+    let hash = trailer::compute_combined_hash(&check.config_hash(), &result.combined_hash);
+    let (files, inputs): (BTreeMap<_, _>, BTreeMap<_, _>) = result
+        .file_hashes
+        .into_iter()
+        .partition(|(relative, _)| project_root.join(relative).is_file());
+
+    let dir = statedir::create(project_root)?.join(CAPTURES_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let short_hash = trailer::truncate_hash(&hash, config.trailer_hash_length());
+    let path = dir.join(format!("{}-{}{}", name, short_hash, CAPTURE_EXTENSION));
+
+    let manifest = CaptureManifest {
+        check: name.to_string(),
+        hash: hash.clone(),
+        captured_at: chrono::Utc::now(),
+        files,
+        inputs,
+    };
+    let max_file_size = check.glob_options().max_file_size;
+    // Write next to the final path and rename, so a failed capture leaves nothing behind
+    let staging = path.with_extension("partial");
+    write_archive(
+        &staging,
+        project_root,
+        config_path,
+        &manifest,
+        max_file_size,
+    )
+    .inspect_err(|_| {
+        let _ = fs::remove_file(&staging);
+    })?;
+    fs::rename(&staging, &path)
+        .with_context(|| format!("Failed to write capture: {}", path.display()))?;
+    Ok((path, manifest))
+}
+
+fn write_archive(
+    path: &Path,
+    project_root: &Path,
+    config_path: &Path,
+    manifest: &CaptureManifest,
+    max_file_size: Option<u64>,
+) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create capture: {}", path.display()))?;
+    let encoder = zstd::Encoder::new(file, 0)
+        .context("Failed to initialize zstd encoder")?
+        .auto_finish();
+    let mut builder = tar::Builder::new(encoder);
+
+    for (relative, hash) in &manifest.files {
+        let source = project_root.join(relative);
+        let content =
+            fs::read(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+        // Large files hashed by their first bytes can't be compared, but every other file
+        // must still be the one that was hashed
+        let truncated = max_file_size.is_some_and(|max| content.len() as u64 > max);
+        if !truncated && blake3::hash(&content).to_hex().as_str() != hash {
+            anyhow::bail!("{} changed while it was being captured", relative);
+        }
+        append(&mut builder, relative, &content)?;
+    }
+
+    let config_name = config_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "verify.yaml".to_string());
+    if !manifest.files.contains_key(&config_name) {
+        let content = fs::read(config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?;
+        append(&mut builder, &config_name, &content)?;
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)?;
+    append(&mut builder, MANIFEST_ENTRY, &manifest_json)?;
+
+    builder
+        .into_inner()
+        .with_context(|| format!("Failed to write capture: {}", path.display()))?;
+    Ok(())
+}
+
+fn append<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    entry_path: &str,
+    content: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, entry_path, content)
+        .with_context(|| format!("Failed to add {} to capture", entry_path))
+}
+
+/// Extract a capture into a new temporary directory, which is kept. `target` is the path
+/// of a capture, or a check name for its most recent capture. Returns the directory and
+/// the capture's manifest.
+pub fn restore(project_root: &Path, target: &str) -> Result<(PathBuf, CaptureManifest)> {
+    let path = if Path::new(target).is_file() {
+        PathBuf::from(target)
+    } else {
+        latest_capture(project_root, target)?
+    };
+
+    let dir = tempfile::Builder::new()
+        .prefix("verify-capture-")
+        .tempdir()
+        .context("Failed to create a temporary directory")?;
+    let file =
+        File::open(&path).with_context(|| format!("Failed to open capture: {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to initialize zstd decoder")?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut manifest = None;
+
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read capture: {}", path.display()))?;
+    for entry in entries {
+        let mut entry =
+            entry.with_context(|| format!("Failed to read capture: {}", path.display()))?;
+        if entry.path()?.as_os_str() == MANIFEST_ENTRY {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            manifest = Some(
+                serde_json::from_str::<CaptureManifest>(&content)
+                    .with_context(|| format!("Invalid capture manifest in {}", path.display()))?,
+            );
+            continue;
+        }
+        // Refuses entries that would land outside the directory
+        entry
+            .unpack_in(dir.path())
+            .with_context(|| format!("Failed to extract capture: {}", path.display()))?;
+    }
+
+    let manifest =
+        manifest.with_context(|| format!("{} is not a verify capture", path.display()))?;
+    Ok((dir.keep(), manifest))
+}
+
+/// The most recently written capture of check `name`
+fn latest_capture(project_root: &Path, name: &str) -> Result<PathBuf> {
+    let dir = statedir::state_dir(project_root).join(CAPTURES_DIR);
+    let mut latest: Option<(std::time::SystemTime, PathBuf)> = None;
+    if dir.is_dir() {
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let check = file_name
+                .strip_suffix(CAPTURE_EXTENSION)
+                .and_then(|stem| stem.rsplit_once('-'))
+                .map(|(check, _)| check);
+            if check != Some(name) {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                latest = Some((modified, entry.path()));
+            }
+        }
+    }
+    latest.map(|(_, path)| path).with_context(|| {
+        format!(
+            "No capture file or capture of check '{}' (run `verify capture {}` first)",
+            name, name
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_capture_and_restore_roundtrip() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let config_path = root.join("verify.yaml");
+        fs::write(
+            &config_path,
+            "verifications:\n  - name: build\n    command: make\n    cache_paths: [\"src/**/*.rs\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/nested/lib.rs"), "pub fn lib() {}").unwrap();
+        fs::write(root.join("README.md"), "not covered").unwrap();
+        let config = Config::load(&config_path).unwrap();
+
+        let (path, manifest) = capture(root, &config_path, &config, "build").unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(file_name, format!("build-{}.tar.zst", &manifest.hash[..16]));
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec!["src/main.rs", "src/nested/lib.rs"]
+        );
+
+        // The files change afterwards, but the capture keeps what was hashed
+        fs::write(root.join("src/main.rs"), "fn main() { changed() }").unwrap();
+        let (restored, restored_manifest) = restore(root, "build").unwrap();
+        assert_eq!(restored_manifest.hash, manifest.hash);
+        assert_eq!(
+            fs::read_to_string(restored.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert!(restored.join("src/nested/lib.rs").is_file());
+        assert!(restored.join("verify.yaml").is_file());
+        assert!(!restored.join("README.md").exists());
+        assert!(!restored.join(MANIFEST_ENTRY).exists());
+
+        // By path too
+        let (by_path, _) = restore(root, path.to_str().unwrap()).unwrap();
+        assert!(by_path.join("src/main.rs").is_file());
+        fs::remove_dir_all(restored).unwrap();
+        fs::remove_dir_all(by_path).unwrap();
+
+        assert!(restore(root, "lint").is_err());
+        assert!(capture(root, &config_path, &config, "lint").is_err());
+    }
+}
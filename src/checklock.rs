@@ -0,0 +1,111 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+
+const LOCKS_DIR: &str = "locks";
+
+/// Advisory lock on a file in `.verify/locks/`, released when dropped.
+/// Used to serialize conflicting work between verify processes (e.g. watch mode and a
+/// manual run): each check has its own lock, so unrelated checks never wait on each other.
+/// When writes are disabled no lock files are created and every lock is granted at once.
+pub struct FileLock {
+    _file: Option<File>,
+}
+
+impl FileLock {
+    /// Take the lock for a check, or return None if another process holds it
+    pub fn try_check(project_root: &Path, check_name: &str) -> Result<Option<Self>> {
+        if !statedir::writes_enabled() {
+            return Ok(Some(Self { _file: None }));
+        }
+        let file = open_lock_file(project_root, &check_lock_name(check_name))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: Some(file) })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock check: {}", check_name))
+            }
+        }
+    }
+
+    /// Take the lock for a check, waiting for any other process holding it
+    pub fn check(project_root: &Path, check_name: &str) -> Result<Self> {
+        Self::wait(project_root, &check_lock_name(check_name))
+    }
+
+    /// Take the lock guarding reads and writes of verify.lock, waiting if needed
+    pub fn lock_file(project_root: &Path) -> Result<Self> {
+        Self::wait(project_root, "verify.lock.lock")
+    }
+
+    fn wait(project_root: &Path, file_name: &str) -> Result<Self> {
+        if !statedir::writes_enabled() {
+            return Ok(Self { _file: None });
+        }
+        let file = open_lock_file(project_root, file_name)?;
+        file.lock()
+            .with_context(|| format!("Failed to take lock: {}", file_name))?;
+        Ok(Self { _file: Some(file) })
+    }
+}
+
+/// Lock file name for a check. Characters that aren't safe in file names are replaced,
+/// which can only make unrelated checks share a lock (over-serializing, never racing).
+fn check_lock_name(check_name: &str) -> String {
+    let safe: String = check_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("check-{}.lock", safe)
+}
+
+fn open_lock_file(project_root: &Path, file_name: &str) -> Result<File> {
+    let dir: PathBuf = statedir::create(project_root)?.join(LOCKS_DIR);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    let path = dir.join(file_name);
+    File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_locks_are_independent() {
+        let dir = tempdir().unwrap();
+
+        let build = FileLock::try_check(dir.path(), "build").unwrap();
+        assert!(build.is_some());
+        assert!(
+            FileLock::try_check(dir.path(), "build").unwrap().is_none(),
+            "Same check should be locked"
+        );
+        assert!(
+            FileLock::try_check(dir.path(), "lint").unwrap().is_some(),
+            "Unrelated check should not be blocked"
+        );
+
+        drop(build);
+        assert!(FileLock::try_check(dir.path(), "build").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_check_lock_name_is_file_safe() {
+        assert_eq!(check_lock_name("build"), "check-build.lock");
+        assert_eq!(check_lock_name("web/test:unit"), "check-web_test_unit.lock");
+    }
+}
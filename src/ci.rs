@@ -0,0 +1,120 @@
+/// CI service whose web log viewer can fold sections of output, detected from the
+/// environment variables each one sets on its runners
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+    Buildkite,
+}
+
+impl CiProvider {
+    /// The CI service verify is running under, if any
+    pub fn detect() -> Option<Self> {
+        Self::from_env(|key| std::env::var(key).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let set = |key: &str| var(key).is_some_and(|value| value == "true");
+        if set("GITHUB_ACTIONS") {
+            Some(CiProvider::GitHubActions)
+        } else if set("GITLAB_CI") {
+            Some(CiProvider::GitLab)
+        } else if set("BUILDKITE") {
+            Some(CiProvider::Buildkite)
+        } else {
+            None
+        }
+    }
+
+    /// Line that opens a collapsed section titled `title`
+    pub fn section_start(&self, title: &str) -> String {
+        match self {
+            CiProvider::GitHubActions => format!("::group::{}", title),
+            CiProvider::GitLab => format!(
+                "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{}",
+                chrono::Utc::now().timestamp(),
+                section_id(title),
+                title
+            ),
+            CiProvider::Buildkite => format!("--- {}", title),
+        }
+    }
+
+    /// Line that closes the section opened for `title`. Buildkite sections run until
+    /// the next one starts, so there's nothing to print.
+    pub fn section_end(&self, title: &str) -> Option<String> {
+        match self {
+            CiProvider::GitHubActions => Some("::endgroup::".to_string()),
+            CiProvider::GitLab => Some(format!(
+                "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+                chrono::Utc::now().timestamp(),
+                section_id(title)
+            )),
+            CiProvider::Buildkite => None,
+        }
+    }
+}
+
+/// GitLab section names may only contain letters, digits, `_`, `.` and `-`
+fn section_id(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> Option<CiProvider> {
+        CiProvider::from_env(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect_provider() {
+        assert_eq!(detect(&[]), None);
+        assert_eq!(
+            detect(&[("GITHUB_ACTIONS", "true")]),
+            Some(CiProvider::GitHubActions)
+        );
+        assert_eq!(detect(&[("GITLAB_CI", "true")]), Some(CiProvider::GitLab));
+        assert_eq!(
+            detect(&[("BUILDKITE", "true")]),
+            Some(CiProvider::Buildkite)
+        );
+        assert_eq!(detect(&[("GITHUB_ACTIONS", "false")]), None);
+    }
+
+    #[test]
+    fn test_section_markers() {
+        let github = CiProvider::GitHubActions;
+        assert_eq!(github.section_start("build"), "::group::build");
+        assert_eq!(github.section_end("build").unwrap(), "::endgroup::");
+
+        let gitlab = CiProvider::GitLab;
+        let start = gitlab.section_start("lint: src/main.rs");
+        assert!(start.contains("section_start:"), "{:?}", start);
+        assert!(
+            start.contains(":lint__src_main.rs[collapsed=true]\r\x1b[0Klint: src/main.rs"),
+            "{:?}",
+            start
+        );
+        let end = gitlab.section_end("lint: src/main.rs").unwrap();
+        assert!(end.contains(":lint__src_main.rs\r"), "{:?}", end);
+
+        let buildkite = CiProvider::Buildkite;
+        assert_eq!(buildkite.section_start("build"), "--- build");
+        assert_eq!(buildkite.section_end("build"), None);
+    }
+}
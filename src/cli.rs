@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -17,6 +17,10 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Parse and validate the config from scratch instead of reusing .verify/config-cache
+    #[arg(long, global = true)]
+    pub no_config_cache: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -29,13 +33,89 @@ pub enum Commands {
         #[arg(value_name = "NAME")]
         names: Vec<String>,
 
-        /// Force run even if cache is fresh
+        /// Force run even if cache is fresh. With NAMEs, only those checks are forced and
+        /// their dependencies still honor the cache
         #[arg(short, long)]
         force: bool,
 
-        /// Stage verify.lock after successful run (for git hooks)
+        /// With --force and NAMEs, also force the dependencies of the named checks
+        #[arg(long, requires = "force", conflicts_with = "workspace")]
+        force_stale_deps: bool,
+
+        /// Run everything CHECK depends on, but not CHECK itself
+        #[arg(
+            long,
+            value_name = "CHECK",
+            visible_alias = "only-deps",
+            conflicts_with_all = ["names", "workspace"]
+        )]
+        until: Option<String>,
+
+        /// Skip checks that already passed in an interrupted run (with unchanged files)
+        #[arg(long, conflicts_with = "workspace")]
+        resume: bool,
+
+        /// Stage verify.lock files (root and subprojects) after successful run (for git hooks)
         #[arg(long)]
         stage: bool,
+
+        /// Run against the staged changes (the git index) in a temporary copy of the
+        /// repository, ignoring unstaged changes and untracked files
+        #[arg(long, conflicts_with_all = ["workspace", "resume"])]
+        staged: bool,
+
+        /// Run checks without writing verify.lock or .verify/ state (nothing is cached,
+        /// recorded in the history or cleaned up)
+        #[arg(long, conflicts_with_all = ["stage", "resume"])]
+        no_cache_write: bool,
+
+        /// Run the checks in a profile from verify.yaml (default: `default_profile`, or
+        /// `hooks.default_profile` in a git hook)
+        #[arg(long, value_name = "PROFILE", conflicts_with_all = ["names", "until", "workspace"])]
+        profile: Option<String>,
+
+        /// Exit with the failed command's own exit code (when exactly one check's command failed)
+        #[arg(long)]
+        passthrough_exit_code: bool,
+
+        /// Ask before running stale dependencies of the named checks (in an interactive terminal)
+        #[arg(long, requires = "names", conflicts_with_all = ["force_stale_deps", "workspace"])]
+        confirm: bool,
+
+        /// Show why each check runs or is skipped: cached vs current config and content
+        /// hashes, the dependency that invalidated it, and whether --force applied
+        #[arg(long, conflicts_with = "workspace")]
+        explain_cache: bool,
+
+        /// Print the output of passing checks too, after their status line (checks can set
+        /// `show_output` instead)
+        #[arg(long, conflicts_with = "workspace")]
+        show_output: bool,
+
+        /// Annotate the run with a message, recorded in the run history
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Also write JSON results to this file, keeping human output on the terminal
+        #[arg(long, value_name = "PATH")]
+        json_file: Option<PathBuf>,
+
+        /// Run every project listed in a workspace file (default: verify-workspace.yaml).
+        /// NAME then selects workspace projects instead of checks.
+        #[arg(
+            long,
+            value_name = "FILE",
+            num_args = 0..=1,
+            default_missing_value = "verify-workspace.yaml",
+            conflicts_with = "stage"
+        )]
+        workspace: Option<PathBuf>,
+
+        /// From inside a subproject, run the config of the outermost project that includes
+        /// it instead, so root aggregates and sibling dependencies apply. NAME then selects
+        /// that config's checks.
+        #[arg(long, conflicts_with = "workspace")]
+        root: bool,
     },
 
     /// Show status of checks
@@ -51,6 +131,45 @@ pub enum Commands {
         /// Exit with code 1 if any check is unverified
         #[arg(long)]
         verify: bool,
+
+        /// Also show the status of the check's transitive dependencies
+        #[arg(long, requires = "name")]
+        explain: bool,
+
+        /// Show a live dashboard that updates as files change, with keys to run checks
+        #[arg(long, conflicts_with_all = ["name", "verify", "detailed"])]
+        watch: bool,
+
+        /// Only include unverified and untracked checks in JSON output (including subprojects)
+        #[arg(long)]
+        only_unverified: bool,
+
+        /// Flatten subprojects in JSON output, naming checks by path (e.g. backend/build)
+        #[arg(long)]
+        flat: bool,
+
+        /// Also write JSON status to this file, keeping human output on the terminal
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        json_file: Option<PathBuf>,
+
+        /// Show the status of the staged changes (the git index) instead of the working tree
+        #[arg(long, conflicts_with = "watch")]
+        staged: bool,
+    },
+
+    /// List check names one per line with their status, for scripts and editor task pickers
+    List {
+        /// Only list checks that aren't verified (what `verify run` would run)
+        #[arg(long)]
+        stale: bool,
+
+        /// Print only the names, e.g. for `verify run $(verify list --stale --names-only)`
+        #[arg(long)]
+        names_only: bool,
+
+        /// Include the checks of subprojects, named by subproject prefix (e.g. backend/build)
+        #[arg(long)]
+        flat: bool,
     },
 
     /// Initialize a new verify.yaml config file
@@ -65,6 +184,10 @@ pub enum Commands {
         /// Specific check name(s) to clear
         #[arg(value_name = "NAME")]
         names: Vec<String>,
+
+        /// Instead, remove verify.lock and .verify/ of subprojects no longer in the config
+        #[arg(long, conflicts_with = "names")]
+        orphaned: bool,
     },
 
     /// Print combined verification hash for checks
@@ -78,6 +201,10 @@ pub enum Commands {
     Sign {
         /// Path to commit message file
         file: PathBuf,
+
+        /// Only include these checks in the trailer (comma-separated; overrides sign_checks)
+        #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+        checks: Vec<String>,
     },
 
     /// Validate HEAD commit trailer against current file state
@@ -85,13 +212,282 @@ pub enum Commands {
         /// Specific check name to validate (omit for all checks)
         #[arg(value_name = "NAME")]
         name: Option<String>,
+
+        /// Validate against HEAD with this patch applied, in a temporary copy of the tree
+        #[arg(long, value_name = "FILE", conflicts_with = "bundle")]
+        patch: Option<PathBuf>,
+
+        /// Validate against the tip of this git bundle, in a temporary copy of the tree
+        #[arg(long, value_name = "FILE")]
+        bundle: Option<PathBuf>,
+
+        /// Trailer value to validate (e.g. "build:a1b2c3d4") instead of the commit's
+        #[arg(long, value_name = "VALUE")]
+        trailer: Option<String>,
     },
 
     /// Sync cache from git commit trailer history
-    Sync {},
+    Sync {
+        /// Number of commits to search for Verified trailers
+        #[arg(long, default_value_t = 50)]
+        depth: usize,
+
+        /// Search the history of this branch or commit instead of HEAD
+        #[arg(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
+
+        /// Search the history of every local and remote-tracking branch
+        #[arg(long, conflicts_with = "git_ref")]
+        all_branches: bool,
+    },
+
+    /// Set up a fresh clone: sync from Verified trailers, then report what's still unverified
+    Bootstrap {
+        /// Number of commits to search for Verified trailers
+        #[arg(long, default_value_t = 50)]
+        depth: usize,
+
+        /// Run the checks that are still unverified after syncing
+        #[arg(long)]
+        run: bool,
+    },
+
+    /// Report HEAD's Verified trailer as a GitHub check run, annotating unverified checks
+    Annotate {
+        /// GitHub API token (default: $GITHUB_TOKEN)
+        #[arg(long, value_name = "TOKEN")]
+        github_token: Option<String>,
+
+        /// Repository as owner/name (default: $GITHUB_REPOSITORY, then the origin remote)
+        #[arg(long, value_name = "OWNER/NAME")]
+        repo: Option<String>,
+
+        /// Name of the check run, as branch protection rules require it
+        #[arg(long, default_value = "verify")]
+        name: String,
+
+        /// GitHub API URL, for GitHub Enterprise (default: $GITHUB_API_URL, then
+        /// https://api.github.com)
+        #[arg(long, value_name = "URL")]
+        api_url: Option<String>,
+    },
+
+    /// Find the commit whose changes invalidated a check
+    Bisect {
+        /// Check to investigate
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Maximum number of commits to search back through
+        #[arg(long, default_value_t = 100)]
+        max_depth: usize,
+    },
+
+    /// Show what has to run, in order, for a check to become verified
+    Why {
+        /// Check to investigate
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Describe a check: its description and owner, command, inputs, dependencies and status
+    Explain {
+        /// Check to describe
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Show how a check's files changed since it was verified
+    Diff {
+        /// Check to compare
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Command to show each file's diff with, given the verified and current copies
+        /// (default: diff -u)
+        #[arg(long, value_name = "COMMAND")]
+        tool: Option<String>,
+    },
+
+    /// Show which files tracked by git are covered by some check's cache_paths, by directory
+    Coverage {
+        /// Number of directory levels to group files by
+        #[arg(long, default_value_t = 1)]
+        depth: usize,
+    },
+
+    /// Report config problems and large files that slow down hashing
+    Doctor {},
+
+    /// Check that the audit log (`audit_log` in verify.yaml) hasn't been tampered with
+    Audit {},
+
+    /// Show recent runs and their messages
+    History {
+        /// Maximum number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Show how often checks were skipped as cached, and the time that saved
+    Stats {},
+
+    /// Run the checks affected by the given files, for hook frameworks like pre-commit
+    HookRun {
+        /// Git hook being run
+        #[arg(long, value_enum, default_value_t = Hook::PreCommit)]
+        hook: Hook,
+
+        /// Changed files, relative to the current directory
+        #[arg(long, num_args = 0.., value_name = "FILE")]
+        files: Vec<String>,
+    },
+
+    /// Serve status, run, explain and watchEvents over JSON-RPC for editors and agents
+    Serve {
+        /// Listen on this Unix socket instead of stdin/stdout
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+
+    /// Run checks, then re-run the ones that become unverified whenever files change
+    Watch {
+        /// Only watch these checks (their stale dependencies still run first)
+        #[arg(value_name = "NAME")]
+        names: Vec<String>,
+
+        /// Milliseconds files must be left unchanged before checks re-run, so a burst of
+        /// saves runs them once
+        #[arg(long, value_name = "MS", default_value_t = 300)]
+        debounce: u64,
+    },
+
+    /// Record a check as verified without running it (needs allow_mark_verified)
+    MarkVerified {
+        /// Check to mark verified
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Why the check counts as verified (e.g. "passed manual QA"), recorded with it
+        #[arg(long, value_name = "NOTE")]
+        because: String,
+    },
 
     /// Re-sign HEAD commit with fresh verification trailer
     Resign {},
+
+    /// Update verify to the latest release
+    SelfUpdate {},
+
+    /// Export or import cached verification state
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
+    /// Save or restore the verification state of the project and its subprojects
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+
+    /// Archive the files a check's hash covers into .verify/captures/ to reproduce it later
+    Capture {
+        /// Check to capture, or with --restore, a capture file or a check (its latest capture)
+        #[arg(value_name = "CHECK")]
+        name: String,
+
+        /// Extract a capture into a new temporary directory and print its path
+        #[arg(long)]
+        restore: bool,
+    },
+
+    /// Read or edit verify.yaml, keeping its comments and layout
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Package all verify.lock files into a bundle (e.g. bundle.tar.zst)
+    Export {
+        /// Path of the bundle to write
+        file: PathBuf,
+
+        /// Also package the files under each check's declared artifacts
+        #[arg(long)]
+        artifacts: bool,
+    },
+
+    /// Seed verify.lock files from a bundle, keeping only checks that match current files
+    Import {
+        /// Path of the bundle to read
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print a top-level setting (KEY) or a field of a check (CHECK.FIELD)
+    Get {
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
+
+    /// Set a top-level setting (KEY) or a field of a check (CHECK.FIELD)
+    Set {
+        #[arg(value_name = "KEY")]
+        key: String,
+
+        /// true/false, a number, null and [a, b] lists are written as given; anything
+        /// else as a string
+        #[arg(value_name = "VALUE", allow_hyphen_values = true)]
+        value: String,
+    },
+
+    /// Append a check to the verifications list
+    AddCheck {
+        /// Name of the check
+        #[arg(long)]
+        name: String,
+
+        /// Command to run (omit for an aggregate check)
+        #[arg(long)]
+        command: Option<String>,
+
+        /// Glob pattern for files the check depends on (repeatable)
+        #[arg(long = "cache-path", value_name = "PATTERN")]
+        cache_paths: Vec<String>,
+
+        /// Check that must pass first (repeatable)
+        #[arg(long, value_name = "CHECK")]
+        depends_on: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotCommands {
+    /// Save all verify.lock files as a named snapshot in .verify/snapshots/
+    Save {
+        /// Snapshot name
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Replace all verify.lock files with a saved snapshot
+    Restore {
+        /// Snapshot name
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Hook {
+    PreCommit,
+    PrePush,
 }
 
 impl Default for Commands {
@@ -99,7 +495,21 @@ impl Default for Commands {
         Commands::Run {
             names: vec![],
             force: false,
+            force_stale_deps: false,
+            until: None,
+            resume: false,
             stage: false,
+            staged: false,
+            no_cache_write: false,
+            profile: None,
+            passthrough_exit_code: false,
+            confirm: false,
+            explain_cache: false,
+            show_output: false,
+            message: None,
+            json_file: None,
+            workspace: None,
+            root: false,
         }
     }
 }
@@ -1,11 +1,16 @@
+use crate::configerror::{Issue, Location, ValidationErrors};
+use crate::hasher::{GlobOptions, HashInputs, HashResult, compute_check_hash, remembered};
+use crate::locksig::Signing;
+use crate::normalize::Normalizer;
+use crate::trailer::{DEFAULT_TRAILER_HASH_LENGTH, MIN_TRAILER_HASH_LENGTH};
 use anyhow::{Context, Result};
 use blake3::Hasher;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Pattern for extracting a metadata value from command output
+/// Pattern for extracting a metadata value from command output (or a file it writes)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum MetadataPattern {
@@ -13,15 +18,461 @@ pub enum MetadataPattern {
     WithReplacement(String, String),
     /// Simple pattern - extracts first capture group
     Simple(String),
+    /// Pattern with options - { pattern | json_path, replacement, precision, file }
+    Detailed(DetailedMetadataPattern),
+}
+
+/// A check's shell command: one for every platform, or a variant per OS
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Command {
+    /// The same command everywhere
+    Shell(String),
+    /// { macos, linux, windows, default }, picked by the OS verify runs on
+    PerPlatform(PlatformCommands),
+}
+
+/// Command variants by OS, with `default` for the platforms that aren't listed
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformCommands {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linux: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl PlatformCommands {
+    fn variants(&self) -> [(&'static str, &Option<String>); 4] {
+        [
+            ("macos", &self.macos),
+            ("linux", &self.linux),
+            ("windows", &self.windows),
+            ("default", &self.default),
+        ]
+    }
+}
+
+impl Command {
+    /// The command to run on `os` (as in `std::env::consts::OS`), if there is one
+    pub fn for_os(&self, os: &str) -> Option<&str> {
+        match self {
+            Command::Shell(command) => Some(command),
+            Command::PerPlatform(commands) => commands
+                .variants()
+                .into_iter()
+                .find(|(name, _)| *name == os)
+                .and_then(|(_, command)| command.as_deref())
+                .or(commands.default.as_deref()),
+        }
+    }
+
+    /// Apply `f` to every variant, as matrix expansion does
+    fn try_map(&self, f: impl Fn(&str) -> Result<String>) -> Result<Command> {
+        let map = |command: &Option<String>| command.as_deref().map(&f).transpose();
+        Ok(match self {
+            Command::Shell(command) => Command::Shell(f(command)?),
+            Command::PerPlatform(commands) => Command::PerPlatform(PlatformCommands {
+                macos: map(&commands.macos)?,
+                linux: map(&commands.linux)?,
+                windows: map(&commands.windows)?,
+                default: map(&commands.default)?,
+            }),
+        })
+    }
+}
+
+/// The command for this platform, empty for checks without one (see
+/// `Verification::is_supported`)
+impl std::ops::Deref for Command {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.for_os(std::env::consts::OS).unwrap_or_default()
+    }
+}
+
+impl From<String> for Command {
+    fn from(command: String) -> Self {
+        Command::Shell(command)
+    }
+}
+
+impl From<&str> for Command {
+    fn from(command: &str) -> Self {
+        Command::Shell(command.to_string())
+    }
+}
+
+/// Metadata pattern written as a mapping, for options beyond pattern and replacement
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DetailedMetadataPattern {
+    /// Regex whose first capture group (or `replacement`) is the value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+
+    /// Dotted path to the value in JSON output (`total.lines.pct`, `suites.0.failures`),
+    /// instead of `pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_path: Option<String>,
+
+    /// Read the value from this file (relative to the project) once the command has run,
+    /// instead of from its output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+
+    /// Replacement with $1, $2... (defaults to the first capture group)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+
+    /// Round numeric values to this many decimal places (0 stores an integer), so
+    /// insignificant digits don't churn the lock file or show up as deltas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u32>,
+}
+
+impl MetadataPattern {
+    /// The regex the value is extracted with (none for `json_path` patterns)
+    pub fn regex(&self) -> Option<&str> {
+        match self {
+            MetadataPattern::Simple(pattern) | MetadataPattern::WithReplacement(pattern, _) => {
+                Some(pattern)
+            }
+            MetadataPattern::Detailed(detailed) => detailed.pattern.as_deref(),
+        }
+    }
+
+    /// Decimal places numeric values are rounded to, if configured
+    pub fn precision(&self) -> Option<u32> {
+        match self {
+            MetadataPattern::Detailed(detailed) => detailed.precision,
+            _ => None,
+        }
+    }
+
+    /// File the value is read from instead of the command output, if configured
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            MetadataPattern::Detailed(detailed) => detailed.file.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// How a metadata key is combined across all checks in a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Sum,
+    Max,
+    Min,
+    Avg,
+}
+
+/// What happens when a cache_paths pattern matches no files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyMatchPolicy {
+    /// Fail hashing the check, so a typo can't verify it against no files
+    Error,
+    /// Warn before `status`, `run` and in `doctor`
+    #[default]
+    Warn,
+    /// Expected, e.g. for generated files that may not exist yet
+    Allow,
+}
+
+/// When `verify run` prints a check's captured output under its status line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShowOutput {
+    /// Also when the check passes, e.g. for checks whose value is their report
+    Always,
+    /// Only when it fails (the default)
+    Failure,
+    /// Never, not even the tail of a failed check's output
+    Never,
+}
+
+/// Order in which a per_file check processes its stale files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PerFileOrder {
+    /// Alphabetical by path
+    #[default]
+    Alpha,
+    /// Most recently modified first
+    Recent,
+    /// Files that failed in the previous run first, then alphabetical
+    FailedFirst,
+    /// Smallest first
+    Size,
+}
+
+/// What happens to files larger than a check's `max_file_size`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LargeFilePolicy {
+    /// Leave them out of the hash, with a warning
+    #[default]
+    Skip,
+    /// Hash their first `max_file_size` bytes and their size
+    Truncate,
+}
+
+/// Deserialize a file size given in bytes or as a string with a unit
+fn deserialize_file_size<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_file_size(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a size like "2GB", "500 KB" or "1024" into bytes (1KB = 1024 bytes)
+fn parse_file_size(text: &str) -> std::result::Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" => 1 << 10,
+        "MB" | "M" => 1 << 20,
+        "GB" | "G" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "invalid file size '{}': unknown unit '{}'",
+                text, unit
+            ));
+        }
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid file size '{}'", text))
+}
+
+/// Longest metadata value (in bytes) stored when neither the check nor the config sets
+/// `max_metadata_length`
+pub const DEFAULT_MAX_METADATA_LENGTH: usize = 1024;
+
+/// Hex digits kept of each `config_components` hash, enough to tell edits apart
+const COMPONENT_HASH_LENGTH: usize = 16;
+
+/// Directory next to a config file whose `*.yaml` fragments add to its verifications
+pub const FRAGMENTS_DIR: &str = "verify.d";
+
+/// A file in verify.d, owned separately from verify.yaml (e.g. one per team)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fragment {
+    #[serde(default)]
+    verifications: Vec<VerificationItem>,
+}
+
+/// The fragments merged into a config file, in filename order
+pub fn fragment_paths(config_path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = config_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(FRAGMENTS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read config fragments: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "yaml") && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
 }
 
 /// Root configuration structure parsed from verify.yaml
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Config {
     pub verifications: Vec<VerificationItem>,
+
+    /// Metadata keys to aggregate across all checks (including subprojects) in a run
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata_totals: BTreeMap<String, Aggregation>,
+
+    /// How `verify sign` and `verify resign` write the Verified trailer
+    #[serde(default)]
+    pub trailer: TrailerFormat,
+
+    /// Hex digits of each hash written to the Verified trailer (16 if unset). Trailers
+    /// written with other lengths are still read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trailer_hash_length: Option<usize>,
+
+    /// Checks included in the Verified trailer (all checks if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sign_checks: Vec<String>,
+
+    /// Give aggregate checks a derived hash (of their dependencies' hashes) in the trailer,
+    /// so a single entry like `all` can be checked without resolving dependencies
+    #[serde(default)]
+    pub hash_aggregates: bool,
+
+    /// Allow `verify mark-verified` to record checks as verified without running them
+    #[serde(default)]
+    pub allow_mark_verified: bool,
+
+    /// Record who ran each check (git user and hostname) in verify.lock and the run history
+    #[serde(default)]
+    pub record_user: bool,
+
+    /// Directory to keep `.verify/` state in instead of each project (relative to this
+    /// file). `VERIFY_CACHE_DIR` overrides it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Sign verify.lock files with the secret in `VERIFY_LOCK_SECRET` and only trust
+    /// signed ones, so results can't be added by editing the lock file by hand
+    #[serde(default)]
+    pub sign_lock: bool,
+
+    /// Named sets of checks for `verify run --profile`, each listing tags and check names
+    /// (an empty list selects every check)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Vec<String>>,
+
+    /// Profile a bare `verify run` uses instead of running every check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+
+    /// Settings for runs from git hooks
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
+    /// Run each git submodule that has its own verify.yaml as a subproject, without
+    /// listing it in `verifications`
+    #[serde(default)]
+    pub submodule_subprojects: bool,
+
+    /// Default `tracked_only` for checks that don't set it
+    #[serde(default)]
+    pub tracked_only: bool,
+
+    /// Default `on_empty_match` for checks that don't set it
+    #[serde(default)]
+    pub on_empty_match: EmptyMatchPolicy,
+
+    /// Default `max_metadata_length` for checks that don't set it (1024 if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_metadata_length: Option<usize>,
+
+    /// Append every check execution to a hash-chained audit log: `true` for
+    /// `.verify/audit.log`, or a path (relative to this file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<AuditLogSetting>,
+
+    /// Where to send an event as each check's command finishes, for dashboards that
+    /// show progress while a run is still going
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventSink>,
+
+    /// Record the verify version in each cache entry and treat entries written by an
+    /// older version as unverified, in case a release changed how files are hashed
+    #[serde(default)]
+    pub invalidate_on_tool_upgrade: bool,
+
+    /// Expand `${VAR}` and `${VAR:-default}` in commands, cache_paths and subproject paths
+    /// when the config is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_interpolation: Option<EnvInterpolation>,
+}
+
+/// How `${VAR}` references to environment variables are expanded at load time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvInterpolation {
+    /// Unset variables without a default expand to nothing, as in the shell
+    Expand,
+    /// Loading fails on an unset variable without a default
+    Strict,
+}
+
+/// Where `audit_log` writes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AuditLogSetting {
+    Enabled(bool),
+    Path(PathBuf),
+}
+
+/// A destination for check events: a URL each event is POSTed to, or a shell command
+/// that reads it on stdin
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventSink {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// Settings of the root config that apply to every project a command touches, subprojects
+/// included (`Config::settings`). A loaded `CacheState` carries them, and the caches of
+/// subprojects get their parent's.
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// The root project, which subproject checks are named relative to in the audit log
+    /// and events, and which event commands run in
+    pub root: PathBuf,
+    /// How verify.lock files are signed and checked (`sign_lock`)
+    pub signing: Signing,
+    pub invalidate_on_tool_upgrade: bool,
+    /// The audit log to append check executions to
+    pub audit_log: Option<PathBuf>,
+    pub events: Vec<EventSink>,
+}
+
+/// How verify behaves when run from a git hook
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HooksConfig {
+    /// Profile to run in hooks instead of `default_profile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+}
+
+/// Format of the Verified commit trailer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrailerFormat {
+    /// One `name:hash` pair per check
+    #[default]
+    Full,
+    /// A single root hash of `verify.manifest.json`, which is committed alongside and
+    /// holds the per-check hashes
+    Compact,
 }
 
 /// Either a verification check or a subproject reference
+// Configs hold a handful of items, so boxing checks wouldn't save anything worthwhile
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum VerificationItem {
@@ -48,18 +499,50 @@ pub struct Subproject {
 
     /// Path to directory containing verify.yaml (relative to current config)
     pub path: PathBuf,
+
+    /// Namespace for the subproject's checks in trailers and flattened output, as in
+    /// `api/build` (default: the subproject's path)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+impl Subproject {
+    /// The namespace its checks are named under: `prefix`, or the path with `/` separators
+    pub fn prefix(&self) -> String {
+        match self.prefix {
+            Some(ref prefix) => prefix.clone(),
+            None => crate::bundle::bundle_key(&self.path)
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+
+    /// The name of one of its checks outside the subproject, e.g. `backend/build`
+    pub fn qualify(&self, check_name: &str) -> String {
+        format!("{}/{}", self.prefix(), check_name)
+    }
 }
 
 /// A single verification check definition
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Verification {
     /// Unique identifier for this check
     pub name: String,
 
-    /// Command to execute (shell command)
+    /// What the check does, shown by `verify explain`, `verify list` and
+    /// `status --detailed`. Not part of the config hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Who to ask about the check (a team, person or chat channel). Not part of the
+    /// config hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// Command to execute (shell command), or one per platform
     /// If None, this is an aggregate check whose status is derived from its dependencies
     #[serde(default)]
-    pub command: Option<String>,
+    pub command: Option<Command>,
 
     /// Glob patterns for files that affect this check's cache validity
     /// If empty or not specified, the check always runs (no verify-level caching)
@@ -75,102 +558,853 @@ pub struct Verification {
     pub timeout_secs: Option<u64>,
 
     /// Metadata extraction patterns
-    /// Keys are metadata field names, values are regex patterns or [pattern, replacement] arrays
+    /// Keys are metadata field names, values are regex patterns, [pattern, replacement] arrays
+    /// or { pattern, replacement, precision } mappings
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub metadata: HashMap<String, MetadataPattern>,
 
+    /// Metadata values longer than this many bytes aren't stored, so a pattern that
+    /// captures too much can't bloat verify.lock (defaults to the top-level
+    /// `max_metadata_length`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_metadata_length: Option<usize>,
+
     /// Run command once per stale file (sets VERIFY_FILE env var)
     #[serde(default)]
     pub per_file: bool,
+
+    /// Order in which stale files are processed in per_file mode
+    #[serde(default)]
+    pub per_file_order: PerFileOrder,
+
+    /// Match cache_paths patterns regardless of letter case
+    #[serde(default)]
+    pub case_insensitive: bool,
+
+    /// Arbitrary string hashed into the check's config, so changing it invalidates
+    /// the cached result for everyone without touching the command or paths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_salt: Option<String>,
+
+    /// Let cache_paths match verify's own files (`verify.lock`, `verify.yaml` and subprojects'
+    /// `.verify/`), which are skipped by default since writing the lock would otherwise
+    /// invalidate the check
+    #[serde(default)]
+    pub track_verify_files: bool,
+
+    /// Only let cache_paths match files tracked by git, so untracked scratch files can't
+    /// make the check stale (defaults to the top-level `tracked_only`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracked_only: Option<bool>,
+
+    /// What happens when one of the cache_paths patterns matches no files: error, warn
+    /// or allow (defaults to the top-level `on_empty_match`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_empty_match: Option<EmptyMatchPolicy>,
+
+    /// Files larger than this many bytes (or a size like "500KB", "100MB", "2GB") aren't
+    /// hashed in full; `large_files` says what happens to them instead
+    #[serde(
+        default,
+        deserialize_with = "deserialize_file_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_file_size: Option<u64>,
+
+    /// What to do with files over `max_file_size` (default: skip)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_files: Option<LargeFilePolicy>,
+
+    /// Steps rewriting each file's contents before it's hashed, in order:
+    /// `strip-trailing-whitespace`, `strip-blank-lines`, `strip-comments:LANG` or
+    /// `{ command }`, so changes they remove (like edited comments) don't invalidate the check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normalize: Vec<Normalizer>,
+
+    /// Regex the command's output must match for the check to pass, even when it exits 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_output: Option<String>,
+
+    /// Regex the command's output must not match, e.g. "error:" lines from a linter
+    /// that exits 0 anyway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_not_output: Option<String>,
+
+    /// Keep only the last this many bytes (or a size like "64KB") of a failed command's
+    /// output for display and JSON results
+    #[serde(
+        default,
+        deserialize_with = "deserialize_file_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_output: Option<u64>,
+
+    /// When this is the only check whose command failed, `verify run` exits with the
+    /// command's exit code instead of 1
+    #[serde(default)]
+    pub propagate_exit_code: bool,
+
+    /// Don't warn when the command changes files in its own cache_paths (e.g. a
+    /// formatter that rewrites sources), which leaves the check stale after every run
+    #[serde(default)]
+    pub allow_self_modification: bool,
+
+    /// Fail the check instead of warning when the command changes files in its own
+    /// cache_paths
+    #[serde(default)]
+    pub fail_on_self_modification: bool,
+
+    /// Always stream the command's output while it runs, as if `--verbose` were passed
+    #[serde(default)]
+    pub verbose: bool,
+
+    /// Never stream the command's output, even with `--verbose`, and only show the last
+    /// lines of it when the check fails
+    #[serde(default)]
+    pub quiet: bool,
+
+    /// When to print the command's captured output after its status line (default:
+    /// `failure`, or `always` with `run --show-output`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_output: Option<ShowOutput>,
+
+    /// Run the command on this SSH host instead of locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runs_on: Option<String>,
+
+    /// Paths the command produces on the `runs_on` host, copied back after it runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+
+    /// Labels that `profiles` can select the check by
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Git submodules whose checked-out commit is hashed along with cache_paths, so the
+    /// check is stale when a submodule moves to another commit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<PathBuf>,
+
+    /// Expand this check into one check per combination of values, e.g.
+    /// `{ target: [ios, macos] }` gives `build-ios` and `build-macos`.
+    /// `{{target}}` in command, cache_paths and depends_on is replaced with the value.
+    #[serde(default, skip_serializing_if = "Matrix::is_empty")]
+    pub matrix: Matrix,
+}
+
+/// Matrix variables for a check template, in the order they were written
+/// (which determines the order of values in expanded check names)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Matrix(pub Vec<(String, Vec<String>)>);
+
+impl Matrix {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Every combination of values, with the last variable varying fastest
+    pub fn combinations(&self) -> Vec<Vec<(&str, &str)>> {
+        let mut combinations = vec![Vec::new()];
+        for (key, values) in &self.0 {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut next = combination.clone();
+                        next.push((key.as_str(), value.as_str()));
+                        next
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+}
+
+impl<'de> Deserialize<'de> for Matrix {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        use serde_yml::Value;
+
+        let scalar = |value: &Value| match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        };
+
+        let mapping = serde_yml::Mapping::deserialize(deserializer)?;
+        let mut variables = Vec::new();
+        for (key, values) in &mapping {
+            let key = key
+                .as_str()
+                .ok_or_else(|| D::Error::custom("matrix variable names must be strings"))?;
+            let values = values
+                .as_sequence()
+                .and_then(|seq| seq.iter().map(scalar).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| {
+                    D::Error::custom(format!(
+                        "matrix variable '{}' must be a list of values",
+                        key
+                    ))
+                })?;
+            variables.push((key.to_string(), values));
+        }
+        Ok(Matrix(variables))
+    }
+}
+
+impl Serialize for Matrix {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, values) in &self.0 {
+            map.serialize_entry(key, values)?;
+        }
+        map.end()
+    }
 }
 
 impl Verification {
+    /// Options for matching this check's cache_paths
+    pub fn glob_options(&self) -> GlobOptions {
+        GlobOptions {
+            case_insensitive: self.case_insensitive,
+            track_verify_files: self.track_verify_files,
+            max_file_size: self.max_file_size,
+            truncate_large_files: self.large_files == Some(LargeFilePolicy::Truncate),
+            tracked_only: self.tracked_only == Some(true),
+            require_matches: self.on_empty_match == Some(EmptyMatchPolicy::Error),
+        }
+    }
+
+    /// Whether the check can run on this platform: false when its command has variants
+    /// per OS but none for this one, and no default
+    pub fn is_supported(&self) -> bool {
+        self.command
+            .as_ref()
+            .is_none_or(|command| command.for_os(std::env::consts::OS).is_some())
+    }
+
+    /// Hash this check's inputs: the files its cache_paths match and the commits of its
+    /// submodules
+    /// Remembered for the rest of the invocation (`hasher::remembered`), until a command runs.
+    pub fn compute_hash(&self, project_root: &Path) -> Result<HashResult> {
+        let inputs = HashInputs {
+            project_root: project_root.to_path_buf(),
+            cache_paths: self.cache_paths.clone(),
+            options: self.glob_options(),
+            submodules: self.submodules.clone(),
+            normalize: self.normalize.clone(),
+        };
+        remembered(inputs, || {
+            let mut result = compute_check_hash(
+                project_root,
+                &self.cache_paths,
+                self.glob_options(),
+                &self.normalize,
+            )?;
+            if !self.submodules.is_empty() {
+                result.add_inputs(crate::submodule::commits(project_root, &self.submodules)?);
+            }
+            Ok(result)
+        })
+    }
+
     /// Compute a deterministic hash of this check's configuration.
     /// Used to detect when the check definition changes in verify.yaml.
     pub fn config_hash(&self) -> String {
         let mut hasher = Hasher::new();
+        for (_, bytes) in self.config_sections() {
+            hasher.update(&bytes);
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Short hashes of each part of the configuration `config_hash` covers (`command`,
+    /// `cache_paths`, `depends_on`, `timeout`, `per_file`, `metadata`, and the optional
+    /// settings that are only hashed when set), so a changed config hash can be explained
+    pub fn config_components(&self) -> BTreeMap<String, String> {
+        let mut components: BTreeMap<String, Hasher> = BTreeMap::new();
+        for (name, bytes) in self.config_sections() {
+            components
+                .entry(name.to_string())
+                .or_default()
+                .update(&bytes);
+        }
+        components
+            .into_iter()
+            .map(|(name, hasher)| {
+                let hash = hasher.finalize().to_hex();
+                (name, hash[..COMPONENT_HASH_LENGTH].to_string())
+            })
+            .collect()
+    }
+
+    /// The configuration `config_hash` covers, in hashing order, as (component, bytes)
+    fn config_sections(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let mut sections: Vec<(&'static str, Vec<u8>)> = Vec::new();
 
         // Hash command
-        hasher.update(b"command:");
+        let mut command = b"command:".to_vec();
         if let Some(ref cmd) = self.command {
-            hasher.update(cmd.as_bytes());
+            command.extend_from_slice(cmd.as_bytes());
+        }
+        command.extend_from_slice(b"\n");
+        sections.push(("command", command));
+
+        // Hash every platform's variant, so editing one invalidates the check everywhere
+        if let Some(Command::PerPlatform(ref commands)) = self.command {
+            let mut variants = b"platform_commands:".to_vec();
+            for (os, command) in commands.variants() {
+                if let Some(command) = command {
+                    variants.extend_from_slice(format!("{}={},", os, command).as_bytes());
+                }
+            }
+            variants.extend_from_slice(b"\n");
+            sections.push(("command", variants));
         }
-        hasher.update(b"\n");
 
         // Hash cache_paths (sorted for determinism)
-        hasher.update(b"cache_paths:");
         let mut sorted_paths = self.cache_paths.clone();
         sorted_paths.sort();
-        for path in &sorted_paths {
-            hasher.update(path.as_bytes());
-            hasher.update(b",");
-        }
-        hasher.update(b"\n");
+        sections.push(("cache_paths", list_section("cache_paths", &sorted_paths)));
+
+        // Hash depends_on (sorted for determinism) - rewiring dependencies changes semantics
+        let mut sorted_deps = self.depends_on.clone();
+        sorted_deps.sort();
+        sections.push(("depends_on", list_section("depends_on", &sorted_deps)));
 
         // Hash timeout
-        hasher.update(b"timeout:");
-        if let Some(timeout) = self.timeout_secs {
-            hasher.update(timeout.to_string().as_bytes());
-        }
-        hasher.update(b"\n");
+        let timeout = self.timeout_secs.map(|t| t.to_string()).unwrap_or_default();
+        sections.push(("timeout", format!("timeout:{}\n", timeout).into_bytes()));
 
         // Hash per_file flag
-        hasher.update(b"per_file:");
-        hasher.update(if self.per_file { b"true" } else { b"false" });
-        hasher.update(b"\n");
+        sections.push((
+            "per_file",
+            format!("per_file:{}\n", self.per_file).into_bytes(),
+        ));
+
+        // Hash case_insensitive flag (only when set, so existing hashes stay stable)
+        if self.case_insensitive {
+            sections.push(("case_insensitive", b"case_insensitive:true\n".to_vec()));
+        }
+
+        if self.track_verify_files {
+            sections.push(("track_verify_files", b"track_verify_files:true\n".to_vec()));
+        }
+
+        if self.tracked_only == Some(true) {
+            sections.push(("tracked_only", b"tracked_only:true\n".to_vec()));
+        }
+
+        // Hash the large file limit (only when set)
+        if let Some(max_file_size) = self.max_file_size {
+            let policy = self.large_files.unwrap_or_default();
+            let section = format!("max_file_size:{}:{:?}\n", max_file_size, policy);
+            sections.push(("max_file_size", section.into_bytes()));
+        }
+
+        // Hash normalize steps in order (only when set)
+        if !self.normalize.is_empty() {
+            let steps: Vec<String> = self.normalize.iter().map(Normalizer::describe).collect();
+            sections.push(("normalize", list_section("normalize", &steps)));
+        }
+
+        // Hash output expectations (only when set)
+        if let Some(ref pattern) = self.expect_output {
+            let section = format!("expect_output:{}\n", pattern);
+            sections.push(("expect_output", section.into_bytes()));
+        }
+        if let Some(ref pattern) = self.expect_not_output {
+            let section = format!("expect_not_output:{}\n", pattern);
+            sections.push(("expect_not_output", section.into_bytes()));
+        }
+
+        // Hash submodules (only when set)
+        if !self.submodules.is_empty() {
+            let paths: Vec<String> = self
+                .submodules
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            sections.push(("submodules", list_section("submodules", &paths)));
+        }
+
+        // Hash cache_salt (only when set)
+        if let Some(ref salt) = self.cache_salt {
+            sections.push(("cache_salt", format!("cache_salt:{}\n", salt).into_bytes()));
+        }
 
         // Hash metadata patterns (sorted keys for determinism)
-        hasher.update(b"metadata:");
+        let mut metadata = b"metadata:".to_vec();
         let mut sorted_keys: Vec<_> = self.metadata.keys().collect();
         sorted_keys.sort();
         for key in sorted_keys {
-            hasher.update(key.as_bytes());
-            hasher.update(b"=");
+            metadata.extend_from_slice(key.as_bytes());
+            metadata.extend_from_slice(b"=");
             match &self.metadata[key] {
                 MetadataPattern::Simple(pattern) => {
-                    hasher.update(pattern.as_bytes());
+                    metadata.extend_from_slice(pattern.as_bytes());
                 }
                 MetadataPattern::WithReplacement(pattern, replacement) => {
-                    hasher.update(pattern.as_bytes());
-                    hasher.update(b"|");
-                    hasher.update(replacement.as_bytes());
+                    metadata.extend_from_slice(pattern.as_bytes());
+                    metadata.extend_from_slice(b"|");
+                    metadata.extend_from_slice(replacement.as_bytes());
+                }
+                MetadataPattern::Detailed(detailed) => {
+                    if let Some(ref pattern) = detailed.pattern {
+                        metadata.extend_from_slice(pattern.as_bytes());
+                    }
+                    if let Some(ref json_path) = detailed.json_path {
+                        metadata.extend_from_slice(b"json:");
+                        metadata.extend_from_slice(json_path.as_bytes());
+                    }
+                    if let Some(ref file) = detailed.file {
+                        metadata.extend_from_slice(b"@");
+                        metadata.extend_from_slice(file.to_string_lossy().as_bytes());
+                    }
+                    if let Some(ref replacement) = detailed.replacement {
+                        metadata.extend_from_slice(b"|");
+                        metadata.extend_from_slice(replacement.as_bytes());
+                    }
+                    if let Some(precision) = detailed.precision {
+                        metadata.extend_from_slice(b"~");
+                        metadata.extend_from_slice(precision.to_string().as_bytes());
+                    }
                 }
             }
-            hasher.update(b",");
+            metadata.extend_from_slice(b",");
         }
+        sections.push(("metadata", metadata));
 
-        hasher.finalize().to_hex().to_string()
+        sections
     }
 }
 
+/// A `config_hash` section listing values: `key:a,b,\n`
+fn list_section(key: &str, values: &[String]) -> Vec<u8> {
+    let mut section = format!("{}:", key).into_bytes();
+    for value in values {
+        section.extend_from_slice(value.as_bytes());
+        section.extend_from_slice(b",");
+    }
+    section.extend_from_slice(b"\n");
+    section
+}
+
 impl Config {
     /// Load configuration from a YAML file
     pub fn load(path: &Path) -> Result<Self> {
         Self::load_with_base(path, path.parent().unwrap_or(Path::new(".")))
     }
 
+    /// The config of the outermost project that includes the one at `path` as a
+    /// subproject, directly or through other subprojects, looking in parent directories up
+    /// to the top of the git repository. None when no parent config includes it.
+    pub fn find_root(path: &Path) -> Result<Option<PathBuf>> {
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut current = dir
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+        let mut root = None;
+        for ancestor in current.clone().ancestors().skip(1) {
+            let config_path = ancestor.join("verify.yaml");
+            if config_path.is_file() {
+                let config = Config::load(&config_path)?;
+                let includes_current = config.subprojects().iter().any(|subproject| {
+                    ancestor
+                        .join(&subproject.path)
+                        .canonicalize()
+                        .is_ok_and(|dir| dir == current)
+                });
+                if includes_current {
+                    current = ancestor.to_path_buf();
+                    root = Some(config_path);
+                }
+            }
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        Ok(root)
+    }
+
+    /// Where `cache_dir` relocates state to, resolved against `base_path` (the directory of
+    /// this config)
+    pub fn cache_dir(&self, base_path: &Path) -> Option<PathBuf> {
+        self.cache_dir.as_ref().map(|dir| base_path.join(dir))
+    }
+
+    /// The settings this config, as the root config, gives every project a command
+    /// touches. Resolve them after state is relocated, since the default audit log is
+    /// in the root's state directory.
+    pub fn settings(&self, project_root: &Path) -> Settings {
+        let audit_log = match &self.audit_log {
+            Some(AuditLogSetting::Enabled(true)) => {
+                Some(crate::statedir::state_dir(project_root).join(crate::audit::AUDIT_FILE))
+            }
+            Some(AuditLogSetting::Enabled(false)) | None => None,
+            Some(AuditLogSetting::Path(log)) => Some(project_root.join(log)),
+        };
+        Settings {
+            root: project_root.to_path_buf(),
+            signing: Signing::new(
+                self.sign_lock,
+                std::env::var(crate::locksig::SECRET_ENV).ok().as_deref(),
+            ),
+            invalidate_on_tool_upgrade: self.invalidate_on_tool_upgrade,
+            audit_log,
+            events: self.events.clone(),
+        }
+    }
+
     /// Load configuration with a specific base path for resolving subproject paths
     pub fn load_with_base(path: &Path, base_path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-        let config: Config = serde_yml::from_str(&content)
+        let mut sources = vec![(path.to_path_buf(), content)];
+        for fragment_path in fragment_paths(path)? {
+            let fragment_content = fs::read_to_string(&fragment_path).with_context(|| {
+                format!(
+                    "Failed to read config fragment: {}",
+                    fragment_path.display()
+                )
+            })?;
+            sources.push((fragment_path, fragment_content));
+        }
+
+        // Reuse the config loaded by an earlier command if none of its files changed (the
+        // key covers the verify version, so its version pin was already checked)
+        let cache_key = crate::configcache::key(path, base_path, &sources);
+        if let Some(config) = crate::configcache::load(base_path, &cache_key) {
+            return Ok(config);
+        }
+
+        // Check the version pin first, so a config using newer syntax tells the user to
+        // upgrade verify rather than failing to parse
+        #[derive(Deserialize)]
+        struct VersionPin {
+            required_version: Option<String>,
+        }
+        if let Ok(VersionPin {
+            required_version: Some(required),
+        }) = serde_yml::from_str(&sources[0].1)
+        {
+            crate::update::check_required_version(&required, path)?;
+        }
+
+        let mut config: Config = serde_yml::from_str(&sources[0].1)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
 
-        config.validate(base_path)?;
+        // Checks from verify.d fragments follow the config's own, in filename order
+        for (fragment_path, fragment_content) in &sources[1..] {
+            let fragment: Fragment = serde_yml::from_str(fragment_content).with_context(|| {
+                format!(
+                    "Failed to parse config fragment: {} (fragments can only add verifications)",
+                    fragment_path.display()
+                )
+            })?;
+            config.verifications.extend(fragment.verifications);
+        }
+
+        if config.submodule_subprojects {
+            config.add_submodule_subprojects(base_path);
+        }
+        if let Some(mode) = config.env_interpolation {
+            config.interpolate_env(mode)?;
+        }
+        config.expand_matrices()?;
+        config.apply_check_defaults();
+        config.infer_artifact_dependencies();
+        if let Err(errors) = config.validate(base_path) {
+            anyhow::bail!("{}", errors.render(&sources));
+        }
+        crate::configcache::store(base_path, &cache_key, &config);
         Ok(config)
     }
 
-    /// Validate the configuration
-    fn validate(&self, base_path: &Path) -> Result<()> {
+    /// Expand environment variables in the commands and cache_paths of checks (matrix
+    /// templates included), in subproject paths, and in `cache_dir` and `audit_log`
+    fn interpolate_env(&mut self, mode: EnvInterpolation) -> Result<()> {
+        let strict = mode == EnvInterpolation::Strict;
+        let expand_path = |path: &Path, field: &str| {
+            expand_env(&path.to_string_lossy(), strict)
+                .map(PathBuf::from)
+                .with_context(|| format!("in {}", field))
+        };
+        if let Some(dir) = &self.cache_dir {
+            self.cache_dir = Some(expand_path(dir, "cache_dir")?);
+        }
+        if let Some(AuditLogSetting::Path(log)) = &self.audit_log {
+            self.audit_log = Some(AuditLogSetting::Path(expand_path(log, "audit_log")?));
+        }
+        for item in &mut self.verifications {
+            match item {
+                VerificationItem::Verification(v) => {
+                    let context = |field: &str| format!("in the {} of check '{}'", field, v.name);
+                    if let Some(command) = &v.command {
+                        let command = command
+                            .try_map(|text| expand_env(text, strict))
+                            .with_context(|| context("command"))?;
+                        v.command = Some(command);
+                    }
+                    for path in &mut v.cache_paths {
+                        *path = expand_env(path, strict).with_context(|| context("cache_paths"))?;
+                    }
+                }
+                VerificationItem::Subproject(subproject) => {
+                    let path = expand_env(&subproject.path.to_string_lossy(), strict)
+                        .with_context(|| {
+                            format!("in the path of subproject '{}'", subproject.name)
+                        })?;
+                    subproject.path = PathBuf::from(path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill in per-check settings left to a top-level default
+    fn apply_check_defaults(&mut self) {
+        for item in &mut self.verifications {
+            if let VerificationItem::Verification(v) = item {
+                v.tracked_only.get_or_insert(self.tracked_only);
+                v.on_empty_match.get_or_insert(self.on_empty_match);
+                v.max_metadata_length.get_or_insert(
+                    self.max_metadata_length
+                        .unwrap_or(DEFAULT_MAX_METADATA_LENGTH),
+                );
+            }
+        }
+    }
+
+    /// Add submodules with a verify.yaml that aren't already subprojects, named by their path
+    fn add_submodule_subprojects(&mut self, base_path: &Path) {
+        let listed: HashSet<String> = self
+            .subprojects()
+            .iter()
+            .map(|subproject| crate::bundle::bundle_key(&subproject.path))
+            .collect();
+        for submodule in crate::submodule::with_configs(base_path) {
+            let path = PathBuf::from(&submodule.path);
+            if listed.contains(&crate::bundle::bundle_key(&path)) {
+                continue;
+            }
+            self.verifications
+                .push(VerificationItem::Subproject(Subproject {
+                    name: submodule.path,
+                    path,
+                    prefix: None,
+                }));
+        }
+    }
+
+    /// Replace checks that have a `matrix` with one concrete check per combination.
+    /// Dependencies on a matrix check's own name are rewired to all of its expansions.
+    fn expand_matrices(&mut self) -> Result<()> {
+        let mut expansions: HashMap<String, Vec<String>> = HashMap::new();
+        let mut expanded_items = Vec::new();
+
+        for item in std::mem::take(&mut self.verifications) {
+            let template = match item {
+                VerificationItem::Verification(v) if !v.matrix.is_empty() => v,
+                item => {
+                    expanded_items.push(item);
+                    continue;
+                }
+            };
+
+            if let Some((key, _)) = template
+                .matrix
+                .0
+                .iter()
+                .find(|(_, values)| values.is_empty())
+            {
+                anyhow::bail!(
+                    "Matrix variable '{}' of check '{}' has no values",
+                    key,
+                    template.name
+                );
+            }
+
+            let mut names = Vec::new();
+            for combination in template.matrix.combinations() {
+                let check = expand_template(&template, &combination)?;
+                names.push(check.name.clone());
+                expanded_items.push(VerificationItem::Verification(check));
+            }
+            expansions.insert(template.name.clone(), names);
+        }
+
+        for item in &mut expanded_items {
+            if let VerificationItem::Verification(v) = item {
+                v.depends_on = v
+                    .depends_on
+                    .iter()
+                    .flat_map(|dep| match expansions.get(dep) {
+                        Some(names) => names.clone(),
+                        None => vec![dep.clone()],
+                    })
+                    .collect();
+            }
+        }
+
+        self.verifications = expanded_items;
+        Ok(())
+    }
+
+    /// Make each check that reads another check's artifacts depend on it, so it can't run
+    /// before they are produced. Skipped when the producer already depends on the reader,
+    /// which `artifact_conflicts` reports instead.
+    fn infer_artifact_dependencies(&mut self) {
+        for (producer, consumer) in self.artifact_readers() {
+            if self.depends_on_transitively(&producer, &consumer) {
+                continue;
+            }
+            if let Some(VerificationItem::Verification(v)) = self
+                .verifications
+                .iter_mut()
+                .find(|item| item.name() == consumer)
+                && !v.depends_on.contains(&producer)
+            {
+                v.depends_on.push(producer);
+            }
+        }
+    }
+
+    /// (producer, reader) pairs where a check's cache_paths include artifacts that
+    /// another check declares
+    fn artifact_readers(&self) -> Vec<(String, String)> {
+        let checks = self.verifications_only();
+        let mut pairs = Vec::new();
+        for producer in &checks {
+            for reader in &checks {
+                if producer.name == reader.name {
+                    continue;
+                }
+                let reads = producer.artifacts.iter().any(|artifact| {
+                    reader
+                        .cache_paths
+                        .iter()
+                        .any(|pattern| reads_artifact(pattern, artifact, reader.glob_options()))
+                });
+                if reads {
+                    pairs.push((producer.name.clone(), reader.name.clone()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// (producer, reader) pairs where the reader can't run after the producer, because
+    /// the producer depends on the reader
+    pub fn artifact_conflicts(&self) -> Vec<(String, String)> {
+        self.artifact_readers()
+            .into_iter()
+            .filter(|(producer, reader)| !self.depends_on_transitively(reader, producer))
+            .collect()
+    }
+
+    /// Whether check `name` depends on `target`, directly or through other checks
+    fn depends_on_transitively(&self, name: &str, target: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            if !visited.insert(current) {
+                continue;
+            }
+            let Some(check) = self.get(current) else {
+                continue;
+            };
+            for dep in &check.depends_on {
+                if dep == target {
+                    return true;
+                }
+                pending.push(dep);
+            }
+        }
+        false
+    }
+
+    /// Signs of a config that has grown inconsistent: checks running the same command,
+    /// dependencies whose cache_paths already cover the dependent's, and aggregate checks
+    /// with nothing to aggregate. None of these are errors, so they're reported as warnings.
+    pub fn consistency_warnings(&self) -> Vec<String> {
+        let checks = self.verifications_only();
+        let mut warnings = Vec::new();
+
+        for (i, check) in checks.iter().enumerate() {
+            let Some(ref command) = check.command else {
+                if check.depends_on.is_empty() && check.cache_paths.is_empty() {
+                    warnings.push(format!(
+                        "{}: aggregate check (no command) has no dependencies, so it's always verified",
+                        check.name
+                    ));
+                }
+                continue;
+            };
+            if let Some(first) = checks[..i]
+                .iter()
+                .find(|other| other.command.as_deref().map(str::trim) == Some(command.trim()))
+            {
+                warnings.push(format!(
+                    "{}: runs the same command as '{}'",
+                    check.name, first.name
+                ));
+            }
+        }
+
+        for check in &checks {
+            if check.cache_paths.is_empty() {
+                continue;
+            }
+            let paths: HashSet<&String> = check.cache_paths.iter().collect();
+            for dep in &check.depends_on {
+                let Some(dependency) = self.get(dep) else {
+                    continue;
+                };
+                let dep_paths: HashSet<&String> = dependency.cache_paths.iter().collect();
+                if paths.len() < dep_paths.len() && paths.is_subset(&dep_paths) {
+                    warnings.push(format!(
+                        "{}: cache_paths are a strict subset of those of its dependency '{}', \
+                         so the dependency may be redundant",
+                        check.name, dep
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Validate the configuration, reporting every problem found
+    fn validate(&self, base_path: &Path) -> std::result::Result<(), ValidationErrors> {
+        let mut issues = Vec::new();
         let mut names = HashSet::new();
 
         // Check for duplicate names
+        let mut occurrences: HashMap<&str, usize> = HashMap::new();
         for item in &self.verifications {
             let name = item.name();
+            let occurrence = occurrences.entry(name).or_default();
             if !names.insert(name.to_string()) {
-                anyhow::bail!("Duplicate verification name: {}", name);
+                issues.push(Issue::new(
+                    format!("Duplicate verification name: {}", name),
+                    Location::Item {
+                        name: name.to_string(),
+                        occurrence: *occurrence,
+                        field: Some("name"),
+                        value: Some(name.to_string()),
+                    },
+                ));
             }
+            *occurrence += 1;
         }
 
         // Check that all dependencies exist (can depend on verifications OR subprojects)
@@ -178,18 +1412,267 @@ impl Config {
             if let VerificationItem::Verification(v) = item {
                 for dep in &v.depends_on {
                     if !names.contains(dep) {
-                        anyhow::bail!(
-                            "Verification '{}' depends on unknown check: {}",
-                            v.name,
-                            dep
-                        );
+                        issues.push(Issue::at_value(
+                            format!(
+                                "Verification '{}' depends on unknown check: {}{}",
+                                v.name,
+                                dep,
+                                self.unknown_dependency_hint(dep, &names, base_path)
+                            ),
+                            &v.name,
+                            "depends_on",
+                            dep,
+                        ));
                     }
                 }
 
                 // Check for self-dependencies
                 if v.depends_on.contains(&v.name) {
-                    anyhow::bail!("Verification '{}' cannot depend on itself", v.name);
+                    issues.push(Issue::at_value(
+                        format!("Verification '{}' cannot depend on itself", v.name),
+                        &v.name,
+                        "depends_on",
+                        &v.name,
+                    ));
+                }
+
+                if v.large_files.is_some() && v.max_file_size.is_none() {
+                    issues.push(Issue::at_item(
+                        format!(
+                            "Verification '{}' has large_files but no max_file_size",
+                            v.name
+                        ),
+                        &v.name,
+                        Some("large_files"),
+                    ));
+                }
+
+                for normalizer in &v.normalize {
+                    if let Some(problem) = normalizer.problem() {
+                        issues.push(Issue::at_item(
+                            format!("Verification '{}' normalize: {}", v.name, problem),
+                            &v.name,
+                            Some("normalize"),
+                        ));
+                    }
                 }
+
+                if v.verbose && v.quiet {
+                    issues.push(Issue::at_item(
+                        format!("Verification '{}' can't be both verbose and quiet", v.name),
+                        &v.name,
+                        Some("quiet"),
+                    ));
+                }
+
+                if v.allow_self_modification && v.fail_on_self_modification {
+                    issues.push(Issue::at_item(
+                        format!(
+                            "Verification '{}' can't both allow and fail on self-modification",
+                            v.name
+                        ),
+                        &v.name,
+                        Some("fail_on_self_modification"),
+                    ));
+                }
+
+                for (key, pattern) in &v.metadata {
+                    if let Some(regex) = pattern.regex()
+                        && let Err(e) = regex::Regex::new(regex)
+                    {
+                        issues.push(Issue::at_value(
+                            format!(
+                                "Verification '{}' metadata '{}' has an invalid pattern: {}",
+                                v.name, key, e
+                            ),
+                            &v.name,
+                            "metadata",
+                            key,
+                        ));
+                    }
+                    let MetadataPattern::Detailed(detailed) = pattern else {
+                        continue;
+                    };
+                    let problem = match (&detailed.pattern, &detailed.json_path) {
+                        (None, None) => Some("needs a pattern or json_path"),
+                        (Some(_), Some(_)) => Some("can't have both a pattern and a json_path"),
+                        (None, Some(_)) if detailed.replacement.is_some() => {
+                            Some("has a replacement but no pattern")
+                        }
+                        _ => None,
+                    };
+                    if let Some(problem) = problem {
+                        issues.push(Issue::at_value(
+                            format!("Verification '{}' metadata '{}' {}", v.name, key, problem),
+                            &v.name,
+                            "metadata",
+                            key,
+                        ));
+                    }
+                }
+
+                if !v.submodules.is_empty() && v.cache_paths.is_empty() {
+                    issues.push(Issue::at_item(
+                        format!(
+                            "Verification '{}' has submodules but no cache_paths, so it isn't tracked",
+                            v.name
+                        ),
+                        &v.name,
+                        Some("submodules"),
+                    ));
+                }
+
+                if !v.artifacts.is_empty() && v.runs_on.is_none() {
+                    issues.push(Issue::at_item(
+                        format!("Verification '{}' has artifacts but no runs_on", v.name),
+                        &v.name,
+                        Some("artifacts"),
+                    ));
+                }
+
+                for (field, pattern) in [
+                    ("expect_output", &v.expect_output),
+                    ("expect_not_output", &v.expect_not_output),
+                ] {
+                    if let Some(pattern) = pattern
+                        && let Err(e) = regex::Regex::new(pattern)
+                    {
+                        issues.push(Issue::at_item(
+                            format!("Verification '{}' has an invalid {}: {}", v.name, field, e),
+                            &v.name,
+                            Some(field),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(length) = self.trailer_hash_length
+            && !(MIN_TRAILER_HASH_LENGTH..=64).contains(&length)
+        {
+            issues.push(Issue::new(
+                format!(
+                    "trailer_hash_length must be between {} and 64",
+                    MIN_TRAILER_HASH_LENGTH
+                ),
+                Location::TopLevel {
+                    key: "trailer_hash_length",
+                    value: None,
+                },
+            ));
+        }
+
+        for name in &self.sign_checks {
+            if self.get(name).is_none() {
+                issues.push(Issue::new(
+                    format!(
+                        "sign_checks lists unknown check: {}{}",
+                        name,
+                        self.unknown_dependency_hint(name, &names, base_path)
+                    ),
+                    Location::TopLevel {
+                        key: "sign_checks",
+                        value: Some(name.clone()),
+                    },
+                ));
+            }
+        }
+
+        let tags: HashSet<&String> = self
+            .verifications_only()
+            .into_iter()
+            .flat_map(|v| &v.tags)
+            .collect();
+        for (profile, entries) in &self.profiles {
+            for entry in entries {
+                if !names.contains(entry) && !tags.contains(entry) {
+                    issues.push(Issue::new(
+                        format!(
+                            "Profile '{}' lists '{}', which is neither a check nor a tag",
+                            profile, entry
+                        ),
+                        Location::TopLevel {
+                            key: "profiles",
+                            value: None,
+                        },
+                    ));
+                }
+            }
+        }
+        for sink in &self.events {
+            if sink.url.is_some() == sink.command.is_some() {
+                issues.push(Issue::new(
+                    "Each entry of events needs either a url or a command".to_string(),
+                    Location::TopLevel {
+                        key: "events",
+                        value: None,
+                    },
+                ));
+            }
+        }
+        for (key, profile) in [
+            ("default_profile", &self.default_profile),
+            ("hooks", &self.hooks.default_profile),
+        ] {
+            if let Some(profile) = profile
+                && !self.profiles.contains_key(profile)
+            {
+                issues.push(Issue::new(
+                    format!("default_profile names unknown profile: {}", profile),
+                    Location::TopLevel { key, value: None },
+                ));
+            }
+        }
+
+        // Subproject checks are named `prefix/name` in trailers, so prefixes must be
+        // usable there and tell subprojects apart
+        let mut prefixes: HashMap<String, &str> = HashMap::new();
+        for subproject in self.subprojects() {
+            let prefix = subproject.prefix();
+            let valid = !prefix.is_empty()
+                && !prefix.starts_with('/')
+                && !prefix.ends_with('/')
+                && !prefix
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, ':' | ','));
+            if !valid {
+                issues.push(Issue::at_value(
+                    format!(
+                        "Subproject '{}' has an invalid prefix '{}': it can't be empty, contain ':', ',' or spaces, or start or end with '/'",
+                        subproject.name,
+                        prefix
+                    ),
+                    &subproject.name,
+                    "prefix",
+                    &prefix,
+                ));
+            } else if let Some(other) = prefixes.insert(prefix.clone(), &subproject.name) {
+                issues.push(Issue::at_item(
+                    format!(
+                        "Subprojects '{}' and '{}' both name their checks '{}/...'; set a different prefix on one of them",
+                        other,
+                        subproject.name,
+                        prefix
+                    ),
+                    &subproject.name,
+                    Some("prefix"),
+                ));
+            }
+        }
+        for check in self.verifications_only() {
+            if let Some((_, subproject)) = prefixes
+                .iter()
+                .find(|(prefix, _)| check.name.starts_with(&format!("{}/", prefix)))
+            {
+                issues.push(Issue::at_item(
+                    format!(
+                        "Verification '{}' has the same name as a check in subproject '{}'; rename it or set a different prefix on the subproject",
+                        check.name,
+                        subproject
+                    ),
+                    &check.name,
+                    None,
+                ));
             }
         }
 
@@ -199,16 +1682,83 @@ impl Config {
                 let subproject_dir = base_path.join(&s.path);
                 let subproject_config = subproject_dir.join("verify.yaml");
                 if !subproject_config.exists() {
-                    anyhow::bail!(
-                        "Subproject '{}' config not found: {}",
-                        s.name,
-                        subproject_config.display()
-                    );
+                    issues.push(Issue::at_value(
+                        format!(
+                            "Subproject '{}' config not found: {}",
+                            s.name,
+                            subproject_config.display()
+                        ),
+                        &s.name,
+                        "path",
+                        &s.path.to_string_lossy(),
+                    ));
+                } else if let (Ok(dir), Ok(own_dir)) =
+                    (subproject_dir.canonicalize(), base_path.canonicalize())
+                    && own_dir.starts_with(&dir)
+                {
+                    // Its checks would run this config again, which runs the subproject again
+                    issues.push(Issue::at_value(
+                        format!(
+                            "Subproject '{}' contains this config's directory, so it would include itself",
+                            s.name
+                        ),
+                        &s.name,
+                        "path",
+                        &s.path.to_string_lossy(),
+                    ));
                 }
             }
         }
 
-        Ok(())
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(issues))
+        }
+    }
+
+    /// Hex digits of each hash `verify sign` and `verify resign` write to the trailer
+    pub fn trailer_hash_length(&self) -> usize {
+        self.trailer_hash_length
+            .unwrap_or(DEFAULT_TRAILER_HASH_LENGTH)
+    }
+
+    /// The profile a run without check names uses: `hooks.default_profile` when run from
+    /// a git hook, otherwise `default_profile`
+    pub fn default_profile(&self, in_hook: bool) -> Option<&str> {
+        in_hook
+            .then_some(self.hooks.default_profile.as_deref())
+            .flatten()
+            .or(self.default_profile.as_deref())
+    }
+
+    /// Names of the checks and subprojects in a profile, in config order: those it lists
+    /// by name or that have one of the tags it lists
+    pub fn profile_checks(&self, profile: &str) -> Result<Vec<String>> {
+        let Some(entries) = self.profiles.get(profile) else {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow::bail!(
+                "Unknown profile: {} (profiles: {})",
+                profile,
+                if known.is_empty() {
+                    "none configured".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+        Ok(self
+            .verifications
+            .iter()
+            .filter(|item| {
+                entries.is_empty()
+                    || entries.iter().any(|entry| {
+                        entry == item.name()
+                            || matches!(item, VerificationItem::Verification(v) if v.tags.contains(entry))
+                    })
+            })
+            .map(|item| item.name().to_string())
+            .collect())
     }
 
     /// Get a verification by name (returns None for subprojects)
@@ -219,6 +1769,50 @@ impl Config {
         })
     }
 
+    /// Context for an unknown `depends_on` name: where it's defined if it's a check inside
+    /// a subproject, otherwise close matches among the names in this config
+    fn unknown_dependency_hint(
+        &self,
+        dep: &str,
+        names: &HashSet<String>,
+        base_path: &Path,
+    ) -> String {
+        for subproject in self.subprojects() {
+            let subproject_dir = base_path.join(&subproject.path);
+            let Ok(sub_config) =
+                Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)
+            else {
+                continue;
+            };
+            if sub_config.get(dep).is_some() {
+                return format!(
+                    "\n  '{}' is a check in subproject '{}'. Depend on '{}' to wait for all of its \
+                     checks (depending on a single subproject check, like '{}:{}', isn't supported yet)",
+                    dep, subproject.name, subproject.name, subproject.name, dep
+                );
+            }
+        }
+
+        let mut suggestions: Vec<(usize, &String)> = names
+            .iter()
+            .map(|name| (edit_distance(dep, name), name))
+            .filter(|(distance, name)| *distance <= (name.chars().count() / 3).max(1))
+            .collect();
+        suggestions.sort();
+        match suggestions.as_slice() {
+            [] => String::new(),
+            [(_, name)] => format!("\n  Did you mean '{}'?", name),
+            _ => {
+                let quoted: Vec<String> = suggestions
+                    .iter()
+                    .take(3)
+                    .map(|(_, name)| format!("'{}'", name))
+                    .collect();
+                format!("\n  Did you mean one of: {}?", quoted.join(", "))
+            }
+        }
+    }
+
     /// Get all verifications (excluding subprojects)
     pub fn verifications_only(&self) -> Vec<&Verification> {
         self.verifications
@@ -249,10 +1843,215 @@ impl Config {
         })
     }
 
-    /// Check if a name refers to a subproject
-    #[allow(dead_code)]
-    pub fn is_subproject(&self, name: &str) -> bool {
-        self.get_subproject(name).is_some()
+    /// Check if a name refers to a subproject
+    #[allow(dead_code)]
+    pub fn is_subproject(&self, name: &str) -> bool {
+        self.get_subproject(name).is_some()
+    }
+}
+
+/// Whether a cache_paths pattern reads an artifact: it matches the artifact path itself,
+/// or its literal directory part (e.g. "dist" in "dist/**/*.js") is inside it
+fn reads_artifact(pattern: &str, artifact: &str, options: GlobOptions) -> bool {
+    let fold = |s: &str| {
+        if options.case_insensitive {
+            s.to_lowercase()
+        } else {
+            s.to_string()
+        }
+    };
+    let artifact = fold(artifact.trim_end_matches('/'));
+    let match_options = glob::MatchOptions {
+        case_sensitive: !options.case_insensitive,
+        ..glob::MatchOptions::new()
+    };
+    if glob::Pattern::new(pattern).is_ok_and(|p| p.matches_with(&artifact, match_options)) {
+        return true;
+    }
+
+    let literal = pattern
+        .split('/')
+        .take_while(|component| !component.contains(['*', '?', '[']))
+        .collect::<Vec<_>>()
+        .join("/");
+    let literal = fold(&literal);
+    literal == artifact || literal.starts_with(&format!("{}/", artifact))
+}
+
+/// Levenshtein distance between two names, counted in characters, with a swap of two
+/// adjacent characters counting as one edit (the most common typo)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Build one concrete check from a matrix template and a combination of values
+fn expand_template(template: &Verification, combination: &[(&str, &str)]) -> Result<Verification> {
+    let substitute = |text: &str| -> Result<String> {
+        let mut result = text.to_string();
+        for (key, value) in combination {
+            result = result
+                .replace(&format!("{{{{{}}}}}", key), value)
+                .replace(&format!("{{{{ {} }}}}", key), value);
+        }
+        // Only `{{name}}` looks like a misspelled variable; other braces, like Go
+        // templates' `{{.State}}`, are left for the command
+        let mut rest = result.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            if is_identifier(name) {
+                anyhow::bail!(
+                    "Unknown matrix variable '{}' in check '{}'",
+                    name,
+                    template.name
+                );
+            }
+            rest = &rest[start + 2..];
+        }
+        Ok(result)
+    };
+
+    let suffix: Vec<&str> = combination.iter().map(|(_, value)| *value).collect();
+    Ok(Verification {
+        name: format!("{}-{}", template.name, suffix.join("-")),
+        description: template
+            .description
+            .as_deref()
+            .map(substitute)
+            .transpose()?,
+        command: template
+            .command
+            .as_ref()
+            .map(|command| command.try_map(substitute))
+            .transpose()?,
+        cache_paths: template
+            .cache_paths
+            .iter()
+            .map(|path| substitute(path))
+            .collect::<Result<_>>()?,
+        depends_on: template
+            .depends_on
+            .iter()
+            .map(|dep| substitute(dep))
+            .collect::<Result<_>>()?,
+        matrix: Matrix::default(),
+        ..template.clone()
+    })
+}
+
+/// Whether `name` could be a matrix variable: a letter or `_`, then letters, digits,
+/// `_` or `-`
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` (the default is used when VAR is unset or
+/// empty) from the environment. `$${` is a literal `${`, for variables the shell should
+/// expand when the command runs. With `strict`, an unset variable without a default is
+/// an error; otherwise it expands to nothing.
+fn expand_env(text: &str, strict: bool) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            anyhow::bail!("Unclosed '${{' in '{}'", text);
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            anyhow::bail!(
+                "Invalid environment variable reference '${{{}}}' (use $${{...}} for the shell)",
+                reference
+            );
+        }
+        match (std::env::var(name).ok(), default) {
+            (Some(value), Some(default)) if value.is_empty() => result.push_str(default),
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) if strict => anyhow::bail!("Environment variable '{}' is not set", name),
+            (None, None) => {}
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// A workspace file (verify-workspace.yaml) listing separate project roots to verify together
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Workspace {
+    /// Projects in the workspace, each with its own verify.yaml.
+    /// Paths are relative to the workspace file.
+    pub projects: Vec<Subproject>,
+}
+
+impl Workspace {
+    /// Load a workspace file, checking for duplicate names and missing project configs
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read workspace file: {}", path.display()))?;
+
+        let workspace: Workspace = serde_yml::from_str(&content)
+            .with_context(|| format!("Failed to parse workspace file: {}", path.display()))?;
+
+        let base_path = path.parent().unwrap_or(Path::new("."));
+        let mut names = HashSet::new();
+        for project in &workspace.projects {
+            if !names.insert(project.name.as_str()) {
+                anyhow::bail!("Duplicate workspace project name: {}", project.name);
+            }
+            let project_config = base_path.join(&project.path).join("verify.yaml");
+            if !project_config.exists() {
+                anyhow::bail!(
+                    "Workspace project '{}' config not found: {}",
+                    project.name,
+                    project_config.display()
+                );
+            }
+        }
+
+        Ok(workspace)
+    }
+
+    /// Get a project by name
+    pub fn get(&self, name: &str) -> Option<&Subproject> {
+        self.projects.iter().find(|p| p.name == name)
     }
 }
 
@@ -305,7 +2104,13 @@ pub fn init_config(path: &Path, force: bool) -> Result<()> {
         );
     }
 
-    let content = generate_example_config();
+    let mut content = generate_example_config();
+    let dir = path.parent().unwrap_or(Path::new("."));
+    if !crate::submodule::with_configs(dir).is_empty() {
+        content.push_str(
+            "\n# Run the checks of git submodules that have their own verify.yaml\nsubmodule_subprojects: true\n",
+        );
+    }
     fs::write(path, content)
         .with_context(|| format!("Failed to write config file: {}", path.display()))?;
 
@@ -412,6 +2217,122 @@ verifications:
         assert_eq!(config.verifications[0].name(), "test");
     }
 
+    #[test]
+    fn test_parse_metadata_patterns() {
+        let yaml = r#"
+verifications:
+  - name: test
+    command: npm test
+    metadata:
+      passed: "(\\d+) passed"
+      ratio: ["(\\d+)/(\\d+)", "$1 of $2"]
+      coverage: { pattern: "([\\d.]+)%", precision: 1 }
+      lines: { file: coverage/summary.json, json_path: total.lines.pct }
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let metadata = &config.get("test").unwrap().metadata;
+        assert!(matches!(metadata["passed"], MetadataPattern::Simple(_)));
+        assert!(matches!(
+            metadata["ratio"],
+            MetadataPattern::WithReplacement(_, _)
+        ));
+        assert_eq!(metadata["coverage"].precision(), Some(1));
+        assert_eq!(
+            metadata["lines"].file(),
+            Some(Path::new("coverage/summary.json"))
+        );
+    }
+
+    #[test]
+    fn test_metadata_needs_pattern_or_json_path() {
+        let check = |metadata: &str| {
+            let yaml = format!(
+                "verifications:\n  - name: test\n    command: npm test\n    metadata:\n      coverage: {}\n",
+                metadata
+            );
+            let config: Config = serde_yml::from_str(&yaml).unwrap();
+            config.validate(Path::new(".")).map_err(|e| e.to_string())
+        };
+        assert!(check("{ file: coverage.json, json_path: total }").is_ok());
+        let err = check("{ file: coverage.txt }").unwrap_err();
+        assert!(
+            err.contains("metadata 'coverage' needs a pattern or json_path"),
+            "{}",
+            err
+        );
+        let err = check("{ pattern: '(\\d+)', json_path: total }").unwrap_err();
+        assert!(
+            err.contains("can't have both a pattern and a json_path"),
+            "{}",
+            err
+        );
+        let err = check("'([\\d.]+%'").unwrap_err();
+        assert!(
+            err.contains("metadata 'coverage' has an invalid pattern"),
+            "{}",
+            err
+        );
+        let err = check("['(\\d+', '$1']").unwrap_err();
+        assert!(err.contains("invalid pattern"), "{}", err);
+    }
+
+    #[test]
+    fn test_max_metadata_length_defaults() {
+        let yaml = r#"
+max_metadata_length: 64
+verifications:
+  - name: build
+    command: make
+  - name: test
+    command: make test
+    max_metadata_length: 4096
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verify.yaml");
+        fs::write(&path, yaml).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.get("build").unwrap().max_metadata_length, Some(64));
+        assert_eq!(config.get("test").unwrap().max_metadata_length, Some(4096));
+
+        fs::write(
+            &path,
+            "verifications:\n  - name: build\n    command: make\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.get("build").unwrap().max_metadata_length,
+            Some(DEFAULT_MAX_METADATA_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_trailer_hash_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verify.yaml");
+        let checks = "verifications:\n  - name: build\n    command: make\n";
+        fs::write(&path, checks).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.trailer_hash_length(), DEFAULT_TRAILER_HASH_LENGTH);
+
+        fs::write(&path, format!("trailer_hash_length: 32\n{}", checks)).unwrap();
+        assert_eq!(Config::load(&path).unwrap().trailer_hash_length(), 32);
+
+        for length in [4, 65] {
+            fs::write(
+                &path,
+                format!("trailer_hash_length: {}\n{}", length, checks),
+            )
+            .unwrap();
+            let err = format!("{:#}", Config::load(&path).unwrap_err());
+            assert!(
+                err.contains("trailer_hash_length must be between 8 and 64"),
+                "{}",
+                err
+            );
+        }
+    }
+
     #[test]
     fn test_parse_subproject() {
         let yaml = r#"
@@ -445,6 +2366,103 @@ verifications:
         assert!(config.validate(Path::new(".")).is_err());
     }
 
+    #[test]
+    fn test_subproject_prefixes() {
+        let yaml = r#"
+verifications:
+  - name: api
+    path: ./services/api/
+  - name: web
+    path: web
+    prefix: frontend
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let subprojects = config.subprojects();
+        assert_eq!(subprojects[0].qualify("build"), "services/api/build");
+        assert_eq!(subprojects[1].qualify("build"), "frontend/build");
+
+        let collision = r#"
+verifications:
+  - name: api
+    path: api
+  - name: web
+    path: web
+    prefix: api
+"#;
+        let config: Config = serde_yml::from_str(collision).unwrap();
+        let err = config.validate(Path::new(".")).unwrap_err().to_string();
+        assert!(err.contains("both name their checks 'api/...'"), "{}", err);
+
+        let shadowed = r#"
+verifications:
+  - name: api/build
+    command: make
+  - name: api
+    path: api
+"#;
+        let config: Config = serde_yml::from_str(shadowed).unwrap();
+        let err = config.validate(Path::new(".")).unwrap_err().to_string();
+        assert!(
+            err.contains("same name as a check in subproject 'api'"),
+            "{}",
+            err
+        );
+
+        let invalid = "verifications:\n  - name: api\n    path: api\n    prefix: 'a:b'\n";
+        let config: Config = serde_yml::from_str(invalid).unwrap();
+        assert!(config.validate(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let yaml = r#"
+profiles:
+  precommit: [fast, docs]
+  full: []
+default_profile: full
+hooks:
+  default_profile: precommit
+verifications:
+  - name: lint
+    command: make lint
+    tags: [fast]
+  - name: test
+    command: make test
+  - name: docs
+    command: make docs
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate(Path::new(".")).is_ok());
+        assert_eq!(config.default_profile(false), Some("full"));
+        assert_eq!(config.default_profile(true), Some("precommit"));
+        assert_eq!(
+            config.profile_checks("precommit").unwrap(),
+            vec!["lint", "docs"]
+        );
+        assert_eq!(
+            config.profile_checks("full").unwrap(),
+            vec!["lint", "test", "docs"]
+        );
+        let err = config.profile_checks("nightly").unwrap_err().to_string();
+        assert!(err.contains("profiles: full, precommit"), "{}", err);
+
+        let invalid = yaml
+            .replace("[fast, docs]", "[fast, slow]")
+            .replace("default_profile: full", "default_profile: all");
+        let config: Config = serde_yml::from_str(&invalid).unwrap();
+        let err = config.validate(Path::new(".")).unwrap_err().to_string();
+        assert!(
+            err.contains("Profile 'precommit' lists 'slow', which is neither a check nor a tag"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("default_profile names unknown profile: all"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_unknown_dependency() {
         let yaml = r#"
@@ -458,6 +2476,175 @@ verifications:
         assert!(config.validate(Path::new(".")).is_err());
     }
 
+    #[test]
+    fn test_artifacts_imply_dependency() {
+        let yaml = r#"
+verifications:
+  - name: test
+    command: ./run-tests dist
+    cache_paths: ["dist/**/*.js", "tests/**"]
+  - name: build
+    command: make
+    cache_paths: ["src/**"]
+    runs_on: builder
+    artifacts: [dist]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.infer_artifact_dependencies();
+        assert_eq!(config.get("test").unwrap().depends_on, vec!["build"]);
+        assert!(config.get("build").unwrap().depends_on.is_empty());
+        assert!(config.artifact_conflicts().is_empty());
+        assert!(config.validate(Path::new(".")).is_ok());
+    }
+
+    #[test]
+    fn test_artifact_conflict_with_explicit_dependency() {
+        let yaml = r#"
+verifications:
+  - name: test
+    command: ./run-tests dist
+    cache_paths: ["dist/**"]
+  - name: build
+    command: make
+    cache_paths: ["src/**"]
+    depends_on: [test]
+    runs_on: builder
+    artifacts: [dist/]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.infer_artifact_dependencies();
+        // Adding test -> build would be a cycle, so it's reported instead
+        assert!(config.get("test").unwrap().depends_on.is_empty());
+        assert_eq!(
+            config.artifact_conflicts(),
+            vec![("build".to_string(), "test".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_consistency_warnings() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command: cargo build
+    cache_paths: ["src/**", "Cargo.toml"]
+  - name: compile
+    command: " cargo build"
+    cache_paths: ["src/**", "Cargo.toml"]
+  - name: test
+    command: cargo test
+    cache_paths: ["src/**"]
+    depends_on: [build]
+  - name: lint
+    command: cargo clippy
+    cache_paths: ["src/**", "Cargo.toml"]
+    depends_on: [build]
+  - name: all
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let warnings = config.consistency_warnings();
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].starts_with("compile: runs the same command as 'build'"));
+        assert!(warnings[1].starts_with("all: aggregate check"));
+        assert!(warnings[2].starts_with("test: cache_paths are a strict subset"));
+    }
+
+    #[test]
+    fn test_reads_artifact() {
+        let options = GlobOptions::default();
+        assert!(reads_artifact("dist/**/*.js", "dist", options));
+        assert!(reads_artifact("dist/app.js", "dist/", options));
+        assert!(reads_artifact("*.tar", "out.tar", options));
+        assert!(reads_artifact("build/out/**", "build", options));
+        assert!(!reads_artifact("src/**", "dist", options));
+        assert!(!reads_artifact("distribution/**", "dist", options));
+        assert!(!reads_artifact("Dist/**", "dist", options));
+        let insensitive = GlobOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        assert!(reads_artifact("Dist/**", "dist", insensitive));
+    }
+
+    #[test]
+    fn test_unknown_dependency_suggests_close_names() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command: cargo build
+  - name: test
+    command: cargo test
+    depends_on: [biuld]
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let err = config.validate(Path::new(".")).unwrap_err().to_string();
+        assert!(err.contains("unknown check: biuld"), "{}", err);
+        assert!(err.contains("Did you mean 'build'?"), "{}", err);
+
+        let yaml = yaml.replace("biuld", "deploy");
+        let config: Config = serde_yml::from_str(&yaml).unwrap();
+        let err = config.validate(Path::new(".")).unwrap_err().to_string();
+        assert!(!err.contains("Did you mean"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_dependency_inside_subproject() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("web")).unwrap();
+        fs::write(
+            dir.path().join("web/verify.yaml"),
+            "verifications:\n  - name: unit\n    command: npm test\n",
+        )
+        .unwrap();
+        let yaml = r#"
+verifications:
+  - name: web
+    path: web
+  - name: e2e
+    command: npm run e2e
+    depends_on: [unit]
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let err = config.validate(dir.path()).unwrap_err().to_string();
+        assert!(
+            err.contains("'unit' is a check in subproject 'web'"),
+            "{}",
+            err
+        );
+        assert!(err.contains("'web:unit'"), "{}", err);
+    }
+
+    #[test]
+    fn test_subproject_containing_config_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("app")).unwrap();
+        fs::write(dir.path().join("verify.yaml"), "verifications: []\n").unwrap();
+        let yaml = r#"
+verifications:
+  - name: root
+    path: ..
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let err = config
+            .validate(&dir.path().join("app"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("Subproject 'root' contains this config's directory"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("build", "build"), 0);
+        assert_eq!(edit_distance("biuld", "build"), 1);
+        assert_eq!(edit_distance("test", "lint"), 3);
+        assert_eq!(edit_distance("lint", "lints"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
     #[test]
     fn test_mixed_verifications_and_subprojects() {
         let yaml = r#"
@@ -559,22 +2746,18 @@ verifications: []
     fn test_config_hash_determinism() {
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["src/**/*.ts".to_string()],
-            depends_on: vec![],
             timeout_secs: Some(300),
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["src/**/*.ts".to_string()],
-            depends_on: vec![],
             timeout_secs: Some(300),
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         assert_eq!(v1.config_hash(), v2.config_hash());
@@ -584,22 +2767,14 @@ verifications: []
     fn test_config_hash_changes_with_command() {
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            command: Some("npm test".into()),
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm run test".to_string()), // different command
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            command: Some("npm run test".into()), // different command
+            ..Default::default()
         };
 
         assert_ne!(v1.config_hash(), v2.config_hash());
@@ -609,22 +2784,16 @@ verifications: []
     fn test_config_hash_changes_with_cache_paths() {
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["src/**/*.ts".to_string()],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["src/**/*.js".to_string()], // different path
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         assert_ne!(v1.config_hash(), v2.config_hash());
@@ -634,50 +2803,121 @@ verifications: []
     fn test_config_hash_changes_with_timeout() {
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
+            command: Some("npm test".into()),
             timeout_secs: Some(300),
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
+            command: Some("npm test".into()),
             timeout_secs: Some(600), // different timeout
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
+        };
+
+        assert_ne!(v1.config_hash(), v2.config_hash());
+    }
+
+    #[test]
+    fn test_config_hash_changes_with_depends_on() {
+        let v1 = Verification {
+            name: "test".to_string(),
+            command: Some("npm test".into()),
+            depends_on: vec!["build".to_string()],
+            ..Default::default()
         };
 
+        let mut v2 = v1.clone();
+        v2.depends_on.push("lint".to_string());
         assert_ne!(v1.config_hash(), v2.config_hash());
+
+        // Order of dependencies doesn't matter
+        let mut v3 = v2.clone();
+        v3.depends_on.reverse();
+        assert_eq!(v2.config_hash(), v3.config_hash());
     }
 
     #[test]
     fn test_config_hash_changes_with_per_file() {
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            command: Some("npm test".into()),
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
+            command: Some("npm test".into()),
             per_file: true, // different per_file setting
+            ..Default::default()
+        };
+
+        assert_ne!(v1.config_hash(), v2.config_hash());
+    }
+
+    #[test]
+    fn test_config_hash_case_insensitive() {
+        let v1 = Verification {
+            name: "test".to_string(),
+            command: Some("npm test".into()),
+            cache_paths: vec!["src/*.ts".to_string()],
+            ..Default::default()
+        };
+
+        let mut v2 = v1.clone();
+        v2.case_insensitive = true;
+
+        assert_ne!(v1.config_hash(), v2.config_hash());
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        assert_eq!(parse_file_size("1024"), Ok(1024));
+        assert_eq!(parse_file_size("500KB"), Ok(500 << 10));
+        assert_eq!(parse_file_size("100 mb"), Ok(100 << 20));
+        assert_eq!(parse_file_size("2G"), Ok(2 << 30));
+        assert!(parse_file_size("2 parsecs").is_err());
+        assert!(parse_file_size("MB").is_err());
+
+        let yaml = r#"
+verifications:
+  - name: unit
+    command: cargo test
+    max_file_size: 10MB
+    large_files: truncate
+  - name: e2e
+    command: ./e2e
+    max_file_size: 4096
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let unit = config.get("unit").unwrap();
+        assert_eq!(unit.max_file_size, Some(10 << 20));
+        assert!(unit.glob_options().truncate_large_files);
+        let e2e = config.get("e2e").unwrap();
+        assert_eq!(e2e.max_file_size, Some(4096));
+        assert!(!e2e.glob_options().truncate_large_files);
+
+        let yaml = "verifications:\n  - name: a\n    command: x\n    large_files: skip\n";
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.validate(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_config_hash_cache_salt() {
+        let v1 = Verification {
+            name: "test".to_string(),
+            command: Some("npm test".into()),
+            cache_paths: vec!["src/*.ts".to_string()],
+            ..Default::default()
         };
 
+        let mut v2 = v1.clone();
+        v2.cache_salt = Some("1".to_string());
+        let mut v3 = v1.clone();
+        v3.cache_salt = Some("2".to_string());
+
         assert_ne!(v1.config_hash(), v2.config_hash());
+        assert_ne!(v2.config_hash(), v3.config_hash());
     }
 
     #[test]
@@ -685,22 +2925,16 @@ verifications: []
         // Cache paths should be sorted, so order doesn't matter
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["a.ts".to_string(), "b.ts".to_string(), "c.ts".to_string()],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
+            command: Some("npm test".into()),
             cache_paths: vec!["c.ts".to_string(), "a.ts".to_string(), "b.ts".to_string()],
-            depends_on: vec![],
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         };
 
         assert_eq!(v1.config_hash(), v2.config_hash());
@@ -718,22 +2952,16 @@ verifications: []
 
         let v1 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
+            command: Some("npm test".into()),
             metadata: metadata1,
-            per_file: false,
+            ..Default::default()
         };
 
         let v2 = Verification {
             name: "test".to_string(),
-            command: Some("npm test".to_string()),
-            cache_paths: vec![],
-            depends_on: vec![],
-            timeout_secs: None,
+            command: Some("npm test".into()),
             metadata: HashMap::new(), // no metadata
-            per_file: false,
+            ..Default::default()
         };
 
         assert_ne!(v1.config_hash(), v2.config_hash());
@@ -835,4 +3063,261 @@ verifications:
         assert!(config.get("frontend").is_none()); // Returns None for subproject
         assert!(config.get_subproject("frontend").is_some()); // But get_subproject works
     }
+
+    #[test]
+    fn test_workspace_load_validates_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("api")).unwrap();
+        fs::write(dir.path().join("api/verify.yaml"), "verifications: []\n").unwrap();
+        let path = dir.path().join("verify-workspace.yaml");
+
+        fs::write(&path, "projects:\n  - name: api\n    path: api\n").unwrap();
+        let workspace = Workspace::load(&path).unwrap();
+        assert!(workspace.get("api").is_some());
+
+        fs::write(
+            &path,
+            "projects:\n  - name: api\n    path: api\n  - name: api\n    path: api\n",
+        )
+        .unwrap();
+        let err = Workspace::load(&path).unwrap_err().to_string();
+        assert!(err.contains("Duplicate workspace project name"));
+
+        fs::write(&path, "projects:\n  - name: web\n    path: web\n").unwrap();
+        let err = Workspace::load(&path).unwrap_err().to_string();
+        assert!(err.contains("config not found"));
+    }
+
+    #[test]
+    fn test_platform_commands() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command:
+      macos: xcrun make
+      default: make
+    cache_paths: ["src/**"]
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let check = config.get("build").unwrap();
+        let command = check.command.as_ref().unwrap();
+        assert_eq!(command.for_os("macos"), Some("xcrun make"));
+        assert_eq!(command.for_os("linux"), Some("make"));
+        assert!(config.validate(Path::new(".")).is_ok());
+
+        // Editing another platform's variant still invalidates the check
+        let edited: Config =
+            serde_yml::from_str(&yaml.replace("xcrun make", "xcrun make -j8")).unwrap();
+        assert_ne!(
+            check.config_hash(),
+            edited.get("build").unwrap().config_hash()
+        );
+
+        let yaml = format!(
+            "verifications:\n  - name: build\n    command:\n      {}: make\n",
+            if std::env::consts::OS == "windows" {
+                "linux"
+            } else {
+                "windows"
+            }
+        );
+        // Without a variant for this platform, only that check is unsupported
+        let config: Config = serde_yml::from_str(&yaml).unwrap();
+        assert!(config.validate(Path::new(".")).is_ok());
+        assert!(!config.get("build").unwrap().is_supported());
+
+        let yaml = "verifications:\n  - name: build\n    command:\n      mac: make\n";
+        assert!(serde_yml::from_str::<Config>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_matrix_expansion() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command: make {{target}} CONFIG={{ config }}
+    cache_paths: ["src/{{target}}/**"]
+    matrix:
+      target: [ios, macos]
+      config: [debug, release]
+  - name: test
+    command: make test
+    depends_on: [build]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.expand_matrices().unwrap();
+
+        let names: Vec<&str> = config.verifications.iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "build-ios-debug",
+                "build-ios-release",
+                "build-macos-debug",
+                "build-macos-release",
+                "test"
+            ]
+        );
+
+        let check = config.get("build-macos-release").unwrap();
+        assert_eq!(check.command.as_deref(), Some("make macos CONFIG=release"));
+        assert_eq!(check.cache_paths, vec!["src/macos/**"]);
+        assert!(check.matrix.is_empty());
+
+        // Depending on the template means depending on every expansion
+        assert_eq!(config.get("test").unwrap().depends_on.len(), 4);
+    }
+
+    #[test]
+    fn test_matrix_templated_dependencies_and_scalars() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command: make {{node}}
+    matrix:
+      node: [18, 20]
+  - name: test
+    command: npm test
+    depends_on: ["build-{{node}}"]
+    matrix:
+      node: [18, 20]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.expand_matrices().unwrap();
+
+        assert_eq!(
+            config.get("build-18").unwrap().command.as_deref(),
+            Some("make 18")
+        );
+        assert_eq!(config.get("test-20").unwrap().depends_on, vec!["build-20"]);
+    }
+
+    #[test]
+    fn test_matrix_keeps_other_template_braces() {
+        let yaml = r#"
+verifications:
+  - name: inspect
+    command: "docker inspect -f '{{.State.Status}}' {{name}}"
+    matrix:
+      name: [api]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.expand_matrices().unwrap();
+
+        assert_eq!(
+            config.get("inspect-api").unwrap().command.as_deref(),
+            Some("docker inspect -f '{{.State.Status}}' api")
+        );
+    }
+
+    #[test]
+    fn test_matrix_unknown_variable_and_empty_values() {
+        let yaml = r#"
+verifications:
+  - name: build
+    command: make {{platform}}
+    matrix:
+      target: [ios]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        let err = config.expand_matrices().unwrap_err().to_string();
+        assert!(
+            err.contains("Unknown matrix variable 'platform'"),
+            "{}",
+            err
+        );
+
+        let yaml = r#"
+verifications:
+  - name: build
+    command: make
+    matrix:
+      target: []
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        assert!(config.expand_matrices().is_err());
+    }
+
+    #[test]
+    fn test_expand_env() {
+        // Cargo sets CARGO_PKG_NAME for test runs
+        let unset = "VERIFY_TEST_UNSET_VARIABLE";
+        assert_eq!(
+            expand_env("tool=${CARGO_PKG_NAME}/bin", true).unwrap(),
+            format!("tool={}/bin", env!("CARGO_PKG_NAME"))
+        );
+        assert_eq!(
+            expand_env(&format!("${{{}:-/opt/tool}}/bin", unset), true).unwrap(),
+            "/opt/tool/bin"
+        );
+        assert_eq!(
+            expand_env("${CARGO_PKG_NAME:-other}", true).unwrap(),
+            env!("CARGO_PKG_NAME")
+        );
+        assert_eq!(
+            expand_env(&format!("a${{{}}}b", unset), false).unwrap(),
+            "ab"
+        );
+        let err = expand_env(&format!("a${{{}}}b", unset), true).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("'VERIFY_TEST_UNSET_VARIABLE' is not set")
+        );
+
+        // Escaped and plain-$ references are left for the shell
+        assert_eq!(
+            expand_env("x=1; echo $${x} $HOME", true).unwrap(),
+            "x=1; echo ${x} $HOME"
+        );
+        assert!(expand_env("${1abc}", false).is_err());
+        assert!(expand_env("${UNCLOSED", false).is_err());
+    }
+
+    #[test]
+    fn test_interpolate_env_fields() {
+        let yaml = r#"
+env_interpolation: strict
+verifications:
+  - name: build
+    command:
+      linux: ${CARGO_PKG_NAME} linux
+      default: ${CARGO_PKG_NAME}
+    cache_paths: ["${CARGO_PKG_NAME}/**"]
+  - name: lib
+    path: ./${CARGO_PKG_NAME}
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        config.interpolate_env(EnvInterpolation::Strict).unwrap();
+        let name = env!("CARGO_PKG_NAME");
+        let build = config.get("build").unwrap();
+        assert_eq!(build.cache_paths, vec![format!("{}/**", name)]);
+        let Some(Command::PerPlatform(commands)) = &build.command else {
+            panic!("expected per-platform commands");
+        };
+        assert_eq!(commands.linux, Some(format!("{} linux", name)));
+        assert_eq!(commands.default.as_deref(), Some(name));
+        assert_eq!(
+            config.subprojects()[0].path,
+            PathBuf::from(format!("./{}", name))
+        );
+
+        let yaml = r#"
+verifications:
+  - name: build
+    command: make
+    cache_paths: ["${VERIFY_TEST_UNSET_VARIABLE}/**"]
+"#;
+        let mut config: Config = serde_yml::from_str(yaml).unwrap();
+        let err = format!(
+            "{:#}",
+            config
+                .interpolate_env(EnvInterpolation::Strict)
+                .unwrap_err()
+        );
+        assert!(
+            err.contains("in the cache_paths of check 'build'"),
+            "{}",
+            err
+        );
+    }
 }
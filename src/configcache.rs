@@ -0,0 +1,159 @@
+use crate::config::Config;
+use crate::statedir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const CONFIG_CACHE_VERSION: u32 = 1;
+const CONFIG_CACHE_FILE: &str = "config-cache";
+
+/// Cleared by `--no-config-cache`
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Parse and validate configs from scratch for the rest of this process
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// A loaded config (matrices expanded, defaults applied, validated) stored in the project's
+/// `.verify/config-cache`, reused while the files it was loaded from are unchanged
+#[derive(Deserialize, Serialize)]
+struct CachedConfig<C> {
+    /// Version for future format changes; mismatches are ignored
+    version: u32,
+    /// Hash of the verify version, the config path and the content of every file it was
+    /// loaded from
+    key: String,
+    config: C,
+}
+
+/// Key for a config loaded from `sources` (the config file, then its fragments)
+pub fn key(path: &Path, base_path: &Path, sources: &[(PathBuf, String)]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(crate::update::CURRENT_VERSION.as_bytes());
+    for part in [path, base_path] {
+        hasher.update(b"\0");
+        hasher.update(part.to_string_lossy().as_bytes());
+    }
+    for (source, content) in sources {
+        hasher.update(b"\0");
+        hasher.update(source.to_string_lossy().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(content.as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// The config cached for `key` in the project at `base_path`, if it's still valid. Its
+/// subprojects' configs must still exist, since validation checked that when it was cached.
+pub fn load(base_path: &Path, key: &str) -> Option<Config> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let content = fs::read_to_string(cache_path(base_path)).ok()?;
+    let cached: CachedConfig<Config> = serde_json::from_str(&content).ok()?;
+    if cached.version != CONFIG_CACHE_VERSION || cached.key != key {
+        return None;
+    }
+    let subprojects_exist = cached.config.subprojects().iter().all(|subproject| {
+        base_path
+            .join(&subproject.path)
+            .join("verify.yaml")
+            .exists()
+    });
+    subprojects_exist.then_some(cached.config)
+}
+
+/// Store a freshly loaded config. Best effort: a cache that can't be written just means
+/// the next command loads the config from scratch.
+pub fn store(base_path: &Path, key: &str, config: &Config) {
+    // Subprojects found from git submodules, and values from environment variables, can
+    // change without any config file changing. A config with `cache_dir` is loaded before
+    // state is relocated, so its entry would land in the project's own `.verify/`.
+    if !ENABLED.load(Ordering::Relaxed)
+        || !statedir::writes_enabled()
+        || config.submodule_subprojects
+        || config.env_interpolation.is_some()
+        || config.cache_dir.is_some()
+    {
+        return;
+    }
+    let cached = CachedConfig {
+        version: CONFIG_CACHE_VERSION,
+        key: key.to_string(),
+        config,
+    };
+    let (Ok(dir), Ok(json)) = (statedir::create(base_path), serde_json::to_string(&cached)) else {
+        return;
+    };
+    // Written aside and renamed, since editors polling status may load it concurrently
+    let temp_path = dir.join(format!("{}.{}.tmp", CONFIG_CACHE_FILE, std::process::id()));
+    if fs::write(&temp_path, json).is_err()
+        || fs::rename(&temp_path, dir.join(CONFIG_CACHE_FILE)).is_err()
+    {
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+fn cache_path(base_path: &Path) -> PathBuf {
+    statedir::state_dir(base_path).join(CONFIG_CACHE_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_config_matches_loaded_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = r#"
+tracked_only: true
+verifications:
+  - name: build
+    command: cargo build
+    cache_paths: ["src/**/*.rs"]
+    max_file_size: 1KB
+    metadata:
+      warnings: "(\\d+) warnings"
+      coverage:
+        json_path: total.pct
+        file: coverage.json
+  - name: test
+    command: cargo test --target {target}
+    matrix:
+      target: [linux, macos]
+    depends_on: [build]
+  - name: api
+    path: api
+"#;
+        fs::write(dir.path().join("verify.yaml"), config).unwrap();
+        fs::create_dir_all(dir.path().join("verify.d")).unwrap();
+        fs::write(
+            dir.path().join("verify.d/lint.yaml"),
+            "verifications:\n  - name: lint\n    command: cargo clippy\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("api")).unwrap();
+        fs::write(dir.path().join("api/verify.yaml"), "verifications: []\n").unwrap();
+
+        let path = dir.path().join("verify.yaml");
+        let loaded = Config::load(&path).unwrap();
+        assert!(cache_path(dir.path()).exists());
+        let cached = Config::load(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&cached).unwrap()
+        );
+
+        let mut sources = vec![(path.clone(), config.to_string())];
+        assert!(load(dir.path(), &key(&path, dir.path(), &sources)).is_none());
+        let fragment = dir.path().join("verify.d/lint.yaml");
+        sources.push((fragment.clone(), fs::read_to_string(&fragment).unwrap()));
+        assert!(load(dir.path(), &key(&path, dir.path(), &sources)).is_some());
+
+        // So is a subproject whose config was removed, which validation would reject
+        fs::remove_file(dir.path().join("api/verify.yaml")).unwrap();
+        assert!(Config::load(&path).is_err());
+    }
+}
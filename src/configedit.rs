@@ -0,0 +1,552 @@
+use crate::config::Config;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// A check to append with `verify config add-check`
+pub struct NewCheck {
+    pub name: String,
+    pub command: Option<String>,
+    pub cache_paths: Vec<String>,
+    pub depends_on: Vec<String>,
+}
+
+/// A config key: a top-level setting (`trailer`), or a field of the check or subproject
+/// with that name (`build.timeout_secs`)
+enum Key<'a> {
+    TopLevel(&'a str),
+    Field { item: &'a str, field: &'a str },
+}
+
+impl<'a> Key<'a> {
+    fn parse(key: &'a str) -> Result<Self> {
+        let key = match key.rsplit_once('.') {
+            Some((item, field)) => Key::Field { item, field },
+            None => Key::TopLevel(key),
+        };
+        let valid = |s: &str| !s.is_empty() && !s.chars().any(char::is_whitespace);
+        match key {
+            Key::TopLevel(name) if valid(name) => Ok(key),
+            Key::Field { item, field } if valid(item) && valid(field) => Ok(key),
+            _ => anyhow::bail!(
+                "Invalid config key '{}': use KEY or CHECK.FIELD",
+                key_text(&key)
+            ),
+        }
+    }
+}
+
+fn key_text(key: &Key) -> String {
+    match key {
+        Key::TopLevel(name) => name.to_string(),
+        Key::Field { item, field } => format!("{}.{}", item, field),
+    }
+}
+
+/// The value of a config key, as written in verify.yaml: scalars as plain text,
+/// lists and maps as YAML
+pub fn get(config_path: &Path, key: &str) -> Result<String> {
+    let key = Key::parse(key)?;
+    let content = read(config_path)?;
+    let document: serde_yml::Value = serde_yml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", config_path.display()))?;
+
+    let value = match key {
+        Key::TopLevel(name) => document.get(name),
+        Key::Field { item, field } => find_item(&document, item)?.get(field),
+    };
+    let Some(value) = value else {
+        anyhow::bail!("'{}' is not set", key_text(&key));
+    };
+    Ok(match value {
+        serde_yml::Value::String(s) => s.clone(),
+        serde_yml::Value::Bool(b) => b.to_string(),
+        serde_yml::Value::Number(n) => n.to_string(),
+        serde_yml::Value::Null => "null".to_string(),
+        other => serde_yml::to_string(other)?.trim_end().to_string(),
+    })
+}
+
+fn find_item<'v>(document: &'v serde_yml::Value, name: &str) -> Result<&'v serde_yml::Value> {
+    document
+        .get("verifications")
+        .and_then(|items| items.as_sequence())
+        .and_then(|items| {
+            items
+                .iter()
+                .find(|item| item.get("name").and_then(|n| n.as_str()) == Some(name))
+        })
+        .with_context(|| format!("Unknown check: {}", name))
+}
+
+/// Set a config key to a scalar value, editing verify.yaml in place so comments, order
+/// and formatting elsewhere are kept. `true`/`false`, numbers, `null` and flow lists
+/// (`[a, b]`) are written as given; anything else is written as a string.
+pub fn set(config_path: &Path, key: &str, value: &str) -> Result<()> {
+    let key = Key::parse(key)?;
+    let content = read(config_path)?;
+    let before = Config::load(config_path)?;
+    let value = yaml_value(value)?;
+
+    let mut doc = Document::parse(&content);
+    let existed = match key {
+        Key::TopLevel(name) => doc.set_top_level(name, &value),
+        Key::Field { item, field } => doc.set_field(item, field, &value)?,
+    };
+
+    let after = write_validated(config_path, &doc.render())?;
+    // serde ignores unknown keys, so a new key that changes nothing is a typo
+    if !existed && serde_json::to_value(&before)? == serde_json::to_value(&after)? {
+        fs::write(config_path, &content)
+            .with_context(|| format!("Failed to write {}", config_path.display()))?;
+        anyhow::bail!("Unknown config key '{}'", key_text(&key));
+    }
+    Ok(())
+}
+
+/// Append a check to the end of the verifications list
+pub fn add_check(config_path: &Path, check: &NewCheck) -> Result<()> {
+    let content = read(config_path)?;
+    let config = Config::load(config_path)?;
+    if config
+        .verifications
+        .iter()
+        .any(|item| item.name() == check.name)
+    {
+        anyhow::bail!("A check named '{}' already exists", check.name);
+    }
+
+    let mut fields = vec![format!("name: {}", yaml_string(&check.name)?)];
+    if let Some(ref command) = check.command {
+        fields.push(format!("command: {}", yaml_string(command)?));
+    }
+    for (field, values) in [
+        ("cache_paths", &check.cache_paths),
+        ("depends_on", &check.depends_on),
+    ] {
+        if !values.is_empty() {
+            fields.push(format!("{}:", field));
+            for value in values {
+                fields.push(format!("  - {}", yaml_string(value)?));
+            }
+        }
+    }
+
+    let mut doc = Document::parse(&content);
+    doc.append_item(&fields);
+    write_validated(config_path, &doc.render())?;
+    Ok(())
+}
+
+fn read(config_path: &Path) -> Result<String> {
+    fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config file: {}", config_path.display()))
+}
+
+/// Check the edited config loads before replacing verify.yaml with it
+fn write_validated(config_path: &Path, content: &str) -> Result<Config> {
+    let dir = config_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create a temp file in {}", dir.display()))?;
+    temp.write_all(content.as_bytes())?;
+    let config = Config::load_with_base(temp.path(), dir)
+        .context("The edited config is invalid, so verify.yaml was left unchanged")?;
+    temp.persist(config_path)
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+    Ok(config)
+}
+
+/// A command-line value as YAML text
+fn yaml_value(value: &str) -> Result<String> {
+    let trimmed = value.trim();
+    let typed = trimmed.starts_with('[')
+        || matches!(
+            serde_yml::from_str::<serde_yml::Value>(trimmed),
+            Ok(serde_yml::Value::Bool(_) | serde_yml::Value::Number(_) | serde_yml::Value::Null)
+        );
+    if typed {
+        serde_yml::from_str::<serde_yml::Value>(trimmed)
+            .with_context(|| format!("Invalid value '{}'", value))?;
+        Ok(trimmed.to_string())
+    } else {
+        yaml_string(value)
+    }
+}
+
+/// A string as a YAML scalar, quoted only when it has to be
+fn yaml_string(value: &str) -> Result<String> {
+    Ok(serde_yml::to_string(value)?.trim_end().to_string())
+}
+
+/// verify.yaml as lines, edited in place
+struct Document {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+impl Document {
+    fn parse(content: &str) -> Self {
+        Self {
+            lines: content.lines().map(String::from).collect(),
+            trailing_newline: content.ends_with('\n') || content.is_empty(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut content = self.lines.join("\n");
+        if self.trailing_newline {
+            content.push('\n');
+        }
+        content
+    }
+
+    /// Set a top-level key, appending it when missing. Returns whether it existed.
+    fn set_top_level(&mut self, key: &str, value: &str) -> bool {
+        match self.top_level_line(key) {
+            Some(line) => {
+                self.replace_value(line, 0, value);
+                true
+            }
+            None => {
+                self.lines.push(format!("{}: {}", key, value));
+                false
+            }
+        }
+    }
+
+    /// Set a field of a verifications item, adding it after the item's last line when
+    /// missing. Returns whether it existed.
+    fn set_field(&mut self, item: &str, field: &str, value: &str) -> Result<bool> {
+        let items = self.items();
+        let Some(&(start, end, field_indent)) = items.iter().find(|(start, end, indent)| {
+            self.item_name(*start, *end, *indent).as_deref() == Some(item)
+        }) else {
+            anyhow::bail!("Unknown check: {}", item);
+        };
+
+        let existing = (start..end).find(|&i| {
+            let line = &self.lines[i];
+            let body = if i == start {
+                line.trim_start().trim_start_matches('-').trim_start()
+            } else if indent(line) == field_indent {
+                line.trim_start()
+            } else {
+                return false;
+            };
+            field_value(body, field).is_some()
+        });
+        match existing {
+            Some(line) => {
+                self.replace_value(line, field_indent, value);
+                Ok(true)
+            }
+            None => {
+                let after = last_content_line(&self.lines, start, end) + 1;
+                let line = format!("{}{}: {}", " ".repeat(field_indent), field, value);
+                self.lines.insert(after, line);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Append an item (given as field lines) to the verifications list
+    fn append_item(&mut self, fields: &[String]) {
+        let items = self.items();
+        let (item_indent, field_indent) = match items.first() {
+            Some(&(start, _, field_indent)) => (indent(&self.lines[start]), field_indent),
+            None => (2, 4),
+        };
+        let at = match self.top_level_line("verifications") {
+            Some(line) => {
+                // `verifications: []` becomes a block list
+                let body = strip_comment(self.lines[line].split_once(':').map_or("", |(_, v)| v));
+                if body.trim() == "[]" {
+                    self.lines[line] = "verifications:".to_string();
+                }
+                let end = block_end(&self.lines, line, 0);
+                last_content_line(&self.lines, line, end) + 1
+            }
+            None => {
+                self.lines.push("verifications:".to_string());
+                self.lines.len()
+            }
+        };
+
+        let mut new_lines = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let line = if i == 0 {
+                format!("{}- {}", " ".repeat(item_indent), field)
+            } else {
+                format!("{}{}", " ".repeat(field_indent), field)
+            };
+            new_lines.push(line);
+        }
+        self.lines.splice(at..at, new_lines);
+    }
+
+    fn top_level_line(&self, key: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .position(|line| indent(line) == 0 && field_value(line, key).is_some())
+    }
+
+    /// The items of the verifications list, as (first line, end line, field indent)
+    fn items(&self) -> Vec<(usize, usize, usize)> {
+        let Some(list) = self.top_level_line("verifications") else {
+            return Vec::new();
+        };
+        let end = block_end(&self.lines, list, 0);
+        let starts: Vec<usize> = (list + 1..end)
+            .filter(|&i| is_content(&self.lines[i]) && is_item_start(&self.lines[i]))
+            .collect();
+        let Some(item_indent) = starts.first().map(|&i| indent(&self.lines[i])) else {
+            return Vec::new();
+        };
+        let starts: Vec<usize> = starts
+            .into_iter()
+            .filter(|&i| indent(&self.lines[i]) == item_indent)
+            .collect();
+
+        starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let item_end = starts.get(n + 1).copied().unwrap_or(end);
+                let line = &self.lines[start];
+                let after_dash =
+                    line.trim_start()[1..].len() - line.trim_start()[1..].trim_start().len();
+                (start, item_end, indent(line) + 1 + after_dash)
+            })
+            .collect()
+    }
+
+    fn item_name(&self, start: usize, end: usize, field_indent: usize) -> Option<String> {
+        (start..end).find_map(|i| {
+            let line = &self.lines[i];
+            let body = if i == start {
+                line.trim_start().trim_start_matches('-').trim_start()
+            } else if indent(line) == field_indent {
+                line.trim_start()
+            } else {
+                return None;
+            };
+            let raw = field_value(body, "name")?;
+            serde_yml::from_str::<String>(strip_comment(raw).trim()).ok()
+        })
+    }
+
+    /// Replace the value on a `key: value` line, keeping any trailing comment, and drop a
+    /// nested block value below it
+    fn replace_value(&mut self, line: usize, field_indent: usize, value: &str) {
+        let text = &self.lines[line];
+        let colon = text.find(':').unwrap_or(text.len());
+        let rest = &text[colon + 1..];
+        let comment = &rest[strip_comment(rest).len()..];
+        let comment = if comment.trim().is_empty() {
+            String::new()
+        } else {
+            format!("  {}", comment.trim())
+        };
+        self.lines[line] = format!("{}: {}{}", &text[..colon], value, comment);
+
+        let children_end = block_end(&self.lines, line, field_indent);
+        let last = last_content_line(&self.lines, line, children_end);
+        self.lines.drain(line + 1..=last);
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+fn is_item_start(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed == "-" || trimmed.starts_with("- ")
+}
+
+/// The value after `key:` on a line starting with that key
+fn field_value<'l>(line: &'l str, key: &str) -> Option<&'l str> {
+    let rest = line.strip_prefix(key)?;
+    let value = rest.strip_prefix(':')?;
+    (value.is_empty() || value.starts_with(char::is_whitespace)).then_some(value)
+}
+
+/// The line range nested under the line at `start` (with the given indent): content
+/// lines indented further, or list items at the same indent for a top-level key
+fn block_end(lines: &[String], start: usize, parent_indent: usize) -> usize {
+    let mut end = start + 1;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if !is_content(line) {
+            continue;
+        }
+        let nested = indent(line) > parent_indent
+            || (parent_indent == 0 && indent(line) == 0 && is_item_start(line));
+        if !nested {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+/// The last content line in `start..end` (or `start` when there's none after it), so
+/// trailing comments and blank lines stay with whatever follows
+fn last_content_line(lines: &[String], start: usize, end: usize) -> usize {
+    (start..end)
+        .rev()
+        .find(|&i| is_content(&lines[i]))
+        .unwrap_or(start)
+}
+
+/// A value without its trailing `# comment` (quotes are respected)
+fn strip_comment(value: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in value.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if previous.is_whitespace() => return value[..i].trim_end(),
+            _ => {}
+        }
+        previous = c;
+    }
+    value.trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const CONFIG: &str = "\
+# Project checks
+trailer: full  # how commits are signed
+
+verifications:
+  - name: build
+    command: cargo build  # the main build
+    cache_paths:
+      - \"src/**/*.rs\"
+
+  # Runs the tests
+  - name: test
+    command: cargo test
+    depends_on: [build]
+";
+
+    fn write_config(content: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("verify.yaml");
+        fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_get() {
+        let (_dir, path) = write_config(CONFIG);
+        assert_eq!(get(&path, "trailer").unwrap(), "full");
+        assert_eq!(get(&path, "build.command").unwrap(), "cargo build");
+        assert_eq!(get(&path, "test.depends_on").unwrap(), "- build");
+        assert!(get(&path, "test.timeout_secs").is_err());
+        assert!(get(&path, "missing.command").is_err());
+    }
+
+    #[test]
+    fn test_set_keeps_comments_and_order() {
+        let (_dir, path) = write_config(CONFIG);
+        set(&path, "build.command", "cargo build --release").unwrap();
+        set(&path, "test.timeout_secs", "300").unwrap();
+        set(&path, "trailer", "compact").unwrap();
+        set(&path, "record_user", "true").unwrap();
+
+        let expected = "\
+# Project checks
+trailer: compact  # how commits are signed
+
+verifications:
+  - name: build
+    command: cargo build --release  # the main build
+    cache_paths:
+      - \"src/**/*.rs\"
+
+  # Runs the tests
+  - name: test
+    command: cargo test
+    depends_on: [build]
+    timeout_secs: 300
+record_user: true
+";
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_set_replaces_block_value() {
+        let (_dir, path) = write_config(CONFIG);
+        set(&path, "build.cache_paths", "[\"src/**\", Cargo.toml]").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("    cache_paths: [\"src/**\", Cargo.toml]\n\n  # Runs"));
+        assert!(!content.contains("src/**/*.rs"));
+    }
+
+    #[test]
+    fn test_set_rejects_unknown_and_invalid_keys() {
+        let (_dir, path) = write_config(CONFIG);
+        let err = set(&path, "build.timout_secs", "300").unwrap_err();
+        assert!(err.to_string().contains("Unknown config key"), "{}", err);
+        assert!(set(&path, "defaults.timeout_secs", "300").is_err());
+        // Values that don't load leave the file alone
+        assert!(set(&path, "build.timeout_secs", "soon").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), CONFIG);
+    }
+
+    #[test]
+    fn test_add_check() {
+        let (_dir, path) = write_config(CONFIG);
+        let check = NewCheck {
+            name: "lint".to_string(),
+            command: Some("npm run lint".into()),
+            cache_paths: vec!["**/*.ts".to_string()],
+            depends_on: vec!["build".to_string()],
+        };
+        add_check(&path, &check).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(
+            content.ends_with(
+                "    depends_on: [build]\n  - name: lint\n    command: npm run lint\n    cache_paths:\n      - '**/*.ts'\n    depends_on:\n      - build\n"
+            ),
+            "{}",
+            content
+        );
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.get("lint").unwrap().cache_paths, vec!["**/*.ts"]);
+        assert!(add_check(&path, &check).is_err());
+    }
+
+    #[test]
+    fn test_add_check_to_empty_list() {
+        let (_dir, path) = write_config("verifications: []\n");
+        let check = NewCheck {
+            name: "build".to_string(),
+            command: Some("echo \"a: b\"".to_string()),
+            cache_paths: vec![],
+            depends_on: vec![],
+        };
+        add_check(&path, &check).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.get("build").unwrap().command.as_deref(),
+            Some("echo \"a: b\"")
+        );
+    }
+}
@@ -0,0 +1,481 @@
+use serde_yml::libyml::parser::{Event, Parser, ScalarStyle};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Every problem found while validating a config, reported together so a large config
+/// doesn't have to be fixed one error at a time
+#[derive(Debug)]
+pub struct ValidationErrors(pub Vec<Issue>);
+
+/// A single validation problem and the part of verify.yaml it's about
+#[derive(Debug)]
+pub struct Issue {
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+/// Where in verify.yaml a problem is, described by what's there rather than by position
+/// (checks expanded from a matrix have no location of their own)
+#[derive(Debug, Clone)]
+pub enum Location {
+    /// The `occurrence`th (from 0) entry of `verifications` with this name, or one of its
+    /// fields, or a single value of that field (e.g. one `depends_on` entry)
+    Item {
+        name: String,
+        occurrence: usize,
+        field: Option<&'static str>,
+        value: Option<String>,
+    },
+    /// A top-level key, or a single value of it (e.g. one `sign_checks` entry)
+    TopLevel {
+        key: &'static str,
+        value: Option<String>,
+    },
+}
+
+impl Issue {
+    pub fn new(message: String, location: Location) -> Self {
+        Self {
+            message,
+            location: Some(location),
+        }
+    }
+
+    pub fn at_item(message: String, name: &str, field: Option<&'static str>) -> Self {
+        Self::new(
+            message,
+            Location::Item {
+                name: name.to_string(),
+                occurrence: 0,
+                field,
+                value: None,
+            },
+        )
+    }
+
+    pub fn at_value(message: String, name: &str, field: &'static str, value: &str) -> Self {
+        Self::new(
+            message,
+            Location::Item {
+                name: name.to_string(),
+                occurrence: 0,
+                field: Some(field),
+                value: Some(value.to_string()),
+            },
+        )
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.0.iter().map(|issue| issue.message.as_str()).collect();
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl ValidationErrors {
+    /// Describe each problem with its file, line and column and the line itself, pointing
+    /// at the offending value. `sources` are the config file and then its verify.d
+    /// fragments, in the order their checks were merged.
+    pub fn render(&self, sources: &[(PathBuf, String)]) -> String {
+        let path = &sources[0].0;
+        let parsed: Vec<(&Path, Option<Node>, Vec<&str>)> = sources
+            .iter()
+            .map(|(path, content)| (path.as_path(), parse(content), content.lines().collect()))
+            .collect();
+        let described: Vec<String> = self
+            .0
+            .iter()
+            .map(|issue| {
+                let found = issue
+                    .location
+                    .as_ref()
+                    .and_then(|location| find_in_sources(&parsed, location));
+                match found {
+                    Some((source, span)) => format!(
+                        "{}:{}:{}: {}{}",
+                        parsed[source].0.display(),
+                        span.line + 1,
+                        span.column + 1,
+                        issue.message,
+                        snippet(&parsed[source].2, &span)
+                    ),
+                    None => format!("{}: {}", path.display(), issue.message),
+                }
+            })
+            .collect();
+
+        match described.as_slice() {
+            [single] => single.clone(),
+            _ => format!(
+                "{} problems in {}:\n\n{}",
+                described.len(),
+                path.display(),
+                described.join("\n\n")
+            ),
+        }
+    }
+}
+
+/// The line (from 1) of the `name` of check `name` in a config file's content
+pub fn check_line(content: &str, name: &str) -> Option<usize> {
+    let location = Location::Item {
+        name: name.to_string(),
+        occurrence: 0,
+        field: None,
+        value: None,
+    };
+    find(&parse(content)?, &location).map(|span| span.line + 1)
+}
+
+/// Zero-based position and width of a YAML node
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    line: usize,
+    column: usize,
+    width: usize,
+}
+
+/// The line a span is on, underlined from the span's start
+fn snippet(lines: &[&str], span: &Span) -> String {
+    let Some(line) = lines.get(span.line) else {
+        return String::new();
+    };
+    let number = (span.line + 1).to_string();
+    let gutter = " ".repeat(number.len());
+    let available = line.chars().count().saturating_sub(span.column).max(1);
+    format!(
+        "\n {} |\n {} | {}\n {} | {}{}",
+        gutter,
+        number,
+        line,
+        gutter,
+        " ".repeat(span.column),
+        "^".repeat(span.width.clamp(1, available))
+    )
+}
+
+/// A YAML node with where it starts
+#[derive(Debug)]
+enum Node {
+    Scalar {
+        value: String,
+        span: Span,
+    },
+    Sequence {
+        items: Vec<Node>,
+        span: Span,
+    },
+    Mapping {
+        entries: Vec<(Node, Node)>,
+        span: Span,
+    },
+    Alias {
+        span: Span,
+    },
+}
+
+impl Node {
+    fn span(&self) -> Span {
+        match self {
+            Node::Scalar { span, .. }
+            | Node::Sequence { span, .. }
+            | Node::Mapping { span, .. }
+            | Node::Alias { span } => *span,
+        }
+    }
+
+    fn scalar(&self) -> Option<&str> {
+        match self {
+            Node::Scalar { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The key node and value node of a mapping entry
+    fn entry(&self, key: &str) -> Option<(&Node, &Node)> {
+        match self {
+            Node::Mapping { entries, .. } => entries
+                .iter()
+                .find(|(k, _)| k.scalar() == Some(key))
+                .map(|(k, v)| (k, v)),
+            _ => None,
+        }
+    }
+
+    /// The node for `value`: this scalar, the matching entry of this sequence, or the
+    /// matching key of this mapping
+    fn value(&self, value: &str) -> Option<&Node> {
+        match self {
+            Node::Scalar { value: v, .. } if v == value => Some(self),
+            Node::Sequence { items, .. } => items.iter().find(|item| item.scalar() == Some(value)),
+            Node::Mapping { .. } => self.entry(value).map(|(key, _)| key),
+            _ => None,
+        }
+    }
+}
+
+/// The source a location is in and its span there. An item's occurrence counts across
+/// the sources in order.
+fn find_in_sources(
+    sources: &[(&Path, Option<Node>, Vec<&str>)],
+    location: &Location,
+) -> Option<(usize, Span)> {
+    let mut location = location.clone();
+    for (index, (_, root, _)) in sources.iter().enumerate() {
+        let Some(root) = root else {
+            continue;
+        };
+        if let Some(span) = find(root, &location) {
+            return Some((index, span));
+        }
+        if let Location::Item {
+            name, occurrence, ..
+        } = &mut location
+        {
+            let count = items(root)
+                .iter()
+                .filter(|item| item_name(item) == Some(name.as_str()))
+                .count();
+            *occurrence = occurrence.checked_sub(count)?;
+        }
+    }
+    None
+}
+
+fn items(root: &Node) -> &[Node] {
+    match root.entry("verifications") {
+        Some((_, Node::Sequence { items, .. })) => items,
+        _ => &[],
+    }
+}
+
+fn item_name(item: &Node) -> Option<&str> {
+    item.entry("name").and_then(|(_, v)| v.scalar())
+}
+
+fn find(root: &Node, location: &Location) -> Option<Span> {
+    match location {
+        Location::TopLevel { key, value } => {
+            let (key, node) = root.entry(key)?;
+            let target = value.as_deref().and_then(|value| node.value(value));
+            Some(target.unwrap_or(key).span())
+        }
+        Location::Item {
+            name,
+            occurrence,
+            field,
+            value,
+        } => {
+            let item = items(root)
+                .iter()
+                .filter(|item| item_name(item) == Some(name))
+                .nth(*occurrence)?;
+            let Some((key, node)) = field.and_then(|field| item.entry(field)) else {
+                // No field, or one that isn't written out (e.g. implied by another field)
+                return item.entry("name").map(|(_, name)| name.span());
+            };
+            let target = value.as_deref().and_then(|value| node.value(value));
+            Some(target.unwrap_or(key).span())
+        }
+    }
+}
+
+/// Parse YAML into nodes with their positions, or None if it doesn't parse (in which case
+/// serde already reported the syntax error)
+fn parse(content: &str) -> Option<Node> {
+    let mut parser = Parser::new(Cow::Borrowed(content.as_bytes()));
+    loop {
+        match parser.parse_next_event().ok()? {
+            (Event::StreamStart | Event::DocumentStart, _) => continue,
+            (Event::StreamEnd, _) => return None,
+            (event, mark) => return node(&mut parser, event, span_at(mark, 1)),
+        }
+    }
+}
+
+fn node(parser: &mut Parser, event: Event, span: Span) -> Option<Node> {
+    match event {
+        Event::Scalar(scalar) => {
+            let value = String::from_utf8_lossy(&scalar.value).into_owned();
+            let width = match scalar.style {
+                ScalarStyle::Plain => value.chars().count(),
+                ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => value.chars().count() + 2,
+                ScalarStyle::Literal | ScalarStyle::Folded => 1,
+            };
+            Some(Node::Scalar {
+                value,
+                span: Span { width, ..span },
+            })
+        }
+        Event::Alias(_) => Some(Node::Alias { span }),
+        Event::SequenceStart(_) => {
+            let mut items = Vec::new();
+            loop {
+                match parser.parse_next_event().ok()? {
+                    (Event::SequenceEnd, _) => return Some(Node::Sequence { items, span }),
+                    (event, mark) => items.push(node(parser, event, span_at(mark, 1))?),
+                }
+            }
+        }
+        Event::MappingStart(_) => {
+            let mut entries = Vec::new();
+            loop {
+                let key = match parser.parse_next_event().ok()? {
+                    (Event::MappingEnd, _) => return Some(Node::Mapping { entries, span }),
+                    (event, mark) => node(parser, event, span_at(mark, 1))?,
+                };
+                let (event, mark) = parser.parse_next_event().ok()?;
+                let value = node(parser, event, span_at(mark, 1))?;
+                entries.push((key, value));
+            }
+        }
+        _ => None,
+    }
+}
+
+fn span_at(mark: serde_yml::libyml::error::Mark, width: usize) -> Span {
+    Span {
+        line: mark.line() as usize,
+        column: mark.column() as usize,
+        width,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "verifications:
+  - name: build
+    command: make
+  - name: test
+    command: make test
+    depends_on: [build, biuld]
+  - name: test
+    command: make test
+sign_checks:
+  - \"deploy\"
+";
+
+    #[test]
+    fn test_find_locations() {
+        let root = parse(CONFIG).unwrap();
+        let unknown_dep = Location::Item {
+            name: "test".to_string(),
+            occurrence: 0,
+            field: Some("depends_on"),
+            value: Some("biuld".to_string()),
+        };
+        assert_eq!(
+            find(&root, &unknown_dep),
+            Some(Span {
+                line: 5,
+                column: 24,
+                width: 5
+            })
+        );
+
+        let duplicate = Location::Item {
+            name: "test".to_string(),
+            occurrence: 1,
+            field: None,
+            value: None,
+        };
+        assert_eq!(find(&root, &duplicate).map(|s| s.line), Some(6));
+        assert_eq!(check_line(CONFIG, "test"), Some(4));
+        assert_eq!(check_line(CONFIG, "lint"), None);
+
+        let sign_check = Location::TopLevel {
+            key: "sign_checks",
+            value: Some("deploy".to_string()),
+        };
+        assert_eq!(
+            find(&root, &sign_check),
+            Some(Span {
+                line: 9,
+                column: 4,
+                width: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_mapping_key() {
+        let root = parse(
+            "verifications:\n  - name: test\n    metadata:\n      passed: '(\\d+) passed'\n      coverage: '([\\d.]+%'\n",
+        )
+        .unwrap();
+        let metadata_key = Location::Item {
+            name: "test".to_string(),
+            occurrence: 0,
+            field: Some("metadata"),
+            value: Some("coverage".to_string()),
+        };
+        assert_eq!(
+            find(&root, &metadata_key),
+            Some(Span {
+                line: 4,
+                column: 6,
+                width: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_all_issues_with_snippets() {
+        let errors = ValidationErrors(vec![
+            Issue::at_value(
+                "Verification 'test' depends on unknown check: biuld".to_string(),
+                "test",
+                "depends_on",
+                "biuld",
+            ),
+            Issue {
+                message: "Something without a location".to_string(),
+                location: None,
+            },
+        ]);
+        let rendered = errors.render(&[(PathBuf::from("verify.yaml"), CONFIG.to_string())]);
+        assert_eq!(
+            rendered,
+            "2 problems in verify.yaml:
+
+verify.yaml:6:25: Verification 'test' depends on unknown check: biuld
+   |
+ 6 |     depends_on: [build, biuld]
+   |                         ^^^^^
+
+verify.yaml: Something without a location"
+        );
+    }
+
+    #[test]
+    fn test_render_issues_in_fragments() {
+        let fragment = "verifications:\n  - name: test\n    command: cargo test\n";
+        let sources = [
+            (PathBuf::from("verify.yaml"), CONFIG.to_string()),
+            (PathBuf::from("verify.d/backend.yaml"), fragment.to_string()),
+        ];
+        // The third `test` is the fragment's first
+        let errors = ValidationErrors(vec![Issue::new(
+            "Duplicate verification name: test".to_string(),
+            Location::Item {
+                name: "test".to_string(),
+                occurrence: 2,
+                field: None,
+                value: None,
+            },
+        )]);
+        assert!(
+            errors
+                .render(&sources)
+                .starts_with("verify.d/backend.yaml:2:11: Duplicate verification name: test"),
+            "{}",
+            errors.render(&sources)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::bundle::bundle_key;
+use crate::config::Config;
+use crate::hasher::{hashed_files, is_verify_file, tracked_files};
+use crate::output::{CoverageOutput, DirectoryCoverageJson};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Which files tracked by git are hashed by at least one check, in the project or any
+/// subproject. verify's own files aren't counted. Directories are grouped by their first
+/// `depth` components.
+pub fn coverage(project_root: &Path, config: &Config, depth: usize) -> Result<CoverageOutput> {
+    let Some(tracked) = tracked_files(project_root) else {
+        anyhow::bail!("verify coverage needs a git repository, to know which files to cover");
+    };
+    let mut hashed = HashSet::new();
+    collect_hashed(project_root, config, "", &mut hashed)?;
+
+    let mut files: Vec<String> = tracked
+        .into_iter()
+        .filter(|file| !is_verify_file(file))
+        .collect();
+    files.sort();
+
+    let mut directories: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut uncovered = Vec::new();
+    for file in &files {
+        let covered = hashed.contains(file);
+        let counts = directories.entry(directory(file, depth)).or_default();
+        counts.0 += 1;
+        if covered {
+            counts.1 += 1;
+        } else {
+            uncovered.push(file.clone());
+        }
+    }
+
+    Ok(CoverageOutput {
+        total: files.len(),
+        covered: files.len() - uncovered.len(),
+        percent: percent(files.len() - uncovered.len(), files.len()),
+        directories: directories
+            .into_iter()
+            .map(|(path, (total, covered))| DirectoryCoverageJson {
+                path,
+                total,
+                covered,
+                percent: percent(covered, total),
+            })
+            .collect(),
+        uncovered,
+    })
+}
+
+/// The files hashed by the checks of `config` and, recursively, its subprojects, relative
+/// to the root (`prefix` is the project's directory, with a trailing `/`)
+fn collect_hashed(
+    project_dir: &Path,
+    config: &Config,
+    prefix: &str,
+    hashed: &mut HashSet<String>,
+) -> Result<()> {
+    for check in config.verifications_only() {
+        for file in hashed_files(project_dir, &check.cache_paths, check.glob_options())? {
+            hashed.insert(format!("{}{}", prefix, file));
+        }
+    }
+    for subproject in config.subprojects() {
+        let dir = project_dir.join(&subproject.path);
+        let sub_config = Config::load_with_base(&dir.join("verify.yaml"), &dir)?;
+        let sub_prefix = format!("{}{}/", prefix, bundle_key(&subproject.path));
+        collect_hashed(&dir, &sub_config, &sub_prefix, hashed)?;
+    }
+    Ok(())
+}
+
+/// Percentage rounded to one decimal place (100 when there's nothing to cover)
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    (covered as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+/// The first `depth` directories of a file's path, e.g. `src/cli/` for `src/cli/args.rs`
+/// at depth 2, or `./` for files at the root
+fn directory(file: &str, depth: usize) -> String {
+    let dirs: Vec<&str> = file.split('/').collect();
+    let dirs = &dirs[..dirs.len() - 1];
+    if dirs.is_empty() || depth == 0 {
+        return "./".to_string();
+    }
+    format!("{}/", dirs[..depth.min(dirs.len())].join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory() {
+        assert_eq!(directory("README.md", 1), "./");
+        assert_eq!(directory("src/main.rs", 1), "src/");
+        assert_eq!(directory("src/cli/args.rs", 1), "src/");
+        assert_eq!(directory("src/cli/args.rs", 2), "src/cli/");
+        assert_eq!(directory("src/main.rs", 3), "src/");
+        assert_eq!(percent(2, 3), 66.7);
+        assert_eq!(percent(0, 0), 100.0);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::cache::{CacheState, UnverifiedReason, VerificationStatus};
+use crate::config::{Config, VerificationItem};
+use crate::hashcache;
+use crate::runner;
+use crate::ui;
+use crate::watch::FileWatcher;
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the dashboard redraws to keep ages current when nothing changes
+const TICK: Duration = Duration::from_secs(1);
+
+/// A check or subproject shown in `verify status --watch`
+pub struct DashboardRow {
+    pub name: String,
+    pub state: DashboardState,
+    /// When the row became unverified, if that can be told from file times
+    pub stale_since: Option<SystemTime>,
+}
+
+pub enum DashboardState {
+    Check(VerificationStatus),
+    Subproject { has_stale: bool },
+}
+
+impl DashboardRow {
+    pub fn is_verified(&self) -> bool {
+        matches!(
+            self.state,
+            DashboardState::Check(VerificationStatus::Verified)
+                | DashboardState::Subproject { has_stale: false }
+        )
+    }
+}
+
+/// Something the dashboard reacts to
+enum Input {
+    Key(KeyCode),
+    Quit,
+    FilesChanged,
+    Tick,
+}
+
+/// Puts the terminal in raw mode on an alternate screen, restoring it when dropped
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+
+    fn leave() -> Result<()> {
+        execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        Ok(())
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = Self::leave();
+    }
+}
+
+/// Run the live status dashboard until the user quits
+pub fn run_status_watch(project_root: &Path, config_path: &Path, verbose: bool) -> Result<()> {
+    if !console::Term::stdout().is_term() {
+        anyhow::bail!("status --watch needs an interactive terminal");
+    }
+
+    let watcher = FileWatcher::new(project_root)?;
+    let mut rows = Vec::new();
+    let mut error = None;
+    let mut message = None;
+    refresh(project_root, config_path, &mut rows, &mut error);
+    let mut selected = 0;
+
+    let screen = Screen::enter()?;
+    loop {
+        selected = selected.min(rows.len().saturating_sub(1));
+        draw(&rows, selected, error.as_deref().or(message.as_deref()))?;
+
+        match next_input(&watcher)? {
+            Input::Quit => break,
+            Input::Key(KeyCode::Up | KeyCode::Char('k')) => {
+                selected = selected.saturating_sub(1);
+            }
+            Input::Key(KeyCode::Down | KeyCode::Char('j')) => {
+                selected += 1;
+            }
+            Input::Key(KeyCode::Enter | KeyCode::Char('r')) => {
+                if let Some(row) = rows.get(selected) {
+                    let names = vec![row.name.clone()];
+                    message = run_outside_dashboard(project_root, config_path, names, verbose)?;
+                    refresh(project_root, config_path, &mut rows, &mut error);
+                }
+            }
+            Input::Key(KeyCode::Char('a')) => {
+                message = run_outside_dashboard(project_root, config_path, Vec::new(), verbose)?;
+                refresh(project_root, config_path, &mut rows, &mut error);
+            }
+            Input::Key(_) | Input::Tick => {}
+            Input::FilesChanged => refresh(project_root, config_path, &mut rows, &mut error),
+        }
+    }
+    drop(screen);
+    Ok(())
+}
+
+/// Wait for a key press, a file change, or the next tick
+fn next_input(watcher: &FileWatcher) -> Result<Input> {
+    let deadline = Instant::now() + TICK;
+    while Instant::now() < deadline {
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            let ctrl_c =
+                key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(Input::Quit);
+            }
+            return Ok(Input::Key(key.code));
+        }
+        if !watcher.wait(Duration::ZERO).is_empty() {
+            return Ok(Input::FilesChanged);
+        }
+    }
+    Ok(Input::Tick)
+}
+
+fn draw(rows: &[DashboardRow], selected: usize, message: Option<&str>) -> Result<()> {
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(terminal::ClearType::All)
+    )?;
+    // Raw mode doesn't translate \n, so return to the first column explicitly
+    for line in ui::dashboard_lines(rows, selected, message, SystemTime::now()) {
+        write!(stdout, "{}\r\n", line)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Reload config and cache and recompute rows. Errors (e.g. a config mid-edit that
+/// doesn't parse) are shown in the dashboard, keeping the last good rows, rather than
+/// ending it.
+fn refresh(
+    project_root: &Path,
+    config_path: &Path,
+    rows: &mut Vec<DashboardRow>,
+    error: &mut Option<String>,
+) {
+    match load_rows(project_root, config_path) {
+        Ok(new_rows) => {
+            *rows = new_rows;
+            *error = None;
+        }
+        Err(e) => *error = Some(format!("{:#}", e)),
+    }
+    hashcache::save_all();
+}
+
+fn load_rows(project_root: &Path, config_path: &Path) -> Result<Vec<DashboardRow>> {
+    let config = Config::load(config_path)?;
+    let cache = CacheState::load(project_root, &Arc::new(config.settings(project_root)))?;
+    let (mut statuses, subprojects_stale) =
+        runner::compute_statuses(project_root, &config, &cache)?;
+
+    let rows = config
+        .verifications
+        .iter()
+        .filter_map(|item| match item {
+            VerificationItem::Verification(v) => {
+                let status = statuses.remove(&v.name)?;
+                Some(DashboardRow {
+                    name: v.name.clone(),
+                    stale_since: stale_since(project_root, config_path, &status),
+                    state: DashboardState::Check(status),
+                })
+            }
+            VerificationItem::Subproject(s) => Some(DashboardRow {
+                name: s.name.clone(),
+                state: DashboardState::Subproject {
+                    has_stale: subprojects_stale.get(&s.name).copied().unwrap_or(true),
+                },
+                stale_since: None,
+            }),
+        })
+        .collect();
+    Ok(rows)
+}
+
+/// Estimate when a check became unverified: the oldest modification among its changed
+/// files (everything changed since it last passed), or the config edit that changed it
+fn stale_since(
+    project_root: &Path,
+    config_path: &Path,
+    status: &VerificationStatus,
+) -> Option<SystemTime> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match status {
+        VerificationStatus::Unverified {
+            reason: UnverifiedReason::FilesChanged { changed_files },
+        } => changed_files
+            .iter()
+            // Entries are "M path", "+ path" or "- path"; deleted files have no mtime
+            .filter_map(|entry| entry.split_once(' '))
+            .filter_map(|(_, path)| modified(&project_root.join(path)))
+            .min(),
+        VerificationStatus::Unverified {
+            reason: UnverifiedReason::ConfigChanged { .. },
+        } => modified(config_path),
+        _ => None,
+    }
+}
+
+/// Leave the dashboard to run checks with normal output, then return to it.
+/// On failure, waits for a key so the output can be read first.
+fn run_outside_dashboard(
+    project_root: &Path,
+    config_path: &Path,
+    names: Vec<String>,
+    verbose: bool,
+) -> Result<Option<String>> {
+    Screen::leave()?;
+    let result = (|| -> Result<i32> {
+        let config = Config::load(config_path)?;
+        let settings = Arc::new(config.settings(project_root));
+        let mut cache = CacheState::load(project_root, &settings)?;
+        let (code, _) = runner::run_checks(
+            project_root,
+            &config,
+            &mut cache,
+            names,
+            runner::RunOptions {
+                verbose,
+                ..Default::default()
+            },
+        )?;
+        Ok(code)
+    })();
+
+    let (message, failed) = match result {
+        Ok(0) => ("Last run passed".to_string(), false),
+        Ok(_) => ("Last run failed".to_string(), true),
+        Err(e) => (format!("Run failed: {:#}", e), true),
+    };
+    if failed {
+        println!("\nPress any key to return to the dashboard");
+        terminal::enable_raw_mode()?;
+        loop {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                break;
+            }
+        }
+        terminal::disable_raw_mode()?;
+    }
+
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+    Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_stale_since_uses_oldest_changed_file() {
+        let dir = tempdir().unwrap();
+        let older = dir.path().join("older.txt");
+        let newer = dir.path().join("newer.txt");
+        fs::write(&older, "a").unwrap();
+        fs::write(&newer, "b").unwrap();
+        let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&older)
+            .unwrap()
+            .set_modified(hour_ago)
+            .unwrap();
+
+        let status = VerificationStatus::Unverified {
+            reason: UnverifiedReason::FilesChanged {
+                changed_files: vec![
+                    "+ newer.txt".to_string(),
+                    "- deleted.txt".to_string(),
+                    "M older.txt".to_string(),
+                ],
+            },
+        };
+        let since = stale_since(dir.path(), &dir.path().join("verify.yaml"), &status);
+        assert_eq!(since, Some(hour_ago));
+    }
+
+    #[test]
+    fn test_stale_since_unknown_for_other_reasons() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("verify.yaml");
+        let never_run = VerificationStatus::Unverified {
+            reason: UnverifiedReason::NeverRun,
+        };
+        assert_eq!(stale_since(dir.path(), &config_path, &never_run), None);
+        assert_eq!(
+            stale_since(dir.path(), &config_path, &VerificationStatus::Verified),
+            None
+        );
+    }
+
+    #[test]
+    fn test_load_rows_includes_checks_in_config_order() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("verify.yaml");
+        fs::write(
+            &config_path,
+            r#"verifications:
+  - name: lint
+    command: "true"
+    cache_paths: ["*.txt"]
+  - name: build
+    command: "true"
+"#,
+        )
+        .unwrap();
+
+        let rows = load_rows(dir.path(), &config_path).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["lint", "build"]);
+        assert!(!rows[0].is_verified());
+        assert!(matches!(
+            rows[1].state,
+            DashboardState::Check(VerificationStatus::Untracked)
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::cache::CacheState;
+use crate::config::Verification;
+use crate::hasher::{hashed_files, matches_cache_paths};
+use crate::output::{DiffFileJson, DiffOutput};
+use crate::trailer::git;
+use crate::ui::Ui;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How many of the commits that touched a file are searched for its recorded content
+const HISTORY_DEPTH: usize = 50;
+
+/// A file of a check that changed since it was verified
+#[derive(Debug)]
+pub struct FileChange {
+    pub path: String,
+    pub change: Change,
+    /// Content matching the recorded hash, found in git, and where (`index`, `HEAD` or a
+    /// commit); None when it wasn't found or only names are known
+    pub recorded: Option<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    fn marker(self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Modified => "M",
+            Change::Deleted => "-",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Modified => "modified",
+            Change::Deleted => "deleted",
+        }
+    }
+}
+
+/// Files whose current hashes differ from the per-file hashes recorded in verify.lock, with
+/// the recorded content of each recovered from git where possible
+pub fn recorded_changes(
+    project_root: &Path,
+    recorded: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    for (path, hash) in current {
+        let change = match recorded.get(path) {
+            None => Change::Added,
+            Some(recorded) if recorded != hash => Change::Modified,
+            Some(_) => continue,
+        };
+        changes.push((path, change));
+    }
+    for path in recorded.keys() {
+        if !current.contains_key(path) {
+            changes.push((path, Change::Deleted));
+        }
+    }
+    changes.sort_by(|a, b| a.0.cmp(b.0));
+
+    changes
+        .into_iter()
+        .map(|(path, change)| FileChange {
+            path: path.clone(),
+            change,
+            recorded: recorded
+                .get(path)
+                .and_then(|hash| recover(project_root, path, hash)),
+        })
+        .collect()
+}
+
+/// Files of `check` with changes since HEAD, by name only. For checks without per-file
+/// hashes in verify.lock, where which files changed since it was verified isn't known.
+pub fn changes_since_head(project_root: &Path, check: &Verification) -> Result<Vec<FileChange>> {
+    let diff = git(
+        project_root,
+        &[
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            "-z",
+            "HEAD",
+        ],
+    )?;
+    let untracked = git(
+        project_root,
+        &["ls-files", "--others", "--exclude-standard", "-z"],
+    )?;
+
+    let mut changes = Vec::new();
+    let mut fields = diff.split('\0');
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        let change = match status {
+            "A" => Change::Added,
+            "D" => Change::Deleted,
+            _ => Change::Modified,
+        };
+        changes.push((path.to_string(), change));
+    }
+    changes.extend(
+        untracked
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| (path.to_string(), Change::Added)),
+    );
+    // Only files the check hashes count, and deleted files it would have hashed
+    let hashed = hashed_files(project_root, &check.cache_paths, check.glob_options())?;
+    changes.retain(|(path, change)| match change {
+        Change::Deleted => matches_cache_paths(path, &check.cache_paths, check.glob_options()),
+        _ => hashed.contains(path),
+    });
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(changes
+        .into_iter()
+        .map(|(path, change)| FileChange {
+            path,
+            change,
+            recorded: None,
+        })
+        .collect())
+}
+
+/// Find the content of `path` whose hash is `hash` in the index, HEAD, or the last commits
+/// that touched the file
+fn recover(project_root: &Path, path: &str, hash: &str) -> Option<(String, Vec<u8>)> {
+    let depth = format!("-{}", HISTORY_DEPTH);
+    let history =
+        git(project_root, &["log", "--format=%H", &depth, "--", path]).unwrap_or_default();
+    let revisions = ["", "HEAD"]
+        .into_iter()
+        .chain(history.lines().filter(|line| !line.is_empty()));
+
+    for revision in revisions {
+        let output = Command::new("git")
+            .args(["show", &format!("{}:./{}", revision, path)])
+            .current_dir(project_root)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if output.status.success() && blake3::hash(&output.stdout).to_hex().as_str() == hash {
+            let source = match revision {
+                "" => "index".to_string(),
+                "HEAD" => "HEAD".to_string(),
+                commit => commit[..12.min(commit.len())].to_string(),
+            };
+            return Some((source, output.stdout));
+        }
+    }
+    None
+}
+
+/// Show how `check`'s files changed since it was verified: a diff of each file whose
+/// recorded content was found in git (with `tool`, or `diff -u`), and the names of the rest
+pub fn run_diff(
+    project_root: &Path,
+    check: &Verification,
+    cache: &CacheState,
+    tool: Option<&str>,
+    json: bool,
+    ui: &Ui,
+) -> Result<()> {
+    if check.cache_paths.is_empty() {
+        anyhow::bail!(
+            "'{}' has no cache_paths, so it has no files to compare",
+            check.name
+        );
+    }
+    let current = check.compute_hash(project_root)?;
+    let cached = cache.get(&check.name);
+    let recorded = cached.map(|c| &c.file_hashes).filter(|h| !h.is_empty());
+    let content_hash = cached.and_then(|c| c.content_hash.as_deref());
+    if recorded.is_none() && content_hash.is_none() {
+        anyhow::bail!(
+            "'{}' hasn't passed yet, so there's no recorded state to compare with",
+            check.name
+        );
+    }
+
+    let changes = if content_hash == Some(current.combined_hash.as_str()) {
+        Vec::new()
+    } else {
+        match recorded {
+            Some(recorded) => recorded_changes(project_root, recorded, &current.file_hashes),
+            None => changes_since_head(project_root, check)?,
+        }
+    };
+
+    if json {
+        let output = DiffOutput {
+            check: check.name.clone(),
+            per_file: recorded.is_some(),
+            files: changes
+                .iter()
+                .map(|change| DiffFileJson {
+                    path: change.path.clone(),
+                    change: change.change.name().to_string(),
+                    recorded_in: change.recorded.as_ref().map(|(source, _)| source.clone()),
+                })
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        ui.print_no_changes(&check.name);
+        return Ok(());
+    }
+    if recorded.is_none() {
+        ui.print_warning(&format!(
+            "verify.lock only records the files of per_file checks, so these are the files of '{}' changed since HEAD",
+            check.name
+        ));
+    }
+
+    let temp = tempfile::tempdir().context("Failed to create temporary directory")?;
+    for change in &changes {
+        let Some((source, content)) = &change.recorded else {
+            let note = (recorded.is_some() && change.change != Change::Added)
+                .then_some("recorded content not found in git");
+            ui.print_file_change(change.change.marker(), &change.path, note);
+            continue;
+        };
+
+        // Name the recorded copy like the file, for tools that show it
+        let old = temp.path().join(source).join(&change.path);
+        if let Some(dir) = old.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&old, content).with_context(|| format!("Failed to write {}", old.display()))?;
+        let new = match change.change {
+            Change::Deleted => Path::new("/dev/null").to_path_buf(),
+            _ => project_root.join(&change.path),
+        };
+        show_diff(tool, &change.path, source, &old, &new)?;
+    }
+    Ok(())
+}
+
+/// Run `tool` (or `diff -u`) on the recorded and current copies of a file
+fn show_diff(tool: Option<&str>, path: &str, source: &str, old: &Path, new: &Path) -> Result<()> {
+    let status = match tool {
+        Some(tool) => Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\" \"$2\"", tool))
+            .arg("sh")
+            .arg(old)
+            .arg(new)
+            .status(),
+        None => Command::new("diff")
+            .arg("-u")
+            .arg("--label")
+            .arg(format!("{} (verified, {})", path, source))
+            .arg("--label")
+            .arg(path)
+            .arg(old)
+            .arg(new)
+            .status(),
+    }
+    .context("Failed to run diff tool")?;
+    // diff tools exit 1 when the files differ
+    if status.code().is_some_and(|code| code > 1) {
+        anyhow::bail!("Diff tool failed on {}", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_changes() {
+        let hashes = |entries: &[(&str, &str)]| -> BTreeMap<String, String> {
+            entries
+                .iter()
+                .map(|(path, hash)| (path.to_string(), hash.to_string()))
+                .collect()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let changes = recorded_changes(
+            dir.path(),
+            &hashes(&[("a.txt", "1"), ("b.txt", "2"), ("c.txt", "3")]),
+            &hashes(&[("a.txt", "1"), ("b.txt", "5"), ("d.txt", "4")]),
+        );
+        let changes: Vec<(&str, Change)> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("b.txt", Change::Modified),
+                ("c.txt", Change::Deleted),
+                ("d.txt", Change::Added),
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::EventSink;
+use crate::ui::Ui;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How long a URL sink gets to accept an event before it's dropped
+const POST_TIMEOUT_SECS: &str = "5";
+
+/// A check's command finished
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckEvent {
+    /// Always "check_finished"
+    pub event: &'static str,
+    pub at: DateTime<Utc>,
+    pub check: String,
+    /// "pass" or "fail"
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub user: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Whether the `CI` environment variable is set
+    pub ci: bool,
+}
+
+impl CheckEvent {
+    pub fn finished(
+        check: &str,
+        passed: bool,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        user: String,
+        host: Option<String>,
+    ) -> Self {
+        Self {
+            event: "check_finished",
+            at: Utc::now(),
+            check: check.to_string(),
+            result: if passed { "pass" } else { "fail" },
+            exit_code,
+            duration_ms,
+            user,
+            host,
+            ci: std::env::var_os("CI").is_some(),
+        }
+    }
+}
+
+/// Send `event` to every sink, as it happens. Checks of subprojects below `root` are named
+/// `path/name`, and sink commands run in `root`. A sink that fails gets a warning rather
+/// than failing the run.
+pub fn emit(sinks: &[EventSink], root: &Path, project_root: &Path, mut event: CheckEvent) {
+    if let Ok(relative) = project_root.strip_prefix(root)
+        && !relative.as_os_str().is_empty()
+    {
+        event.check = format!("{}/{}", relative.to_string_lossy(), event.check);
+    }
+    let Ok(body) = serde_json::to_vec(&event) else {
+        return;
+    };
+
+    for sink in sinks {
+        let (target, mut command) = match (&sink.url, &sink.command) {
+            (Some(url), _) => {
+                let mut command = Command::new("curl");
+                command.args([
+                    "-sSf",
+                    "-X",
+                    "POST",
+                    "-H",
+                    "Content-Type: application/json",
+                    "--max-time",
+                    POST_TIMEOUT_SECS,
+                    "--data-binary",
+                    "@-",
+                    url,
+                ]);
+                (url, command)
+            }
+            (None, Some(shell)) => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(shell).current_dir(root);
+                (shell, command)
+            }
+            (None, None) => continue,
+        };
+        if let Err(e) = send(&mut command, &body) {
+            Ui::new(false).print_warning(&format!("event to '{}' failed: {}", target, e));
+        }
+    }
+}
+
+/// Run a sink's process with the event on stdin
+fn send(command: &mut Command, body: &[u8]) -> Result<(), String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A sink that exits without reading its input isn't an error
+        let _ = stdin.write_all(body);
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        })
+    }
+}
@@ -12,29 +12,42 @@ pub struct DependencyGraph {
 }
 
 impl DependencyGraph {
-    /// Build a dependency graph from configuration (verifications only, not subprojects)
+    /// Build a dependency graph from configuration. Subprojects are nodes too, so checks
+    /// that depend on one are ordered after it; callers skip the subproject names in
+    /// waves and dependency lists where they only want checks.
     pub fn from_config(config: &Config) -> Result<Self> {
-        Self::from_verifications(&config.verifications_only())
+        let subprojects: Vec<&str> = config
+            .subprojects()
+            .into_iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        Self::from_verifications(&config.verifications_only(), &subprojects)
     }
 
-    /// Build a dependency graph from a list of verifications
-    pub fn from_verifications(verifications: &[&Verification]) -> Result<Self> {
+    /// Build a dependency graph from a list of verifications and the subproject names
+    /// they may depend on
+    pub fn from_verifications(
+        verifications: &[&Verification],
+        subprojects: &[&str],
+    ) -> Result<Self> {
         let mut graph = DiGraph::new();
         let mut name_to_node = HashMap::new();
 
         // Add all nodes
+        for name in subprojects {
+            let node = graph.add_node(name.to_string());
+            name_to_node.insert(name.to_string(), node);
+        }
         for v in verifications {
             let node = graph.add_node(v.name.clone());
             name_to_node.insert(v.name.clone(), node);
         }
 
-        // Add edges (dependency -> dependent)
-        // Skip dependencies that are subprojects (not in this graph)
+        // Add edges (dependency -> dependent). Unknown names are rejected by config
+        // validation, so every dependency has a node.
         for v in verifications {
             let dependent_node = name_to_node[&v.name];
             for dep_name in &v.depends_on {
-                // Only add edge if dependency is a verification (in the graph)
-                // Subproject dependencies are handled separately in the runner
                 if let Some(&dep_node) = name_to_node.get(dep_name) {
                     graph.add_edge(dep_node, dependent_node, ());
                 }
@@ -125,7 +138,6 @@ impl DependencyGraph {
     }
 
     /// Get all transitive dependencies for a check (including the check itself)
-    #[allow(dead_code)]
     pub fn transitive_dependencies(&self, name: &str) -> Vec<String> {
         let mut result = vec![name.to_string()];
 
@@ -143,7 +155,6 @@ impl DependencyGraph {
         result
     }
 
-    #[allow(dead_code)]
     fn collect_deps(&self, node: NodeIndex, visited: &mut HashMap<NodeIndex, bool>) {
         if visited.contains_key(&node) {
             return;
@@ -213,15 +224,13 @@ mod tests {
                 .map(|(name, deps)| {
                     VerificationItem::Verification(Verification {
                         name: name.to_string(),
-                        command: Some("echo test".to_string()),
-                        cache_paths: vec![],
+                        command: Some("echo test".into()),
                         depends_on: deps.into_iter().map(String::from).collect(),
-                        timeout_secs: None,
-                        metadata: std::collections::HashMap::new(),
-                        per_file: false,
+                        ..Default::default()
                     })
                 })
                 .collect(),
+            ..Default::default()
         }
     }
 
@@ -514,4 +523,21 @@ mod tests {
         assert_eq!(deps.len(), 1);
         assert!(deps.contains(&"nonexistent".to_string()));
     }
+
+    #[test]
+    fn test_subproject_dependency_orders_waves() {
+        let config = make_config(vec![("build", vec![]), ("e2e", vec!["web", "build"])]);
+        let graph =
+            DependencyGraph::from_verifications(&config.verifications_only(), &["web"]).unwrap();
+
+        let waves = graph.execution_waves();
+        assert_eq!(waves.len(), 2);
+        assert!(waves[0].contains(&"web".to_string()));
+        assert!(waves[0].contains(&"build".to_string()));
+        assert_eq!(waves[1], vec!["e2e"]);
+
+        let deps = graph.transitive_dependencies("e2e");
+        assert!(deps.contains(&"web".to_string()));
+        assert!(graph.dependencies("web").is_empty());
+    }
 }
@@ -0,0 +1,304 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, Metadata};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HASH_CACHE_VERSION: u32 = 1;
+const HASH_CACHE_DIR: &str = ".verify";
+const HASH_CACHE_FILE: &str = "hashcache";
+
+/// Files modified this recently are never cached. A write within the same filesystem
+/// timestamp tick as the hash could otherwise leave size and mtime unchanged.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Hash caches already loaded by this process, keyed by project root.
+/// This is the first level; `.verify/hashcache` on disk is the second.
+static LOADED: LazyLock<Mutex<HashMap<PathBuf, HashCache>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Cache of file content hashes stored in `.verify/hashcache`, keyed by relative path.
+/// An entry is only reused while the file's size and mtime are unchanged, so repeated
+/// invocations skip rehashing files that haven't been touched.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HashCache {
+    /// Version for future format changes; mismatches start from an empty cache
+    version: u32,
+
+    /// Cached hash for each file, keyed by path relative to the project root
+    files: BTreeMap<String, CachedHash>,
+
+    /// Whether entries were added or replaced since loading
+    #[serde(skip)]
+    dirty: bool,
+
+    /// Hashes of files still in the racy window. They're never saved, and are only reused
+    /// by this process until verify runs a command (see `forget_recent`), since nothing
+    /// else in a verify invocation writes to the project.
+    #[serde(skip)]
+    recent: HashMap<String, CachedHash>,
+}
+
+/// A file hash together with the metadata it was computed for
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct CachedHash {
+    size: u64,
+    mtime_ns: u64,
+    hash: String,
+}
+
+impl HashCache {
+    /// Load the hash cache for a project, returning an empty cache if the file is
+    /// missing, unreadable, or from another version
+    pub fn load(project_root: &Path) -> Self {
+        let path = cache_path(project_root);
+        let cache: Option<HashCache> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
+        match cache {
+            Some(cache) if cache.version == HASH_CACHE_VERSION => cache,
+            _ => Self {
+                version: HASH_CACHE_VERSION,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Save the cache to `.verify/hashcache` atomically, dropping entries for files
+    /// that no longer exist. Does nothing if no entries changed or writes are disabled.
+    pub fn save(&mut self, project_root: &Path) -> Result<()> {
+        if !self.dirty || !statedir::writes_enabled() {
+            return Ok(());
+        }
+        self.files
+            .retain(|relative, _| project_root.join(relative).is_file());
+
+        let path = cache_path(project_root);
+        let dir = statedir::create(project_root)?;
+
+        let temp_path = dir.join(format!("{}.tmp", HASH_CACHE_FILE));
+        let file = File::create(&temp_path).with_context(|| {
+            format!("Failed to create temp hash cache: {}", temp_path.display())
+        })?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .with_context(|| "Failed to serialize hash cache")?;
+        fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to save hash cache: {}", path.display()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Cached hash for a file, if its size and mtime still match the recorded ones
+    pub fn get(&self, relative: &str, metadata: &Metadata) -> Option<&str> {
+        let (size, mtime_ns) = stamp(metadata)?;
+        [self.files.get(relative), self.recent.get(relative)]
+            .into_iter()
+            .flatten()
+            .find(|entry| entry.size == size && entry.mtime_ns == mtime_ns)
+            .map(|entry| entry.hash.as_str())
+    }
+
+    /// Record a freshly computed hash. Files modified within the racy window are only
+    /// remembered in memory, and files whose mtime can't be read not at all.
+    pub fn insert(&mut self, relative: &str, metadata: &Metadata, hash: &str) {
+        let Some((size, mtime_ns)) = stamp(metadata) else {
+            return;
+        };
+        let entry = CachedHash {
+            size,
+            mtime_ns,
+            hash: hash.to_string(),
+        };
+        let modified = UNIX_EPOCH + Duration::from_nanos(mtime_ns);
+        let settled = SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= RACY_WINDOW);
+        if !settled {
+            self.recent.insert(relative.to_string(), entry);
+            return;
+        }
+
+        self.recent.remove(relative);
+        if self.files.get(relative) != Some(&entry) {
+            self.files.insert(relative.to_string(), entry);
+            self.dirty = true;
+        }
+    }
+}
+
+/// Run `f` with the project's hash cache, loading it on first use in this process.
+/// Every hash cache is behind one lock, so `f` should only look up or record entries.
+/// New entries are written by `save_all`.
+pub fn with_hash_cache<T>(project_root: &Path, f: impl FnOnce(&mut HashCache) -> T) -> T {
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = loaded
+        .entry(project_root.to_path_buf())
+        .or_insert_with(|| HashCache::load(project_root));
+    f(cache)
+}
+
+/// Persist new entries in every hash cache loaded by this process. Called once a
+/// command is done hashing rather than after each check. Failing to persist is not an
+/// error, since the cache only saves time.
+pub fn save_all() {
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    for (project_root, cache) in loaded.iter_mut() {
+        let _ = cache.save(project_root);
+    }
+}
+
+/// Drop the in-memory hashes of recently modified files from every loaded hash cache.
+/// Called around each check command, which may rewrite files within the racy window.
+pub fn forget_recent() {
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    for cache in loaded.values_mut() {
+        cache.recent.clear();
+    }
+}
+
+/// Whether a project-relative path is inside the hash cache directory
+pub fn is_cache_path(relative: &str) -> bool {
+    relative
+        .strip_prefix(HASH_CACHE_DIR)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn cache_path(project_root: &Path) -> PathBuf {
+    statedir::state_dir(project_root).join(HASH_CACHE_FILE)
+}
+
+/// Size and mtime (nanoseconds since the epoch) identifying a file's state
+fn stamp(metadata: &Metadata) -> Option<(u64, u64)> {
+    let modified = metadata.modified().ok()?;
+    let mtime_ns = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    Some((metadata.len(), u64::try_from(mtime_ns).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Write a file and move its mtime out of the racy window
+    fn write_settled(path: &Path, content: &str) -> Metadata {
+        fs::write(path, content).unwrap();
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+        fs::metadata(path).unwrap()
+    }
+
+    #[test]
+    fn test_hit_when_size_and_mtime_match() {
+        let dir = tempdir().unwrap();
+        let metadata = write_settled(&dir.path().join("a.txt"), "content");
+
+        let mut cache = HashCache::load(dir.path());
+        cache.insert("a.txt", &metadata, "abc");
+        assert_eq!(cache.get("a.txt", &metadata), Some("abc"));
+        assert_eq!(cache.get("b.txt", &metadata), None);
+    }
+
+    #[test]
+    fn test_miss_when_metadata_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        let metadata = write_settled(&path, "content");
+
+        let mut cache = HashCache::load(dir.path());
+        cache.insert("a.txt", &metadata, "abc");
+
+        // Same size, different mtime
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(30))
+            .unwrap();
+        assert_eq!(cache.get("a.txt", &fs::metadata(&path).unwrap()), None);
+
+        // Different size
+        let metadata = write_settled(&path, "longer content");
+        assert_eq!(cache.get("a.txt", &metadata), None);
+    }
+
+    #[test]
+    fn test_recently_modified_files_not_cached() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "content").unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+
+        let mut cache = HashCache::load(dir.path());
+        cache.insert("a.txt", &metadata, "abc");
+        assert!(!cache.dirty);
+        cache.save(dir.path()).unwrap();
+        assert_eq!(HashCache::load(dir.path()).get("a.txt", &metadata), None);
+
+        // Reused in memory until a command runs
+        assert_eq!(cache.get("a.txt", &metadata), Some("abc"));
+        cache.recent.clear();
+        assert_eq!(cache.get("a.txt", &metadata), None);
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempdir().unwrap();
+        let metadata = write_settled(&dir.path().join("a.txt"), "content");
+        write_settled(&dir.path().join("gone.txt"), "content");
+
+        let mut cache = HashCache::load(dir.path());
+        cache.insert("a.txt", &metadata, "abc");
+        cache.insert("gone.txt", &metadata, "def");
+        fs::remove_file(dir.path().join("gone.txt")).unwrap();
+        cache.save(dir.path()).unwrap();
+
+        let loaded = HashCache::load(dir.path());
+        assert_eq!(loaded.get("a.txt", &metadata), Some("abc"));
+        assert!(!loaded.files.contains_key("gone.txt"));
+    }
+
+    #[test]
+    fn test_entries_written_by_save_all() {
+        let dir = tempdir().unwrap();
+        let metadata = write_settled(&dir.path().join("a.txt"), "content");
+
+        with_hash_cache(dir.path(), |cache| cache.insert("a.txt", &metadata, "abc"));
+        assert!(!cache_path(dir.path()).exists());
+
+        save_all();
+        assert_eq!(
+            HashCache::load(dir.path()).get("a.txt", &metadata),
+            Some("abc")
+        );
+    }
+
+    #[test]
+    fn test_load_ignores_corrupt_and_old_versions() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(HASH_CACHE_DIR)).unwrap();
+        let path = cache_path(dir.path());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(HashCache::load(dir.path()).files.is_empty());
+
+        fs::write(
+            &path,
+            r#"{"version":0,"files":{"a.txt":{"size":1,"mtime_ns":1,"hash":"x"}}}"#,
+        )
+        .unwrap();
+        let cache = HashCache::load(dir.path());
+        assert!(cache.files.is_empty());
+        assert_eq!(cache.version, HASH_CACHE_VERSION);
+    }
+
+    #[test]
+    fn test_is_cache_path() {
+        assert!(is_cache_path(".verify/hashcache"));
+        assert!(!is_cache_path(".verifyrc"));
+        assert!(!is_cache_path("src/.verify/x"));
+    }
+}
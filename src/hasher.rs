@@ -1,13 +1,49 @@
 use anyhow::{Context, Result};
 use blake3::Hasher;
-use glob::glob;
-use std::collections::BTreeMap;
+use glob::{MatchOptions, glob_with};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use crate::hashcache::{self, is_cache_path, with_hash_cache};
+use crate::normalize::{Normalizer, normalize};
+
+/// Bytes of file contents read for hashing by this process
+static BYTES_HASHED: AtomicU64 = AtomicU64::new(0);
+
+/// How many bytes of file contents this process has read to hash them, for cache stats
+pub fn bytes_hashed() -> u64 {
+    BYTES_HASHED.load(Ordering::Relaxed)
+}
+
+/// Check hashes computed by this process since files last may have changed
+static CHECK_HASHES: LazyLock<Mutex<CheckHashes>> =
+    LazyLock::new(|| Mutex::new(CheckHashes::default()));
+
+/// Options controlling how cache_paths patterns are matched against files
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlobOptions {
+    /// Match letters regardless of case (in every path segment, not just wildcard ones)
+    pub case_insensitive: bool,
+    /// Also match verify's own files (see `is_verify_file`)
+    pub track_verify_files: bool,
+    /// Files larger than this many bytes are skipped, or truncated with `truncate_large_files`
+    pub max_file_size: Option<u64>,
+    /// Hash the first `max_file_size` bytes of larger files instead of skipping them
+    pub truncate_large_files: bool,
+    /// Only match files tracked by git (ignored when not in a git repository)
+    pub tracked_only: bool,
+    /// Fail when a pattern matches no files (`on_empty_match: error`)
+    pub require_matches: bool,
+}
 
 /// Result of hashing all files for a verification check
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HashResult {
     /// Combined hash of all files
     pub combined_hash: String,
@@ -15,44 +51,155 @@ pub struct HashResult {
     pub file_hashes: BTreeMap<String, String>,
 }
 
-/// Compute content hash for a verification check's cache paths
-pub fn compute_check_hash(project_root: &Path, cache_paths: &[String]) -> Result<HashResult> {
+/// Compute content hash for a verification check's cache paths, hashing each file's
+/// contents as rewritten by `normalizers`
+pub fn compute_check_hash(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+    normalizers: &[Normalizer],
+) -> Result<HashResult> {
+    let stamped = stat_files(project_root, cache_paths, options)?;
     let mut all_files: BTreeMap<String, String> = BTreeMap::new();
 
-    // Expand all glob patterns and collect matching files
-    for pattern in cache_paths {
-        let full_pattern = project_root.join(pattern);
-        let pattern_str = full_pattern.to_string_lossy();
+    // Reuse cached hashes (shared by every check in this process), then hash the
+    // remaining files in parallel. The hash cache is only locked to look up and record
+    // entries, so checks hashing at the same time don't wait on each other.
+    let mut pending = Vec::new();
+    with_hash_cache(project_root, |hash_cache| {
+        for (relative, path, metadata, prefix) in stamped {
+            // The hash cache holds hashes of the files as they are, not normalized
+            if prefix.is_none()
+                && normalizers.is_empty()
+                && let Some(hash) = hash_cache.get(&relative, &metadata)
+            {
+                all_files.insert(relative, hash.to_string());
+                continue;
+            }
+            pending.push((relative, path, metadata, prefix));
+        }
+    });
+
+    let hashes: Vec<Result<String>> = pending
+        .par_iter()
+        .map(|(relative, path, _, prefix)| {
+            match prefix {
+                Some(limit) => hash_file_prefix(path, *limit),
+                None if normalizers.is_empty() => hash_file(path),
+                None => hash_normalized_file(path, project_root, relative, normalizers),
+            }
+            .with_context(|| format!("Failed to hash file: {}", path.display()))
+        })
+        .collect();
+    let mut hashed = Vec::new();
+    for ((relative, _, metadata, prefix), hash) in pending.into_iter().zip(hashes) {
+        let hash = hash?;
+        // Not cached when truncated or normalized: the hash cache holds full-file hashes
+        if prefix.is_none() && normalizers.is_empty() {
+            hashed.push((relative.clone(), metadata, hash.clone()));
+        }
+        all_files.insert(relative, hash);
+    }
+    if !hashed.is_empty() {
+        with_hash_cache(project_root, |hash_cache| {
+            for (relative, metadata, hash) in &hashed {
+                hash_cache.insert(relative, metadata, hash);
+            }
+        });
+    }
 
-        let entries =
-            glob(&pattern_str).with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+    Ok(HashResult {
+        combined_hash: combine(&all_files),
+        file_hashes: all_files,
+    })
+}
 
-        for entry in entries {
-            let path =
-                entry.with_context(|| format!("Error reading glob entry for: {}", pattern))?;
+impl HashResult {
+    /// Hash more inputs (e.g. submodule commits) with the files, keyed like them
+    pub fn add_inputs(&mut self, inputs: BTreeMap<String, String>) {
+        self.file_hashes.extend(inputs);
+        self.combined_hash = combine(&self.file_hashes);
+    }
+}
 
-            if path.is_file() {
-                let relative = path
-                    .strip_prefix(project_root)
-                    .unwrap_or(&path)
-                    .to_string_lossy()
-                    .to_string();
-
-                // Only hash each file once (in case patterns overlap)
-                if let std::collections::btree_map::Entry::Vacant(e) = all_files.entry(relative) {
-                    let hash = hash_file(&path)
-                        .with_context(|| format!("Failed to hash file: {}", path.display()))?;
-                    e.insert(hash);
-                }
-            }
+/// Everything a check's hash depends on besides file contents, identifying it in
+/// `CheckHashes`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashInputs {
+    pub project_root: PathBuf,
+    pub cache_paths: Vec<String>,
+    pub options: GlobOptions,
+    pub submodules: Vec<PathBuf>,
+    pub normalize: Vec<Normalizer>,
+}
+
+/// Check hashes remembered until files may have changed, so status, run and sync hash each
+/// set of inputs once per invocation, however many code paths (and subproject levels)
+/// ask. The hash cache already saves reading unchanged files; this also saves matching
+/// cache_paths, reading file metadata and asking git for submodule commits again.
+#[derive(Debug, Default)]
+pub struct CheckHashes {
+    hashes: HashMap<HashInputs, HashResult>,
+    /// Bumped by `clear`, so a hash computed while files changed isn't remembered
+    generation: u64,
+}
+
+impl CheckHashes {
+    fn get(&self, inputs: &HashInputs) -> Option<HashResult> {
+        self.hashes.get(inputs).cloned()
+    }
+
+    /// Remember a hash computed in `generation`, unless files may have changed since
+    fn insert(&mut self, inputs: HashInputs, result: HashResult, generation: u64) {
+        if generation == self.generation {
+            self.hashes.insert(inputs, result);
         }
     }
 
-    // Create deterministic combined hash
+    fn clear(&mut self) {
+        self.hashes.clear();
+        self.generation += 1;
+    }
+}
+
+/// The hash of a check's inputs from earlier in this invocation, or else `compute`'s.
+/// The lock isn't held while computing, so checks hashing in parallel don't wait.
+pub fn remembered(
+    inputs: HashInputs,
+    compute: impl FnOnce() -> Result<HashResult>,
+) -> Result<HashResult> {
+    let generation = {
+        let hashes = CHECK_HASHES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(result) = hashes.get(&inputs) {
+            return Ok(result);
+        }
+        hashes.generation
+    };
+    let result = compute()?;
+    CHECK_HASHES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(inputs, result.clone(), generation);
+    Ok(result)
+}
+
+/// Forget remembered check hashes and the in-memory hashes of recently modified files.
+/// Called whenever project files may have been changed: after each check command, and
+/// before each request or watch cycle of a long-running process.
+pub fn files_may_have_changed() {
+    CHECK_HASHES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+    hashcache::forget_recent();
+}
+
+/// Deterministic combined hash of per-file hashes
+fn combine(file_hashes: &BTreeMap<String, String>) -> String {
     // BTreeMap ensures sorted, deterministic ordering
     let mut combined_hasher = Hasher::new();
 
-    for (path, hash) in &all_files {
+    for (path, hash) in file_hashes {
         // Include path in hash to detect renames
         combined_hasher.update(path.as_bytes());
         combined_hasher.update(b":");
@@ -60,12 +207,375 @@ pub fn compute_check_hash(project_root: &Path, cache_paths: &[String]) -> Result
         combined_hasher.update(b"\n");
     }
 
-    let combined_hash = combined_hasher.finalize().to_hex().to_string();
+    combined_hasher.finalize().to_hex().to_string()
+}
 
-    Ok(HashResult {
-        combined_hash,
-        file_hashes: all_files,
-    })
+/// A file to hash: its relative and full path, metadata, and the byte count to hash if
+/// it's truncated
+type StampedFile = (String, PathBuf, std::fs::Metadata, Option<u64>);
+
+/// The files `compute_check_hash` hashes: those cache_paths match, less those over
+/// `max_file_size` unless they're truncated
+fn stat_files(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<Vec<StampedFile>> {
+    let mut stamped = Vec::new();
+    for (relative, path) in matched_files(project_root, cache_paths, options)? {
+        let metadata = std::fs::metadata(&path)
+            .with_context(|| format!("Failed to hash file: {}", path.display()))?;
+        let prefix = match options.max_file_size {
+            Some(max) if metadata.len() > max => {
+                if !options.truncate_large_files {
+                    continue;
+                }
+                Some(max)
+            }
+            _ => None,
+        };
+        stamped.push((relative, path, metadata, prefix));
+    }
+    Ok(stamped)
+}
+
+/// The project-relative paths of the files a check with these cache_paths hashes, for
+/// anything that needs to know which files a check depends on without hashing them.
+/// Patterns matching nothing aren't an error here, even with `require_matches`.
+pub fn hashed_files(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<BTreeSet<String>> {
+    let options = GlobOptions {
+        require_matches: false,
+        ..options
+    };
+    Ok(stat_files(project_root, cache_paths, options)?
+        .into_iter()
+        .map(|(relative, ..)| relative)
+        .collect())
+}
+
+/// The files cache_paths match, keyed by project-relative path with forward slashes
+/// (so lock files are identical across platforms)
+fn matched_files(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<BTreeMap<String, PathBuf>> {
+    let (files, empty) = match_patterns(project_root, cache_paths, options)?;
+    if options.require_matches
+        && let Some(pattern) = empty.first()
+    {
+        anyhow::bail!(
+            "cache_paths pattern '{}' matches no files (on_empty_match: error)",
+            pattern
+        );
+    }
+    Ok(files)
+}
+
+/// The cache_paths patterns that match no files, as they'd be hashed (so a pattern only
+/// matching untracked files with `tracked_only` counts)
+pub fn empty_patterns(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<Vec<String>> {
+    Ok(match_patterns(project_root, cache_paths, options)?.1)
+}
+
+/// The files cache_paths match, and the patterns that matched none of them
+fn match_patterns(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<(BTreeMap<String, PathBuf>, Vec<String>)> {
+    let tracked = if options.tracked_only {
+        tracked_files(project_root)
+    } else {
+        None
+    };
+    // glob drops `.` components from the paths it returns, so a root like `././api`
+    // (a `./api` subproject of `.`) wouldn't be a prefix of them
+    let project_root: PathBuf = project_root
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect();
+    let project_root = project_root.as_path();
+    let mut files = BTreeMap::new();
+    let mut empty = Vec::new();
+    for pattern in cache_paths {
+        let mut matched = false;
+        for path in glob_files(project_root, pattern, options)? {
+            let relative = path
+                .strip_prefix(project_root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            // The hash cache itself changes on every run, so never track it
+            if is_cache_path(&relative) {
+                continue;
+            }
+            // Nor, by default, other files verify writes, which would invalidate
+            // a broad pattern like `**/*` every time the lock is saved
+            if !options.track_verify_files && is_verify_file(&relative) {
+                continue;
+            }
+            if let Some(tracked) = &tracked
+                && !tracked.contains(&relative)
+            {
+                continue;
+            }
+
+            // Patterns may overlap, so each file is kept once
+            matched = true;
+            files.entry(relative).or_insert(path);
+        }
+        if !matched {
+            empty.push(pattern.clone());
+        }
+    }
+    Ok((files, empty))
+}
+
+/// Files git tracks under `project_root`, relative to it, or None outside a git repository
+pub fn tracked_files(project_root: &Path) -> Option<HashSet<String>> {
+    let dir = if project_root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        project_root
+    };
+    let output = Command::new("git")
+        .args(["ls-files", "-z"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        output
+            .stdout
+            .split(|byte| *byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect(),
+    )
+}
+
+/// Files matched by cache_paths that are larger than `min_size` bytes, largest first
+pub fn large_files(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+    min_size: u64,
+) -> Result<Vec<(String, u64)>> {
+    let mut large = Vec::new();
+    for (relative, path) in matched_files(project_root, cache_paths, options)? {
+        let size = std::fs::metadata(&path)?.len();
+        if size > min_size {
+            large.push((relative, size));
+        }
+    }
+    large.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(large)
+}
+
+/// Whether a project-relative path is verify state (`.verify/` or a lock file) at any depth,
+/// which changes whenever checks run
+pub fn is_verify_state(relative: &str) -> bool {
+    let mut components = relative.split('/').peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() {
+            return matches!(component, "verify.lock" | "verify.lock.tmp");
+        }
+        if component == ".verify" {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether a project-relative path is verify state or a verify config file (including
+/// verify.d fragments)
+pub fn is_verify_file(relative: &str) -> bool {
+    let mut components = relative.rsplit('/');
+    let file = components.next();
+    is_verify_state(relative)
+        || file == Some("verify.yaml")
+        || (file.is_some_and(|file| file.ends_with(".yaml"))
+            && components.next() == Some(crate::config::FRAGMENTS_DIR))
+}
+
+/// Expand a single cache_paths pattern (including brace alternatives) into matching files
+fn glob_files(
+    project_root: &Path,
+    pattern: &str,
+    options: GlobOptions,
+) -> Result<Vec<std::path::PathBuf>> {
+    let match_options = MatchOptions {
+        case_sensitive: !options.case_insensitive,
+        ..MatchOptions::new()
+    };
+
+    let mut files = Vec::new();
+    for expanded in file_patterns(pattern) {
+        let expanded = if options.case_insensitive {
+            case_insensitive_pattern(&expanded)
+        } else {
+            expanded
+        };
+        let full_pattern = project_root.join(&expanded);
+        let pattern_str = full_pattern.to_string_lossy();
+
+        let entries = glob_with(&pattern_str, match_options)
+            .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+
+        for entry in entries {
+            let path =
+                entry.with_context(|| format!("Error reading glob entry for: {}", pattern))?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Whether a project-relative file path matches any of the cache_paths patterns,
+/// as it would when hashing (so verify's own files only match with `track_verify_files`)
+pub fn matches_cache_paths(relative: &str, cache_paths: &[String], options: GlobOptions) -> bool {
+    if is_cache_path(relative) || (!options.track_verify_files && is_verify_file(relative)) {
+        return false;
+    }
+    let match_options = MatchOptions {
+        case_sensitive: !options.case_insensitive,
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    cache_paths
+        .iter()
+        .flat_map(|pattern| file_patterns(pattern))
+        .any(|pattern| {
+            glob::Pattern::new(&pattern).is_ok_and(|p| p.matches_with(relative, match_options))
+        })
+}
+
+/// The glob patterns a cache_paths pattern stands for: its brace alternatives, with a
+/// trailing `**` (which on its own only lists directories) meaning every file under it
+fn file_patterns(pattern: &str) -> Vec<String> {
+    expand_braces(pattern)
+        .into_iter()
+        .map(|expanded| {
+            if expanded == "**" || expanded.ends_with("/**") {
+                format!("{}/*", expanded)
+            } else {
+                expanded
+            }
+        })
+        .collect()
+}
+
+/// Expand brace alternatives, e.g. `src/**/*.{ts,tsx}` into `src/**/*.ts` and `src/**/*.tsx`.
+/// Nested braces are supported. Braces without a top-level comma are kept literally.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+
+    let mut open = None;
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let start = open.unwrap();
+                    if commas.is_empty() {
+                        continue;
+                    }
+
+                    let prefix: String = chars[..start].iter().collect();
+                    let suffix: String = chars[i + 1..].iter().collect();
+                    let mut bounds = vec![start];
+                    bounds.extend(&commas);
+                    bounds.push(i);
+
+                    return bounds
+                        .windows(2)
+                        .flat_map(|w| {
+                            let alternative: String = chars[w[0] + 1..w[1]].iter().collect();
+                            expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    vec![pattern.to_string()]
+}
+
+/// Rewrite ASCII letters outside character classes as `[xX]` so that literal path
+/// segments are matched case-insensitively too (glob only applies case options to
+/// segments containing wildcards).
+fn case_insensitive_pattern(pattern: &str) -> String {
+    let mut result = String::with_capacity(pattern.len() * 2);
+    let mut in_class = false;
+    for c in pattern.chars() {
+        match c {
+            '[' if !in_class => {
+                in_class = true;
+                result.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                result.push(c);
+            }
+            c if !in_class && c.is_ascii_alphabetic() => {
+                result.push('[');
+                result.push(c.to_ascii_lowercase());
+                result.push(c.to_ascii_uppercase());
+                result.push(']');
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// The cache_paths patterns that match no files (as `empty_patterns` finds them), split
+/// into those that would match ignoring case and the rest. On case-sensitive filesystems
+/// the first usually come from configs written on case-insensitive ones. Only the
+/// patterns that matched nothing are globbed again.
+pub fn unmatched_patterns(
+    project_root: &Path,
+    cache_paths: &[String],
+    options: GlobOptions,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let empty = empty_patterns(project_root, cache_paths, options)?;
+    if options.case_insensitive || empty.is_empty() {
+        return Ok((Vec::new(), empty));
+    }
+
+    let insensitive = GlobOptions {
+        case_insensitive: true,
+        ..options
+    };
+    let still_empty = empty_patterns(project_root, &empty, insensitive)?;
+    Ok(empty
+        .into_iter()
+        .partition(|pattern| !still_empty.contains(pattern)))
 }
 
 /// Hash a single file using BLAKE3
@@ -82,11 +592,38 @@ fn hash_file(path: &Path) -> Result<String> {
             break;
         }
         hasher.update(&buffer[..bytes_read]);
+        BYTES_HASHED.fetch_add(bytes_read as u64, Ordering::Relaxed);
     }
 
     Ok(hasher.finalize().to_hex().to_string())
 }
 
+/// Hash a file's contents after normalizing them
+fn hash_normalized_file(
+    path: &Path,
+    project_root: &Path,
+    relative: &str,
+    normalizers: &[Normalizer],
+) -> Result<String> {
+    let content = std::fs::read(path)?;
+    BYTES_HASHED.fetch_add(content.len() as u64, Ordering::Relaxed);
+    let content = normalize(normalizers, project_root, relative, content)?;
+    Ok(blake3::hash(&content).to_hex().to_string())
+}
+
+/// Hash the first `limit` bytes of a file along with its full size, so a truncated file
+/// still changes hash when it grows or shrinks
+fn hash_file_prefix(path: &Path, limit: u64) -> Result<String> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = Hasher::new();
+    let bytes_read = std::io::copy(&mut BufReader::new(file).take(limit), &mut hasher)?;
+    BYTES_HASHED.fetch_add(bytes_read, Ordering::Relaxed);
+    hasher.update(b"size:");
+    hasher.update(&size.to_le_bytes());
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
 /// Compare two hash results and return list of changed files
 pub fn find_changed_files(
     old_hashes: &BTreeMap<String, String>,
@@ -279,11 +816,21 @@ mod tests {
 
     // ==================== compute_check_hash tests ====================
 
+    /// Hash the files matching `patterns` with default options and no normalizers
+    fn hash(root: &Path, patterns: &[&str]) -> HashResult {
+        hash_with(root, patterns, GlobOptions::default())
+    }
+
+    fn hash_with(root: &Path, patterns: &[&str], options: GlobOptions) -> HashResult {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        compute_check_hash(root, &patterns, options, &[]).unwrap()
+    }
+
     #[test]
     fn test_compute_check_hash_empty_patterns() {
         let dir = tempdir().unwrap();
 
-        let result = compute_check_hash(dir.path(), &[]).unwrap();
+        let result = hash(dir.path(), &[]);
         assert!(result.file_hashes.is_empty());
         // Combined hash of nothing should still be deterministic
         assert!(!result.combined_hash.is_empty());
@@ -295,7 +842,7 @@ mod tests {
         let file_path = dir.path().join("test.txt");
         fs::write(&file_path, "content").unwrap();
 
-        let result = compute_check_hash(dir.path(), &["test.txt".to_string()]).unwrap();
+        let result = hash(dir.path(), &["test.txt"]);
         assert_eq!(result.file_hashes.len(), 1);
         assert!(result.file_hashes.contains_key("test.txt"));
     }
@@ -307,7 +854,7 @@ mod tests {
         fs::write(dir.path().join("b.rs"), "fn b() {}").unwrap();
         fs::write(dir.path().join("c.txt"), "text file").unwrap();
 
-        let result = compute_check_hash(dir.path(), &["*.rs".to_string()]).unwrap();
+        let result = hash(dir.path(), &["*.rs"]);
         assert_eq!(result.file_hashes.len(), 2);
         assert!(result.file_hashes.contains_key("a.rs"));
         assert!(result.file_hashes.contains_key("b.rs"));
@@ -320,21 +867,41 @@ mod tests {
         let dir = tempdir().unwrap();
         fs::write(dir.path().join("test.rs"), "content").unwrap();
 
-        let result =
-            compute_check_hash(dir.path(), &["*.rs".to_string(), "test.rs".to_string()]).unwrap();
+        let result = hash(dir.path(), &["*.rs", "test.rs"]);
 
         // Should only have one entry despite matching both patterns
         assert_eq!(result.file_hashes.len(), 1);
     }
 
+    #[test]
+    fn test_compute_check_hash_many_files() {
+        // Files are hashed in parallel; each still gets its own hash
+        let dir = tempdir().unwrap();
+        for i in 0..50 {
+            fs::write(dir.path().join(format!("{}.txt", i)), i.to_string()).unwrap();
+        }
+
+        let result = hash(dir.path(), &["*.txt"]);
+        assert_eq!(result.file_hashes.len(), 50);
+        for (relative, hash) in &result.file_hashes {
+            assert_eq!(hash, &hash_file(&dir.path().join(relative)).unwrap());
+        }
+
+        // A later check overlapping the first reuses the same hashes
+        let subset = hash(dir.path(), &["1*.txt"]);
+        for (relative, hash) in &subset.file_hashes {
+            assert_eq!(hash, &result.file_hashes[relative]);
+        }
+    }
+
     #[test]
     fn test_compute_check_hash_determinism() {
         let dir = tempdir().unwrap();
         fs::write(dir.path().join("a.txt"), "aaa").unwrap();
         fs::write(dir.path().join("b.txt"), "bbb").unwrap();
 
-        let result1 = compute_check_hash(dir.path(), &["*.txt".to_string()]).unwrap();
-        let result2 = compute_check_hash(dir.path(), &["*.txt".to_string()]).unwrap();
+        let result1 = hash(dir.path(), &["*.txt"]);
+        let result2 = hash(dir.path(), &["*.txt"]);
 
         assert_eq!(result1.combined_hash, result2.combined_hash);
         assert_eq!(result1.file_hashes, result2.file_hashes);
@@ -346,13 +913,13 @@ mod tests {
         let dir = tempdir().unwrap();
         fs::write(dir.path().join("a.txt"), "content").unwrap();
 
-        let result1 = compute_check_hash(dir.path(), &["a.txt".to_string()]).unwrap();
+        let result1 = hash(dir.path(), &["a.txt"]);
 
         // Remove and create with different name
         fs::remove_file(dir.path().join("a.txt")).unwrap();
         fs::write(dir.path().join("b.txt"), "content").unwrap();
 
-        let result2 = compute_check_hash(dir.path(), &["b.txt".to_string()]).unwrap();
+        let result2 = hash(dir.path(), &["b.txt"]);
 
         // Individual file hashes should be the same (same content)
         let hash1 = result1.file_hashes.get("a.txt").unwrap();
@@ -371,19 +938,45 @@ mod tests {
         fs::write(sub_dir.join("main.rs"), "fn main() {}").unwrap();
         fs::write(sub_dir.join("lib.rs"), "pub fn lib() {}").unwrap();
 
-        let result = compute_check_hash(dir.path(), &["src/*.rs".to_string()]).unwrap();
+        let result = hash(dir.path(), &["src/*.rs"]);
         assert_eq!(result.file_hashes.len(), 2);
         assert!(result.file_hashes.contains_key("src/main.rs"));
         assert!(result.file_hashes.contains_key("src/lib.rs"));
     }
 
+    #[test]
+    fn test_compute_check_hash_trailing_double_star() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/ios/views")).unwrap();
+        fs::write(dir.path().join("src/ios/a"), "a").unwrap();
+        fs::write(dir.path().join("src/ios/views/b"), "b").unwrap();
+        fs::write(dir.path().join("top"), "top").unwrap();
+
+        let result = hash(dir.path(), &["src/ios/**"]);
+        let files: Vec<_> = result.file_hashes.keys().collect();
+        assert_eq!(files, ["src/ios/a", "src/ios/views/b"]);
+        assert!(matches_cache_paths(
+            "src/ios/a",
+            &["src/ios/**".to_string()],
+            GlobOptions::default()
+        ));
+
+        let everything = hash(dir.path(), &["**"]);
+        assert_eq!(everything.file_hashes.len(), 3);
+
+        fs::write(dir.path().join("src/ios/a"), "edited").unwrap();
+        files_may_have_changed();
+        let edited = hash(dir.path(), &["src/ios/**"]);
+        assert_ne!(edited.combined_hash, result.combined_hash);
+    }
+
     #[test]
     fn test_compute_check_hash_no_matching_files() {
         let dir = tempdir().unwrap();
         fs::write(dir.path().join("test.txt"), "content").unwrap();
 
         // Pattern that matches nothing
-        let result = compute_check_hash(dir.path(), &["*.rs".to_string()]).unwrap();
+        let result = hash(dir.path(), &["*.rs"]);
         assert!(result.file_hashes.is_empty());
     }
 
@@ -394,12 +987,230 @@ mod tests {
         fs::write(dir.path().join("code.ts"), "typescript").unwrap();
         fs::write(dir.path().join("readme.md"), "docs").unwrap();
 
-        let result =
-            compute_check_hash(dir.path(), &["*.rs".to_string(), "*.ts".to_string()]).unwrap();
+        let result = hash(dir.path(), &["*.rs", "*.ts"]);
 
         assert_eq!(result.file_hashes.len(), 2);
         assert!(result.file_hashes.contains_key("code.rs"));
         assert!(result.file_hashes.contains_key("code.ts"));
         assert!(!result.file_hashes.contains_key("readme.md"));
     }
+
+    // ==================== glob option tests ====================
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("src/*.rs"), vec!["src/*.rs"]);
+        assert_eq!(
+            expand_braces("src/*.{ts,tsx}"),
+            vec!["src/*.ts", "src/*.tsx"]
+        );
+        assert_eq!(
+            expand_braces("{a,b}/{c,d{e,f}}"),
+            vec!["a/c", "a/de", "a/df", "b/c", "b/de", "b/df"]
+        );
+        // Braces without a comma, or unbalanced, are kept literally
+        assert_eq!(expand_braces("{single}.txt"), vec!["{single}.txt"]);
+        assert_eq!(expand_braces("{a,b"), vec!["{a,b"]);
+    }
+
+    #[test]
+    fn test_compute_check_hash_brace_expansion() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.ts"), "a").unwrap();
+        fs::write(dir.path().join("b.tsx"), "b").unwrap();
+        fs::write(dir.path().join("c.js"), "c").unwrap();
+
+        let result = hash(dir.path(), &["*.{ts,tsx}"]);
+        assert_eq!(result.file_hashes.len(), 2);
+        assert!(result.file_hashes.contains_key("a.ts"));
+        assert!(result.file_hashes.contains_key("b.tsx"));
+    }
+
+    #[test]
+    fn test_compute_check_hash_case_insensitive() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("Docs")).unwrap();
+        fs::write(dir.path().join("Docs/README.MD"), "readme").unwrap();
+
+        let sensitive = hash(dir.path(), &["docs/*.md"]);
+        assert!(sensitive.file_hashes.is_empty());
+
+        let insensitive = hash_with(
+            dir.path(),
+            &["docs/*.md"],
+            GlobOptions {
+                case_insensitive: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(insensitive.file_hashes.len(), 1);
+        assert!(insensitive.file_hashes.contains_key("Docs/README.MD"));
+    }
+
+    #[test]
+    fn test_is_verify_file() {
+        assert!(is_verify_file("verify.lock"));
+        assert!(is_verify_file("packages/web/verify.lock"));
+        assert!(is_verify_file(".verify/history.jsonl"));
+        assert!(is_verify_file("packages/web/.verify/hashcache"));
+        assert!(is_verify_file("packages/web/verify.yaml"));
+        assert!(is_verify_file("verify.d/frontend.yaml"));
+        assert!(!is_verify_file("verify.d/README.md"));
+        assert!(!is_verify_state("verify.yaml"));
+        assert!(!is_verify_file("src/verify.rs"));
+        assert!(!is_verify_file("docs/verify.lock.md"));
+    }
+
+    #[test]
+    fn test_compute_check_hash_skips_verify_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("verify.yaml"), "verifications: []").unwrap();
+        fs::write(dir.path().join("verify.lock"), "{}").unwrap();
+        fs::create_dir_all(dir.path().join("sub/.verify")).unwrap();
+        fs::write(dir.path().join("sub/.verify/hashcache"), "").unwrap();
+
+        let patterns = ["**/*", "sub/.verify/*"];
+        let result = hash(dir.path(), &patterns);
+        assert_eq!(result.file_hashes.keys().collect::<Vec<_>>(), ["main.rs"]);
+
+        let tracked = GlobOptions {
+            track_verify_files: true,
+            ..Default::default()
+        };
+        let result = hash_with(dir.path(), &patterns, tracked);
+        assert!(result.file_hashes.contains_key("verify.lock"));
+        assert!(result.file_hashes.contains_key("verify.yaml"));
+        assert!(result.file_hashes.contains_key("sub/.verify/hashcache"));
+    }
+
+    #[test]
+    fn test_compute_check_hash_max_file_size() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("small.txt"), "small").unwrap();
+        fs::write(dir.path().join("large.bin"), "0123456789").unwrap();
+        let patterns = ["*".to_string()];
+
+        let skip = GlobOptions {
+            max_file_size: Some(8),
+            ..Default::default()
+        };
+        let result = hash_with(dir.path(), &["*"], skip);
+        assert_eq!(result.file_hashes.keys().collect::<Vec<_>>(), ["small.txt"]);
+        assert_eq!(
+            large_files(dir.path(), &patterns, skip, 8).unwrap(),
+            vec![("large.bin".to_string(), 10)]
+        );
+
+        let truncate = GlobOptions {
+            max_file_size: Some(8),
+            truncate_large_files: true,
+            ..Default::default()
+        };
+        let before = hash_with(dir.path(), &["*"], truncate);
+        // Bytes past the limit are ignored, but a change in size isn't
+        fs::write(dir.path().join("large.bin"), "01234567xx").unwrap();
+        let after = hash_with(dir.path(), &["*"], truncate);
+        assert_eq!(before.combined_hash, after.combined_hash);
+        fs::write(dir.path().join("large.bin"), "01234567xxx").unwrap();
+        let grown = hash_with(dir.path(), &["*"], truncate);
+        assert_ne!(before.combined_hash, grown.combined_hash);
+    }
+
+    #[test]
+    fn test_check_hashes_forget_on_clear() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let inputs = HashInputs {
+            project_root: dir.path().to_path_buf(),
+            cache_paths: vec!["*.txt".to_string()],
+            options: GlobOptions::default(),
+            submodules: vec![],
+            normalize: vec![],
+        };
+        let hash = || compute_check_hash(dir.path(), &inputs.cache_paths, inputs.options, &[]);
+
+        let mut hashes = CheckHashes::default();
+        assert!(hashes.get(&inputs).is_none());
+        hashes.insert(inputs.clone(), hash().unwrap(), hashes.generation);
+        let remembered = hashes.get(&inputs).unwrap();
+
+        // Remembered as it was, even though the file changed
+        fs::write(dir.path().join("a.txt"), "changed").unwrap();
+        assert_eq!(
+            hashes.get(&inputs).unwrap().combined_hash,
+            remembered.combined_hash
+        );
+
+        // Once files may have changed, neither the old hash nor one computed before
+        // the change is remembered
+        let generation = hashes.generation;
+        hashes.clear();
+        assert!(hashes.get(&inputs).is_none());
+        hashes.insert(inputs.clone(), remembered.clone(), generation);
+        assert!(hashes.get(&inputs).is_none());
+
+        let other = HashInputs {
+            cache_paths: vec!["*.rs".to_string()],
+            ..inputs.clone()
+        };
+        hashes.insert(inputs.clone(), hash().unwrap(), hashes.generation);
+        assert!(hashes.get(&other).is_none());
+        assert_ne!(
+            hashes.get(&inputs).unwrap().combined_hash,
+            remembered.combined_hash
+        );
+    }
+
+    #[test]
+    fn test_matches_cache_paths() {
+        let patterns = ["src/*.{ts,tsx}".to_string(), "docs/**".to_string()];
+        let options = GlobOptions::default();
+        assert!(matches_cache_paths("src/app.ts", &patterns, options));
+        assert!(matches_cache_paths("src/app.tsx", &patterns, options));
+        assert!(matches_cache_paths(
+            "docs/guide/intro.md",
+            &patterns,
+            options
+        ));
+        assert!(!matches_cache_paths("src/lib/app.ts", &patterns, options));
+        assert!(!matches_cache_paths("Src/app.ts", &patterns, options));
+
+        let insensitive = GlobOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        assert!(matches_cache_paths("Src/App.TS", &patterns, insensitive));
+
+        let everything = ["**/*".to_string()];
+        assert!(!matches_cache_paths("verify.lock", &everything, options));
+    }
+
+    #[test]
+    fn test_unmatched_patterns_split_by_case() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("Makefile"), "all:").unwrap();
+        fs::write(dir.path().join("main.c"), "int main;").unwrap();
+        fs::create_dir_all(dir.path().join("Assets/icons")).unwrap();
+        fs::write(dir.path().join("Assets/icons/logo.PNG"), "png").unwrap();
+
+        let patterns = vec![
+            "makefile".to_string(),
+            "*.c".to_string(),
+            "missing.txt".to_string(),
+            "assets/**/*.png".to_string(),
+        ];
+        let (mismatched, empty) =
+            unmatched_patterns(dir.path(), &patterns, GlobOptions::default()).unwrap();
+        assert_eq!(mismatched, vec!["makefile", "assets/**/*.png"]);
+        assert_eq!(empty, vec!["missing.txt"]);
+
+        let insensitive = GlobOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let (mismatched, empty) = unmatched_patterns(dir.path(), &patterns, insensitive).unwrap();
+        assert!(mismatched.is_empty());
+        assert_eq!(empty, vec!["missing.txt"]);
+    }
 }
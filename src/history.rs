@@ -0,0 +1,133 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// A completed `verify run`, appended to `.verify/history.jsonl`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Free-form annotation from `verify run --message`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Check names requested on the command line (empty means all checks)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<String>,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Checks that didn't run because a dependency failed
+    #[serde(default)]
+    pub blocked: usize,
+    pub duration_ms: u64,
+    /// Who marked the checks verified with `verify mark-verified` (nothing was run)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marked_by: Option<String>,
+    /// Who ran the checks, when `record_user` is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// Append a run to the project's history
+pub fn append(project_root: &Path, record: &RunRecord) -> Result<()> {
+    if !statedir::writes_enabled() {
+        return Ok(());
+    }
+    statedir::create(project_root)?;
+    let path = history_path(project_root);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open run history: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+        .with_context(|| format!("Failed to write run history: {}", path.display()))?;
+    Ok(())
+}
+
+/// Load up to `limit` of the most recent runs, newest first.
+/// Lines that can't be parsed are skipped.
+pub fn load(project_root: &Path, limit: usize) -> Result<Vec<RunRecord>> {
+    let path = history_path(project_root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read run history: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect())
+}
+
+fn history_path(project_root: &Path) -> PathBuf {
+    statedir::state_dir(project_root).join(HISTORY_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(message: Option<&str>, failed: usize) -> RunRecord {
+        RunRecord {
+            timestamp: Utc::now(),
+            message: message.map(String::from),
+            checks: Vec::new(),
+            total: 2,
+            passed: 2 - failed,
+            failed,
+            skipped: 0,
+            blocked: 0,
+            duration_ms: 10,
+            marked_by: None,
+            user: None,
+            host: None,
+        }
+    }
+
+    #[test]
+    fn test_load_missing_history() {
+        let dir = tempdir().unwrap();
+        assert!(load(dir.path(), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_append_and_load_newest_first() {
+        let dir = tempdir().unwrap();
+        append(dir.path(), &record(Some("first"), 0)).unwrap();
+        append(dir.path(), &record(None, 1)).unwrap();
+        append(dir.path(), &record(Some("third"), 0)).unwrap();
+
+        let runs = load(dir.path(), 2).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].message.as_deref(), Some("third"));
+        assert_eq!(runs[1].message, None);
+        assert_eq!(runs[1].failed, 1);
+    }
+
+    #[test]
+    fn test_load_skips_corrupt_lines() {
+        let dir = tempdir().unwrap();
+        append(dir.path(), &record(Some("ok"), 0)).unwrap();
+        let path = history_path(dir.path());
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("not json\n");
+        fs::write(&path, content).unwrap();
+
+        let runs = load(dir.path(), 10).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].message.as_deref(), Some("ok"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::config::{Config, VerificationItem};
+use crate::hasher::{hashed_files, matches_cache_paths};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Component, Path};
+
+/// Whether verify is running from a git hook. Git sets `GIT_INDEX_FILE` for the hooks
+/// `git commit` runs (pre-commit, commit-msg, ...).
+pub fn in_git_hook() -> bool {
+    std::env::var_os("GIT_INDEX_FILE").is_some()
+}
+
+/// Checks affected by changes to `files` (paths relative to the current directory, as
+/// hook frameworks pass them): checks that hash one of the files (or, for a deleted file,
+/// would have hashed it), subprojects containing one, and everything that depends on those.
+/// Returned in config order.
+pub fn affected_checks(
+    project_root: &Path,
+    config: &Config,
+    files: &[String],
+) -> Result<Vec<String>> {
+    let root = std::path::absolute(project_root)
+        .with_context(|| format!("Failed to resolve {}", project_root.display()))?;
+    let mut relative_files = Vec::new();
+    for file in files {
+        let path =
+            std::path::absolute(file).with_context(|| format!("Failed to resolve {}", file))?;
+        // Files outside the project can't affect its checks
+        if let Ok(relative) = path.strip_prefix(&root) {
+            relative_files.push(normalize(relative));
+        }
+    }
+
+    let mut affected: HashSet<&str> = HashSet::new();
+    for item in &config.verifications {
+        let hit = match item {
+            VerificationItem::Verification(_) if relative_files.is_empty() => false,
+            VerificationItem::Verification(v) => {
+                let hashed = hashed_files(project_root, &v.cache_paths, v.glob_options())?;
+                relative_files.iter().any(|file| {
+                    hashed.contains(file)
+                        || (!root.join(file).exists()
+                            && matches_cache_paths(file, &v.cache_paths, v.glob_options()))
+                })
+            }
+            VerificationItem::Subproject(s) => {
+                let prefix = format!("{}/", normalize(&s.path));
+                relative_files.iter().any(|file| file.starts_with(&prefix))
+            }
+        };
+        if hit {
+            affected.insert(item.name());
+        }
+    }
+
+    // A check whose dependency re-runs has to re-run too
+    loop {
+        let dependents: Vec<&str> = config
+            .verifications_only()
+            .into_iter()
+            .filter(|v| !affected.contains(v.name.as_str()))
+            .filter(|v| v.depends_on.iter().any(|d| affected.contains(d.as_str())))
+            .map(|v| v.name.as_str())
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        affected.extend(dependents);
+    }
+
+    Ok(config
+        .verifications
+        .iter()
+        .map(|item| item.name())
+        .filter(|name| affected.contains(name))
+        .map(String::from)
+        .collect())
+}
+
+/// Project-relative path with forward slashes and no "." or ".." components
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop();
+            }
+            other => parts.push(other.as_os_str().to_string_lossy().into_owned()),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_affected_checks() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("web")).unwrap();
+        fs::write(
+            dir.path().join("web/verify.yaml"),
+            "verifications:\n  - name: build\n    command: echo\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("verify.yaml"),
+            r#"verifications:
+  - name: lint
+    command: echo
+    cache_paths: ["src/**/*.rs"]
+  - name: docs
+    command: echo
+    cache_paths: ["docs/**"]
+  - name: test
+    command: echo
+    cache_paths: ["tests/**"]
+    depends_on: [lint]
+  - name: all
+    depends_on: [test, docs]
+  - name: web
+    path: ./web
+"#,
+        )
+        .unwrap();
+        let config = Config::load(&dir.path().join("verify.yaml")).unwrap();
+        let root = dir.path();
+        let files = |paths: &[&str]| -> Vec<String> {
+            paths
+                .iter()
+                .map(|p| root.join(p).to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(
+            affected_checks(root, &config, &files(&["src/main.rs"])).unwrap(),
+            ["lint", "test", "all"]
+        );
+        assert_eq!(
+            affected_checks(root, &config, &files(&["docs/a.md", "web/src/app.ts"])).unwrap(),
+            ["docs", "all", "web"]
+        );
+        assert!(
+            affected_checks(root, &config, &files(&["README.md", "verify.lock"]))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            affected_checks(root, &config, &["/elsewhere/src/main.rs".to_string()])
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
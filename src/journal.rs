@@ -0,0 +1,194 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const JOURNAL_FILE: &str = "run-journal.json";
+
+/// Checks that passed so far in the in-flight `verify run`, kept in
+/// `.verify/run-journal.json` of the root project so `verify run --resume` can pick up
+/// where a crashed or interrupted run stopped. Removed once the run finishes.
+pub struct RunJournal {
+    /// Where the journal is written, or None when journaling is off (workspace runs)
+    path: Option<PathBuf>,
+    /// Root project directory; subproject checks are keyed relative to it
+    root: PathBuf,
+    state: JournalState,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct JournalState {
+    started_at: Option<DateTime<Utc>>,
+    /// Completed checks, keyed by name ("path/name" for checks in subprojects)
+    completed: BTreeMap<String, CompletedCheck>,
+}
+
+/// The hashes a check passed with, so a resumed run only skips it if nothing changed
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct CompletedCheck {
+    content_hash: String,
+    config_hash: String,
+}
+
+impl RunJournal {
+    /// Start journaling a run. With `resume`, checks completed by the previous unfinished
+    /// run are carried over; otherwise any leftover journal is discarded.
+    pub fn start(project_root: &Path, resume: bool) -> Result<Self> {
+        if !statedir::writes_enabled() {
+            return Ok(Self::disabled());
+        }
+        let path = journal_path(project_root);
+        let state = if resume && path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read run journal: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse run journal: {}", path.display()))?
+        } else {
+            JournalState {
+                started_at: Some(Utc::now()),
+                completed: BTreeMap::new(),
+            }
+        };
+
+        let journal = Self {
+            path: Some(path),
+            root: project_root.to_path_buf(),
+            state,
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// A journal that records nothing
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            root: PathBuf::new(),
+            state: JournalState::default(),
+        }
+    }
+
+    /// Whether the check already passed in this run with the same content and config
+    pub fn is_completed(
+        &self,
+        project_root: &Path,
+        name: &str,
+        content_hash: &str,
+        config_hash: &str,
+    ) -> bool {
+        self.state
+            .completed
+            .get(&self.key(project_root, name))
+            .is_some_and(|c| c.content_hash == content_hash && c.config_hash == config_hash)
+    }
+
+    /// Record that a check passed, writing the journal immediately
+    pub fn record(
+        &mut self,
+        project_root: &Path,
+        name: &str,
+        content_hash: &str,
+        config_hash: &str,
+    ) -> Result<()> {
+        if self.path.is_none() {
+            return Ok(());
+        }
+        let completed = CompletedCheck {
+            content_hash: content_hash.to_string(),
+            config_hash: config_hash.to_string(),
+        };
+        let key = self.key(project_root, name);
+        if self.state.completed.get(&key) == Some(&completed) {
+            return Ok(());
+        }
+        self.state.completed.insert(key, completed);
+        self.save()
+    }
+
+    /// The run finished, so there is nothing left to resume
+    pub fn finish(self) -> Result<()> {
+        if let Some(path) = &self.path
+            && path.exists()
+        {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to remove run journal: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn key(&self, project_root: &Path, name: &str) -> String {
+        match project_root.strip_prefix(&self.root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                format!("{}/{}", relative.to_string_lossy(), name)
+            }
+            _ => name.to_string(),
+        }
+    }
+
+    /// Write the journal atomically, so a crash mid-write can't corrupt it
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let dir = statedir::create(&self.root)?;
+
+        let temp_path = dir.join(format!("{}.tmp", JOURNAL_FILE));
+        let file = File::create(&temp_path).with_context(|| {
+            format!("Failed to create temp run journal: {}", temp_path.display())
+        })?;
+        serde_json::to_writer(BufWriter::new(file), &self.state)
+            .with_context(|| "Failed to serialize run journal")?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to save run journal: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn journal_path(project_root: &Path) -> PathBuf {
+    statedir::state_dir(project_root).join(JOURNAL_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resume_carries_over_completed_checks() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("packages/web");
+
+        let mut journal = RunJournal::start(dir.path(), false).unwrap();
+        journal.record(dir.path(), "build", "c1", "k1").unwrap();
+        journal.record(&sub, "test", "c2", "k2").unwrap();
+        drop(journal); // crashed: never finished
+
+        let journal = RunJournal::start(dir.path(), true).unwrap();
+        assert!(journal.is_completed(dir.path(), "build", "c1", "k1"));
+        assert!(journal.is_completed(&sub, "test", "c2", "k2"));
+        assert!(!journal.is_completed(dir.path(), "test", "c2", "k2"));
+        assert!(!journal.is_completed(dir.path(), "build", "changed", "k1"));
+        assert!(!journal.is_completed(dir.path(), "build", "c1", "changed"));
+    }
+
+    #[test]
+    fn test_fresh_start_and_finish_discard_journal() {
+        let dir = tempdir().unwrap();
+        let mut journal = RunJournal::start(dir.path(), false).unwrap();
+        journal.record(dir.path(), "build", "c1", "k1").unwrap();
+        drop(journal);
+
+        let journal = RunJournal::start(dir.path(), false).unwrap();
+        assert!(!journal.is_completed(dir.path(), "build", "c1", "k1"));
+        journal.finish().unwrap();
+        assert!(!journal_path(dir.path()).exists());
+
+        // Resuming with no journal left starts empty
+        let journal = RunJournal::start(dir.path(), true).unwrap();
+        assert!(!journal.is_completed(dir.path(), "build", "c1", "k1"));
+    }
+}
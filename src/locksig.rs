@@ -0,0 +1,71 @@
+/// Environment variable holding the secret verify.lock files are signed with. Keep it out
+/// of the repository (e.g. in CI secrets and each developer's environment).
+pub const SECRET_ENV: &str = "VERIFY_LOCK_SECRET";
+
+/// Context string deriving the signing key from the secret
+const KEY_CONTEXT: &str = "verify 2024 verify.lock signature";
+
+/// How verify.lock files are signed and checked, from the root config's `sign_lock` and
+/// the secret
+#[derive(Default)]
+pub struct Signing {
+    /// The root config has `sign_lock: true`
+    required: bool,
+    key: Option<[u8; 32]>,
+}
+
+impl std::fmt::Debug for Signing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key would give the secret away
+        f.debug_struct("Signing")
+            .field("required", &self.required)
+            .field("has_key", &self.has_key())
+            .finish()
+    }
+}
+
+impl Signing {
+    /// Sign every verify.lock written with `secret`, and with `required`, only trust lock
+    /// files whose signature matches
+    pub fn new(required: bool, secret: Option<&str>) -> Self {
+        Self {
+            required,
+            key: secret
+                .filter(|secret| !secret.is_empty())
+                .map(|secret| blake3::derive_key(KEY_CONTEXT, secret.as_bytes())),
+        }
+    }
+
+    /// Whether lock files must be signed (`sign_lock: true`)
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// Whether the secret is available to sign and check lock files with
+    pub fn has_key(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Whether unsigned or mismatching lock files are distrusted when loaded: signing is
+    /// required and the secret is available (without it there's nothing to check against)
+    pub fn enforced(&self) -> bool {
+        self.required && self.has_key()
+    }
+
+    /// Signature of a lock file's signed contents, if the secret is available
+    pub fn sign(&self, contents: &[u8]) -> Option<String> {
+        self.key
+            .map(|key| blake3::keyed_hash(&key, contents).to_hex().to_string())
+    }
+
+    /// Whether `signature` was made from `contents` with the secret. Always false without
+    /// the secret.
+    pub fn verify(&self, contents: &[u8], signature: Option<&str>) -> bool {
+        let (Some(key), Some(signature)) = (self.key, signature) else {
+            return false;
+        };
+        // Hash equality is constant-time
+        blake3::Hash::from_hex(signature)
+            .is_ok_and(|signature| signature == blake3::keyed_hash(&key, contents))
+    }
+}
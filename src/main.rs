@@ -1,22 +1,59 @@
+mod annotate;
+mod audit;
+mod autorun;
+mod bundle;
 mod cache;
+mod capture;
+mod checklock;
+mod ci;
 mod cli;
 mod config;
+mod configcache;
+mod configedit;
+mod configerror;
+mod coverage;
+mod dashboard;
+mod diff;
+mod events;
 mod graph;
+mod hashcache;
 mod hasher;
+mod history;
+mod hook;
+mod journal;
+mod locksig;
 mod metadata;
+mod migrations;
+mod normalize;
 mod output;
+mod patch;
+mod project;
+mod registry;
+mod remote;
 mod runner;
+mod server;
+mod snapshot;
+mod statedir;
+mod stats;
+mod submodule;
+mod tmpdir;
 mod trailer;
 mod ui;
+mod update;
+mod watch;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
-use std::path::Path;
+use cli::{CacheCommands, Cli, Commands, ConfigCommands, SnapshotCommands};
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::Arc;
 
 fn main() -> ExitCode {
-    match run() {
+    let result = run();
+    hashcache::save_all();
+    match result {
         Ok(code) => ExitCode::from(code as u8),
         Err(e) => {
             let ui = ui::Ui::new(false);
@@ -29,8 +66,14 @@ fn main() -> ExitCode {
 fn run() -> Result<i32> {
     let cli = Cli::parse();
 
+    // `run --root` runs the outermost config that includes this one as a subproject
+    let root_config = match cli.command {
+        Some(Commands::Run { root: true, .. }) => config::Config::find_root(&cli.config)?,
+        _ => None,
+    };
+
     // Determine project root (directory containing config file)
-    let config_path = &cli.config;
+    let config_path = root_config.as_ref().unwrap_or(&cli.config);
     let project_root = config_path
         .parent()
         .map(|p| {
@@ -43,17 +86,78 @@ fn run() -> Result<i32> {
         .unwrap_or(Path::new("."))
         .to_path_buf();
 
+    // The environment relocates state directories ahead of the config's `cache_dir`
+    if let Some(dir) = std::env::var_os(statedir::CACHE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        statedir::relocate(absolute(PathBuf::from(dir)));
+    }
+    let root = Root {
+        config_path,
+        project_root: &project_root,
+        loaded: OnceCell::new(),
+    };
+
+    if cli.no_config_cache {
+        configcache::disable();
+    }
+
     let ui = ui::Ui::new(cli.verbose);
 
     match cli.command.unwrap_or_default() {
+        Commands::List {
+            stale,
+            names_only,
+            flat,
+        } => {
+            let config = root.config()?;
+            let cache = root.cache()?;
+            let mut status = runner::collect_status(&project_root, config, &cache, None)?;
+            if stale {
+                status.retain_unverified();
+            }
+            if flat {
+                status.flatten();
+            }
+            let checks = status.into_checks();
+            if cli.json {
+                let output = output::ListOutput {
+                    checks: checks
+                        .into_iter()
+                        .map(|check| output::ListItemJson {
+                            name: check.name,
+                            status: check.status,
+                            description: check.description,
+                        })
+                        .collect(),
+                };
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                for check in checks {
+                    if names_only {
+                        println!("{}", check.name);
+                    } else if let Some(description) = check.description {
+                        println!("{}\t{}\t{}", check.name, check.status, description);
+                    } else {
+                        println!("{}\t{}", check.name, check.status);
+                    }
+                }
+            }
+            Ok(0)
+        }
+
         Commands::Init { force } => {
             config::init_config(config_path, force)?;
             ui.print_init_success(&config_path.display().to_string());
             Ok(0)
         }
 
-        Commands::Clean { names } => {
-            cache::clean_cache(&project_root, names.clone())?;
+        Commands::Clean { names, orphaned } => {
+            if orphaned {
+                let config = root.config()?;
+                let removed = registry::remove_orphaned(&project_root, config)?;
+                ui.print_orphans_removed(&removed);
+                return Ok(0);
+            }
+            cache::clean_cache(&project_root, root.settings()?, names.clone())?;
             ui.print_cache_cleaned(&names);
             Ok(0)
         }
@@ -62,8 +166,54 @@ fn run() -> Result<i32> {
             name,
             detailed,
             verify,
+            explain,
+            watch,
+            only_unverified,
+            flat,
+            json_file,
+            staged,
         } => {
-            let config = config::Config::load(config_path)?;
+            if watch {
+                if cli.json {
+                    anyhow::bail!("--watch can't be combined with --json");
+                }
+                // Relocates state; the dashboard reloads the config as it changes
+                root.load()?;
+                dashboard::run_status_watch(&project_root, config_path, cli.verbose)?;
+                return Ok(0);
+            }
+            if (only_unverified || flat) && !cli.json && json_file.is_none() {
+                anyhow::bail!(
+                    "--only-unverified and --flat only apply to JSON output (--json or --json-file)"
+                );
+            }
+
+            // With --staged, check a temporary copy of the index against this project's cache
+            let staged_tree = if staged {
+                Some(patch::PatchedTree::from_index(&project_root)?)
+            } else {
+                None
+            };
+            let tree_config;
+            let (status_root, config) = match staged_tree {
+                Some(ref tree) => {
+                    // Loaded after the root config, which may relocate state
+                    root.load()?;
+                    let file_name = config_path.file_name().unwrap_or("verify.yaml".as_ref());
+                    tree_config = config::Config::load(&tree.project_root().join(file_name))?;
+                    (tree.project_root(), &tree_config)
+                }
+                None => (project_root.as_path(), root.config()?),
+            };
+            if let Some(ref tree) = staged_tree {
+                tree.import_lock_files(&project_root, config)?;
+            }
+            if !cli.json {
+                for warning in config_warnings(status_root, config) {
+                    ui.print_warning(&warning);
+                }
+            }
+            warn_orphaned_subprojects(&ui, &project_root, config, cli.json)?;
 
             // Validate check name if provided
             if let Some(ref name) = name
@@ -72,9 +222,21 @@ fn run() -> Result<i32> {
                 anyhow::bail!("Unknown check: {}", name);
             }
 
-            let cache = cache::CacheState::load(&project_root)?;
-            let has_unverified =
-                runner::run_status(&project_root, &config, &cache, cli.json, detailed, name)?;
+            let cache = cache::CacheState::load(status_root, root.settings()?)?;
+            let has_unverified = runner::run_status(
+                status_root,
+                config,
+                &cache,
+                runner::StatusOptions {
+                    json: cli.json,
+                    detailed,
+                    explain,
+                    only_unverified,
+                    flat,
+                    json_file: json_file.as_deref(),
+                    name,
+                },
+            )?;
             if verify && has_unverified {
                 Ok(1)
             } else {
@@ -85,10 +247,82 @@ fn run() -> Result<i32> {
         Commands::Run {
             names,
             force,
+            force_stale_deps,
+            until,
+            resume,
             stage,
+            staged,
+            no_cache_write,
+            profile,
+            passthrough_exit_code,
+            confirm,
+            explain_cache,
+            show_output,
+            message,
+            json_file,
+            workspace,
+            root: _,
         } => {
-            let config = config::Config::load(config_path)?;
-            let mut cache = cache::CacheState::load(&project_root)?;
+            if no_cache_write {
+                statedir::disable_writes();
+            }
+            if let Some(workspace_path) = workspace {
+                let workspace = config::Workspace::load(&workspace_path)?;
+                for name in &names {
+                    if workspace.get(name).is_none() {
+                        anyhow::bail!("Unknown workspace project: {}", name);
+                    }
+                }
+
+                let workspace_root = workspace_path
+                    .parent()
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                return runner::run_workspace(
+                    workspace_root,
+                    &workspace,
+                    names,
+                    runner::RunOptions {
+                        force: runner::Force::new(force, &[], false),
+                        json: cli.json,
+                        json_file: json_file.as_deref(),
+                        verbose: cli.verbose,
+                        message,
+                        explain_cache,
+                        show_output,
+                        ..Default::default()
+                    },
+                );
+            }
+
+            // With --staged, run in a temporary copy of the index, starting from this
+            // project's cache and bringing the results back afterwards
+            let staged_tree = if staged {
+                Some(patch::PatchedTree::from_index(&project_root)?)
+            } else {
+                None
+            };
+            let tree_config;
+            let (run_root, config) = match staged_tree {
+                Some(ref tree) => {
+                    // Loaded after the root config, which may relocate state
+                    root.load()?;
+                    let file_name = config_path.file_name().unwrap_or("verify.yaml".as_ref());
+                    tree_config = config::Config::load(&tree.project_root().join(file_name))?;
+                    (tree.project_root(), &tree_config)
+                }
+                None => (project_root.as_path(), root.config()?),
+            };
+            if let Some(ref tree) = staged_tree {
+                tree.import_lock_files(&project_root, config)?;
+            }
+            let mut cache = cache::CacheState::load(run_root, root.settings()?)?;
+            if !cli.json {
+                for warning in config_warnings(run_root, config) {
+                    ui.print_warning(&warning);
+                }
+            }
+            warn_orphaned_subprojects(&ui, &project_root, config, cli.json)?;
 
             // Validate requested check names exist
             for name in &names {
@@ -97,39 +331,175 @@ fn run() -> Result<i32> {
                 }
             }
 
-            let result = runner::run_checks(
-                &project_root,
-                &config,
-                &mut cache,
-                names,
-                force,
-                cli.json,
-                cli.verbose,
-            )?;
+            // Stale dependencies of the named checks run first, and can take far longer
+            // than the checks themselves, so say so before starting
+            if !names.is_empty() && !force_stale_deps && until.is_none() && !cli.json {
+                let dependencies = runner::stale_dependencies(run_root, config, &cache, &names)?;
+                if !dependencies.is_empty() {
+                    ui.print_stale_dependencies(&names, &dependencies);
+                    if confirm && ui.confirm("Run them?") == Some(false) {
+                        eprintln!("Run cancelled");
+                        return Ok(1);
+                    }
+                }
+            }
 
-            // Stage verify.lock if requested and checks passed
+            // Running a check's direct dependencies runs the rest of its dependencies too
+            let names = match until {
+                Some(check) => {
+                    let Some(v) = config.get(&check) else {
+                        anyhow::bail!("Unknown check: {}", check);
+                    };
+                    if v.depends_on.is_empty() {
+                        eprintln!("'{}' has no dependencies to run", check);
+                        return Ok(0);
+                    }
+                    v.depends_on.clone()
+                }
+                None => names,
+            };
+
+            // Without check names, run the requested or default profile
+            let profile = profile.or_else(|| {
+                names.is_empty().then(|| {
+                    config
+                        .default_profile(hook::in_git_hook())
+                        .map(String::from)
+                })?
+            });
+            let names = match profile {
+                Some(profile) => {
+                    let names = config.profile_checks(&profile)?;
+                    if names.is_empty() {
+                        eprintln!("Profile '{}' has no checks to run", profile);
+                        return Ok(0);
+                    }
+                    names
+                }
+                None => names,
+            };
+
+            let options = runner::RunOptions {
+                force: runner::Force::new(force, &names, force_stale_deps),
+                resume,
+                json: cli.json,
+                json_file: json_file.as_deref(),
+                verbose: cli.verbose,
+                message,
+                passthrough_exit_code,
+                explain_cache,
+                show_output,
+            };
+            let (result, subproject_dirs) =
+                runner::run_checks(run_root, config, &mut cache, names, options)?;
+            if let Some(ref tree) = staged_tree
+                && !no_cache_write
+            {
+                tree.export_lock_files(&project_root, config)?;
+            }
+
+            // Stage verify.lock files (root and subprojects touched by the run)
+            // if requested and checks passed
             if stage && result == 0 {
-                let lock_path = project_root.join("verify.lock");
-                if lock_path.exists() {
+                let lock_files: Vec<PathBuf> = std::iter::once(PathBuf::from("verify.lock"))
+                    .chain(subproject_dirs.iter().map(|dir| dir.join("verify.lock")))
+                    .filter(|path| project_root.join(path).exists())
+                    .collect();
+                if !lock_files.is_empty() {
                     std::process::Command::new("git")
-                        .args(["add", "verify.lock"])
+                        .arg("add")
+                        .args(&lock_files)
                         .current_dir(&project_root)
                         .status()
                         .ok(); // Ignore errors (might not be in git repo)
                 }
+
+                // With compact trailers, the manifest `verify sign` will commit to
+                if config.trailer == config::TrailerFormat::Compact {
+                    let hashes = trailer::compute_all_hashes(run_root, config, &cache)?;
+                    let hashes = trailer::select_signed_checks(config, hashes, &[])?;
+                    let manifest = trailer::format_manifest(&hashes);
+                    if let Err(e) = trailer::stage_manifest(&project_root, &manifest) {
+                        ui.print_warning(&format!("{:#}", e));
+                    }
+                }
+            }
+
+            Ok(result)
+        }
+
+        // The hook only decides when this runs; affected checks are the same either way
+        Commands::HookRun { hook: _, files } => {
+            let config = root.config()?;
+            let mut names = hook::affected_checks(&project_root, config, &files)?;
+            if let Some(profile) = config.default_profile(true) {
+                let selected = config.profile_checks(profile)?;
+                names.retain(|name| selected.contains(name));
+            }
+            if names.is_empty() {
+                return Ok(0);
             }
 
+            let mut cache = root.cache()?;
+            let (result, _) = runner::run_checks(
+                &project_root,
+                config,
+                &mut cache,
+                names,
+                runner::RunOptions {
+                    json: cli.json,
+                    verbose: cli.verbose,
+                    ..Default::default()
+                },
+            )?;
             Ok(result)
         }
 
+        Commands::Watch { names, debounce } => {
+            if cli.json {
+                anyhow::bail!("watch can't be combined with --json");
+            }
+            // Relocates state; each round reloads the config
+            root.load()?;
+            autorun::run_watch(
+                &project_root,
+                config_path,
+                names,
+                std::time::Duration::from_millis(debounce),
+                cli.verbose,
+            )?;
+            Ok(0)
+        }
+
+        Commands::Serve { socket } => {
+            // Relocates state; each request reloads the config
+            root.load()?;
+            match socket {
+                Some(socket) => server::serve_socket(&project_root, config_path, &socket)?,
+                None => server::serve_stdio(&project_root, config_path)?,
+            }
+            Ok(0)
+        }
+
+        Commands::MarkVerified { name, because } => {
+            let config = root.config()?;
+            let mut cache = root.cache()?;
+            let marked = runner::mark_verified(&project_root, config, &mut cache, &name, &because)?;
+            ui.print_marked_verified(&name, &marked.by);
+            Ok(0)
+        }
+
         Commands::Hash { name } => {
-            let config = config::Config::load(config_path)?;
-            let cache = cache::CacheState::load(&project_root)?;
+            let config = root.config()?;
+            let cache = root.cache()?;
 
-            let hashes = trailer::compute_all_hashes(&project_root, &config, &cache)?;
+            let hashes = trailer::compute_all_hashes(&project_root, config, &cache)?;
 
             if let Some(ref check_name) = name {
-                if config.get(check_name).is_none() {
+                let subprojects =
+                    project::Subprojects::load(&project_root, config, cache.settings())?;
+                if config.get(check_name).is_none() && subprojects.find_check(check_name).is_none()
+                {
                     anyhow::bail!("Unknown check: {}", check_name);
                 }
                 match hashes.get(check_name) {
@@ -152,26 +522,105 @@ fn run() -> Result<i32> {
             }
         }
 
-        Commands::Sign { file } => {
-            let config = config::Config::load(config_path)?;
-            let cache = cache::CacheState::load(&project_root)?;
+        Commands::Sign { file, checks } => {
+            let config = root.config()?;
+            let cache = root.cache()?;
+
+            let hashes = trailer::compute_all_hashes(&project_root, config, &cache)?;
+            let hashes = trailer::select_signed_checks(config, hashes, &checks)?;
+            if hashes.is_empty() {
+                return Ok(0);
+            }
 
-            let hashes = trailer::compute_all_hashes(&project_root, &config, &cache)?;
-            trailer::write_trailer(&file, &hashes)?;
+            let trailer_value = match config.trailer {
+                config::TrailerFormat::Full => {
+                    trailer::format_trailer_value(&hashes, config.trailer_hash_length())
+                }
+                config::TrailerFormat::Compact => {
+                    // The commit is already staged, so the manifest must have been too
+                    let manifest = trailer::format_manifest(&hashes);
+                    trailer::check_staged_manifest(&project_root, &manifest)?;
+                    trailer::format_compact_trailer_value(&manifest, config.trailer_hash_length())
+                }
+            };
+            trailer::write_trailer(&file, &trailer_value)?;
             Ok(0)
         }
 
-        Commands::Check { name } => {
-            let config = config::Config::load(config_path)?;
+        Commands::Check {
+            name,
+            patch,
+            bundle,
+            trailer,
+        } => {
+            // With --patch/--bundle, validate a temporary copy of the tree instead
+            let patched = if let Some(ref patch) = patch {
+                Some(patch::PatchedTree::from_patch(&project_root, patch)?)
+            } else if let Some(ref bundle) = bundle {
+                Some(patch::PatchedTree::from_bundle(&project_root, bundle)?)
+            } else {
+                None
+            };
 
-            if let Some(ref check_name) = name {
-                if config.get(check_name).is_none() {
-                    anyhow::bail!("Unknown check: {}", check_name);
+            let tree_config;
+            let (check_root, config) = match patched {
+                Some(ref tree) => {
+                    // Loaded after the root config, which may relocate state
+                    root.load()?;
+                    let file_name = config_path.file_name().unwrap_or("verify.yaml".as_ref());
+                    tree_config = config::Config::load(&tree.project_root().join(file_name))?;
+                    (tree.project_root(), &tree_config)
+                }
+                None => (project_root.as_path(), root.config()?),
+            };
+
+            if let Some(ref check_name) = name
+                && config.get(check_name).is_none()
+            {
+                anyhow::bail!("Unknown check: {}", check_name);
+            }
+
+            // With sign_lock, a lock file that wasn't written with the secret fails the
+            // check, since its results may have been edited in by hand. Whether to sign is
+            // up to the local config, not a patch or bundle being checked.
+            let signing = &root.settings()?.signing;
+            if signing.required() {
+                if !signing.has_key() {
+                    anyhow::bail!(
+                        "sign_lock is set but {} isn't, so verify.lock can't be checked",
+                        locksig::SECRET_ENV
+                    );
+                }
+                let tampered: Vec<String> = bundle::collect_lock_files(check_root, config)?
+                    .into_iter()
+                    .filter(|(_, contents)| {
+                        !serde_json::from_slice::<cache::CacheState>(contents)
+                            .is_ok_and(|lock| lock.has_valid_signature(signing))
+                    })
+                    .map(|(key, _)| Path::new(&key).join("verify.lock").display().to_string())
+                    .collect();
+                if !tampered.is_empty() {
+                    for path in &tampered {
+                        ui.print_error(&format!(
+                            "{}: signature doesn't match (edited by hand, or written without {})",
+                            path,
+                            locksig::SECRET_ENV
+                        ));
+                    }
+                    return Ok(1);
                 }
             }
 
+            let trailer_hashes = match (trailer, &patched) {
+                (Some(value), _) => Some(trailer::resolve_trailer_value(&value, || {
+                    trailer::read_manifest_file(check_root)
+                })?),
+                (None, Some(tree)) => tree.trailer().cloned(),
+                (None, None) => trailer::read_trailer(&project_root)?,
+            };
+
             let has_unverified =
-                runner::run_check_trailer(&project_root, &config, cli.json, name)?;
+                runner::run_check_trailer(check_root, config, trailer_hashes, cli.json, name)?;
             if has_unverified {
                 Ok(1)
             } else {
@@ -180,9 +629,10 @@ fn run() -> Result<i32> {
         }
 
         Commands::Resign {} => {
-            let config = config::Config::load(config_path)?;
-            let cache = cache::CacheState::load(&project_root)?;
-            let hashes = trailer::compute_all_hashes(&project_root, &config, &cache)?;
+            let config = root.config()?;
+            let cache = root.cache()?;
+            let hashes = trailer::compute_all_hashes(&project_root, config, &cache)?;
+            let hashes = trailer::select_signed_checks(config, hashes, &[])?;
             if hashes.is_empty() {
                 eprintln!("No verified checks to sign");
                 return Ok(0);
@@ -192,8 +642,11 @@ fn run() -> Result<i32> {
             if let Some(existing) = trailer::read_trailer(&project_root)? {
                 let all_match = hashes.len() == existing.len()
                     && hashes.iter().all(|(name, hash)| {
-                        existing.get(name).map(|s| s.as_str())
-                            == Some(trailer::truncate_hash(hash))
+                        // Re-sign trailers written with shorter hashes than configured
+                        existing.get(name).is_some_and(|existing| {
+                            trailer::hash_matches(existing, hash)
+                                && existing.len() >= config.trailer_hash_length()
+                        })
                     });
                 if all_match {
                     if !cli.json {
@@ -203,19 +656,553 @@ fn run() -> Result<i32> {
                 }
             }
 
-            let trailer_value = trailer::format_trailer_value(&hashes);
-            trailer::resign_head(&project_root, &hashes)?;
+            let trailer_value = match config.trailer {
+                config::TrailerFormat::Full => {
+                    trailer::format_trailer_value(&hashes, config.trailer_hash_length())
+                }
+                config::TrailerFormat::Compact => {
+                    // Amend the updated manifest into HEAD along with the trailer
+                    let manifest = trailer::format_manifest(&hashes);
+                    trailer::stage_manifest(&project_root, &manifest)?;
+                    trailer::format_compact_trailer_value(&manifest, config.trailer_hash_length())
+                }
+            };
+            trailer::resign_head(&project_root, &trailer_value)?;
             if !cli.json {
                 eprintln!("Resigned HEAD with: {}", trailer_value);
             }
             Ok(0)
         }
 
-        Commands::Sync {} => {
-            let config = config::Config::load(config_path)?;
-            let mut cache = cache::CacheState::load(&project_root)?;
-            runner::run_sync(&project_root, &config, &mut cache, cli.json, cli.verbose)?;
+        Commands::Sync {
+            depth,
+            git_ref,
+            all_branches,
+        } => {
+            if git_ref.as_deref().is_some_and(|r| r.starts_with('-')) {
+                anyhow::bail!("Invalid --ref: {}", git_ref.unwrap_or_default());
+            }
+            let revisions: Vec<&str> = if all_branches {
+                vec!["--branches", "--remotes"]
+            } else {
+                git_ref.as_deref().into_iter().collect()
+            };
+            let config = root.config()?;
+            let mut cache = root.cache()?;
+            let summary = runner::run_sync(
+                &project_root,
+                config,
+                &mut cache,
+                depth,
+                &revisions,
+                cli.json,
+                cli.verbose,
+            )?;
+            Ok(summary.exit_code())
+        }
+
+        Commands::Annotate {
+            github_token,
+            repo,
+            name,
+            api_url,
+        } => {
+            if cli.json {
+                anyhow::bail!("annotate doesn't support --json; use check for the status");
+            }
+            let config = root.config()?;
+            let trailer_hashes = trailer::read_trailer(&project_root)?;
+            let options = annotate::AnnotateOptions {
+                token: github_token,
+                repo,
+                name,
+                api_url,
+            };
+            let annotation = annotate::annotate(
+                &project_root,
+                config_path,
+                config,
+                trailer_hashes.as_ref(),
+                &options,
+            )?;
+            // The check run carries the result, so creating it succeeds either way
+            println!(
+                "Created check run '{}' on {}: {} of {} checks verified",
+                options.name,
+                &annotation.head_sha[..annotation.head_sha.len().min(7)],
+                annotation.verified,
+                annotation.total
+            );
+            if !annotation.url.is_empty() {
+                println!("{}", annotation.url);
+            }
             Ok(0)
         }
+
+        Commands::Bootstrap { depth, run } => {
+            if cli.json {
+                anyhow::bail!("bootstrap doesn't support --json; use sync, status and run instead");
+            }
+            let config = root.config()?;
+            let mut cache = root.cache()?;
+            runner::run_bootstrap(&project_root, config, &mut cache, depth, run, cli.verbose)
+        }
+
+        Commands::Bisect { name, max_depth } => {
+            let config = root.config()?;
+            if config.get(&name).is_none() {
+                anyhow::bail!("Unknown check: {}", name);
+            }
+
+            let config_file = config_path.file_name().unwrap_or("verify.yaml".as_ref());
+            let found = runner::run_bisect(
+                &project_root,
+                Path::new(config_file),
+                &name,
+                max_depth,
+                cli.json,
+            )?;
+            Ok(if found { 0 } else { 1 })
+        }
+
+        Commands::Why { name } => {
+            let config = root.config()?;
+            if config.get(&name).is_none() {
+                anyhow::bail!("Unknown check: {}", name);
+            }
+
+            let cache = root.cache()?;
+            runner::run_why(&project_root, config, &cache, &name, cli.json)?;
+            Ok(0)
+        }
+
+        Commands::Explain { name } => {
+            let config = root.config()?;
+            let cache = root.cache()?;
+            runner::run_explain(&project_root, config, &cache, &name, cli.json)?;
+            Ok(0)
+        }
+
+        Commands::Diff { name, tool } => {
+            let config = root.config()?;
+            let Some(check) = config.get(&name) else {
+                anyhow::bail!("Unknown check: {}", name);
+            };
+            let cache = root.cache()?;
+            diff::run_diff(&project_root, check, &cache, tool.as_deref(), cli.json, &ui)?;
+            Ok(0)
+        }
+
+        Commands::Coverage { depth } => {
+            let config = root.config()?;
+            let report = coverage::coverage(&project_root, config, depth)?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                ui.print_coverage(&report);
+            }
+            Ok(0)
+        }
+
+        Commands::Doctor {} => {
+            let config = root.config()?;
+            let mut warnings = config_warnings(&project_root, config);
+            warnings.extend(large_file_warnings(&project_root, config)?);
+            warnings.extend(submodule_warnings(&project_root, config));
+            for warning in &warnings {
+                ui.print_warning(warning);
+            }
+            if warnings.is_empty() {
+                eprintln!("No problems found");
+                return Ok(0);
+            }
+            Ok(1)
+        }
+
+        Commands::Audit {} => {
+            let Some(path) = root.settings()?.audit_log.clone() else {
+                anyhow::bail!("No audit log configured (set audit_log in verify.yaml)");
+            };
+            if !path.exists() {
+                anyhow::bail!("Audit log not found: {}", path.display());
+            }
+            let report = audit::verify_chain(&path)?;
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                ui.print_audit_report(&report);
+            }
+            Ok(if report.intact { 0 } else { 1 })
+        }
+
+        Commands::History { limit } => {
+            // Relocates state
+            root.load()?;
+            let runs = history::load(&project_root, limit)?;
+            if cli.json {
+                let output = output::HistoryOutput { runs };
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                ui.print_history(&runs);
+            }
+            Ok(0)
+        }
+
+        Commands::Stats {} => {
+            // Relocates state
+            root.load()?;
+            let summary = stats::StatsSummary::new(&stats::load(&project_root)?);
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                ui.print_stats(&summary);
+            }
+            Ok(0)
+        }
+
+        Commands::SelfUpdate {} => {
+            match update::self_update()? {
+                update::UpdateOutcome::UpToDate => {
+                    eprintln!("verify {} is the latest version", update::CURRENT_VERSION);
+                }
+                update::UpdateOutcome::Updated { version } => {
+                    eprintln!("Updated verify {} -> {}", update::CURRENT_VERSION, version);
+                }
+            }
+            Ok(0)
+        }
+
+        Commands::Cache { command } => {
+            let config = root.config()?;
+
+            match command {
+                CacheCommands::Export { file, artifacts } => {
+                    let locks = bundle::collect_lock_files(&project_root, config)?;
+                    let state = bundle::collect_state_files(&project_root, config)?;
+                    let artifacts = if artifacts {
+                        bundle::collect_artifact_files(&project_root, config)?
+                    } else {
+                        bundle::ArtifactFiles::new()
+                    };
+                    bundle::write_bundle(&file, &locks, &state, &artifacts)?;
+                    if !cli.json {
+                        let artifact_count: usize = artifacts.values().map(|f| f.len()).sum();
+                        if artifact_count > 0 {
+                            eprintln!(
+                                "Exported {} lock file(s) and {} artifact file(s) to {}",
+                                locks.len(),
+                                artifact_count,
+                                file.display()
+                            );
+                        } else {
+                            eprintln!(
+                                "Exported {} lock file(s) to {}",
+                                locks.len(),
+                                file.display()
+                            );
+                        }
+                    }
+                    Ok(0)
+                }
+                CacheCommands::Import { file } => {
+                    let mut cache = root.cache()?;
+                    runner::run_cache_import(&project_root, config, &mut cache, &file, cli.json)?;
+                    Ok(0)
+                }
+            }
+        }
+
+        Commands::Snapshot { command } => {
+            let config = root.config()?;
+
+            match command {
+                SnapshotCommands::Save { name } => {
+                    let count = snapshot::save(&project_root, config, &name)?;
+                    if !cli.json {
+                        eprintln!("Saved snapshot '{}' ({} lock file(s))", name, count);
+                    }
+                }
+                SnapshotCommands::Restore { name } => {
+                    let count = snapshot::restore(&project_root, config, &name)?;
+                    if !cli.json {
+                        eprintln!("Restored snapshot '{}' ({} lock file(s))", name, count);
+                    }
+                }
+            }
+            Ok(0)
+        }
+
+        Commands::Capture { name, restore } => {
+            if restore {
+                // Relocates state
+                root.load()?;
+                let (dir, manifest) = capture::restore(&project_root, &name)?;
+                if !cli.json {
+                    eprintln!(
+                        "Restored {} file(s) of '{}' ({}) captured {}",
+                        manifest.files.len(),
+                        manifest.check,
+                        trailer::truncate_hash(
+                            &manifest.hash,
+                            trailer::DEFAULT_TRAILER_HASH_LENGTH
+                        ),
+                        manifest.captured_at.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                }
+                println!("{}", dir.display());
+            } else {
+                let config = root.config()?;
+                let (path, manifest) = capture::capture(&project_root, config_path, config, &name)?;
+                if !cli.json {
+                    eprintln!("Captured {} file(s) of '{}'", manifest.files.len(), name);
+                }
+                println!("{}", path.display());
+            }
+            Ok(0)
+        }
+
+        Commands::Config { command } => {
+            match command {
+                ConfigCommands::Get { key } => {
+                    println!("{}", configedit::get(config_path, &key)?);
+                }
+                ConfigCommands::Set { key, value } => {
+                    configedit::set(config_path, &key, &value)?;
+                }
+                ConfigCommands::AddCheck {
+                    name,
+                    command,
+                    cache_paths,
+                    depends_on,
+                } => {
+                    let check = configedit::NewCheck {
+                        name,
+                        command,
+                        cache_paths,
+                        depends_on,
+                    };
+                    configedit::add_check(config_path, &check)?;
+                    if !cli.json {
+                        eprintln!("Added check '{}'", check.name);
+                    }
+                }
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// The config verify was pointed at, loaded once by the commands that use it, along with
+/// the settings it gives every project those commands touch
+struct Root<'a> {
+    config_path: &'a Path,
+    project_root: &'a Path,
+    loaded: OnceCell<(config::Config, Arc<config::Settings>)>,
+}
+
+impl Root<'_> {
+    /// Load the config on first use, relocating state directories to its `cache_dir`
+    /// (unless VERIFY_CACHE_DIR already did) before anything reads or writes state
+    fn load(&self) -> Result<&(config::Config, Arc<config::Settings>)> {
+        if let Some(loaded) = self.loaded.get() {
+            return Ok(loaded);
+        }
+        let config = config::Config::load(self.config_path)?;
+        if let Some(dir) = config.cache_dir(self.project_root) {
+            statedir::relocate(absolute(dir));
+        }
+        let settings = Arc::new(config.settings(self.project_root));
+        Ok(self.loaded.get_or_init(|| (config, settings)))
+    }
+
+    fn config(&self) -> Result<&config::Config> {
+        Ok(&self.load()?.0)
+    }
+
+    fn settings(&self) -> Result<&Arc<config::Settings>> {
+        Ok(&self.load()?.1)
+    }
+
+    /// The root project's cache
+    fn cache(&self) -> Result<cache::CacheState> {
+        cache::CacheState::load(self.project_root, self.settings()?)
+    }
+}
+
+/// `path` made absolute, so relocated state stays put when commands change directory
+fn absolute(path: PathBuf) -> PathBuf {
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+/// Record the configured subprojects, warning about removed ones that left state behind
+fn warn_orphaned_subprojects(
+    ui: &ui::Ui,
+    project_root: &Path,
+    config: &config::Config,
+    json: bool,
+) -> Result<()> {
+    let orphaned = registry::record(project_root, config)?;
+    if !json {
+        for path in orphaned {
+            ui.print_warning(&format!(
+                "{}: subproject is no longer in the config but its verify.lock or .verify/ remain \
+                 (remove with `verify clean --orphaned`)",
+                path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Problems with the config worth a warning on `verify run` and `verify status`
+fn config_warnings(project_root: &Path, config: &config::Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Checks that read another check's artifacts but can't be ordered after it, since the
+    // producer depends on them and so may run on stale or missing artifacts
+    for (producer, reader) in config.artifact_conflicts() {
+        warnings.push(format!(
+            "{}: cache_paths include artifacts of '{}', but '{}' depends on '{}'",
+            reader, producer, producer, reader
+        ));
+    }
+
+    cache_paths_warnings(project_root, config, "", &mut warnings);
+
+    warnings.extend(config.consistency_warnings());
+    warnings
+}
+
+/// Warnings about the files each check's cache_paths match, in the project and in every
+/// subproject, whose checks are named with their prefix (as in `backend/build`)
+fn cache_paths_warnings(
+    project_root: &Path,
+    config: &config::Config,
+    prefix: &str,
+    warnings: &mut Vec<String>,
+) {
+    for check in config.verifications_only() {
+        let name = format!("{}{}", prefix, check.name);
+        let Ok((mismatched, empty)) =
+            hasher::unmatched_patterns(project_root, &check.cache_paths, check.glob_options())
+        else {
+            continue;
+        };
+
+        // Patterns that only match when ignoring case usually come from configs
+        // written on case-insensitive filesystems
+        for pattern in &mismatched {
+            warnings.push(format!(
+                "{}: '{}' matches no files, but would match ignoring case (set case_insensitive: true)",
+                name, pattern
+            ));
+        }
+
+        // Other patterns that match nothing, which are usually typos
+        if check.on_empty_match.unwrap_or_default() == config::EmptyMatchPolicy::Warn {
+            for pattern in &empty {
+                warnings.push(format!(
+                    "{}: '{}' matches no files (set on_empty_match: allow if that's expected)",
+                    name, pattern
+                ));
+            }
+        }
+
+        // Files left out of the hash by max_file_size
+        if let Some(max_file_size) = check.max_file_size
+            && check.large_files != Some(config::LargeFilePolicy::Truncate)
+            && let Ok(skipped) = hasher::large_files(
+                project_root,
+                &check.cache_paths,
+                check.glob_options(),
+                max_file_size,
+            )
+            && !skipped.is_empty()
+        {
+            warnings.push(format!(
+                "{}: not hashing {} file(s) over max_file_size ({}): {}",
+                name,
+                skipped.len(),
+                output::format_size(max_file_size),
+                describe_files(&skipped)
+            ));
+        }
+    }
+
+    // Subprojects whose config doesn't load are reported when they're run
+    for subproject in config.subprojects() {
+        let dir = project_root.join(&subproject.path);
+        if let Ok(sub_config) = config::Config::load_with_base(&dir.join("verify.yaml"), &dir) {
+            let prefix = format!("{}{}/", prefix, subproject.prefix());
+            cache_paths_warnings(&dir, &sub_config, &prefix, warnings);
+        }
+    }
+}
+
+/// Files hashed in full that are large enough to slow down `verify status`
+const LARGE_FILE_SIZE: u64 = 100 << 20;
+
+/// Checks whose cache_paths match files over `LARGE_FILE_SIZE` that are hashed in full,
+/// listing the largest, for `verify doctor`
+fn large_file_warnings(project_root: &Path, config: &config::Config) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    for check in config.verifications_only() {
+        let threshold = check.max_file_size.unwrap_or(u64::MAX).min(LARGE_FILE_SIZE);
+        let mut large = hasher::large_files(
+            project_root,
+            &check.cache_paths,
+            check.glob_options(),
+            threshold,
+        )?;
+        // Files over max_file_size are already skipped or truncated
+        if let Some(max_file_size) = check.max_file_size {
+            large.retain(|(_, size)| *size <= max_file_size);
+        }
+        if !large.is_empty() {
+            warnings.push(format!(
+                "{}: large files slow down hashing: {} (set max_file_size to skip or truncate them)",
+                check.name,
+                describe_files(&large)
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Checks whose cache_paths reach into a submodule that isn't checked out, so they hash
+/// none of its files, for `verify doctor`
+fn submodule_warnings(project_root: &Path, config: &config::Config) -> Vec<String> {
+    let uninitialized: Vec<submodule::Submodule> = submodule::list(project_root, &[])
+        .into_iter()
+        .filter(|submodule| !submodule.is_initialized(project_root))
+        .collect();
+    let mut warnings = Vec::new();
+    for check in config.verifications_only() {
+        for submodule in &uninitialized {
+            if let Some(pattern) = check
+                .cache_paths
+                .iter()
+                .find(|pattern| submodule::pattern_reaches(pattern, &submodule.path))
+            {
+                warnings.push(format!(
+                    "{}: '{}' reaches into submodule '{}', which isn't initialized (run `git submodule update --init`)",
+                    check.name, pattern, submodule.path
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// "a.bin (3.0 GB), b.bin (120.0 MB)", listing at most five files
+fn describe_files(files: &[(String, u64)]) -> String {
+    let mut listed: Vec<String> = files
+        .iter()
+        .take(5)
+        .map(|(path, size)| format!("{} ({})", path, output::format_size(*size)))
+        .collect();
+    if files.len() > 5 {
+        listed.push(format!("and {} more", files.len() - 5));
     }
+    listed.join(", ")
 }
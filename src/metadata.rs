@@ -1,8 +1,10 @@
-use crate::config::MetadataPattern;
+use crate::config::{Aggregation, MetadataPattern};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::path::Path;
 
 /// A metadata value extracted from command output
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -23,16 +25,47 @@ impl fmt::Display for MetadataValue {
     }
 }
 
-/// Extract metadata from command output using configured patterns
+/// Remove string values longer than `max_length` bytes, returning each removed key with
+/// its length
+pub fn drop_oversized(
+    metadata: &mut BTreeMap<String, MetadataValue>,
+    max_length: usize,
+) -> Vec<(String, usize)> {
+    let mut dropped = Vec::new();
+    metadata.retain(|key, value| match value {
+        MetadataValue::String(s) if s.len() > max_length => {
+            dropped.push((key.clone(), s.len()));
+            false
+        }
+        _ => true,
+    });
+    dropped
+}
+
+/// Extract metadata from command output using configured patterns. Patterns with a
+/// `file` read that file under `project_root` instead; missing files give no value.
 pub fn extract_metadata(
+    project_root: &Path,
     output: &str,
     patterns: &HashMap<String, MetadataPattern>,
 ) -> BTreeMap<String, MetadataValue> {
     let mut result = BTreeMap::new();
 
     for (key, pattern) in patterns {
-        if let Some(value) = apply_pattern(output, pattern) {
-            result.insert(key.clone(), parse_value(&value));
+        let source = match pattern.file() {
+            Some(file) => match std::fs::read_to_string(project_root.join(file)) {
+                Ok(content) => Cow::Owned(content),
+                Err(_) => continue,
+            },
+            None => Cow::Borrowed(output),
+        };
+        if let Some(value) = apply_pattern(&source, pattern) {
+            let value = parse_value(&value);
+            let value = match pattern.precision() {
+                Some(precision) => round_value(value, precision),
+                None => value,
+            };
+            result.insert(key.clone(), value);
         }
     }
 
@@ -40,26 +73,47 @@ pub fn extract_metadata(
 }
 
 fn apply_pattern(output: &str, pattern: &MetadataPattern) -> Option<String> {
-    match pattern {
-        MetadataPattern::Simple(pat) => {
-            let re = Regex::new(pat).ok()?;
-            // Use last match since relevant output is typically at the end
-            let caps = re.captures_iter(output).last()?;
-            caps.get(1).map(|m| m.as_str().to_string())
-        }
-        MetadataPattern::WithReplacement(pat, repl) => {
-            let re = Regex::new(pat).ok()?;
-            // Use last match since relevant output is typically at the end
-            let caps = re.captures_iter(output).last()?;
-            // Expand $1, $2, etc. in replacement string
-            let mut result = repl.clone();
-            for (i, cap) in caps.iter().enumerate().skip(1) {
-                if let Some(m) = cap {
-                    result = result.replace(&format!("${}", i), m.as_str());
-                }
+    let (pat, repl) = match pattern {
+        MetadataPattern::Simple(pat) => (pat, None),
+        MetadataPattern::WithReplacement(pat, repl) => (pat, Some(repl)),
+        MetadataPattern::Detailed(detailed) => {
+            if let Some(json_path) = &detailed.json_path {
+                return json_value(output, json_path);
             }
-            Some(result)
+            (detailed.pattern.as_ref()?, detailed.replacement.as_ref())
         }
+    };
+    let re = Regex::new(pat).ok()?;
+    // Use last match since relevant output is typically at the end
+    let caps = re.captures_iter(output).last()?;
+    let Some(repl) = repl else {
+        return caps.get(1).map(|m| m.as_str().to_string());
+    };
+    // Expand $1, $2, etc. in replacement string
+    let mut result = repl.clone();
+    for (i, cap) in caps.iter().enumerate().skip(1) {
+        if let Some(m) = cap {
+            result = result.replace(&format!("${}", i), m.as_str());
+        }
+    }
+    Some(result)
+}
+
+/// The scalar at a dotted path (object keys and array indices) in a JSON document
+fn json_value(output: &str, json_path: &str) -> Option<String> {
+    let document: serde_json::Value = serde_json::from_str(output.trim()).ok()?;
+    let mut value = &document;
+    for segment in json_path.split('.') {
+        value = match value {
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => value.get(segment)?,
+        };
+    }
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
     }
 }
 
@@ -76,20 +130,85 @@ fn parse_value(s: &str) -> MetadataValue {
     MetadataValue::String(s.to_string())
 }
 
+/// Round a numeric value to `precision` decimal places (0 gives an integer)
+fn round_value(value: MetadataValue, precision: u32) -> MetadataValue {
+    match value {
+        MetadataValue::Float(f) if precision == 0 => MetadataValue::Integer(f.round() as i64),
+        MetadataValue::Float(f) => MetadataValue::Float(round_to(f, precision)),
+        other => other,
+    }
+}
+
+fn round_to(value: f64, precision: u32) -> f64 {
+    let scale = 10f64.powi(precision as i32);
+    (value * scale).round() / scale
+}
+
 /// Compute delta between two numeric metadata values
 pub fn compute_delta(current: &MetadataValue, prev: &MetadataValue) -> Option<f64> {
-    match (current, prev) {
-        (MetadataValue::Integer(c), MetadataValue::Integer(p)) => Some((*c - *p) as f64),
-        (MetadataValue::Float(c), MetadataValue::Float(p)) => Some(*c - *p),
-        (MetadataValue::Integer(c), MetadataValue::Float(p)) => Some(*c as f64 - *p),
-        (MetadataValue::Float(c), MetadataValue::Integer(p)) => Some(*c - *p as f64),
-        _ => None,
+    let delta = match (current, prev) {
+        (MetadataValue::Integer(c), MetadataValue::Integer(p)) => (*c - *p) as f64,
+        (MetadataValue::Float(c), MetadataValue::Float(p)) => *c - *p,
+        (MetadataValue::Integer(c), MetadataValue::Float(p)) => *c as f64 - *p,
+        (MetadataValue::Float(c), MetadataValue::Integer(p)) => *c - *p as f64,
+        _ => return None,
+    };
+    // Drop floating point noise, so 85.3 after 85.1 is a delta of 0.2, not 0.19999999999999574
+    Some(round_to(delta, 9))
+}
+
+/// Combine values of a metadata key from several checks.
+/// Non-numeric values are ignored; returns None if there are no numeric values.
+pub fn aggregate_values(
+    values: &[MetadataValue],
+    aggregation: Aggregation,
+) -> Option<MetadataValue> {
+    let numbers: Vec<&MetadataValue> = values
+        .iter()
+        .filter(|v| !matches!(v, MetadataValue::String(_)))
+        .collect();
+    if numbers.is_empty() {
+        return None;
     }
+
+    // Sums stay integers unless there's a float among the values or the sum overflows
+    let integer_sum = numbers.iter().try_fold(0i64, |total, v| match v {
+        MetadataValue::Integer(i) => total.checked_add(*i),
+        _ => None,
+    });
+    let as_f64 = |v: &MetadataValue| match v {
+        MetadataValue::Integer(i) => *i as f64,
+        MetadataValue::Float(f) => *f,
+        MetadataValue::String(_) => unreachable!(),
+    };
+
+    let value = match aggregation {
+        Aggregation::Sum => match integer_sum {
+            Some(total) => MetadataValue::Integer(total),
+            None => MetadataValue::Float(numbers.iter().map(|v| as_f64(v)).sum()),
+        },
+        Aggregation::Avg => MetadataValue::Float(
+            numbers.iter().map(|v| as_f64(v)).sum::<f64>() / numbers.len() as f64,
+        ),
+        Aggregation::Max | Aggregation::Min => {
+            let pick = numbers.iter().copied().reduce(|a, b| {
+                let a_wins = if aggregation == Aggregation::Max {
+                    as_f64(a) >= as_f64(b)
+                } else {
+                    as_f64(a) <= as_f64(b)
+                };
+                if a_wins { a } else { b }
+            })?;
+            pick.clone()
+        }
+    };
+    Some(value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DetailedMetadataPattern;
 
     #[test]
     fn test_simple_pattern() {
@@ -100,7 +219,7 @@ mod tests {
         );
 
         let output = "Processing...\nTotal: 42 items\nDone!";
-        let metadata = extract_metadata(output, &patterns);
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
 
         assert_eq!(metadata.len(), 1);
         match metadata.get("count") {
@@ -109,6 +228,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_drop_oversized() {
+        let mut metadata = BTreeMap::from([
+            (
+                "version".to_string(),
+                MetadataValue::String("1.2.0".to_string()),
+            ),
+            ("log".to_string(), MetadataValue::String("x".repeat(100))),
+            ("count".to_string(), MetadataValue::Integer(123456789)),
+        ]);
+        let dropped = drop_oversized(&mut metadata, 8);
+        assert_eq!(dropped, vec![("log".to_string(), 100)]);
+        assert_eq!(metadata.len(), 2);
+        assert!(metadata.contains_key("version"));
+    }
+
     #[test]
     fn test_float_extraction() {
         let mut patterns = HashMap::new();
@@ -118,7 +253,7 @@ mod tests {
         );
 
         let output = "Coverage: 85.5%";
-        let metadata = extract_metadata(output, &patterns);
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
 
         match metadata.get("coverage") {
             Some(MetadataValue::Float(f)) => assert!((f - 85.5).abs() < 0.001),
@@ -135,7 +270,7 @@ mod tests {
         );
 
         let output = "Results: 5/10";
-        let metadata = extract_metadata(output, &patterns);
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
 
         match metadata.get("ratio") {
             Some(MetadataValue::String(s)) => assert_eq!(s, "5 of 10"),
@@ -152,7 +287,7 @@ mod tests {
         );
 
         let output = "No total here";
-        let metadata = extract_metadata(output, &patterns);
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
 
         assert!(metadata.is_empty());
     }
@@ -172,6 +307,100 @@ mod tests {
         assert!((delta - 5.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_compute_delta_drops_float_noise() {
+        let current = MetadataValue::Float(85.3);
+        let prev = MetadataValue::Float(85.1);
+        assert_eq!(compute_delta(&current, &prev), Some(0.2));
+        assert_eq!(compute_delta(&prev, &prev), Some(0.0));
+    }
+
+    #[test]
+    fn test_precision_rounds_stored_values() {
+        let mut patterns = HashMap::new();
+        patterns.insert(
+            "coverage".to_string(),
+            MetadataPattern::Detailed(DetailedMetadataPattern {
+                pattern: Some(r"Coverage: ([\d.]+)%".to_string()),
+                json_path: None,
+                file: None,
+                replacement: None,
+                precision: Some(1),
+            }),
+        );
+        patterns.insert(
+            "seconds".to_string(),
+            MetadataPattern::Detailed(DetailedMetadataPattern {
+                pattern: Some(r"took ([\d.]+)s".to_string()),
+                json_path: None,
+                file: None,
+                replacement: None,
+                precision: Some(0),
+            }),
+        );
+
+        let output = "Coverage: 85.5012%\ntook 12.7s";
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
+
+        match metadata.get("coverage") {
+            Some(MetadataValue::Float(f)) => assert_eq!(*f, 85.5),
+            other => panic!("Expected Float(85.5), got {:?}", other),
+        }
+        match metadata.get("seconds") {
+            Some(MetadataValue::Integer(13)) => {}
+            other => panic!("Expected Integer(13), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_path_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("coverage")).unwrap();
+        std::fs::write(
+            dir.path().join("coverage/summary.json"),
+            r#"{"total": {"lines": {"pct": 87.25}}, "suites": [{"name": "unit", "tests": 42}]}"#,
+        )
+        .unwrap();
+        let detailed = |json_path: &str, file: Option<&str>| {
+            MetadataPattern::Detailed(DetailedMetadataPattern {
+                pattern: None,
+                json_path: Some(json_path.to_string()),
+                file: file.map(std::path::PathBuf::from),
+                replacement: None,
+                precision: None,
+            })
+        };
+        let mut patterns = HashMap::new();
+        patterns.insert(
+            "coverage".to_string(),
+            detailed("total.lines.pct", Some("coverage/summary.json")),
+        );
+        patterns.insert(
+            "tests".to_string(),
+            detailed("suites.0.tests", Some("coverage/summary.json")),
+        );
+        patterns.insert(
+            "missing".to_string(),
+            detailed("total", Some("coverage/missing.json")),
+        );
+        patterns.insert("version".to_string(), detailed("version", None));
+
+        let metadata = extract_metadata(dir.path(), r#"{"version": "1.2.0"}"#, &patterns);
+        match metadata.get("coverage") {
+            Some(MetadataValue::Float(f)) => assert_eq!(*f, 87.25),
+            other => panic!("Expected Float(87.25), got {:?}", other),
+        }
+        match metadata.get("tests") {
+            Some(MetadataValue::Integer(42)) => {}
+            other => panic!("Expected Integer(42), got {:?}", other),
+        }
+        match metadata.get("version") {
+            Some(MetadataValue::String(s)) => assert_eq!(s, "1.2.0"),
+            other => panic!("Expected String(\"1.2.0\"), got {:?}", other),
+        }
+        assert!(!metadata.contains_key("missing"));
+    }
+
     #[test]
     fn test_compute_delta_strings() {
         let current = MetadataValue::String("a".to_string());
@@ -189,11 +418,60 @@ mod tests {
 
         // Output with multiple matches - should use the last one (99)
         let output = "Total: 10\nProcessing...\nTotal: 50\nMore work...\nTotal: 99";
-        let metadata = extract_metadata(output, &patterns);
+        let metadata = extract_metadata(Path::new(""), output, &patterns);
 
         match metadata.get("count") {
             Some(MetadataValue::Integer(99)) => {}
             other => panic!("Expected Integer(99) (last match), got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_aggregate_values() {
+        let values = vec![
+            MetadataValue::Integer(10),
+            MetadataValue::Integer(32),
+            MetadataValue::String("n/a".to_string()),
+        ];
+
+        match aggregate_values(&values, Aggregation::Sum) {
+            Some(MetadataValue::Integer(42)) => {}
+            other => panic!("Expected Integer(42), got {:?}", other),
+        }
+        match aggregate_values(&values, Aggregation::Max) {
+            Some(MetadataValue::Integer(32)) => {}
+            other => panic!("Expected Integer(32), got {:?}", other),
+        }
+        match aggregate_values(&values, Aggregation::Min) {
+            Some(MetadataValue::Integer(10)) => {}
+            other => panic!("Expected Integer(10), got {:?}", other),
+        }
+        match aggregate_values(&values, Aggregation::Avg) {
+            Some(MetadataValue::Float(f)) => assert!((f - 21.0).abs() < 0.001),
+            other => panic!("Expected Float(21.0), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_sum_overflow_falls_back_to_float() {
+        let values = vec![MetadataValue::Integer(i64::MAX), MetadataValue::Integer(1)];
+
+        match aggregate_values(&values, Aggregation::Sum) {
+            Some(MetadataValue::Float(f)) => assert_eq!(f, i64::MAX as f64 + 1.0),
+            other => panic!("Expected Float, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_values_mixed_and_empty() {
+        let values = vec![MetadataValue::Integer(1), MetadataValue::Float(0.5)];
+        match aggregate_values(&values, Aggregation::Sum) {
+            Some(MetadataValue::Float(f)) => assert!((f - 1.5).abs() < 0.001),
+            other => panic!("Expected Float(1.5), got {:?}", other),
+        }
+
+        let strings = vec![MetadataValue::String("x".to_string())];
+        assert!(aggregate_values(&strings, Aggregation::Sum).is_none());
+        assert!(aggregate_values(&[], Aggregation::Avg).is_none());
+    }
 }
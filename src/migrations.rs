@@ -0,0 +1,149 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// Version of the verify.lock format this verify reads and writes
+pub const LOCK_VERSION: u32 = 5;
+
+/// Upgrades one check entry of a lock file by one version
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades entries from version `n + 1` to `n + 2`. A format change
+/// bumps `LOCK_VERSION` and adds its step here, so older lock files keep loading.
+const MIGRATIONS: [Migration; LOCK_VERSION as usize - 1] = [
+    // 1 -> 2, 2 -> 3, 3 -> 4 and 4 -> 5 each changed how hashes are computed
+    forget_hashes,
+    forget_hashes,
+    forget_hashes,
+    forget_hashes,
+];
+
+/// Drop the hashes an entry was verified with, which no longer match anything verify
+/// computes, keeping its metadata, duration and failed files. The check runs once more.
+fn forget_hashes(entry: &mut Map<String, Value>) {
+    for key in [
+        "config_hash",
+        "config_components",
+        "content_hash",
+        "file_hashes",
+    ] {
+        entry.remove(key);
+    }
+}
+
+/// The version of a parsed lock file. Lock files from before versioning count as 1.
+pub fn version_of(lock: &Value) -> Option<u32> {
+    match lock.get("version") {
+        None => Some(1),
+        Some(version) => version.as_u64().and_then(|v| u32::try_from(v).ok()),
+    }
+}
+
+/// Bring a parsed lock file up to `LOCK_VERSION` one version at a time. Returns the
+/// version it had if it was migrated, and fails for a lock file written by a newer verify
+/// rather than losing what it records.
+pub fn migrate(lock: &mut Value) -> Result<Option<u32>> {
+    let Some(from) = version_of(lock) else {
+        return Ok(None);
+    };
+    if from > LOCK_VERSION {
+        anyhow::bail!(
+            "verify.lock was written by a newer version of verify (lock format {}, this \
+             version reads up to {}); upgrade verify to use it",
+            from,
+            LOCK_VERSION
+        );
+    }
+    if from == LOCK_VERSION || from == 0 {
+        return Ok(None);
+    }
+    let Some(object) = lock.as_object_mut() else {
+        return Ok(None);
+    };
+
+    for step in &MIGRATIONS[from as usize - 1..] {
+        if let Some(Value::Object(checks)) = object.get_mut("checks") {
+            for entry in checks.values_mut() {
+                if let Value::Object(entry) = entry {
+                    step(entry);
+                }
+            }
+        }
+    }
+    // Signed over the old contents, so it can't match; saving signs it again
+    object.remove("signature");
+    object.insert("version".to_string(), Value::from(LOCK_VERSION));
+    Ok(Some(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lock(version: Option<u32>) -> Value {
+        let mut lock = json!({
+            "checks": {
+                "build": {
+                    "config_hash": "old-config",
+                    "config_components": { "command": "abc" },
+                    "content_hash": "old-content",
+                    "file_hashes": { "a.txt": "old-file" },
+                    "metadata": { "warnings": 3 },
+                    "duration_ms": 1200,
+                    "failed_files": ["b.txt"]
+                }
+            },
+            "signature": "old-signature"
+        });
+        if let Some(version) = version {
+            lock["version"] = json!(version);
+        }
+        lock
+    }
+
+    #[test]
+    fn test_migrates_each_old_version_to_current() {
+        for version in 1..LOCK_VERSION {
+            let mut migrated = lock(Some(version));
+            assert_eq!(migrate(&mut migrated).unwrap(), Some(version));
+            assert_eq!(migrated["version"], LOCK_VERSION);
+            assert!(migrated.get("signature").is_none());
+
+            let build = &migrated["checks"]["build"];
+            for key in [
+                "config_hash",
+                "config_components",
+                "content_hash",
+                "file_hashes",
+            ] {
+                assert!(build.get(key).is_none(), "v{} kept {}", version, key);
+            }
+            assert_eq!(build["metadata"]["warnings"], 3);
+            assert_eq!(build["duration_ms"], 1200);
+            assert_eq!(build["failed_files"], json!(["b.txt"]));
+        }
+    }
+
+    #[test]
+    fn test_unversioned_lock_is_version_one() {
+        let mut migrated = lock(None);
+        assert_eq!(migrate(&mut migrated).unwrap(), Some(1));
+        assert_eq!(migrated["version"], LOCK_VERSION);
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut current = lock(Some(LOCK_VERSION));
+        let before = current.clone();
+        assert_eq!(migrate(&mut current).unwrap(), None);
+        assert_eq!(current, before);
+    }
+
+    #[test]
+    fn test_refuses_newer_version() {
+        let mut newer = lock(Some(LOCK_VERSION + 1));
+        let err = migrate(&mut newer).unwrap_err().to_string();
+        assert!(err.contains("newer version of verify"), "{}", err);
+        assert!(err.contains("upgrade verify"), "{}", err);
+    }
+}
@@ -0,0 +1,483 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A step rewriting a file's contents before it's hashed (a check's `normalize`), so
+/// changes it removes don't invalidate the check
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Normalizer {
+    /// `strip-trailing-whitespace`, `strip-blank-lines` or `strip-comments:LANG`
+    Builtin(String),
+    /// { command }: a shell command given the contents on stdin, whose output is hashed
+    Filter { command: String },
+}
+
+/// Comment and string syntax of the languages `strip-comments` knows. Strings stay open
+/// across newlines, so a comment marker on a later line of a multi-line string is kept:
+/// leaving a comment in when unsure is safe, while removing code would hide changes.
+struct CommentSyntax {
+    line: &'static str,
+    block: Option<(&'static str, &'static str)>,
+    /// `line` only starts a comment at the start of a word, so `${#x}` isn't one
+    line_at_word_start: bool,
+    /// Whether `'` starts a string, rather than a character literal or lifetime
+    single_quoted_strings: bool,
+    /// Whether backquotes delimit strings, and if so whether `\` escapes in them
+    backquote_strings: Option<bool>,
+    /// Whether `r"…"` and `r#"…"#` are raw strings (Rust)
+    raw_strings: bool,
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax {
+    line: "//",
+    block: Some(("/*", "*/")),
+    line_at_word_start: false,
+    single_quoted_strings: false,
+    backquote_strings: None,
+    raw_strings: false,
+};
+
+const RUST: CommentSyntax = CommentSyntax {
+    raw_strings: true,
+    ..C_LIKE
+};
+
+/// Go's backquoted raw strings have no escapes
+const GO: CommentSyntax = CommentSyntax {
+    backquote_strings: Some(false),
+    ..C_LIKE
+};
+
+/// JavaScript and TypeScript, with template literals
+const JS: CommentSyntax = CommentSyntax {
+    single_quoted_strings: true,
+    backquote_strings: Some(true),
+    ..C_LIKE
+};
+
+const HASH: CommentSyntax = CommentSyntax {
+    line: "#",
+    block: None,
+    line_at_word_start: true,
+    single_quoted_strings: true,
+    backquote_strings: None,
+    raw_strings: false,
+};
+
+/// Shell, with command substitution in backquotes
+const SHELL: CommentSyntax = CommentSyntax {
+    backquote_strings: Some(true),
+    ..HASH
+};
+
+const DASH_DASH: CommentSyntax = CommentSyntax {
+    line: "--",
+    block: None,
+    line_at_word_start: false,
+    single_quoted_strings: true,
+    backquote_strings: None,
+    raw_strings: false,
+};
+
+/// Languages for `strip-comments:LANG`, by name
+const LANGUAGES: &[(&str, &CommentSyntax)] = &[
+    ("c", &C_LIKE),
+    ("cpp", &C_LIKE),
+    ("csharp", &C_LIKE),
+    ("go", &GO),
+    ("java", &C_LIKE),
+    ("js", &JS),
+    ("kotlin", &C_LIKE),
+    ("python", &HASH),
+    ("ruby", &HASH),
+    ("rust", &RUST),
+    ("shell", &SHELL),
+    ("sql", &DASH_DASH),
+    ("swift", &C_LIKE),
+    ("toml", &HASH),
+    ("ts", &JS),
+    ("yaml", &HASH),
+];
+
+/// A parsed `Normalizer`
+enum Step<'a> {
+    TrailingWhitespace,
+    BlankLines,
+    Comments(&'static CommentSyntax),
+    Command(&'a str),
+}
+
+impl Normalizer {
+    fn step(&self) -> std::result::Result<Step<'_>, String> {
+        let name = match self {
+            Normalizer::Filter { command } => return Ok(Step::Command(command)),
+            Normalizer::Builtin(name) => name.as_str(),
+        };
+        match name.split_once(':') {
+            None if name == "strip-trailing-whitespace" => Ok(Step::TrailingWhitespace),
+            None if name == "strip-blank-lines" => Ok(Step::BlankLines),
+            Some(("strip-comments", language)) => LANGUAGES
+                .iter()
+                .find(|(known, _)| *known == language)
+                .map(|(_, syntax)| Step::Comments(syntax))
+                .ok_or_else(|| {
+                    let known: Vec<&str> = LANGUAGES.iter().map(|(name, _)| *name).collect();
+                    format!(
+                        "unknown language '{}' for strip-comments (known: {})",
+                        language,
+                        known.join(", ")
+                    )
+                }),
+            None if name == "strip-comments" => {
+                Err("strip-comments needs a language, as in strip-comments:rust".to_string())
+            }
+            _ => Err(format!(
+                "unknown normalize step '{}' (expected strip-trailing-whitespace, \
+                 strip-blank-lines, strip-comments:LANG or {{ command }})",
+                name
+            )),
+        }
+    }
+
+    /// Why this step can't be used, for `Config::validate`
+    pub fn problem(&self) -> Option<String> {
+        self.step().err()
+    }
+
+    /// How the step appears in config_hash
+    pub fn describe(&self) -> String {
+        match self {
+            Normalizer::Builtin(name) => name.clone(),
+            Normalizer::Filter { command } => format!("command:{}", command),
+        }
+    }
+}
+
+/// Apply `normalizers` in order to the contents of the file at `relative`
+pub fn normalize(
+    normalizers: &[Normalizer],
+    project_root: &Path,
+    relative: &str,
+    mut content: Vec<u8>,
+) -> Result<Vec<u8>> {
+    for normalizer in normalizers {
+        content = match normalizer.step().map_err(anyhow::Error::msg)? {
+            Step::TrailingWhitespace => map_lines(&content, |line| Some(trim_end(line))),
+            Step::BlankLines => map_lines(&content, |line| {
+                (!trim_end(line).is_empty()).then_some(line)
+            }),
+            Step::Comments(syntax) => strip_comments(&content, syntax),
+            Step::Command(command) => filter(command, project_root, relative, &content)?,
+        };
+    }
+    Ok(content)
+}
+
+/// Rebuild `content` from its lines as `f` rewrites them, dropping those it returns None for
+fn map_lines<'a>(content: &'a [u8], f: impl Fn(&'a [u8]) -> Option<&'a [u8]>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        let (text, newline) = match line.strip_suffix(b"\n") {
+            Some(text) => (text, true),
+            None => (line, false),
+        };
+        if let Some(text) = f(text) {
+            out.extend_from_slice(text);
+            if newline {
+                out.push(b'\n');
+            }
+        }
+    }
+    out
+}
+
+fn trim_end(line: &[u8]) -> &[u8] {
+    let end = line
+        .iter()
+        .rposition(|b| !b" \t\r".contains(b))
+        .map_or(0, |i| i + 1);
+    &line[..end]
+}
+
+/// The string literal starting at `rest`, if one does: the length of its opening
+/// delimiter, its closing delimiter, and whether `\` escapes in it
+fn string_start(rest: &[u8], syntax: &CommentSyntax) -> Option<(usize, Vec<u8>, bool)> {
+    if syntax.raw_strings
+        && let Some(after_r) = rest.strip_prefix(b"r")
+    {
+        let hashes = after_r.iter().take_while(|&&b| b == b'#').count();
+        if after_r.get(hashes) == Some(&b'"') {
+            let mut close = vec![b'"'];
+            close.extend(std::iter::repeat_n(b'#', hashes));
+            return Some((hashes + 2, close, false));
+        }
+    }
+    // Triple quotes first, so a string's content isn't read as strings closing and opening
+    let quotes: [&[u8]; 5] = [b"\"\"\"", b"'''", b"\"", b"'", b"`"];
+    quotes.into_iter().find_map(|quote| {
+        let (enabled, escapes) = match quote[0] {
+            b'\'' => (syntax.single_quoted_strings, true),
+            b'`' => (
+                syntax.backquote_strings.is_some(),
+                syntax.backquote_strings == Some(true),
+            ),
+            _ => (true, true),
+        };
+        (enabled && rest.starts_with(quote)).then(|| (quote.len(), quote.to_vec(), escapes))
+    })
+}
+
+/// Remove comments outside string literals, along with the trailing whitespace they leave
+/// and lines that held nothing else
+fn strip_comments(content: &[u8], syntax: &CommentSyntax) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    // The current output line, and whether a comment was removed from it
+    let mut line = Vec::new();
+    let mut stripped = false;
+    // The closing delimiter of the string being read, and whether `\` escapes in it
+    let mut quote: Option<(Vec<u8>, bool)> = None;
+    let mut in_block: Option<&str> = None;
+    let mut i = 0;
+
+    let end_line = |out: &mut Vec<u8>, line: &mut Vec<u8>, stripped: bool, newline: bool| {
+        if stripped {
+            let len = trim_end(line).len();
+            line.truncate(len);
+            if line.is_empty() {
+                return;
+            }
+        }
+        out.append(line);
+        if newline {
+            out.push(b'\n');
+        }
+    };
+
+    while i < content.len() {
+        let rest = &content[i..];
+        let byte = content[i];
+
+        if let Some(close) = in_block {
+            if rest.starts_with(close.as_bytes()) {
+                in_block = None;
+                i += close.len();
+            } else {
+                if byte == b'\n' {
+                    end_line(&mut out, &mut line, stripped, true);
+                    stripped = true;
+                }
+                i += 1;
+            }
+            continue;
+        }
+
+        if let Some((close, escapes)) = &quote {
+            // Newlines in a string are kept in the line being built
+            if *escapes && byte == b'\\' && i + 1 < content.len() {
+                line.extend_from_slice(&rest[..2]);
+                i += 2;
+            } else if rest.starts_with(close) {
+                line.extend_from_slice(close);
+                i += close.len();
+                quote = None;
+            } else {
+                line.push(byte);
+                i += 1;
+            }
+            continue;
+        }
+
+        let word_start = i == 0 || content[i - 1].is_ascii_whitespace();
+        if byte == b'\n' {
+            end_line(&mut out, &mut line, stripped, true);
+            stripped = false;
+            i += 1;
+        } else if rest.starts_with(syntax.line.as_bytes())
+            && (word_start || !syntax.line_at_word_start)
+        {
+            // Skip to the end of the line, leaving the newline
+            let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+            stripped = true;
+            i += len;
+        } else if let Some((open, close)) = syntax
+            .block
+            .filter(|(open, _)| rest.starts_with(open.as_bytes()))
+        {
+            in_block = Some(close);
+            stripped = true;
+            i += open.len();
+        } else if let Some((open, close, escapes)) = string_start(rest, syntax) {
+            line.extend_from_slice(&rest[..open]);
+            quote = Some((close, escapes));
+            i += open;
+        } else if byte == b'\'' {
+            // A character literal like '"' or '\'' mustn't start a string
+            let len = match rest {
+                [_, b'\\', _, b'\'', ..] => 4,
+                [_, _, b'\'', ..] => 3,
+                _ => 1,
+            };
+            line.extend_from_slice(&rest[..len]);
+            i += len;
+        } else {
+            line.push(byte);
+            i += 1;
+        }
+    }
+    end_line(&mut out, &mut line, stripped, false);
+    out
+}
+
+/// Run a `{ command }` step: the contents go to its stdin and its stdout replaces them
+fn filter(command: &str, project_root: &Path, relative: &str, content: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(project_root)
+        .env("VERIFY_FILE", relative)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run normalize command '{}'", command))?;
+
+    // Written from another thread, so a filter that streams its output can't deadlock
+    let output = std::thread::scope(|scope| {
+        let stdin = child.stdin.take();
+        scope.spawn(move || {
+            if let Some(mut stdin) = stdin {
+                // A filter that exits without reading everything isn't an error by itself
+                let _ = stdin.write_all(content);
+            }
+        });
+        child.wait_with_output()
+    })
+    .with_context(|| format!("Failed to run normalize command '{}'", command))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "normalize command '{}' failed for {}: {}",
+            command,
+            relative,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(steps: &[&str], content: &str) -> String {
+        let normalizers: Vec<Normalizer> = steps
+            .iter()
+            .map(|step| Normalizer::Builtin(step.to_string()))
+            .collect();
+        let out = normalize(&normalizers, Path::new("."), "file", content.into()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_strip_trailing_whitespace() {
+        assert_eq!(
+            apply(&["strip-trailing-whitespace"], "a  \nb\t\r\n\nc "),
+            "a\nb\n\nc"
+        );
+    }
+
+    #[test]
+    fn test_strip_blank_lines() {
+        assert_eq!(apply(&["strip-blank-lines"], "a\n\n  \nb\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn test_strip_comments_rust() {
+        let original = "fn main() {\n    let s = \"// not a comment\";\n    let q = '\"';\n    run(); // call it\n}\n";
+        let commented = "// A program\nfn main() {\n    /* the\n       string */\n    let s = \"// not a comment\";\n    let q = '\"';\n    run();\n}\n";
+        let stripped = apply(&["strip-comments:rust"], original);
+        assert_eq!(stripped, apply(&["strip-comments:rust"], commented));
+        assert!(stripped.contains("\"// not a comment\""), "{}", stripped);
+        assert!(stripped.contains("    run();\n"), "{}", stripped);
+        assert!(!stripped.contains("call it"), "{}", stripped);
+    }
+
+    #[test]
+    fn test_strip_comments_keeps_multiline_strings() {
+        // A comment marker on a later line of a string is part of the string
+        let rust = "let url = \"first line\n    https://example.com/one\";\n";
+        assert_eq!(apply(&["strip-comments:rust"], rust), rust);
+        assert_eq!(
+            apply(&["strip-comments:rust"], "let s = r#\"a \" // b\"#; // c\n"),
+            "let s = r#\"a \" // b\"#;\n"
+        );
+        let go = "s := `first\n// kept`\n";
+        assert_eq!(apply(&["strip-comments:go"], go), go);
+        assert_eq!(
+            apply(
+                &["strip-comments:python"],
+                "s = \"\"\"\nsay \"hi\" # kept\n\"\"\"  # removed\n"
+            ),
+            "s = \"\"\"\nsay \"hi\" # kept\n\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_hash_only_at_word_start() {
+        assert_eq!(
+            apply(&["strip-comments:shell"], "echo ${#ARR[@]} one # count\n"),
+            "echo ${#ARR[@]} one\n"
+        );
+        assert_eq!(
+            apply(&["strip-comments:yaml"], "url: http://host/#anchor\n"),
+            "url: http://host/#anchor\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_hash() {
+        assert_eq!(
+            apply(&["strip-comments:python"], "# header\nx = '#1'  # note\n"),
+            "x = '#1'\n"
+        );
+    }
+
+    #[test]
+    fn test_filter_command() {
+        let normalizers = vec![Normalizer::Filter {
+            command: "tr a-z A-Z".to_string(),
+        }];
+        let out = normalize(&normalizers, Path::new("."), "file", b"abc\n".to_vec()).unwrap();
+        assert_eq!(out, b"ABC\n");
+
+        let failing = vec![Normalizer::Filter {
+            command: "exit 3".to_string(),
+        }];
+        let err = normalize(&failing, Path::new("."), "file", b"abc".to_vec()).unwrap_err();
+        assert!(err.to_string().contains("failed for file"), "{}", err);
+    }
+
+    #[test]
+    fn test_problems() {
+        let problem = |name: &str| Normalizer::Builtin(name.to_string()).problem();
+        assert_eq!(problem("strip-comments:rust"), None);
+        assert!(
+            problem("strip-comments:cobol")
+                .unwrap()
+                .contains("unknown language")
+        );
+        assert!(
+            problem("strip-comments")
+                .unwrap()
+                .contains("needs a language")
+        );
+        assert!(
+            problem("lowercase")
+                .unwrap()
+                .contains("unknown normalize step")
+        );
+    }
+}
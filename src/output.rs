@@ -1,15 +1,135 @@
 use crate::cache::{UnverifiedReason, VerificationStatus};
-use crate::metadata::{MetadataValue, compute_delta};
+use crate::config::Aggregation;
+use crate::history::RunRecord;
+use crate::metadata::{MetadataValue, aggregate_values, compute_delta};
 use serde::Serialize;
 use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Guidance shown for untracked checks
+const UNTRACKED_HINT: &str =
+    "add cache_paths so the result can be cached; without them the check runs every time";
 
 /// JSON output for `verify status`
 #[derive(Debug, Serialize)]
 pub struct StatusOutput {
+    /// Counts of every check, in subprojects too (including those `--only-unverified`
+    /// leaves out)
+    pub summary: StatusSummary,
     pub checks: Vec<StatusItemJson>,
 }
 
+impl StatusOutput {
+    pub fn new(checks: Vec<StatusItemJson>) -> Self {
+        Self {
+            summary: StatusSummary::of(&checks),
+            checks,
+        }
+    }
+
+    /// Drop verified checks, and subprojects left with no checks (`--only-unverified`)
+    pub fn retain_unverified(&mut self) {
+        retain_unverified(&mut self.checks);
+    }
+
+    /// Replace subprojects with their checks, named by subproject prefix (its path unless
+    /// configured), e.g. "backend/build" (`--flat`)
+    pub fn flatten(&mut self) {
+        let mut flat = Vec::new();
+        flatten_items(std::mem::take(&mut self.checks), "", &mut flat);
+        self.checks = flat;
+    }
+
+    /// The top-level checks, leaving out subprojects (flatten first to keep their checks)
+    pub fn into_checks(self) -> Vec<CheckStatusJson> {
+        self.checks
+            .into_iter()
+            .filter_map(|item| match item {
+                StatusItemJson::Check(check) => Some(check),
+                StatusItemJson::Subproject(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn retain_unverified(items: &mut Vec<StatusItemJson>) {
+    items.retain_mut(|item| match item {
+        StatusItemJson::Check(check) => check.status != "verified",
+        StatusItemJson::Subproject(sub) => {
+            retain_unverified(&mut sub.checks);
+            !sub.checks.is_empty()
+        }
+    });
+}
+
+fn flatten_items(items: Vec<StatusItemJson>, prefix: &str, flat: &mut Vec<StatusItemJson>) {
+    for item in items {
+        match item {
+            StatusItemJson::Check(mut check) => {
+                check.name = format!("{}{}", prefix, check.name);
+                flat.push(StatusItemJson::Check(check));
+            }
+            StatusItemJson::Subproject(sub) => {
+                let prefix = format!("{}{}/", prefix, sub.prefix);
+                flatten_items(sub.checks, &prefix, flat);
+            }
+        }
+    }
+}
+
+/// How many checks of a status tree are in each state, so scripts needn't walk the tree
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatusSummary {
+    pub verified: usize,
+    /// Checks that need to run, other than failed ones
+    pub unverified: usize,
+    /// Checks whose last run failed
+    pub failed: usize,
+    pub untracked: usize,
+    /// Checks with no command for this platform
+    pub unsupported: usize,
+    pub total: usize,
+    /// Every check is verified or unsupported here (untracked checks count against it, as
+    /// with `status --verify`)
+    pub all_verified: bool,
+}
+
+impl StatusSummary {
+    /// Counts of `items`, adding up the summaries of subprojects among them
+    pub fn of(items: &[StatusItemJson]) -> Self {
+        let mut summary = Self::default();
+        for item in items {
+            match item {
+                StatusItemJson::Check(check) => match check.status.as_str() {
+                    "verified" => summary.verified += 1,
+                    "untracked" => summary.untracked += 1,
+                    "unsupported" => summary.unsupported += 1,
+                    "failed" => summary.failed += 1,
+                    _ => summary.unverified += 1,
+                },
+                StatusItemJson::Subproject(sub) => {
+                    summary.verified += sub.summary.verified;
+                    summary.unverified += sub.summary.unverified;
+                    summary.failed += sub.summary.failed;
+                    summary.untracked += sub.summary.untracked;
+                    summary.unsupported += sub.summary.unsupported;
+                }
+            }
+        }
+        summary.total = summary.verified
+            + summary.unverified
+            + summary.failed
+            + summary.untracked
+            + summary.unsupported;
+        summary.all_verified = summary.verified + summary.unsupported == summary.total;
+        summary
+    }
+}
+
 /// Either a check status or a subproject with nested checks
+// Only built for output, so boxing checks wouldn't save anything worthwhile
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum StatusItemJson {
@@ -24,15 +144,21 @@ pub struct SubprojectStatusJson {
     #[serde(rename = "type")]
     pub item_type: String,
     pub path: String,
+    /// Namespace of its checks in trailers and `--flat` output
+    pub prefix: String,
+    /// Counts of its checks, including those of its own subprojects
+    pub summary: StatusSummary,
     pub checks: Vec<StatusItemJson>,
 }
 
 impl SubprojectStatusJson {
-    pub fn new(name: &str, path: &str, checks: Vec<StatusItemJson>) -> Self {
+    pub fn new(name: &str, path: &str, prefix: &str, checks: Vec<StatusItemJson>) -> Self {
         Self {
             name: name.to_string(),
             item_type: "subproject".to_string(),
             path: path.to_string(),
+            prefix: prefix.to_string(),
+            summary: StatusSummary::of(&checks),
             checks,
         }
     }
@@ -44,12 +170,42 @@ pub struct CheckStatusJson {
     pub status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reason: Option<String>,
+    /// Suggested fix for the reason, when there's an obvious one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stale_dependency: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub changed_files: Option<Vec<String>>,
+    /// Parts of the check's config that changed (reason "config_changed"), when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_config: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// Who marked the check verified without running it, when and why
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marked_verified: Option<crate::cache::MarkedVerified>,
+    /// Who ran the check when it last passed (with `record_user`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_by: Option<crate::cache::VerifiedBy>,
+    /// Exit code of the failed last run (status "failed"; absent on timeouts)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// When the last run failed (status "failed")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The failed last run was stopped for running past `timeout_secs` (status "failed")
+    #[serde(skip_serializing_if = "is_false")]
+    pub timed_out: bool,
+    /// Status of transitive dependencies (only set when status is filtered to this check)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<CheckStatusJson>>,
+    /// The check's `description`, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The check's `owner`, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
 }
 
 impl CheckStatusJson {
@@ -58,32 +214,40 @@ impl CheckStatusJson {
         status: &VerificationStatus,
         cache: Option<&crate::cache::CheckCache>,
     ) -> Self {
-        let metadata = cache
-            .filter(|c| !c.metadata.is_empty())
-            .map(|c| {
-                c.metadata
-                    .iter()
-                    .map(|(k, v)| {
-                        let json_value = match v {
-                            MetadataValue::Integer(i) => serde_json::Value::Number((*i).into()),
-                            MetadataValue::Float(f) => serde_json::Number::from_f64(*f)
-                                .map(serde_json::Value::Number)
-                                .unwrap_or(serde_json::Value::Null),
-                            MetadataValue::String(s) => serde_json::Value::String(s.clone()),
-                        };
-                        (k.clone(), json_value)
-                    })
-                    .collect()
-            });
+        let metadata = cache.filter(|c| !c.metadata.is_empty()).map(|c| {
+            c.metadata
+                .iter()
+                .map(|(k, v)| {
+                    let json_value = match v {
+                        MetadataValue::Integer(i) => serde_json::Value::Number((*i).into()),
+                        MetadataValue::Float(f) => serde_json::Number::from_f64(*f)
+                            .map(serde_json::Value::Number)
+                            .unwrap_or(serde_json::Value::Null),
+                        MetadataValue::String(s) => serde_json::Value::String(s.clone()),
+                    };
+                    (k.clone(), json_value)
+                })
+                .collect()
+        });
 
         match status {
             VerificationStatus::Verified => Self {
                 name: name.to_string(),
                 status: "verified".to_string(),
                 reason: None,
+                hint: None,
                 stale_dependency: None,
                 changed_files: None,
+                changed_config: None,
                 metadata,
+                marked_verified: cache.and_then(|c| c.marked_verified.clone()),
+                verified_by: cache.and_then(|c| c.verified_by.clone()),
+                exit_code: None,
+                failed_at: None,
+                timed_out: false,
+                dependencies: None,
+                description: None,
+                owner: None,
             },
             VerificationStatus::Unverified { reason } => {
                 let (reason_str, stale_dep, changed_files) = match reason {
@@ -97,41 +261,288 @@ impl CheckStatusJson {
                         Some(dependency.clone()),
                         None,
                     ),
-                    UnverifiedReason::ConfigChanged => {
+                    UnverifiedReason::ConfigChanged { .. } => {
                         (Some("config_changed".to_string()), None, None)
                     }
                     UnverifiedReason::NeverRun => (Some("never_run".to_string()), None, None),
+                    UnverifiedReason::ToolUpgraded { .. } => {
+                        (Some("tool_upgraded".to_string()), None, None)
+                    }
                 };
 
                 Self {
                     name: name.to_string(),
                     status: "unverified".to_string(),
                     reason: reason_str,
+                    hint: None,
                     stale_dependency: stale_dep,
                     changed_files,
+                    changed_config: match reason {
+                        UnverifiedReason::ConfigChanged { changed } if !changed.is_empty() => {
+                            Some(changed.clone())
+                        }
+                        _ => None,
+                    },
                     metadata,
+                    marked_verified: None,
+                    verified_by: None,
+                    exit_code: None,
+                    failed_at: None,
+                    timed_out: false,
+                    dependencies: None,
+                    description: None,
+                    owner: None,
                 }
             }
+            VerificationStatus::Failed {
+                exit_code,
+                at,
+                timed_out,
+            } => Self {
+                name: name.to_string(),
+                status: "failed".to_string(),
+                reason: None,
+                hint: None,
+                stale_dependency: None,
+                changed_files: None,
+                changed_config: None,
+                metadata: None,
+                marked_verified: None,
+                verified_by: None,
+                exit_code: *exit_code,
+                failed_at: Some(*at),
+                timed_out: *timed_out,
+                dependencies: None,
+                description: None,
+                owner: None,
+            },
             VerificationStatus::Untracked => Self {
                 name: name.to_string(),
                 status: "untracked".to_string(),
-                reason: None,
+                reason: Some("no_cache_paths".to_string()),
+                hint: Some(UNTRACKED_HINT.to_string()),
+                stale_dependency: None,
+                changed_files: None,
+                changed_config: None,
+                metadata: None,
+                marked_verified: None,
+                verified_by: None,
+                exit_code: None,
+                failed_at: None,
+                timed_out: false,
+                dependencies: None,
+                description: None,
+                owner: None,
+            },
+            VerificationStatus::Unsupported => Self {
+                name: name.to_string(),
+                status: "unsupported".to_string(),
+                reason: Some(format!("no_command_for_{}", std::env::consts::OS)),
+                hint: None,
                 stale_dependency: None,
                 changed_files: None,
+                changed_config: None,
                 metadata: None,
+                marked_verified: None,
+                verified_by: None,
+                exit_code: None,
+                failed_at: None,
+                timed_out: false,
+                dependencies: None,
+                description: None,
+                owner: None,
             },
         }
     }
 }
 
+impl CheckStatusJson {
+    /// A subproject dependency with unverified checks, as a `verify why` blocker
+    pub fn unverified_subproject(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: "unverified".to_string(),
+            reason: Some("subproject_unverified".to_string()),
+            hint: None,
+            stale_dependency: None,
+            changed_files: None,
+            changed_config: None,
+            metadata: None,
+            marked_verified: None,
+            verified_by: None,
+            exit_code: None,
+            failed_at: None,
+            timed_out: false,
+            dependencies: None,
+            description: None,
+            owner: None,
+        }
+    }
+}
+
+/// A commit referenced in `verify bisect` output
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitJson {
+    pub commit: String,
+    pub subject: String,
+}
+
+/// JSON output for `verify bisect`
+#[derive(Debug, Serialize)]
+pub struct BisectOutput {
+    pub check: String,
+    /// Most recent commit whose trailer matches the check and its dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_verified: Option<CommitJson>,
+    /// First commit after last_verified whose changes invalidated the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalidated_by: Option<CommitJson>,
+    /// Checks (the check itself or its dependencies) whose hashes changed in invalidated_by
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_checks: Vec<String>,
+}
+
+/// JSON output for `verify sync`
+#[derive(Debug, Serialize)]
+pub struct SyncOutput {
+    /// Checks seeded from the trailer
+    pub checks: Vec<StatusItemJson>,
+    pub summary: SyncSummary,
+}
+
+/// Outcome of `verify sync` for each tracked check (aggregates and untracked checks
+/// aren't synced)
+#[derive(Debug, Default, Serialize)]
+pub struct SyncSummary {
+    /// Checks whose files and config match the trailer, now verified
+    pub synced: Vec<String>,
+    /// Checks not in the trailer, or all of them when no trailer was found
+    pub skipped: Vec<String>,
+    /// Checks in the trailer whose hash doesn't match the current files or config
+    pub mismatched: Vec<String>,
+}
+
+impl SyncSummary {
+    /// 0 when every check was synced, 1 when none were, and 3 when only some were
+    pub fn exit_code(&self) -> i32 {
+        if self.skipped.is_empty() && self.mismatched.is_empty() {
+            0
+        } else if self.synced.is_empty() {
+            1
+        } else {
+            3
+        }
+    }
+}
+
+/// JSON output for `verify why`
+#[derive(Debug, Serialize)]
+pub struct WhyOutput {
+    pub check: String,
+    pub verified: bool,
+    /// Checks and subprojects that have to run for the check to be verified, in run order
+    pub blockers: Vec<CheckStatusJson>,
+    /// `verify run` command that runs the blockers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// JSON output for `verify explain`
+#[derive(Debug, Serialize)]
+pub struct ExplainOutput {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// The command run on this platform (absent for aggregates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub cache_paths: Vec<String>,
+    pub depends_on: Vec<String>,
+    pub status: CheckStatusJson,
+}
+
+/// JSON output for `verify diff`
+#[derive(Debug, Serialize)]
+pub struct DiffOutput {
+    pub check: String,
+    /// Whether verify.lock has per-file hashes for the check (per_file checks only).
+    /// Without them, `files` lists the check's files changed since HEAD.
+    pub per_file: bool,
+    pub files: Vec<DiffFileJson>,
+}
+
+/// A file in `verify diff` output
+#[derive(Debug, Serialize)]
+pub struct DiffFileJson {
+    pub path: String,
+    /// "added", "modified" or "deleted"
+    pub change: String,
+    /// Where the recorded content was found: "index", "HEAD" or a commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded_in: Option<String>,
+}
+
+/// JSON output for `verify coverage`
+#[derive(Debug, Serialize)]
+pub struct CoverageOutput {
+    /// Files tracked by git, other than verify's own
+    pub total: usize,
+    /// Files matched by at least one check's cache_paths
+    pub covered: usize,
+    pub percent: f64,
+    pub directories: Vec<DirectoryCoverageJson>,
+    /// Files no check's cache_paths match, so changing them can't make any check stale
+    pub uncovered: Vec<String>,
+}
+
+/// A directory in `verify coverage` output
+#[derive(Debug, Serialize)]
+pub struct DirectoryCoverageJson {
+    /// Directory with a trailing `/`, or `./` for files at the root
+    pub path: String,
+    pub total: usize,
+    pub covered: usize,
+    pub percent: f64,
+}
+
+/// JSON output for `verify list`
+#[derive(Debug, Serialize)]
+pub struct ListOutput {
+    pub checks: Vec<ListItemJson>,
+}
+
+/// A check in `verify list` output
+#[derive(Debug, Serialize)]
+pub struct ListItemJson {
+    pub name: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// JSON output for `verify history`
+#[derive(Debug, Serialize)]
+pub struct HistoryOutput {
+    pub runs: Vec<RunRecord>,
+}
+
 /// JSON output for `verify run`
 #[derive(Debug, Serialize)]
 pub struct RunOutput {
     pub results: Vec<RunItemJson>,
     pub summary: RunSummary,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata_totals: BTreeMap<String, MetadataValue>,
+    /// Annotation from `verify run --message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 /// Either a check result or a subproject with nested results
+// One per check in a run; not worth boxing
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum RunItemJson {
@@ -169,14 +580,92 @@ pub struct CheckRunJson {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<u64>,
     pub cached: bool,
+    /// False for checks without cache_paths, whose results are never cached
+    #[serde(skip_serializing_if = "is_true")]
+    pub tracked: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exit_code: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<String>,
+    /// The output wasn't all printable text (invalid UTF-8 or control characters, shown
+    /// as U+FFFD), e.g. a tool dumping binary data
+    #[serde(skip_serializing_if = "is_false")]
+    pub output_binary: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata_deltas: Option<HashMap<String, f64>>,
+    /// Why the check ran or was skipped (`run --explain-cache`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_decision: Option<CacheDecisionJson>,
+    /// Result of each file of a per_file check: skipped as cached, or run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<FileRunJson>>,
+    /// For a check skipped because its cache was fresh, how long it took when it last
+    /// ran (0 if unknown), counted as time saved in `verify stats`
+    #[serde(skip)]
+    pub cache_hit: Option<u64>,
+    /// For a "blocked" check, the failed dependency it was waiting on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_by: Option<String>,
+    /// The check failed because its command ran past `timeout_secs` and was stopped
+    #[serde(skip_serializing_if = "is_false")]
+    pub timed_out: bool,
+}
+
+/// One file of a per_file check in `verify run` results
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRunJson {
+    pub path: String,
+    /// "pass", "fail", or "skipped" (unchanged since it last passed)
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// The command ran past the check's `timeout_secs` and was stopped
+    #[serde(skip_serializing_if = "is_false")]
+    pub timed_out: bool,
+}
+
+impl FileRunJson {
+    /// A file whose command ran
+    pub fn ran(path: &str, success: bool, duration_ms: u64, exit_code: Option<i32>) -> Self {
+        Self {
+            path: path.to_string(),
+            result: if success { "pass" } else { "fail" }.to_string(),
+            duration_ms: Some(duration_ms),
+            exit_code,
+            timed_out: false,
+        }
+    }
+
+    /// A file skipped because it passed before and hasn't changed
+    pub fn cached(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            result: "skipped".to_string(),
+            duration_ms: None,
+            exit_code: None,
+            timed_out: false,
+        }
+    }
+}
+
+/// The cache comparison behind running or skipping a check in `verify run`
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheDecisionJson {
+    /// "run", "cached", or "resumed" (passed earlier in the interrupted run)
+    pub decision: String,
+    pub config_hash: String,
+    pub cached_config_hash: Option<String>,
+    pub content_hash: String,
+    pub cached_content_hash: Option<String>,
+    /// Dependency that ran or failed in this run, invalidating the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalidated_by: Option<String>,
+    /// Whether `--force` made the check run
+    pub forced: bool,
 }
 
 impl CheckRunJson {
@@ -194,10 +683,17 @@ impl CheckRunJson {
             result: "pass".to_string(),
             duration_ms: Some(duration_ms),
             cached,
+            tracked: true,
             exit_code: Some(0),
             output: None,
+            output_binary: false,
             metadata: metadata_json,
             metadata_deltas,
+            cache_decision: None,
+            files: None,
+            cache_hit: None,
+            blocked_by: None,
+            timed_out: false,
         }
     }
 
@@ -216,10 +712,19 @@ impl CheckRunJson {
             result: "fail".to_string(),
             duration_ms: Some(duration_ms),
             cached: false,
+            tracked: true,
             exit_code,
+            output_binary: output
+                .as_deref()
+                .is_some_and(|output| output.contains(char::REPLACEMENT_CHARACTER)),
             output,
             metadata: metadata_json,
             metadata_deltas,
+            cache_decision: None,
+            files: None,
+            cache_hit: None,
+            blocked_by: None,
+            timed_out: false,
         }
     }
 
@@ -229,14 +734,50 @@ impl CheckRunJson {
             result: "skipped".to_string(),
             duration_ms: None,
             cached: true,
+            tracked: true,
+            exit_code: None,
+            output: None,
+            output_binary: false,
+            metadata: None,
+            metadata_deltas: None,
+            cache_decision: None,
+            files: None,
+            cache_hit: None,
+            blocked_by: None,
+            timed_out: false,
+        }
+    }
+
+    /// A check that never got to run because `dependency` failed
+    pub fn blocked(name: &str, dependency: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            result: "blocked".to_string(),
+            duration_ms: None,
+            cached: false,
+            tracked: true,
             exit_code: None,
             output: None,
+            output_binary: false,
             metadata: None,
             metadata_deltas: None,
+            cache_decision: None,
+            files: None,
+            cache_hit: None,
+            blocked_by: Some(dependency.to_string()),
+            timed_out: false,
         }
     }
 }
 
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 /// Convert metadata to JSON format and compute deltas
 #[allow(clippy::type_complexity)]
 fn convert_metadata(
@@ -286,7 +827,10 @@ pub struct RunSummary {
     pub total: usize,
     pub passed: usize,
     pub failed: usize,
+    /// Checks skipped as cached, or for having no command for this platform
     pub skipped: usize,
+    /// Checks that didn't run because a dependency failed (not counted in `failed`)
+    pub blocked: usize,
 }
 
 /// Collected results during a run
@@ -296,6 +840,11 @@ pub struct RunResults {
     pub passed: usize,
     pub failed: usize,
     pub skipped: usize,
+    pub blocked: usize,
+    /// Cache decision for the next check added, with `run --explain-cache`
+    cache_decision: Option<CacheDecisionJson>,
+    /// Per-file results for the next check added, if it's a per_file check
+    files: Option<Vec<FileRunJson>>,
 }
 
 impl RunResults {
@@ -307,19 +856,35 @@ impl RunResults {
         metadata: &BTreeMap<String, MetadataValue>,
         prev_metadata: Option<&BTreeMap<String, MetadataValue>>,
     ) {
-        self.results.push(RunItemJson::Check(CheckRunJson::pass(
+        self.push_check(CheckRunJson::pass(
             name,
             duration_ms,
             cached,
             metadata,
             prev_metadata,
-        )));
+        ));
         self.passed += 1;
     }
 
     pub fn add_skipped(&mut self, name: &str) {
-        self.results
-            .push(RunItemJson::Check(CheckRunJson::skipped(name)));
+        self.push_check(CheckRunJson::skipped(name));
+        self.skipped += 1;
+    }
+
+    /// Add a check skipped for having no command for this platform
+    pub fn add_unsupported(&mut self, name: &str) {
+        let mut check = CheckRunJson::skipped(name);
+        check.result = "unsupported".to_string();
+        check.cached = false;
+        self.push_check(check);
+        self.skipped += 1;
+    }
+
+    /// Add a check skipped because its cache is fresh, which last took `last_duration_ms`
+    pub fn add_cached(&mut self, name: &str, last_duration_ms: Option<u64>) {
+        let mut check = CheckRunJson::skipped(name);
+        check.cache_hit = Some(last_duration_ms.unwrap_or(0));
+        self.push_check(check);
         self.skipped += 1;
     }
 
@@ -332,28 +897,70 @@ impl RunResults {
         metadata: &BTreeMap<String, MetadataValue>,
         prev_metadata: Option<&BTreeMap<String, MetadataValue>>,
     ) {
-        self.results.push(RunItemJson::Check(CheckRunJson::fail(
+        self.push_check(CheckRunJson::fail(
             name,
             duration_ms,
             exit_code,
             output,
             metadata,
             prev_metadata,
-        )));
+        ));
         self.failed += 1;
     }
 
+    /// Add a check that didn't run because its dependency `dependency` failed
+    pub fn add_blocked(&mut self, name: &str, dependency: &str) {
+        self.push_check(CheckRunJson::blocked(name, dependency));
+        self.blocked += 1;
+    }
+
+    /// Checks in the run, whatever their result
+    pub fn total(&self) -> usize {
+        self.passed + self.failed + self.skipped + self.blocked
+    }
+
+    /// Whether any check failed or was blocked by a failure, failing the run
+    pub fn has_failures(&self) -> bool {
+        self.failed > 0 || self.blocked > 0
+    }
+
+    /// Attach a cache decision to the next check added
+    pub fn explain_next(&mut self, decision: CacheDecisionJson) {
+        self.cache_decision = Some(decision);
+    }
+
+    /// Attach per-file results to the next check added
+    pub fn files_of_next(&mut self, files: Vec<FileRunJson>) {
+        self.files = Some(files);
+    }
+
+    fn push_check(&mut self, mut check: CheckRunJson) {
+        check.cache_decision = self.cache_decision.take();
+        check.files = self.files.take();
+        self.results.push(RunItemJson::Check(check));
+    }
+
+    /// Mark the most recently added check as untracked (no cache_paths)
+    /// Mark the check just added as having failed by timing out
+    pub fn mark_timed_out(&mut self) {
+        if let Some(RunItemJson::Check(check)) = self.results.last_mut() {
+            check.timed_out = true;
+        }
+    }
+
+    pub fn mark_untracked(&mut self) {
+        if let Some(RunItemJson::Check(check)) = self.results.last_mut() {
+            check.tracked = false;
+        }
+    }
+
     pub fn add_subproject(&mut self, name: &str, path: &str, sub_results: RunResults) {
         self.passed += sub_results.passed;
         self.failed += sub_results.failed;
         self.skipped += sub_results.skipped;
+        self.blocked += sub_results.blocked;
 
-        let summary = RunSummary {
-            total: sub_results.passed + sub_results.failed + sub_results.skipped,
-            passed: sub_results.passed,
-            failed: sub_results.failed,
-            skipped: sub_results.skipped,
-        };
+        let summary = sub_results.to_summary();
 
         self.results
             .push(RunItemJson::Subproject(SubprojectRunJson::new(
@@ -364,30 +971,124 @@ impl RunResults {
             )));
     }
 
-    pub fn into_output(self) -> RunOutput {
-        let total = self.passed + self.failed + self.skipped;
+    pub fn into_output(
+        self,
+        metadata_totals: BTreeMap<String, MetadataValue>,
+        message: Option<String>,
+    ) -> RunOutput {
         RunOutput {
+            summary: self.to_summary(),
             results: self.results,
-            summary: RunSummary {
-                total,
-                passed: self.passed,
-                failed: self.failed,
-                skipped: self.skipped,
-            },
+            metadata_totals,
+            message,
+        }
+    }
+
+    /// Aggregate the configured metadata keys across every check in the run
+    /// (including cached checks and subprojects). Keys without numeric values are omitted.
+    pub fn metadata_totals(
+        &self,
+        totals: &BTreeMap<String, Aggregation>,
+    ) -> BTreeMap<String, MetadataValue> {
+        fn collect(
+            items: &[RunItemJson],
+            totals: &BTreeMap<String, Aggregation>,
+            values: &mut BTreeMap<String, Vec<MetadataValue>>,
+        ) {
+            for item in items {
+                match item {
+                    RunItemJson::Check(check) => {
+                        let Some(metadata) = &check.metadata else {
+                            continue;
+                        };
+                        for key in totals.keys() {
+                            let value = match metadata.get(key) {
+                                Some(serde_json::Value::Number(n)) => match n.as_i64() {
+                                    Some(i) => MetadataValue::Integer(i),
+                                    None => MetadataValue::Float(n.as_f64().unwrap_or_default()),
+                                },
+                                _ => continue,
+                            };
+                            values.entry(key.clone()).or_default().push(value);
+                        }
+                    }
+                    RunItemJson::Subproject(sub) => collect(&sub.results, totals, values),
+                }
+            }
+        }
+
+        let mut values = BTreeMap::new();
+        collect(&self.results, totals, &mut values);
+
+        values
+            .into_iter()
+            .filter_map(|(key, vals)| {
+                let value = aggregate_values(&vals, totals[&key])?;
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    /// Directories of all subprojects that took part in the run (including nested ones),
+    /// relative to the project root
+    pub fn subproject_paths(&self) -> Vec<PathBuf> {
+        fn collect(items: &[RunItemJson], base: &Path, paths: &mut Vec<PathBuf>) {
+            for item in items {
+                if let RunItemJson::Subproject(sub) = item {
+                    let dir = base.join(&sub.path);
+                    collect(&sub.results, &dir, paths);
+                    paths.push(dir);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        collect(&self.results, Path::new(""), &mut paths);
+        paths
+    }
+
+    /// Failed checks whose own command exited with a code (including subprojects),
+    /// as (name, exit_code). Checks failed by a dependency or killed by a signal are left out.
+    pub fn command_failures(&self) -> Vec<(String, i32)> {
+        fn collect(items: &[RunItemJson], failures: &mut Vec<(String, i32)>) {
+            for item in items {
+                match item {
+                    RunItemJson::Check(check) => {
+                        if check.result == "fail"
+                            && let Some(code) = check.exit_code
+                        {
+                            failures.push((check.name.clone(), code));
+                        }
+                    }
+                    RunItemJson::Subproject(sub) => collect(&sub.results, failures),
+                }
+            }
         }
+
+        let mut failures = Vec::new();
+        collect(&self.results, &mut failures);
+        failures
     }
 
-    #[allow(dead_code)]
     pub fn to_summary(&self) -> RunSummary {
         RunSummary {
-            total: self.passed + self.failed + self.skipped,
+            total: self.total(),
             passed: self.passed,
             failed: self.failed,
             skipped: self.skipped,
+            blocked: self.blocked,
         }
     }
 }
 
+/// Write JSON output to a file (`--json-file`), so CI can keep human output on the terminal
+pub fn write_json_file(path: &Path, output: &impl Serialize) -> anyhow::Result<()> {
+    use anyhow::Context;
+    let json = serde_json::to_string_pretty(output)?;
+    std::fs::write(path, json + "\n")
+        .with_context(|| format!("Failed to write JSON output: {}", path.display()))
+}
+
 /// Format duration for human display
 pub fn format_duration(ms: u64) -> String {
     if ms < 1000 {
@@ -401,6 +1102,35 @@ pub fn format_duration(ms: u64) -> String {
     }
 }
 
+/// Format a file size in bytes with a binary unit ("512 B", "1.5 MB")
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format how long something has been the case, at the coarsest useful unit ("12m", "3h")
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86400)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -409,9 +1139,16 @@ mod tests {
     fn make_cache_with_metadata(metadata: BTreeMap<String, MetadataValue>) -> CheckCache {
         CheckCache {
             config_hash: Some("confighash".to_string()),
+            config_components: BTreeMap::new(),
             content_hash: Some("contenthash".to_string()),
             file_hashes: BTreeMap::new(),
             metadata,
+            duration_ms: None,
+            failed_files: Vec::new(),
+            marked_verified: None,
+            verified_by: None,
+            last_failure: None,
+            verify_version: None,
         }
     }
 
@@ -480,6 +1217,8 @@ mod tests {
             CheckStatusJson::from_status("build", &VerificationStatus::Untracked, Some(&cache));
 
         assert_eq!(result.status, "untracked");
+        assert_eq!(result.reason.as_deref(), Some("no_cache_paths"));
+        assert!(result.hint.is_some());
         assert!(result.metadata.is_none());
     }
 
@@ -518,4 +1257,125 @@ mod tests {
         assert!(obj.contains_key("metadata"));
         assert_eq!(obj["metadata"]["count"], serde_json::json!(5));
     }
+
+    fn status_item(name: &str, status: &VerificationStatus) -> StatusItemJson {
+        StatusItemJson::Check(CheckStatusJson::from_status(name, status, None))
+    }
+
+    fn nested_status() -> StatusOutput {
+        let stale = VerificationStatus::Unverified {
+            reason: UnverifiedReason::NeverRun,
+        };
+        let verified = VerificationStatus::Verified;
+        StatusOutput::new(vec![
+            status_item("lint", &verified),
+            StatusItemJson::Subproject(SubprojectStatusJson::new(
+                "backend",
+                "services/backend",
+                "services/backend",
+                vec![
+                    status_item("build", &stale),
+                    StatusItemJson::Subproject(SubprojectStatusJson::new(
+                        "db",
+                        "db",
+                        "db",
+                        vec![status_item("migrate", &verified)],
+                    )),
+                ],
+            )),
+            status_item("docs", &VerificationStatus::Untracked),
+        ])
+    }
+
+    #[test]
+    fn test_status_output_summary() {
+        let mut output = nested_status();
+        let expected = StatusSummary {
+            verified: 2,
+            unverified: 1,
+            failed: 0,
+            untracked: 1,
+            unsupported: 0,
+            total: 4,
+            all_verified: false,
+        };
+        assert_eq!(output.summary, expected);
+        let StatusItemJson::Subproject(backend) = &output.checks[1] else {
+            panic!("Expected subproject");
+        };
+        assert_eq!(backend.summary.total, 2);
+        assert_eq!(backend.summary.unverified, 1);
+
+        // Counts still cover the checks filtering leaves out
+        output.retain_unverified();
+        assert_eq!(output.summary, expected);
+
+        let verified = vec![status_item("lint", &VerificationStatus::Verified)];
+        assert!(StatusSummary::of(&verified).all_verified);
+
+        // Failed checks are counted apart from ones that just need to run
+        let failed = VerificationStatus::Failed {
+            exit_code: Some(1),
+            at: chrono::Utc::now(),
+            timed_out: false,
+        };
+        let summary = StatusSummary::of(&[
+            status_item("lint", &failed),
+            StatusItemJson::Subproject(SubprojectStatusJson::new(
+                "web",
+                "web",
+                "web",
+                vec![status_item("test", &failed)],
+            )),
+        ]);
+        assert_eq!((summary.failed, summary.unverified), (2, 0));
+        assert_eq!(summary.total, 2);
+        assert!(!summary.all_verified);
+    }
+
+    fn check_names(output: &StatusOutput) -> Vec<String> {
+        output
+            .checks
+            .iter()
+            .map(|item| match item {
+                StatusItemJson::Check(c) => c.name.clone(),
+                StatusItemJson::Subproject(s) => format!("[{}]", s.name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_status_output_retain_unverified() {
+        let mut output = nested_status();
+        output.retain_unverified();
+        assert_eq!(check_names(&output), vec!["[backend]", "docs"]);
+        let StatusItemJson::Subproject(backend) = &output.checks[0] else {
+            panic!("Expected subproject");
+        };
+        // The fully verified db subproject is dropped
+        assert_eq!(backend.checks.len(), 1);
+    }
+
+    #[test]
+    fn test_status_output_flatten() {
+        let mut output = nested_status();
+        output.flatten();
+        assert_eq!(
+            check_names(&output),
+            vec![
+                "lint",
+                "services/backend/build",
+                "services/backend/db/migrate",
+                "docs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(12 * 60 + 5)), "12m");
+        assert_eq!(format_age(Duration::from_secs(3 * 3600)), "3h");
+        assert_eq!(format_age(Duration::from_secs(2 * 86400 + 60)), "2d");
+    }
 }
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::bundle;
+use crate::cache;
+use crate::config::Config;
+use crate::hasher;
+use crate::trailer::{
+    find_verified_value, git, read_manifest_file, read_trailer, resolve_trailer_value,
+};
+
+/// A temporary copy of the repository at HEAD (or another commit), optionally with a
+/// patch or bundle applied. The working tree of the original repository is never touched.
+/// The copy is removed when this value is dropped.
+pub struct PatchedTree {
+    _dir: TempDir,
+    repo: PathBuf,
+    project_root: PathBuf,
+    trailer: Option<BTreeMap<String, String>>,
+}
+
+impl PatchedTree {
+    /// Copy the repository at HEAD, without applying anything.
+    /// Use `checkout` to move the copy to other commits.
+    pub fn at_head(project_root: &Path) -> Result<Self> {
+        let (dir, repo, prefix) = clone_head(project_root)?;
+        let project_root = repo.join(prefix);
+        let trailer = read_trailer(&project_root)?;
+        Ok(Self {
+            _dir: dir,
+            repo,
+            project_root,
+            trailer,
+        })
+    }
+
+    /// Check out another commit in the copy. The trailer is re-read from its message.
+    pub fn checkout(&mut self, commit: &str) -> Result<()> {
+        git(
+            &self.repo,
+            &["checkout", "--quiet", "--force", "--detach", commit],
+        )?;
+        hasher::files_may_have_changed();
+        self.trailer = read_trailer(&self.project_root)?;
+        Ok(())
+    }
+
+    /// Apply a patch file (plain diff or `git format-patch` output) on top of HEAD.
+    /// The Verified trailer is taken from the patch's commit message, if present.
+    pub fn from_patch(project_root: &Path, patch: &Path) -> Result<Self> {
+        let patch = patch
+            .canonicalize()
+            .with_context(|| format!("Failed to read patch: {}", patch.display()))?;
+        let content = std::fs::read_to_string(&patch)
+            .with_context(|| format!("Failed to read patch: {}", patch.display()))?;
+
+        let (dir, repo, prefix) = clone_head(project_root)?;
+        let patch_arg = patch.to_string_lossy();
+        git(&repo, &["apply", "--whitespace=nowarn", &patch_arg])
+            .with_context(|| format!("Failed to apply patch: {}", patch.display()))?;
+
+        // A compact trailer's manifest comes from the patched tree
+        let project_root = repo.join(prefix);
+        let trailer = find_verified_value(&content)
+            .map(|value| resolve_trailer_value(&value, || read_manifest_file(&project_root)))
+            .transpose()?;
+        Ok(Self {
+            _dir: dir,
+            project_root,
+            repo,
+            trailer,
+        })
+    }
+
+    /// Check out the tip of a git bundle on top of the current history.
+    /// Uses the bundle's HEAD if it has one, otherwise its first ref.
+    /// The Verified trailer is taken from the tip commit's message.
+    pub fn from_bundle(project_root: &Path, bundle: &Path) -> Result<Self> {
+        let bundle = bundle
+            .canonicalize()
+            .with_context(|| format!("Failed to read bundle: {}", bundle.display()))?;
+        let bundle_arg = bundle.to_string_lossy();
+
+        let (dir, repo, prefix) = clone_head(project_root)?;
+        let heads = git(&repo, &["bundle", "list-heads", &bundle_arg])
+            .with_context(|| format!("Invalid git bundle: {}", bundle.display()))?;
+        let refs: Vec<&str> = heads
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .collect();
+        let tip = refs
+            .iter()
+            .find(|r| **r == "HEAD")
+            .or_else(|| refs.first())
+            .with_context(|| format!("Git bundle has no refs: {}", bundle.display()))?;
+
+        git(&repo, &["fetch", "--quiet", &bundle_arg, tip])
+            .with_context(|| format!("Failed to fetch from bundle: {}", bundle.display()))?;
+        git(&repo, &["checkout", "--quiet", "--detach", "FETCH_HEAD"])?;
+
+        let project_root = repo.join(prefix);
+        let trailer = read_trailer(&project_root)?;
+        Ok(Self {
+            _dir: dir,
+            repo,
+            project_root,
+            trailer,
+        })
+    }
+
+    /// Check out the git index on top of HEAD: what the next commit would contain, without
+    /// unstaged changes or untracked files. There's no trailer, since nothing is committed.
+    pub fn from_index(project_root: &Path) -> Result<Self> {
+        // The tree is written to the original repository's objects, which the clone shares
+        let tree = git(project_root, &["write-tree"])
+            .context("Failed to read the staged changes (are there unresolved conflicts?)")?;
+
+        let (dir, repo, prefix) = clone_head(project_root)?;
+        git(&repo, &["read-tree", "--reset", "-u", &tree])?;
+
+        Ok(Self {
+            _dir: dir,
+            project_root: repo.join(prefix),
+            repo,
+            trailer: None,
+        })
+    }
+
+    /// Copy the verify.lock of the project and every subproject from `from` into the copy,
+    /// so checks are compared against the original's cache rather than the committed one
+    pub fn import_lock_files(&self, from: &Path, config: &Config) -> Result<()> {
+        copy_lock_files(from, &self.project_root, config)
+    }
+
+    /// Copy the copy's lock files back into the project at `to`, recording what ran here
+    pub fn export_lock_files(&self, to: &Path, config: &Config) -> Result<()> {
+        copy_lock_files(&self.project_root, to, config)
+    }
+
+    /// Project root inside the temporary copy
+    pub fn project_root(&self) -> &Path {
+        &self.project_root
+    }
+
+    /// Verified trailer carried by the patch or bundle, if any
+    pub fn trailer(&self) -> Option<&BTreeMap<String, String>> {
+        self.trailer.as_ref()
+    }
+}
+
+fn copy_lock_files(from: &Path, to: &Path, config: &Config) -> Result<()> {
+    for (key, contents) in bundle::collect_lock_files(from, config)? {
+        let project_dir = to.join(&key);
+        // A subproject only in one of the trees has nothing to copy into
+        if project_dir.is_dir() {
+            cache::write_lock_file(&project_dir, &contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Make a shared clone of the repository containing project_root, checked out at its HEAD.
+/// Returns (temp_dir, clone_root, prefix) where prefix is project_root relative to the repo root.
+fn clone_head(project_root: &Path) -> Result<(TempDir, PathBuf, String)> {
+    let toplevel = git(project_root, &["rev-parse", "--show-toplevel"])
+        .context("Failed to find git repository. Is this a git repository?")?;
+    let prefix = git(project_root, &["rev-parse", "--show-prefix"])?;
+    let head = git(project_root, &["rev-parse", "HEAD"])?;
+
+    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let repo = dir.path().join("repo");
+    let repo_arg = repo.to_string_lossy();
+    git(
+        dir.path(),
+        &[
+            "clone",
+            "--quiet",
+            "--shared",
+            "--no-checkout",
+            &toplevel,
+            &repo_arg,
+        ],
+    )?;
+    git(&repo, &["checkout", "--quiet", "--detach", &head])?;
+
+    Ok((dir, repo, prefix))
+}
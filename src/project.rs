@@ -0,0 +1,119 @@
+use crate::cache::CacheState;
+use crate::config::{Config, Settings, Subproject, Verification};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A subproject's config and cache, loaded once along with those of its own subprojects,
+/// so that commands walking the tree don't read them again at every level
+pub struct ProjectTree {
+    /// The subproject's directory (its parent's directory joined with its path)
+    pub dir: PathBuf,
+    /// Namespace of its checks outside the subproject, as in `backend/build`
+    pub prefix: String,
+    pub config: Config,
+    pub cache: CacheState,
+    pub subprojects: Subprojects,
+}
+
+impl ProjectTree {
+    fn load(parent_root: &Path, subproject: &Subproject, settings: &Arc<Settings>) -> Result<Self> {
+        let dir = parent_root.join(&subproject.path);
+        let config = Config::load_with_base(&dir.join("verify.yaml"), &dir)?;
+        let cache = CacheState::load(&dir, settings)?;
+        let subprojects = Subprojects::load(&dir, &config, settings)?;
+        Ok(ProjectTree {
+            dir,
+            prefix: subproject.prefix(),
+            config,
+            cache,
+            subprojects,
+        })
+    }
+}
+
+/// The subprojects of a config, by subproject name
+#[derive(Default)]
+pub struct Subprojects(BTreeMap<String, ProjectTree>);
+
+impl Subprojects {
+    /// Load every subproject of `config` (the config of the project in `project_root`),
+    /// recursively, with the root's `settings`
+    pub fn load(project_root: &Path, config: &Config, settings: &Arc<Settings>) -> Result<Self> {
+        let mut subprojects = BTreeMap::new();
+        for subproject in config.subprojects() {
+            let tree = ProjectTree::load(project_root, subproject, settings)?;
+            subprojects.insert(subproject.name.clone(), tree);
+        }
+        Ok(Subprojects(subprojects))
+    }
+
+    pub fn get(&self, name: &str) -> Result<&ProjectTree> {
+        self.0
+            .get(name)
+            .with_context(|| format!("Subproject '{}' isn't loaded", name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut ProjectTree> {
+        self.0
+            .get_mut(name)
+            .with_context(|| format!("Subproject '{}' isn't loaded", name))
+    }
+
+    /// A check of a subproject by its path-qualified name, e.g. `backend/build` or
+    /// `backend/api/build` for a nested subproject
+    pub fn find_check(&self, name: &str) -> Option<&Verification> {
+        self.0.values().find_map(|tree| {
+            let rest = name.strip_prefix(&tree.prefix)?.strip_prefix('/')?;
+            tree.config
+                .get(rest)
+                .or_else(|| tree.subprojects.find_check(rest))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_loads_nested_subprojects_and_finds_qualified_checks() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(
+            &root.join("verify.yaml"),
+            "verifications:\n  - name: backend\n    path: ./services/backend\n    prefix: be\n",
+        );
+        write(
+            &root.join("services/backend/verify.yaml"),
+            "verifications:\n  - name: build\n    command: echo\n  - name: api\n    path: api\n",
+        );
+        write(
+            &root.join("services/backend/api/verify.yaml"),
+            "verifications:\n  - name: lint\n    command: echo\n",
+        );
+
+        let config = Config::load(&root.join("verify.yaml")).unwrap();
+        let subprojects = Subprojects::load(root, &config, &Default::default()).unwrap();
+        let backend = subprojects.get("backend").unwrap();
+        assert_eq!(backend.dir, root.join("./services/backend"));
+        assert_eq!(
+            backend.subprojects.get("api").unwrap().dir,
+            backend.dir.join("api")
+        );
+
+        assert_eq!(subprojects.find_check("be/build").unwrap().name, "build");
+        assert_eq!(subprojects.find_check("be/api/lint").unwrap().name, "lint");
+        assert!(subprojects.find_check("services/backend/build").is_none());
+        assert!(subprojects.find_check("be/lint").is_none());
+        assert!(subprojects.get("api").is_err());
+    }
+}
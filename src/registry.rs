@@ -0,0 +1,204 @@
+use crate::config::Config;
+use crate::statedir;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Component, Path, PathBuf};
+
+const REGISTRY_FILE: &str = "subprojects.json";
+
+/// Every subproject the root config has referenced, kept in `.verify/subprojects.json`
+/// so the state a subproject leaves behind (`verify.lock` and `.verify/`) can be found
+/// after it's removed from the config
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Registry {
+    /// Subproject directories relative to the root project, including nested ones
+    paths: BTreeSet<String>,
+}
+
+/// Record the subprojects currently in the config and return the previously recorded
+/// ones that are no longer configured but still have state on disk
+pub fn record(project_root: &Path, config: &Config) -> Result<Vec<String>> {
+    let configured = configured_subprojects(project_root, config)?;
+    let mut registry = load(project_root)?;
+    let orphaned = orphaned(project_root, &registry, &configured);
+
+    // Forget removed subprojects once nothing of theirs is left to clean up
+    let paths: BTreeSet<String> = configured
+        .into_iter()
+        .chain(orphaned.iter().cloned())
+        .collect();
+    if paths != registry.paths && statedir::writes_enabled() {
+        registry.paths = paths;
+        save(project_root, &registry)?;
+    }
+    Ok(orphaned)
+}
+
+/// Delete the state of subprojects that are no longer in the config, returning their paths
+pub fn remove_orphaned(project_root: &Path, config: &Config) -> Result<Vec<String>> {
+    let configured = configured_subprojects(project_root, config)?;
+    let mut registry = load(project_root)?;
+    let orphaned = orphaned(project_root, &registry, &configured);
+
+    for path in &orphaned {
+        let dir = project_root.join(path);
+        let lock = dir.join("verify.lock");
+        if lock.exists() {
+            fs::remove_file(&lock)
+                .with_context(|| format!("Failed to remove {}", lock.display()))?;
+        }
+        let state = statedir::state_dir(&dir);
+        if state.exists() {
+            fs::remove_dir_all(&state)
+                .with_context(|| format!("Failed to remove {}", state.display()))?;
+        }
+    }
+
+    if registry.paths != configured {
+        registry.paths = configured;
+        save(project_root, &registry)?;
+    }
+    Ok(orphaned)
+}
+
+/// Recorded subprojects missing from the config whose directory still has state
+fn orphaned(
+    project_root: &Path,
+    registry: &Registry,
+    configured: &BTreeSet<String>,
+) -> Vec<String> {
+    registry
+        .paths
+        .difference(configured)
+        .filter(|path| !path.is_empty() && has_state(&project_root.join(path)))
+        .cloned()
+        .collect()
+}
+
+fn has_state(dir: &Path) -> bool {
+    dir.join("verify.lock").exists() || statedir::state_dir(dir).exists()
+}
+
+/// Paths of all subprojects (recursively), relative to the root project
+fn configured_subprojects(project_root: &Path, config: &Config) -> Result<BTreeSet<String>> {
+    let mut paths = BTreeSet::new();
+    collect_subprojects(project_root, Path::new(""), config, &mut paths)?;
+    Ok(paths)
+}
+
+fn collect_subprojects(
+    project_root: &Path,
+    relative: &Path,
+    config: &Config,
+    paths: &mut BTreeSet<String>,
+) -> Result<()> {
+    for subproject in config.subprojects() {
+        let sub_relative = relative.join(&subproject.path);
+        let subproject_dir = project_root.join(&sub_relative);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        paths.insert(normalize(&sub_relative));
+        collect_subprojects(project_root, &sub_relative, &sub_config, paths)?;
+    }
+    Ok(())
+}
+
+/// "./packages/web/" and "packages/web" name the same subproject
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if parts.last().is_some_and(|p| p != "..") => {
+                parts.pop();
+            }
+            other => parts.push(other.as_os_str().to_string_lossy().into_owned()),
+        }
+    }
+    parts.join("/")
+}
+
+fn load(project_root: &Path) -> Result<Registry> {
+    let path = registry_path(project_root);
+    if !path.exists() {
+        return Ok(Registry::default());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read subproject registry: {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse subproject registry: {}", path.display()))
+}
+
+/// Write the registry atomically, so a crash mid-write can't corrupt it
+fn save(project_root: &Path, registry: &Registry) -> Result<()> {
+    let dir = statedir::create(project_root)?;
+
+    let path = registry_path(project_root);
+    let temp_path = dir.join(format!("{}.tmp", REGISTRY_FILE));
+    let file = File::create(&temp_path).with_context(|| {
+        format!(
+            "Failed to create temp subproject registry: {}",
+            temp_path.display()
+        )
+    })?;
+    serde_json::to_writer_pretty(BufWriter::new(file), registry)
+        .with_context(|| "Failed to serialize subproject registry")?;
+    fs::rename(&temp_path, &path)
+        .with_context(|| format!("Failed to save subproject registry: {}", path.display()))?;
+    Ok(())
+}
+
+fn registry_path(project_root: &Path) -> PathBuf {
+    statedir::state_dir(project_root).join(REGISTRY_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("./packages/web/")), "packages/web");
+        assert_eq!(normalize(Path::new("packages/web/../api")), "packages/api");
+        assert_eq!(normalize(Path::new("../shared")), "../shared");
+    }
+
+    #[test]
+    fn test_removed_subproject_is_orphaned_until_cleaned() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let check = "verifications:\n  - name: build\n    command: echo\n";
+        write(&root.join("api/verify.yaml"), check);
+        write(&root.join("web/verify.yaml"), check);
+        write(&root.join("web/verify.lock"), "{}");
+        write(&root.join("web/.verify/hashcache"), "");
+
+        let both = "verifications:\n  - name: api\n    path: ./api\n  - name: web\n    path: web\n";
+        write(&root.join("verify.yaml"), both);
+        let config = Config::load(&root.join("verify.yaml")).unwrap();
+        assert!(record(root, &config).unwrap().is_empty());
+
+        write(
+            &root.join("verify.yaml"),
+            "verifications:\n  - name: api\n    path: api\n",
+        );
+        let config = Config::load(&root.join("verify.yaml")).unwrap();
+        assert_eq!(record(root, &config).unwrap(), vec!["web".to_string()]);
+        assert_eq!(record(root, &config).unwrap(), vec!["web".to_string()]);
+
+        assert_eq!(remove_orphaned(root, &config).unwrap(), vec!["web"]);
+        assert!(!root.join("web/verify.lock").exists());
+        assert!(!root.join("web/.verify").exists());
+        assert!(root.join("web/verify.yaml").exists());
+        assert!(record(root, &config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::runner::TIMEOUT_GRACE;
+use crate::tmpdir::TMPDIR_ENV;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Directory (relative to the remote user's home) that holds synced project copies
+const REMOTE_BASE_DIR: &str = ".verify-remote";
+
+/// Directory in a remote project copy listing the inputs last uploaded for each check
+const INPUTS_DIR: &str = ".verify-inputs";
+
+/// Runs check commands on another machine over SSH.
+///
+/// The check's input files (everything matched by `cache_paths`) are copied into a
+/// per-project directory on the host before the command runs there, and declared
+/// `artifacts` are copied back afterwards. The remote copy persists between runs so
+/// incremental build state on the host is reused, but inputs deleted locally are deleted
+/// there too. Verification state stays local.
+pub struct SshExecutor {
+    host: String,
+    remote_dir: String,
+}
+
+impl SshExecutor {
+    pub fn new(host: &str, project_root: &Path) -> Self {
+        let root = project_root
+            .canonicalize()
+            .unwrap_or_else(|_| project_root.to_path_buf());
+        let name = root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        let id = blake3::hash(root.to_string_lossy().as_bytes()).to_hex();
+        Self {
+            host: host.to_string(),
+            remote_dir: format!("{}/{}-{}", REMOTE_BASE_DIR, name, &id[..12]),
+        }
+    }
+
+    /// Copy `check`'s input files (relative to the project root) to the remote project
+    /// directory, removing the ones uploaded for it last time that it no longer has.
+    /// Build output on the host is left alone, as it isn't an input.
+    pub fn upload(&self, project_root: &Path, check: &str, files: &[&str]) -> Result<()> {
+        // The list of inputs goes along in the archive, next to the previous one
+        let id = blake3::hash(check.as_bytes()).to_hex();
+        let listed = format!("{}/{}", INPUTS_DIR, &id[..12]);
+        let staging = tempfile::tempdir().context("Failed to create temp directory")?;
+        fs::create_dir(staging.path().join(INPUTS_DIR))
+            .and_then(|_| {
+                let list: String = files.iter().map(|file| format!("{}\n", file)).collect();
+                fs::write(staging.path().join(format!("{}.new", listed)), list)
+            })
+            .context("Failed to write the list of inputs")?;
+
+        let mut tar = Command::new("tar")
+            .arg("-cf")
+            .arg("-")
+            .arg("-C")
+            .arg(staging.path())
+            .arg(format!("{}.new", listed))
+            // Relative to the previous -C otherwise
+            .arg("-C")
+            .arg(std::path::absolute(project_root)?)
+            .arg("--")
+            .args(files)
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to run tar")?;
+        let archive = tar.stdout.take().expect("tar stdout is piped");
+
+        let listed = shell_quote(&listed);
+        let extract = format!(
+            "mkdir -p {dir} && cd {dir} && tar -xf - && \
+             {{ [ ! -f {listed} ] || grep -vxF -f {listed}.new {listed} | \
+             while IFS= read -r file; do rm -f -- \"$file\"; done; }} && \
+             mv {listed}.new {listed}",
+            dir = shell_quote(&self.remote_dir),
+            listed = listed,
+        );
+        let result = self.run_ssh(&extract, Stdio::from(archive));
+        let status = tar.wait().context("Failed to wait for tar")?;
+        result?;
+        if !status.success() {
+            anyhow::bail!("Failed to archive inputs for {}", self.host);
+        }
+        Ok(())
+    }
+
+    /// Build the command that runs `command` in the remote project directory. Like a local
+    /// command, it gets a fresh `VERIFY_TMPDIR`, removed once it finishes.
+    ///
+    /// Stopping the local ssh client on timeout doesn't stop the remote command, so with
+    /// `timeout` a watchdog on the host stops it too. sshd starts each session in its own
+    /// process group, which the watchdog signals (`kill 0`) the way the local watchdog
+    /// signals the ssh client's: SIGTERM, then SIGKILL after `TIMEOUT_GRACE`, ignoring
+    /// the SIGTERM itself so it gets to send the SIGKILL.
+    pub fn command(
+        &self,
+        command: &str,
+        env_vars: &[(&str, &str)],
+        timeout: Option<Duration>,
+    ) -> Command {
+        let env: String = env_vars
+            .iter()
+            .map(|(key, value)| format!("{}={} ", key, shell_quote(value)))
+            .collect();
+        let run = format!(
+            "{}=\"$tmp\" {}sh -c {}",
+            TMPDIR_ENV,
+            env,
+            shell_quote(command)
+        );
+        let run = match timeout {
+            Some(timeout) => format!(
+                "(trap '' TERM; sleep {}; kill -TERM 0; sleep {}; kill -KILL 0) \
+                 >/dev/null 2>&1 </dev/null & {}; status=$?; kill -KILL $! 2>/dev/null",
+                timeout.as_secs(),
+                TIMEOUT_GRACE.as_secs(),
+                run
+            ),
+            None => format!("{}; status=$?", run),
+        };
+        // The timeout watchdog's SIGTERM reaches this shell too, which still cleans up
+        let remote_command = format!(
+            "cd {} && tmp=$(mktemp -d) && trap 'rm -rf \"$tmp\"; exit 143' TERM && \
+             {{ {}; rm -rf \"$tmp\"; exit $status; }}",
+            shell_quote(&self.remote_dir),
+            run
+        );
+        let mut cmd = Command::new(ssh_program());
+        cmd.arg(&self.host).arg(remote_command);
+        cmd
+    }
+
+    /// Copy artifacts (paths relative to the project root) back from the remote
+    pub fn download(&self, project_root: &Path, artifacts: &[String]) -> Result<()> {
+        if artifacts.is_empty() {
+            return Ok(());
+        }
+
+        let quoted: Vec<String> = artifacts.iter().map(|a| shell_quote(a)).collect();
+        let archive = format!(
+            "cd {} && tar -cf - -- {}",
+            shell_quote(&self.remote_dir),
+            quoted.join(" ")
+        );
+        let mut ssh = Command::new(ssh_program())
+            .arg(&self.host)
+            .arg(archive)
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run ssh for {}", self.host))?;
+        let stream = ssh.stdout.take().expect("ssh stdout is piped");
+
+        let extract = Command::new("tar")
+            .arg("-xf")
+            .arg("-")
+            .arg("-C")
+            .arg(project_root)
+            .stdin(Stdio::from(stream))
+            .status()
+            .context("Failed to run tar")?;
+        let status = ssh.wait().context("Failed to wait for ssh")?;
+        if !status.success() || !extract.success() {
+            anyhow::bail!(
+                "Failed to copy artifacts from {}: {}",
+                self.host,
+                artifacts.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    fn run_ssh(&self, remote_command: &str, stdin: Stdio) -> Result<()> {
+        let status = Command::new(ssh_program())
+            .arg(&self.host)
+            .arg(remote_command)
+            .stdin(stdin)
+            .status()
+            .with_context(|| format!("Failed to run ssh for {}", self.host))?;
+        if !status.success() {
+            anyhow::bail!("Failed to sync inputs to {}", self.host);
+        }
+        Ok(())
+    }
+}
+
+/// SSH client to use, overridable with VERIFY_SSH (like GIT_SSH)
+fn ssh_program() -> String {
+    std::env::var("VERIFY_SSH").unwrap_or_else(|_| "ssh".to_string())
+}
+
+/// Quote a string for a POSIX shell, since ssh passes the remote command through one
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+    }
+
+    #[test]
+    fn test_remote_dir_is_stable_per_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = SshExecutor::new("host", dir.path());
+        let b = SshExecutor::new("other", dir.path());
+        assert_eq!(a.remote_dir, b.remote_dir);
+        assert!(a.remote_dir.starts_with(".verify-remote/"));
+
+        let other = tempfile::tempdir().unwrap();
+        assert_ne!(
+            a.remote_dir,
+            SshExecutor::new("host", other.path()).remote_dir
+        );
+    }
+}
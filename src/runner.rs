@@ -1,21 +1,39 @@
-use crate::cache::{CacheState, UnverifiedReason, VerificationStatus};
-use crate::config::{Config, Subproject, Verification, VerificationItem};
+use crate::audit;
+use crate::cache::{Attribution, CacheState, MarkedVerified, UnverifiedReason, VerificationStatus};
+use crate::checklock::FileLock;
+use crate::config::{
+    Config, PerFileOrder, Settings, ShowOutput, Subproject, Verification, VerificationItem,
+    Workspace,
+};
+use crate::events;
 use crate::graph::DependencyGraph;
-use crate::hasher::{HashResult, compute_check_hash, find_changed_files};
-use crate::metadata::{MetadataValue, extract_metadata};
+use crate::hasher::{self, HashResult, find_changed_files};
+use crate::history::{self, RunRecord};
+use crate::journal::RunJournal;
+use crate::metadata::{MetadataValue, drop_oversized, extract_metadata};
 use crate::output::{
-    CheckStatusJson, RunResults, StatusItemJson, StatusOutput, SubprojectStatusJson,
+    BisectOutput, CacheDecisionJson, CheckStatusJson, CommitJson, ExplainOutput, FileRunJson,
+    RunOutput, RunResults, StatusItemJson, StatusOutput, SubprojectStatusJson, SyncOutput,
+    SyncSummary, WhyOutput, write_json_file,
 };
+use crate::project::{ProjectTree, Subprojects};
+use crate::remote::SshExecutor;
+use crate::statedir;
+use crate::stats::{self, StatsRecord};
+use crate::tmpdir::{CheckTmpDir, TMPDIR_ENV};
 use crate::ui::{
-    Ui, create_running_indicator, finish_cached, finish_fail_with_metadata,
+    CheckOutcome, Ui, create_running_indicator, finish_cached, finish_fail_with_metadata,
     finish_pass_with_metadata,
 };
 use anyhow::Result;
-use std::collections::{BTreeMap, HashMap};
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::process::{Command, Stdio};
-use std::time::Instant;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Result of executing a single check
 #[allow(dead_code)]
@@ -30,78 +48,418 @@ pub struct CheckExecution {
     pub hash_result: HashResult,
 }
 
-/// Execute a single command
+/// Where a command's output streams to as it runs: through the Ui, labelled with the
+/// check (or `check:file` for per_file invocations) it belongs to
+#[derive(Clone, Copy)]
+struct OutputStream<'a> {
+    ui: &'a Ui,
+    label: &'a str,
+}
+
+/// How a command invocation ended: whether it succeeded, its exit code (None when killed
+/// by a signal), its combined output, and whether it was killed for running past its
+/// timeout
+type CommandResult = (bool, Option<i32>, String, bool);
+
+/// Time a command gets to exit after being asked to on timeout, before it's killed
+pub const TIMEOUT_GRACE: Duration = Duration::from_secs(2);
+
+/// Execute a single command, stopping it (and every process it started) once it has run
+/// for `timeout_secs`
 fn execute_command(
     command: &str,
     project_root: &Path,
-    _timeout_secs: Option<u64>,
-    verbose: bool,
+    timeout_secs: Option<u64>,
+    stream: Option<OutputStream>,
     env_vars: &[(&str, &str)],
-) -> (bool, Option<i32>, String) {
-    if verbose {
-        // Stream output in real-time while also capturing it
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c")
-            .arg(command)
-            .current_dir(project_root)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        for (key, value) in env_vars {
-            cmd.env(key, value);
-        }
-        let mut child = match cmd.spawn() {
-            Ok(child) => child,
-            Err(e) => return (false, None, format!("Failed to execute command: {}", e)),
+) -> CommandResult {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).current_dir(project_root);
+    for (key, value) in env_vars {
+        cmd.env(key, value);
+    }
+    run_process(cmd, stream, timeout_secs.map(Duration::from_secs))
+}
+
+/// Run a prepared command, capturing combined stdout and stderr
+/// (and streaming it as it arrives in verbose mode)
+fn run_process(
+    mut cmd: Command,
+    stream: Option<OutputStream>,
+    timeout: Option<Duration>,
+) -> CommandResult {
+    // Its own process group, so the processes it starts can be stopped with it on timeout
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let result = capture_process(cmd, stream, timeout);
+    // The command may have rewritten files whose hashes are only remembered in memory
+    hasher::files_may_have_changed();
+    result
+}
+
+/// Process groups of the commands watchdogs are running, with each watchdog's `reaped`
+/// flag. Being outside verify's group, they don't get the terminal's Ctrl-C, so
+/// `forward_interrupts` passes it on.
+static CHILD_GROUPS: Mutex<Vec<(u32, Arc<Mutex<bool>>)>> = Mutex::new(Vec::new());
+
+/// Once a command runs in its own process group, pass SIGINT and SIGTERM on to every
+/// such group before verify exits the way the signal would have made it
+#[cfg(unix)]
+fn forward_interrupts() {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use std::sync::Once;
+
+    static FORWARDING: Once = Once::new();
+    FORWARDING.call_once(|| {
+        let Ok(mut signals) = signal_hook::iterator::Signals::new([SIGINT, SIGTERM]) else {
+            return;
         };
+        std::thread::spawn(move || {
+            let Some(received) = signals.forever().next() else {
+                return;
+            };
+            let name = if received == SIGINT { "INT" } else { "TERM" };
+            let groups = CHILD_GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+            for (pid, reaped) in groups.iter() {
+                let reaped = reaped.lock().unwrap_or_else(|e| e.into_inner());
+                if !*reaped {
+                    signal_group(*pid, name);
+                }
+            }
+            let _ = signal_hook::low_level::emulate_default_handler(received);
+        });
+    });
+}
 
-        let mut combined_output = String::new();
+#[cfg(not(unix))]
+fn forward_interrupts() {}
+
+/// Stops a command's process group if it's still running when its timeout passes: first
+/// with SIGTERM, then SIGKILL if it hasn't exited after `TIMEOUT_GRACE`
+struct Watchdog {
+    pid: u32,
+    done: mpsc::Sender<()>,
+    thread: std::thread::JoinHandle<bool>,
+    /// Set once the command's process is reaped, after which its id (and so its process
+    /// group's) may belong to another process. Held while signalling, so the process
+    /// can't be reaped in between.
+    reaped: Arc<Mutex<bool>>,
+}
 
-        // Read stdout
-        if let Some(stdout) = child.stdout.take() {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().map_while(Result::ok) {
-                println!("{}", line);
-                combined_output.push_str(&line);
-                combined_output.push('\n');
+impl Watchdog {
+    fn start(pid: u32, timeout: Duration) -> Self {
+        let (done, finished) = mpsc::channel();
+        let reaped = Arc::new(Mutex::new(false));
+        forward_interrupts();
+        CHILD_GROUPS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((pid, Arc::clone(&reaped)));
+        let thread_reaped = Arc::clone(&reaped);
+        let thread = std::thread::spawn(move || {
+            let signal = |signal: &str| {
+                let reaped = thread_reaped.lock().unwrap_or_else(|e| e.into_inner());
+                if !*reaped {
+                    signal_group(pid, signal);
+                }
+            };
+            if finished.recv_timeout(timeout) != Err(RecvTimeoutError::Timeout) {
+                return false;
+            }
+            signal("TERM");
+            if finished.recv_timeout(TIMEOUT_GRACE) == Err(RecvTimeoutError::Timeout) {
+                signal("KILL");
             }
+            true
+        });
+        Self {
+            pid,
+            done,
+            thread,
+            reaped,
         }
+    }
 
-        // Read stderr
-        if let Some(stderr) = child.stderr.take() {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines().map_while(Result::ok) {
-                eprintln!("{}", line);
-                combined_output.push_str(&line);
-                combined_output.push('\n');
+    /// Wait for the command's process to exit and reap it, polling so the watchdog can
+    /// signal its group in the meantime
+    fn reap(&self, child: &mut Child) -> std::io::Result<ExitStatus> {
+        loop {
+            let mut reaped = self.reaped.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(status) = child.try_wait()? {
+                *reaped = true;
+                return Ok(status);
             }
+            drop(reaped);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Call once the command has exited. Returns whether it was stopped for timing out.
+    fn finish(self) -> bool {
+        CHILD_GROUPS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(pid, _)| *pid != self.pid);
+        // Wakes the thread if it's still waiting
+        let _ = self.done.send(());
+        self.thread.join().unwrap_or(false)
+    }
+}
+
+/// Send a signal to the process group led by `pid`
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: &str) {
+    let _ = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Stop the process tree rooted at `pid` (there's no gentler signal to try first)
+#[cfg(not(unix))]
+fn signal_group(pid: u32, _signal: &str) {
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .status();
+}
+
+fn capture_process(
+    mut cmd: Command,
+    stream: Option<OutputStream>,
+    timeout: Option<Duration>,
+) -> CommandResult {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            return (
+                false,
+                None,
+                format!("Failed to execute command: {}", e),
+                false,
+            );
         }
+    };
+    let watchdog = timeout.map(|timeout| Watchdog::start(child.id(), timeout));
+
+    let output = match stream {
+        // Stream output in real-time while also capturing it
+        Some(stream) => read_streamed(&mut child, stream),
+        None => read_all(&mut child),
+    };
+    let status = match &watchdog {
+        Some(watchdog) => watchdog.reap(&mut child),
+        None => child.wait(),
+    };
+
+    let timed_out = watchdog.is_some_and(Watchdog::finish);
+    match status {
+        _ if timed_out => (false, None, output, true),
+        Ok(status) => (status.success(), status.code(), output, false),
+        Err(e) => (
+            false,
+            None,
+            format!("Failed to wait for command: {}", e),
+            false,
+        ),
+    }
+}
 
-        let status = child.wait();
-        match status {
-            Ok(status) => (status.success(), status.code(), combined_output),
-            Err(e) => (false, None, format!("Failed to wait for command: {}", e)),
+/// Print a command's stdout and then stderr line by line as they arrive, returning them
+/// combined
+fn read_streamed(child: &mut Child, stream: OutputStream) -> String {
+    let mut combined_output = String::new();
+
+    // Read stdout
+    if let Some(stdout) = child.stdout.take() {
+        for line in printable_lines(stdout) {
+            stream.ui.print_streamed_line(stream.label, &line, false);
+            combined_output.push_str(&line);
+            combined_output.push('\n');
         }
-    } else {
-        // Original behavior: capture all output at once
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c").arg(command).current_dir(project_root);
-        for (key, value) in env_vars {
-            cmd.env(key, value);
+    }
+
+    // Read stderr
+    if let Some(stderr) = child.stderr.take() {
+        for line in printable_lines(stderr) {
+            stream.ui.print_streamed_line(stream.label, &line, true);
+            combined_output.push_str(&line);
+            combined_output.push('\n');
         }
-        let result = cmd.output();
+    }
 
-        match result {
-            Ok(output) => {
-                let success = output.status.success();
-                let exit_code = output.status.code();
-                let combined_output = format!(
-                    "{}{}",
-                    String::from_utf8_lossy(&output.stdout),
-                    String::from_utf8_lossy(&output.stderr)
-                );
-                (success, exit_code, combined_output)
+    combined_output
+}
+
+/// A command's stdout followed by its stderr, read at the same time so neither pipe fills
+/// up and blocks the command
+fn read_all(child: &mut Child) -> String {
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    };
+    let stdout = child
+        .stdout
+        .take()
+        .map(|p| Box::new(p) as Box<dyn Read + Send>);
+    let stderr = child
+        .stderr
+        .take()
+        .map(|p| Box::new(p) as Box<dyn Read + Send>);
+    let (stdout, stderr) = std::thread::scope(|scope| {
+        let stderr = scope.spawn(|| read(stderr));
+        (read(stdout), stderr.join().unwrap_or_default())
+    });
+    format!("{}{}", printable(&stdout), printable(&stderr))
+}
+
+/// Lines of a command's output stream as printable text. Invalid UTF-8 doesn't end the
+/// stream, so binary output is read to the end instead of blocking the command.
+fn printable_lines(stream: impl std::io::Read) -> impl Iterator<Item = String> {
+    BufReader::new(stream)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .map(|line| printable(line.strip_suffix(b"\r").unwrap_or(&line)))
+}
+
+/// Command output that's safe to show on a terminal and store: invalid UTF-8 and control
+/// characters (except newlines, tabs and ANSI color codes) become U+FFFD, and carriage
+/// returns become newlines, so binary output can't corrupt the terminal
+fn printable(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' | '\t' => result.push(c),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' => result.push('\n'),
+            '\x1b' => match color_code(chars.clone()) {
+                Some(len) => {
+                    result.push(c);
+                    result.extend(chars.by_ref().take(len));
+                }
+                None => result.push(char::REPLACEMENT_CHARACTER),
+            },
+            c if c.is_control() => result.push(char::REPLACEMENT_CHARACTER),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Length of an ANSI color code (`[...m`) following an escape character, if it is one
+fn color_code(mut chars: impl Iterator<Item = char>) -> Option<usize> {
+    if chars.next()? != '[' {
+        return None;
+    }
+    let mut len = 1;
+    for c in chars {
+        len += 1;
+        match c {
+            'm' => return Some(len),
+            '0'..='9' | ';' => {}
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The output kept for a failed check: with `max_output`, only its last bytes
+fn retained_output(check: &Verification, output: &str) -> String {
+    let Some(max) = check.max_output.map(|max| max as usize) else {
+        return output.to_string();
+    };
+    if output.len() <= max {
+        return output.to_string();
+    }
+    let mut start = output.len() - max;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[{} bytes of output omitted]\n{}", start, &output[start..])
+}
+
+/// Where a check's command runs: locally, or on the SSH host named by `runs_on`
+enum Executor {
+    Local,
+    Ssh(SshExecutor),
+}
+
+impl Executor {
+    fn for_check(check: &Verification, project_root: &Path) -> Self {
+        match &check.runs_on {
+            Some(host) => Executor::Ssh(SshExecutor::new(host, project_root)),
+            None => Executor::Local,
+        }
+    }
+
+    /// Make the check's input files available where the command will run
+    fn prepare(
+        &self,
+        project_root: &Path,
+        check: &Verification,
+        hash_result: &HashResult,
+    ) -> Result<()> {
+        match self {
+            Executor::Local => Ok(()),
+            Executor::Ssh(ssh) => {
+                let files: Vec<&str> = hash_result.file_hashes.keys().map(String::as_str).collect();
+                ssh.upload(project_root, &check.name, &files)
+            }
+        }
+    }
+
+    /// Run one invocation of `check`'s command. Locally it gets a fresh `VERIFY_TMPDIR`,
+    /// removed once it finishes.
+    fn execute(
+        &self,
+        check: &str,
+        command: &str,
+        project_root: &Path,
+        timeout_secs: Option<u64>,
+        stream: Option<OutputStream>,
+        env_vars: &[(&str, &str)],
+    ) -> CommandResult {
+        match self {
+            Executor::Local => {
+                let tmp = match CheckTmpDir::create(project_root, check) {
+                    Ok(tmp) => tmp,
+                    Err(e) => return (false, None, format!("{:#}", e), false),
+                };
+                let tmp_path = tmp.path().to_string_lossy();
+                let mut env_vars = env_vars.to_vec();
+                env_vars.push((TMPDIR_ENV, &tmp_path));
+                execute_command(command, project_root, timeout_secs, stream, &env_vars)
+            }
+            Executor::Ssh(ssh) => {
+                let timeout = timeout_secs.map(Duration::from_secs);
+                run_process(ssh.command(command, env_vars, timeout), stream, timeout)
+            }
+        }
+    }
+
+    /// Bring declared artifacts back into the project. Missing artifacts are only
+    /// an error when the command succeeded (a failed command may not produce them).
+    fn finish(&self, project_root: &Path, check: &Verification, success: bool) -> Result<()> {
+        match self {
+            Executor::Local => Ok(()),
+            Executor::Ssh(ssh) => {
+                let downloaded = ssh.download(project_root, &check.artifacts);
+                hasher::files_may_have_changed();
+                match downloaded {
+                    Err(e) if success => Err(e),
+                    _ => Ok(()),
+                }
             }
-            Err(e) => (false, None, format!("Failed to execute command: {}", e)),
         }
     }
 }
@@ -113,7 +471,11 @@ fn compute_status(
     cache: &CacheState,
     dep_staleness: &HashMap<String, bool>,
 ) -> VerificationStatus {
-    // First check if any dependency is unverified
+    if !check.is_supported() {
+        return VerificationStatus::Unsupported;
+    }
+
+    // Then check if any dependency is unverified
     for dep in &check.depends_on {
         if dep_staleness.get(dep).copied().unwrap_or(true) {
             return VerificationStatus::Unverified {
@@ -124,8 +486,9 @@ fn compute_status(
         }
     }
 
-    // Aggregate checks (no command): status is derived purely from dependencies
-    if check.command.is_none() {
+    // Aggregate checks (no command): status is derived from dependencies, and from their
+    // cache_paths when they have any, which guard it like a check's own files
+    if check.command.is_none() && check.cache_paths.is_empty() {
         return VerificationStatus::Verified;
     }
 
@@ -138,8 +501,19 @@ fn compute_status(
     let config_hash = check.config_hash();
     let status = cache.check_staleness(&check.name, &hash_result.combined_hash, &config_hash);
 
-    // Enrich with changed files if unverified due to files
+    // Enrich with changed files if unverified due to files, or the changed parts of the
+    // config if unverified due to config
     match &status {
+        VerificationStatus::Unverified {
+            reason: UnverifiedReason::ConfigChanged { .. },
+        } => match cache.get(&check.name) {
+            Some(cached) => VerificationStatus::Unverified {
+                reason: UnverifiedReason::ConfigChanged {
+                    changed: cached.changed_config(&check.config_components()),
+                },
+            },
+            None => status,
+        },
         VerificationStatus::Unverified {
             reason: UnverifiedReason::FilesChanged { .. },
         } => {
@@ -178,63 +552,138 @@ fn get_stale_files_from_cache(
         .collect()
 }
 
+/// Sort stale per_file paths (alphabetical on input) by the check's `per_file_order`
+fn order_stale_files(
+    project_root: &Path,
+    files: &mut [String],
+    order: PerFileOrder,
+    previously_failed: &[String],
+) {
+    match order {
+        PerFileOrder::Alpha => {}
+        PerFileOrder::Recent => {
+            // Newest first; files whose mtime can't be read go last
+            files.sort_by_cached_key(|path| {
+                std::cmp::Reverse(
+                    std::fs::metadata(project_root.join(path))
+                        .and_then(|m| m.modified())
+                        .ok(),
+                )
+            });
+        }
+        PerFileOrder::FailedFirst => {
+            files.sort_by_key(|path| !previously_failed.contains(path));
+        }
+        PerFileOrder::Size => {
+            files.sort_by_cached_key(|path| {
+                std::fs::metadata(project_root.join(path))
+                    .map(|m| m.len())
+                    .unwrap_or(u64::MAX)
+            });
+        }
+    }
+}
+
+/// How `run_status` shows the status of the checks. The default prints every check.
+#[derive(Default)]
+pub struct StatusOptions<'a> {
+    /// Print JSON instead of status lines
+    pub json: bool,
+    /// Show each check's metadata and changed files
+    pub detailed: bool,
+    /// With `name`, also list the status of the check's transitive dependencies
+    pub explain: bool,
+    /// Leave verified checks out of the JSON output
+    pub only_unverified: bool,
+    /// List subprojects' checks in the JSON output by prefixed name instead of nesting them
+    pub flat: bool,
+    /// Also write the JSON output to this file
+    pub json_file: Option<&'a Path>,
+    /// Only show this check
+    pub name: Option<String>,
+}
+
 /// Run the status command. Returns true if any displayed check is unverified.
 pub fn run_status(
     project_root: &Path,
     config: &Config,
     cache: &CacheState,
-    json: bool,
-    _detailed: bool,
-    name: Option<String>,
+    options: StatusOptions,
 ) -> Result<bool> {
     let ui = Ui::new(false);
-    let (status_items, has_unverified) =
-        run_status_recursive(project_root, config, cache, &ui, json, 0, &name)?;
+    let subprojects = Subprojects::load(project_root, config, cache.settings())?;
+    let (status_items, has_unverified) = run_status_recursive(
+        project_root,
+        config,
+        cache,
+        &subprojects,
+        &ui,
+        options.json,
+        options.detailed,
+        options.explain,
+        0,
+        &options.name,
+    )?;
 
-    if json {
-        let output = StatusOutput {
-            checks: status_items,
-        };
+    let mut output = StatusOutput::new(status_items);
+    if options.only_unverified {
+        output.retain_unverified();
+    }
+    if options.flat {
+        output.flatten();
+    }
+    if options.json {
         println!("{}", serde_json::to_string_pretty(&output)?);
     }
+    if let Some(path) = options.json_file {
+        write_json_file(path, &output)?;
+    }
 
     Ok(has_unverified)
 }
 
+/// Status of all checks without printing anything, as `verify --json status` would
+/// report it. With `name`, just that check along with its dependencies (for `verify serve`).
+pub fn collect_status(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+    name: Option<String>,
+) -> Result<StatusOutput> {
+    let ui = Ui::new(false);
+    let subprojects = Subprojects::load(project_root, config, cache.settings())?;
+    let (checks, _) = run_status_recursive(
+        project_root,
+        config,
+        cache,
+        &subprojects,
+        &ui,
+        true,
+        false,
+        false,
+        0,
+        &name,
+    )?;
+    Ok(StatusOutput::new(checks))
+}
+
 /// Recursively process status for config and all subprojects.
 /// Returns (status_items, has_unverified).
+#[allow(clippy::too_many_arguments)]
 fn run_status_recursive(
     project_root: &Path,
     config: &Config,
     cache: &CacheState,
+    subprojects: &Subprojects,
     ui: &Ui,
     json: bool,
+    detailed: bool,
+    explain: bool,
     indent: usize,
     filter_name: &Option<String>,
 ) -> Result<(Vec<StatusItemJson>, bool)> {
     let graph = DependencyGraph::from_config(config)?;
-
-    // Track which checks are stale (for dependency propagation)
-    let mut is_stale: HashMap<String, bool> = HashMap::new();
     let mut has_unverified = false;
-
-    // Pre-compute subproject staleness so verifications that depend on them
-    // can correctly determine their own status
-    for item in &config.verifications {
-        if let VerificationItem::Subproject(s) = item {
-            let subproject_dir = project_root.join(&s.path);
-            let sub_config_path = subproject_dir.join("verify.yaml");
-            if sub_config_path.exists() {
-                let sub_config = Config::load_with_base(&sub_config_path, &subproject_dir)?;
-                let sub_cache = CacheState::load(&subproject_dir)?;
-                let has_stale = check_has_stale(&subproject_dir, &sub_config, &sub_cache)?;
-                is_stale.insert(s.name.clone(), has_stale);
-            }
-        }
-    }
-
-    // Process verifications in execution order
-    let waves = graph.execution_waves();
     let mut status_items: Vec<StatusItemJson> = Vec::new();
 
     // Build a map of verification name to position in config for ordering
@@ -243,23 +692,31 @@ fn run_status_recursive(
         verification_order.insert(item.name().to_string(), idx);
     }
 
-    // Process all verifications first (in wave order for dependency propagation)
+    let (statuses, subprojects_stale) =
+        compute_subproject_statuses(project_root, config, cache, subprojects)?;
     let mut verification_statuses: HashMap<String, (VerificationStatus, CheckStatusJson)> =
-        HashMap::new();
-
-    for wave in waves {
-        for name in wave {
-            let check = config.get(&name).unwrap();
-            let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
-            let status = compute_status(check, &hash_result, cache, &is_stale);
-
-            // Record staleness for dependent checks
-            let is_not_verified = !matches!(status, VerificationStatus::Verified);
-            is_stale.insert(name.clone(), is_not_verified);
-
-            let json_item = CheckStatusJson::from_status(&name, &status, cache.get(&name));
-
-            verification_statuses.insert(name.clone(), (status, json_item));
+        statuses
+            .into_iter()
+            .map(|(name, status)| {
+                let json_item = CheckStatusJson::from_status(&name, &status, cache.get(&name));
+                (name, (status, json_item))
+            })
+            .collect();
+
+    // When filtering to a single check, collect the status of its transitive
+    // dependencies so an unverified upstream check isn't hidden from view
+    let mut dependency_chain: Vec<(String, VerificationStatus)> = Vec::new();
+    if let Some(filter) = filter_name {
+        let mut deps: Vec<String> = graph
+            .transitive_dependencies(filter)
+            .into_iter()
+            .filter(|dep| dep != filter)
+            .collect();
+        deps.sort_by_key(|dep| verification_order.get(dep).copied().unwrap_or(usize::MAX));
+        for dep in deps {
+            if let Some((status, _)) = verification_statuses.get(&dep) {
+                dependency_chain.push((dep, status.clone()));
+            }
         }
     }
 
@@ -270,23 +727,53 @@ fn run_status_recursive(
                 // Skip if filtering by name and this isn't the one
                 let show = filter_name.as_ref().is_none_or(|n| n == &v.name);
 
-                let (status, json_item) = verification_statuses.remove(&v.name).unwrap();
+                let (status, mut json_item) = verification_statuses.remove(&v.name).unwrap();
 
                 if show {
-                    if !matches!(status, VerificationStatus::Verified) {
+                    if !status.is_settled() {
                         has_unverified = true;
                     }
 
-                    if json {
-                        status_items.push(StatusItemJson::Check(json_item));
-                    } else {
+                    if !json {
                         let empty = BTreeMap::new();
-                        let metadata = cache
-                            .get(&v.name)
-                            .map(|c| &c.metadata)
-                            .unwrap_or(&empty);
+                        let entry = cache.get(&v.name);
+                        let metadata = entry.map(|c| &c.metadata).unwrap_or(&empty);
                         ui.print_status(&v.name, &status, metadata, indent);
+                        if detailed {
+                            ui.print_check_docs(
+                                v.description.as_deref(),
+                                v.owner.as_deref(),
+                                indent,
+                            );
+                        }
+                        if detailed
+                            && matches!(status, VerificationStatus::Verified)
+                            && let Some(by) = entry.and_then(|c| c.verified_by.as_ref())
+                        {
+                            ui.print_verified_by(by, indent);
+                        }
+
+                        if explain {
+                            for (dep, dep_status) in &dependency_chain {
+                                ui.print_status(dep, dep_status, &BTreeMap::new(), indent + 1);
+                            }
+                        }
+                    }
+
+                    // JSON items are collected even for human output, for --json-file
+                    json_item.description = v.description.clone();
+                    json_item.owner = v.owner.clone();
+                    if filter_name.is_some() {
+                        json_item.dependencies = Some(
+                            dependency_chain
+                                .iter()
+                                .map(|(dep, dep_status)| {
+                                    CheckStatusJson::from_status(dep, dep_status, cache.get(dep))
+                                })
+                                .collect(),
+                        );
                     }
+                    status_items.push(StatusItemJson::Check(json_item));
                 }
             }
             VerificationItem::Subproject(s) => {
@@ -295,19 +782,26 @@ fn run_status_recursive(
                     continue;
                 }
 
-                let (sub_items, sub_unverified) =
-                    run_status_subproject(project_root, s, ui, json, indent)?;
+                let has_stale = subprojects_stale.get(&s.name).copied().unwrap_or(true);
+                let (sub_items, sub_unverified) = run_status_subproject(
+                    s,
+                    subprojects.get(&s.name)?,
+                    has_stale,
+                    ui,
+                    json,
+                    detailed,
+                    indent,
+                )?;
                 if sub_unverified {
                     has_unverified = true;
                 }
 
-                if json {
-                    status_items.push(StatusItemJson::Subproject(SubprojectStatusJson::new(
-                        &s.name,
-                        s.path.to_string_lossy().as_ref(),
-                        sub_items,
-                    )));
-                }
+                status_items.push(StatusItemJson::Subproject(SubprojectStatusJson::new(
+                    &s.name,
+                    s.path.to_string_lossy().as_ref(),
+                    &s.prefix(),
+                    sub_items,
+                )));
             }
         }
     }
@@ -315,113 +809,452 @@ fn run_status_recursive(
     Ok((status_items, has_unverified))
 }
 
-/// Run status for a subproject. Returns (status_items, has_unverified).
-fn run_status_subproject(
-    parent_root: &Path,
-    subproject: &Subproject,
-    ui: &Ui,
-    json: bool,
-    indent: usize,
-) -> Result<(Vec<StatusItemJson>, bool)> {
-    let subproject_dir = parent_root.join(&subproject.path);
-    let subproject_config_path = subproject_dir.join("verify.yaml");
-
-    let sub_config = Config::load_with_base(&subproject_config_path, &subproject_dir)?;
-    let sub_cache = CacheState::load(&subproject_dir)?;
-
-    // For human output, print subproject header
-    if !json {
-        // Determine if subproject has any stale checks
-        let has_stale = check_has_stale(&subproject_dir, &sub_config, &sub_cache)?;
-        ui.print_subproject_header(&subproject.name, indent, has_stale);
-    }
-
-    // Recursively process subproject (no name filtering within subprojects)
-    run_status_recursive(
-        &subproject_dir,
-        &sub_config,
-        &sub_cache,
-        ui,
-        json,
-        indent + 1,
-        &None,
-    )
+/// Compute the status of every check in a config, in wave order so that dependency
+/// staleness propagates. Also returns whether each subproject has unverified checks.
+pub fn compute_statuses(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+) -> Result<(HashMap<String, VerificationStatus>, HashMap<String, bool>)> {
+    let subprojects = Subprojects::load(project_root, config, cache.settings())?;
+    compute_subproject_statuses(project_root, config, cache, &subprojects)
 }
 
-/// Check if a config has any unverified checks
-fn check_has_stale(project_root: &Path, config: &Config, cache: &CacheState) -> Result<bool> {
+/// `compute_statuses` with the config's subprojects already loaded
+fn compute_subproject_statuses(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+    subprojects: &Subprojects,
+) -> Result<(HashMap<String, VerificationStatus>, HashMap<String, bool>)> {
     let graph = DependencyGraph::from_config(config)?;
+
+    // Track which checks are stale (for dependency propagation)
     let mut is_stale: HashMap<String, bool> = HashMap::new();
 
     // Pre-compute subproject staleness so verifications that depend on them
     // can correctly determine their own status
+    let mut subprojects_stale: HashMap<String, bool> = HashMap::new();
     for subproject in config.subprojects() {
-        let subproject_dir = project_root.join(&subproject.path);
-        let sub_config_path = subproject_dir.join("verify.yaml");
-        if sub_config_path.exists() {
-            let sub_config = Config::load_with_base(&sub_config_path, &subproject_dir)?;
-            let sub_cache = CacheState::load(&subproject_dir)?;
-            let has_stale = check_has_stale(&subproject_dir, &sub_config, &sub_cache)?;
-            is_stale.insert(subproject.name.clone(), has_stale);
-        }
+        let has_stale = check_has_stale(subprojects.get(&subproject.name)?)?;
+        is_stale.insert(subproject.name.clone(), has_stale);
+        subprojects_stale.insert(subproject.name.clone(), has_stale);
     }
 
+    let mut statuses: HashMap<String, VerificationStatus> = HashMap::new();
     for wave in graph.execution_waves() {
         for name in wave {
-            if let Some(check) = config.get(&name) {
-                let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
-                let status = compute_status(check, &hash_result, cache, &is_stale);
-                let stale = !matches!(status, VerificationStatus::Verified);
-                is_stale.insert(name.clone(), stale);
-                if stale {
-                    return Ok(true);
-                }
-            }
+            let Some(check) = config.get(&name) else {
+                continue;
+            };
+            let hash_result = check.compute_hash(project_root)?;
+            let status = compute_status(check, &hash_result, cache, &is_stale);
+
+            // Record staleness for dependent checks
+            let is_not_verified = !status.is_settled();
+            is_stale.insert(name.clone(), is_not_verified);
+            statuses.insert(name, status);
         }
     }
 
-    // Check if any subprojects are stale (already computed above)
+    Ok((statuses, subprojects_stale))
+}
+
+/// Something that has to run before a check can be verified
+pub enum Blocker {
+    Check(String, VerificationStatus),
+    /// A subproject dependency with unverified checks
+    Subproject(String),
+}
+
+/// The minimal set of checks and subprojects that have to run for `name` to become
+/// verified, in the order `verify run` runs them: the unverified checks among it and its
+/// transitive dependencies (aggregates have nothing to run, unless their own cache_paths
+/// changed), after any stale subprojects they depend on.
+pub fn why_blockers(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+    name: &str,
+) -> Result<Vec<Blocker>> {
+    let graph = DependencyGraph::from_config(config)?;
+    let (mut statuses, subprojects_stale) = compute_statuses(project_root, config, cache)?;
+    let closure = graph.transitive_dependencies(name);
+
+    let mut blockers = Vec::new();
     for subproject in config.subprojects() {
-        if is_stale.get(&subproject.name).copied().unwrap_or(true) {
-            return Ok(true);
+        if closure.contains(&subproject.name)
+            && subprojects_stale
+                .get(&subproject.name)
+                .copied()
+                .unwrap_or(true)
+        {
+            blockers.push(Blocker::Subproject(subproject.name.clone()));
+        }
+    }
+    for wave in graph.execution_waves() {
+        for check in wave {
+            let is_plain_aggregate =
+                |c: &Verification| c.command.is_none() && c.cache_paths.is_empty();
+            if !closure.contains(&check) || config.get(&check).is_none_or(is_plain_aggregate) {
+                continue;
+            }
+            if let Some(status) = statuses.remove(&check)
+                && !status.is_settled()
+            {
+                blockers.push(Blocker::Check(check, status));
+            }
         }
     }
+    Ok(blockers)
+}
 
-    Ok(false)
+/// The stale checks and subprojects that running `names` runs first (those not named
+/// themselves), in run order, with the last recorded duration of each
+pub fn stale_dependencies(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+    names: &[String],
+) -> Result<Vec<(String, Option<u64>)>> {
+    let mut dependencies: Vec<(String, Option<u64>)> = Vec::new();
+    for name in names {
+        for blocker in why_blockers(project_root, config, cache, name)? {
+            let dependency = match blocker {
+                Blocker::Check(check, _) => check,
+                Blocker::Subproject(subproject) => subproject,
+            };
+            if names.contains(&dependency) || dependencies.iter().any(|(d, _)| *d == dependency) {
+                continue;
+            }
+            let estimate = cache.get(&dependency).and_then(|c| c.duration_ms);
+            dependencies.push((dependency, estimate));
+        }
+    }
+    Ok(dependencies)
 }
 
-/// Validate HEAD commit trailer against current file state.
-/// Returns true if any check is unverified (trailer mismatch or missing).
-pub fn run_check_trailer(
+/// Run the why command: print what has to run for `name` to become verified, and the
+/// `verify run` command that runs it
+/// Describe a check for `verify explain`: what it does and who owns it, what it runs,
+/// the files it reads and what it depends on, with its current status.
+pub fn run_explain(
     project_root: &Path,
     config: &Config,
+    cache: &CacheState,
+    name: &str,
     json: bool,
-    name: Option<String>,
-) -> Result<bool> {
-    let ui = Ui::new(false);
-
-    // Read trailer from HEAD
-    let trailer_hashes = crate::trailer::read_trailer(project_root)?;
-
-    // Compute expected hashes from current files (excludes aggregates)
-    let expected_hashes = crate::trailer::compute_all_expected_hashes(project_root, config)?;
+) -> Result<()> {
+    let Some(check) = config.get(name) else {
+        anyhow::bail!("Unknown check: {}", name);
+    };
+    let (statuses, _) = compute_statuses(project_root, config, cache)?;
+    let status = statuses
+        .get(name)
+        .cloned()
+        .unwrap_or(VerificationStatus::Untracked);
+    let output = ExplainOutput {
+        name: name.to_string(),
+        description: check.description.clone(),
+        owner: check.owner.clone(),
+        command: check.command.as_deref().map(String::from),
+        cache_paths: check.cache_paths.clone(),
+        depends_on: check.depends_on.clone(),
+        status: CheckStatusJson::from_status(name, &status, cache.get(name)),
+    };
 
-    let graph = DependencyGraph::from_config(config)?;
-    let waves = graph.execution_waves();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
 
-    let mut has_unverified = false;
-    let mut status_items: Vec<StatusItemJson> = Vec::new();
-    // Track which checks are verified so composites can resolve from deps
-    let mut verified_checks: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let ui = Ui::new(false);
+    let empty = BTreeMap::new();
+    let metadata = cache.get(name).map(|c| &c.metadata).unwrap_or(&empty);
+    ui.print_status(name, &status, metadata, 0);
+    ui.print_check_docs(check.description.as_deref(), check.owner.as_deref(), 0);
+    ui.print_explain(&output);
+    Ok(())
+}
 
-    for wave in waves {
+pub fn run_why(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+    name: &str,
+    json: bool,
+) -> Result<()> {
+    let ui = Ui::new(false);
+    let blockers = why_blockers(project_root, config, cache, name)?;
+    // `verify run` takes check names; running a check runs the subprojects it depends on
+    let command = (!blockers.is_empty()).then(|| {
+        let mut names: Vec<&str> = blockers
+            .iter()
+            .filter_map(|blocker| match blocker {
+                Blocker::Check(check, _) => Some(check.as_str()),
+                Blocker::Subproject(_) => None,
+            })
+            .collect();
+        if names.is_empty() {
+            names.push(name);
+        }
+        format!("verify run {}", names.join(" "))
+    });
+
+    if json {
+        let output = WhyOutput {
+            check: name.to_string(),
+            verified: blockers.is_empty(),
+            blockers: blockers
+                .iter()
+                .map(|blocker| match blocker {
+                    Blocker::Check(check, status) => {
+                        CheckStatusJson::from_status(check, status, cache.get(check))
+                    }
+                    Blocker::Subproject(subproject) => {
+                        CheckStatusJson::unverified_subproject(subproject)
+                    }
+                })
+                .collect(),
+            command,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let Some(command) = command else {
+        ui.print_status(name, &VerificationStatus::Verified, &BTreeMap::new(), 0);
+        return Ok(());
+    };
+    ui.print_why_header(name);
+    for blocker in &blockers {
+        match blocker {
+            Blocker::Check(check, status) => ui.print_status(check, status, &BTreeMap::new(), 0),
+            Blocker::Subproject(subproject) => ui.print_subproject_header(subproject, 0, true),
+        }
+    }
+    ui.print_run_suggestion(&command);
+    Ok(())
+}
+
+/// Run status for a subproject, given whether it has any stale checks.
+/// Returns (status_items, has_unverified).
+fn run_status_subproject(
+    subproject: &Subproject,
+    tree: &ProjectTree,
+    has_stale: bool,
+    ui: &Ui,
+    json: bool,
+    detailed: bool,
+    indent: usize,
+) -> Result<(Vec<StatusItemJson>, bool)> {
+    // For human output, print subproject header
+    if !json {
+        ui.print_subproject_header(&subproject.name, indent, has_stale);
+    }
+
+    // Recursively process subproject (no name filtering within subprojects)
+    run_status_recursive(
+        &tree.dir,
+        &tree.config,
+        &tree.cache,
+        &tree.subprojects,
+        ui,
+        json,
+        detailed,
+        false,
+        indent + 1,
+        &None,
+    )
+}
+
+/// Check if a subproject has any unverified checks
+fn check_has_stale(tree: &ProjectTree) -> Result<bool> {
+    let ProjectTree {
+        dir: project_root,
+        config,
+        cache,
+        subprojects,
+        ..
+    } = tree;
+    let graph = DependencyGraph::from_config(config)?;
+    let mut is_stale: HashMap<String, bool> = HashMap::new();
+
+    // Pre-compute subproject staleness so verifications that depend on them
+    // can correctly determine their own status
+    for subproject in config.subprojects() {
+        let has_stale = check_has_stale(subprojects.get(&subproject.name)?)?;
+        is_stale.insert(subproject.name.clone(), has_stale);
+    }
+
+    for wave in graph.execution_waves() {
+        for name in wave {
+            if let Some(check) = config.get(&name) {
+                let hash_result = check.compute_hash(project_root)?;
+                let status = compute_status(check, &hash_result, cache, &is_stale);
+                let stale = !status.is_settled();
+                is_stale.insert(name.clone(), stale);
+                if stale {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    // Check if any subprojects are stale (already computed above)
+    for subproject in config.subprojects() {
+        if is_stale.get(&subproject.name).copied().unwrap_or(true) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Validate a Verified trailer (usually HEAD's) against the file state under project_root.
+/// Returns true if any check is unverified (trailer mismatch or missing).
+pub fn run_check_trailer(
+    project_root: &Path,
+    config: &Config,
+    trailer_hashes: Option<BTreeMap<String, String>>,
+    json: bool,
+    name: Option<String>,
+) -> Result<bool> {
+    let ui = Ui::new(false);
+
+    // Compute expected hashes from current files (excludes aggregates)
+    let expected_hashes = crate::trailer::compute_all_expected_hashes(project_root, config)?;
+
+    let (status_items, has_unverified) = check_trailer_recursive(
+        project_root,
+        config,
+        &expected_hashes,
+        trailer_hashes.as_ref(),
+        config.trailer_hash_length(),
+        "",
+        name.as_deref(),
+        &ui,
+        json,
+        true,
+        0,
+    )?;
+
+    if json {
+        let output = StatusOutput::new(status_items);
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(has_unverified)
+}
+
+/// The status of every check against a Verified trailer, as `verify --json check` reports
+/// it, and whether any is unverified
+pub fn collect_trailer_status(
+    project_root: &Path,
+    config: &Config,
+    trailer_hashes: Option<&BTreeMap<String, String>>,
+) -> Result<(StatusOutput, bool)> {
+    let expected_hashes = crate::trailer::compute_all_expected_hashes(project_root, config)?;
+    let (checks, has_unverified) = check_trailer_recursive(
+        project_root,
+        config,
+        &expected_hashes,
+        trailer_hashes,
+        config.trailer_hash_length(),
+        "",
+        None,
+        &Ui::new(false),
+        true,
+        true,
+        0,
+    )?;
+    Ok((StatusOutput::new(checks), has_unverified))
+}
+
+/// Compare the checks of one project against the trailer, where the hashes of checks in
+/// subprojects are keyed `prefix/name`. Prints (or collects) their status when `show`,
+/// noting checks that only matched on a hash shorter than `hash_length`.
+/// Returns the JSON items and whether any check (only `name`, if given) is unverified.
+#[allow(clippy::too_many_arguments)]
+fn check_trailer_recursive(
+    project_root: &Path,
+    config: &Config,
+    expected_hashes: &BTreeMap<String, String>,
+    trailer_hashes: Option<&BTreeMap<String, String>>,
+    hash_length: usize,
+    prefix: &str,
+    name: Option<&str>,
+    ui: &Ui,
+    json: bool,
+    show: bool,
+    indent: usize,
+) -> Result<(Vec<StatusItemJson>, bool)> {
+    let graph = DependencyGraph::from_config(config)?;
+    let waves = graph.execution_waves();
+
+    let mut has_unverified = false;
+    let mut status_items: Vec<StatusItemJson> = Vec::new();
+    // Track which checks are verified so composites can resolve from deps
+    let mut verified_checks: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    // Subprojects first, so checks that depend on them can resolve
+    for subproject in config.subprojects() {
+        let subproject_dir = project_root.join(&subproject.path);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        let sub_prefix = format!("{}{}/", prefix, subproject.prefix());
+        let selected = name.is_none_or(|filter| filter == subproject.name);
+        let show_subproject = show && selected;
+        if show_subproject && !json {
+            let has_stale = expected_hashes
+                .iter()
+                .filter(|(key, _)| key.starts_with(&sub_prefix))
+                .any(|(key, hash)| {
+                    !trailer_hashes
+                        .and_then(|m| m.get(key))
+                        .is_some_and(|value| crate::trailer::hash_matches(value, hash))
+                });
+            ui.print_subproject_header(&subproject.name, indent, has_stale);
+        }
+        let (sub_items, sub_unverified) = check_trailer_recursive(
+            &subproject_dir,
+            &sub_config,
+            expected_hashes,
+            trailer_hashes,
+            hash_length,
+            &sub_prefix,
+            None,
+            ui,
+            json,
+            show_subproject,
+            indent + 1,
+        )?;
+        if !sub_unverified {
+            verified_checks.insert(subproject.name.clone());
+        }
+        if selected {
+            has_unverified |= sub_unverified;
+        }
+        if show_subproject && json {
+            status_items.push(StatusItemJson::Subproject(SubprojectStatusJson::new(
+                &subproject.name,
+                subproject.path.to_string_lossy().as_ref(),
+                &subproject.prefix(),
+                sub_items,
+            )));
+        }
+    }
+
+    for wave in waves {
         for check_name in wave {
             let check = match config.get(&check_name) {
                 Some(v) => v,
-                None => continue, // subproject, skip
+                None => continue, // subproject, handled above
             };
 
             let is_composite = check.command.is_none();
+            // Length of a matching trailer hash shorter than configured
+            let mut short_match = None;
 
             let (is_verified, reason): (bool, Option<UnverifiedReason>) = if is_composite {
                 // Composite check: verified iff all dependencies are verified
@@ -440,7 +1273,8 @@ pub fn run_check_trailer(
                 }
             } else {
                 // Regular check: compare expected hash against trailer
-                let expected = match expected_hashes.get(&check_name) {
+                let key = format!("{}{}", prefix, check_name);
+                let expected = match expected_hashes.get(&key) {
                     Some(h) => h,
                     None => {
                         // Untracked check (no cache_paths), skip
@@ -448,14 +1282,15 @@ pub fn run_check_trailer(
                     }
                 };
 
-                let truncated_expected = crate::trailer::truncate_hash(expected);
+                let trailer_value = trailer_hashes.and_then(|m| m.get(&key)).map(|s| s.as_str());
 
-                let trailer_value = trailer_hashes
-                    .as_ref()
-                    .and_then(|m| m.get(&check_name))
-                    .map(|s| s.as_str());
-
-                let matched = trailer_value == Some(truncated_expected);
+                let matched = trailer_value
+                    .is_some_and(|value| crate::trailer::hash_matches(value, expected));
+                if let Some(value) =
+                    trailer_value.filter(|value| matched && value.len() < hash_length)
+                {
+                    short_match = Some(value.len());
+                }
                 let reason = if !matched {
                     if trailer_value.is_none() {
                         Some(UnverifiedReason::NeverRun)
@@ -475,15 +1310,16 @@ pub fn run_check_trailer(
             }
 
             // Skip if filtering and not the requested check
-            if let Some(ref filter) = name {
-                if filter != &check_name {
-                    continue;
-                }
+            if name.is_some_and(|filter| filter != check_name) {
+                continue;
             }
 
             if !is_verified {
                 has_unverified = true;
             }
+            if !show {
+                continue;
+            }
 
             let status = if is_verified {
                 VerificationStatus::Verified
@@ -494,66 +1330,335 @@ pub fn run_check_trailer(
             };
 
             if json {
-                let json_item = CheckStatusJson::from_status(&check_name, &status, None);
+                let mut json_item = CheckStatusJson::from_status(&check_name, &status, None);
+                if let Some(length) = short_match {
+                    json_item.hint = Some(format!(
+                        "matched on only {} trailer hash characters, so a collision can't be \
+                         ruled out; re-sign to use {}",
+                        length, hash_length
+                    ));
+                }
                 status_items.push(StatusItemJson::Check(json_item));
             } else {
-                ui.print_status(&check_name, &status, &BTreeMap::new(), 0);
+                ui.print_status(&check_name, &status, &BTreeMap::new(), indent);
+                if let Some(length) = short_match {
+                    ui.print_short_trailer_hash(length, indent);
+                }
             }
         }
     }
 
-    if json {
-        let output = StatusOutput {
-            checks: status_items,
+    Ok((status_items, has_unverified))
+}
+
+/// Find the commit whose changes invalidated a check.
+/// Walks HEAD's first-parent history in a temporary copy of the repository back to the most
+/// recent commit whose Verified trailer matches the check and its dependencies, then reports
+/// the first later commit where any of their hashes changed.
+/// Returns true if a verified commit was found within max_depth commits.
+pub fn run_bisect(
+    project_root: &Path,
+    config_file: &Path,
+    name: &str,
+    max_depth: usize,
+    json: bool,
+) -> Result<bool> {
+    let ui = Ui::new(false);
+    let history = crate::trailer::read_history(project_root, max_depth)?;
+    let mut tree = crate::patch::PatchedTree::at_head(project_root)?;
+
+    // Hashes of the check and its dependencies at each visited commit, newest first
+    let mut states: Vec<BTreeMap<String, String>> = Vec::new();
+    let mut last_verified = None;
+
+    for (index, (commit, _)) in history.iter().enumerate() {
+        tree.checkout(commit)?;
+        let Some(state) = bisect_state(tree.project_root(), config_file, name)? else {
+            // The check doesn't exist this far back
+            break;
         };
-        println!("{}", serde_json::to_string_pretty(&output)?);
+        if index == 0 && state.is_empty() {
+            anyhow::bail!(
+                "Check '{}' has no tracked cache_paths, so it is never verified",
+                name
+            );
+        }
+
+        let verified = tree.trailer().is_some_and(|trailer| {
+            state.iter().all(|(check_name, hash)| {
+                trailer
+                    .get(check_name)
+                    .is_some_and(|value| crate::trailer::hash_matches(value, hash))
+            })
+        });
+        states.push(state);
+
+        if verified {
+            last_verified = Some(index);
+            break;
+        }
     }
 
-    Ok(has_unverified)
+    let commit_json = |index: usize| {
+        let (commit, subject) = &history[index];
+        CommitJson {
+            commit: commit.clone(),
+            subject: subject.clone(),
+        }
+    };
+
+    let mut result = BisectOutput {
+        check: name.to_string(),
+        last_verified: last_verified.map(commit_json),
+        invalidated_by: None,
+        changed_checks: Vec::new(),
+    };
+
+    if let Some(verified_index) = last_verified {
+        // Walk forward from the verified commit to the first one with different hashes
+        let baseline = &states[verified_index];
+        if let Some(index) = (0..verified_index).rev().find(|i| &states[*i] != baseline) {
+            let state = &states[index];
+            let mut changed: Vec<String> = baseline
+                .keys()
+                .chain(state.keys())
+                .filter(|check_name| baseline.get(*check_name) != state.get(*check_name))
+                .cloned()
+                .collect();
+            changed.sort();
+            changed.dedup();
+
+            result.invalidated_by = Some(commit_json(index));
+            result.changed_checks = changed;
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        ui.print_bisect(&result, max_depth);
+    }
+
+    Ok(last_verified.is_some())
+}
+
+/// Expected hashes of a check and its transitive dependencies (regular checks with
+/// cache_paths only), keyed by check name.
+/// Returns None if the config can't be loaded or doesn't define the check.
+fn bisect_state(
+    project_root: &Path,
+    config_file: &Path,
+    name: &str,
+) -> Result<Option<BTreeMap<String, String>>> {
+    let Ok(config) = Config::load(&project_root.join(config_file)) else {
+        return Ok(None);
+    };
+    if config.get(name).is_none() {
+        return Ok(None);
+    }
+    let Ok(graph) = DependencyGraph::from_config(&config) else {
+        return Ok(None);
+    };
+
+    let mut state = BTreeMap::new();
+    for check_name in graph.transitive_dependencies(name) {
+        if let Some(check) = config.get(&check_name)
+            && check.command.is_some()
+            && !check.cache_paths.is_empty()
+        {
+            let hash = crate::trailer::compute_expected_hash(project_root, check)?;
+            state.insert(check_name, hash);
+        }
+    }
+    Ok(Some(state))
 }
 
 /// Sync cache from git commit trailer history.
-/// Searches recent commits for a Verified trailer and seeds the lock file
-/// for checks whose current file state matches the trailer hashes.
-/// Returns true if any checks were synced.
+/// Searches `depth` commits of `revisions` (HEAD when empty) for Verified trailers and
+/// seeds the lock file for checks whose current file state matches any of their hashes.
+/// Returns which tracked checks were synced, didn't match, or weren't in the trailer.
 pub fn run_sync(
     project_root: &Path,
     config: &Config,
     cache: &mut CacheState,
+    depth: usize,
+    revisions: &[&str],
     json: bool,
     verbose: bool,
-) -> Result<bool> {
+) -> Result<SyncSummary> {
     let ui = Ui::new(verbose);
 
-    // Search recent history for a trailer
-    let trailer_hashes = crate::trailer::read_trailer_from_history(project_root, 50)?;
-
-    let trailer_hashes = match trailer_hashes {
-        Some(h) => h,
-        None => {
-            if !json {
-                eprintln!("No Verified trailer found in recent history");
-            }
-            return Ok(false);
+    // Merge every trailer in the searched history, keeping each check's hashes newest
+    // first, so a check can match an older commit when a later one re-signed it differently
+    let trailers = crate::trailer::read_trailers_from_history(project_root, depth, revisions)?;
+    let mut trailer_hashes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for trailer in &trailers {
+        for (name, hash) in trailer {
+            trailer_hashes
+                .entry(name.clone())
+                .or_default()
+                .push(hash.clone());
         }
-    };
+    }
 
-    if verbose {
+    // Without a trailer, every tracked check counts as missing from it
+    let trailer_found = !trailers.is_empty();
+    if !trailer_found && !json {
+        eprintln!("No Verified trailer found in the last {} commits", depth);
+    }
+
+    if verbose && trailer_found {
         eprintln!("Trailer hashes found: {:?}", trailer_hashes);
     }
 
+    let mut summary = SyncSummary::default();
+    let status_items = sync_recursive(
+        project_root,
+        config,
+        cache,
+        &trailer_hashes,
+        "",
+        &mut summary,
+        &ui,
+        json,
+        verbose,
+        0,
+    )?;
+
+    if json {
+        let output = SyncOutput {
+            checks: status_items,
+            summary,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(output.summary);
+    }
+    if trailer_found {
+        if summary.synced.is_empty() {
+            eprintln!("No checks matched the trailer");
+        }
+        if !summary.mismatched.is_empty() {
+            eprintln!(
+                "Didn't match the trailer: {}",
+                summary.mismatched.join(", ")
+            );
+        }
+        if !summary.skipped.is_empty() {
+            eprintln!("Not in the trailer: {}", summary.skipped.join(", "));
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Onboard a fresh clone: sync from the trailers in the last `depth` commits, then list
+/// the checks (subprojects' included) that are still unverified and, with `run`, run them.
+/// Returns the exit code: the run's, or without `run` 1 when anything is unverified.
+pub fn run_bootstrap(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    depth: usize,
+    run: bool,
+    verbose: bool,
+) -> Result<i32> {
+    let summary = run_sync(project_root, config, cache, depth, &[], false, verbose)?;
+
+    let mut status = collect_status(project_root, config, cache, None)?;
+    status.retain_unverified();
+    status.flatten();
+    let remaining: Vec<String> = status
+        .into_checks()
+        .into_iter()
+        .map(|check| check.name)
+        .collect();
+    if remaining.is_empty() {
+        eprintln!("All checks are verified");
+        return Ok(0);
+    }
+    eprintln!(
+        "Synced {} check(s) from trailers; still unverified: {}",
+        summary.synced.len(),
+        remaining.join(", ")
+    );
+    if !run {
+        eprintln!("Run them with `verify bootstrap --run` or `verify run`");
+        return Ok(1);
+    }
+
+    // A plain run only executes what isn't verified, which is just what's left
+    let (exit_code, _) = run_checks(
+        project_root,
+        config,
+        cache,
+        vec![],
+        RunOptions {
+            verbose,
+            ..Default::default()
+        },
+    )?;
+    Ok(exit_code)
+}
+
+/// Seed one project's cache from the trailer, where checks in subprojects are keyed
+/// `prefix/name`, recording the outcome of each check in `summary` by that key.
+/// Returns the JSON items for the project.
+#[allow(clippy::too_many_arguments)]
+fn sync_recursive(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    trailer_hashes: &BTreeMap<String, Vec<String>>,
+    prefix: &str,
+    summary: &mut SyncSummary,
+    ui: &Ui,
+    json: bool,
+    verbose: bool,
+    indent: usize,
+) -> Result<Vec<StatusItemJson>> {
     let graph = DependencyGraph::from_config(config)?;
     let waves = graph.execution_waves();
 
-    let mut synced_count = 0u32;
     let mut verified_checks: std::collections::HashSet<String> = std::collections::HashSet::new();
     let mut status_items: Vec<StatusItemJson> = Vec::new();
+    let mut synced = false;
+
+    // Subprojects first, so checks that depend on them can resolve
+    for subproject in config.subprojects() {
+        let subproject_dir = project_root.join(&subproject.path);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        let mut sub_cache = CacheState::load(&subproject_dir, cache.settings())?;
+        if !json {
+            ui.print_subproject_header(&subproject.name, indent, false);
+        }
+        let sub_items = sync_recursive(
+            &subproject_dir,
+            &sub_config,
+            &mut sub_cache,
+            trailer_hashes,
+            &format!("{}{}/", prefix, subproject.prefix()),
+            summary,
+            ui,
+            json,
+            verbose,
+            indent + 1,
+        )?;
+        if json {
+            status_items.push(StatusItemJson::Subproject(SubprojectStatusJson::new(
+                &subproject.name,
+                subproject.path.to_string_lossy().as_ref(),
+                &subproject.prefix(),
+                sub_items,
+            )));
+        }
+    }
 
     for wave in waves {
         for check_name in wave {
             let check = match config.get(&check_name) {
                 Some(v) => v,
-                None => continue, // subproject, skip
+                None => continue, // subproject, handled above
             };
 
             // Aggregate checks: verified iff all dependencies are verified
@@ -575,25 +1680,31 @@ pub fn run_sync(
 
             // Compute current hashes from files on disk
             let config_hash = check.config_hash();
-            let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
-            let combined = crate::trailer::compute_combined_hash(&config_hash, &hash_result.combined_hash);
-            let truncated = crate::trailer::truncate_hash(&combined);
+            let hash_result = check.compute_hash(project_root)?;
+            let combined =
+                crate::trailer::compute_combined_hash(&config_hash, &hash_result.combined_hash);
+            let truncated = crate::trailer::truncate_hash(&combined, config.trailer_hash_length());
 
-            let trailer_value = trailer_hashes.get(&check_name).map(|s| s.as_str());
+            let key = format!("{}{}", prefix, check_name);
+            let trailer_values = trailer_hashes.get(&key);
 
             if verbose {
                 eprintln!(
                     "  {} trailer={} computed={} config_hash={} content_hash={}",
-                    check_name,
-                    trailer_value.unwrap_or("(missing)"),
+                    key,
+                    trailer_values.map_or("(missing)".to_string(), |values| values.join(",")),
                     truncated,
                     &config_hash[..8],
                     &hash_result.combined_hash[..8],
                 );
             }
 
-            if trailer_value == Some(truncated) {
-                // Trailer matches — seed the cache entry
+            if trailer_values.is_some_and(|values| {
+                values
+                    .iter()
+                    .any(|value| crate::trailer::hash_matches(value, &combined))
+            }) {
+                // A trailer matches — seed the cache entry
                 let file_hashes = if check.per_file {
                     hash_result.file_hashes.clone()
                 } else {
@@ -611,78 +1722,734 @@ pub fn run_sync(
                 );
 
                 verified_checks.insert(check_name.clone());
-                synced_count += 1;
+                synced = true;
+                summary.synced.push(key);
 
                 if json {
                     let status = VerificationStatus::Verified;
                     let json_item = CheckStatusJson::from_status(&check_name, &status, None);
                     status_items.push(StatusItemJson::Check(json_item));
                 } else {
-                    ui.print_status(&check_name, &VerificationStatus::Verified, &BTreeMap::new(), 0);
+                    ui.print_status(
+                        &check_name,
+                        &VerificationStatus::Verified,
+                        &BTreeMap::new(),
+                        indent,
+                    );
                 }
+            } else if trailer_values.is_some() {
+                summary.mismatched.push(key);
+            } else {
+                summary.skipped.push(key);
             }
         }
     }
 
-    if synced_count > 0 {
-        cache.save(project_root)?;
+    if synced {
+        cache.save(project_root)?;
+    }
+
+    Ok(status_items)
+}
+
+/// Record a check as verified at its current files without running it, for checks
+/// verified outside verify (e.g. manual QA). Requires `allow_mark_verified` in the config.
+pub fn mark_verified(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    name: &str,
+    because: &str,
+) -> Result<MarkedVerified> {
+    if !config.allow_mark_verified {
+        anyhow::bail!("mark-verified is disabled; set allow_mark_verified: true in verify.yaml");
+    }
+    let Some(check) = config.get(name) else {
+        anyhow::bail!("Unknown check: {}", name);
+    };
+    if check.command.is_none() {
+        anyhow::bail!(
+            "'{}' is an aggregate check; mark its dependencies verified instead",
+            name
+        );
+    }
+    if check.cache_paths.is_empty() {
+        anyhow::bail!(
+            "'{}' has no cache_paths, so there are no files to mark verified",
+            name
+        );
+    }
+
+    let hash_result = check.compute_hash(project_root)?;
+    let marked = MarkedVerified {
+        by: current_user(project_root),
+        at: chrono::Utc::now(),
+        because: because.to_string(),
+    };
+    cache.mark_verified(
+        name,
+        check.config_hash(),
+        hash_result.combined_hash,
+        hash_result.file_hashes,
+        check.per_file,
+        marked.clone(),
+    );
+    cache.record_config_components(name, check.config_components());
+    cache.save(project_root)?;
+
+    let recorded = history::append(
+        project_root,
+        &RunRecord {
+            timestamp: marked.at,
+            message: Some(marked.because.clone()),
+            checks: vec![name.to_string()],
+            total: 1,
+            passed: 1,
+            failed: 0,
+            skipped: 0,
+            blocked: 0,
+            duration_ms: 0,
+            marked_by: Some(marked.by.clone()),
+            user: None,
+            host: None,
+        },
+    );
+    warn_unrecorded("run history", recorded);
+    Ok(marked)
+}
+
+/// The git user ("Name <email>"), falling back to the login name
+fn current_user(project_root: &Path) -> String {
+    let git_config = |key: &str| {
+        Command::new("git")
+            .args(["config", key])
+            .current_dir(project_root)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    match (git_config("user.name"), git_config("user.email")) {
+        (Some(name), Some(email)) => format!("{} <{}>", name, email),
+        (Some(name), None) => name,
+        (None, Some(email)) => email,
+        (None, None) => std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string()),
+    }
+}
+
+/// The current user and hostname, for `record_user`
+fn current_attribution(project_root: &Path) -> Attribution {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| {
+            Command::new("hostname")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .filter(|host| !host.is_empty());
+    Attribution {
+        user: current_user(project_root),
+        host,
+    }
+}
+
+/// Record a check execution in the audit log, if `audit_log` is set, and send it to
+/// the `events` sinks
+#[allow(clippy::too_many_arguments)]
+fn audit_execution(
+    settings: &Settings,
+    project_root: &Path,
+    check: &Verification,
+    content_hash: &str,
+    config_hash: &str,
+    passed: bool,
+    exit_code: Option<i32>,
+    duration_ms: u64,
+) -> Result<()> {
+    if settings.audit_log.is_none() && settings.events.is_empty() {
+        return Ok(());
+    }
+    let Attribution { user, host } = current_attribution(project_root);
+    events::emit(
+        &settings.events,
+        &settings.root,
+        project_root,
+        events::CheckEvent::finished(
+            &check.name,
+            passed,
+            exit_code,
+            duration_ms,
+            user.clone(),
+            host.clone(),
+        ),
+    );
+    let Some(path) = &settings.audit_log else {
+        return Ok(());
+    };
+    let log = audit::AuditLog {
+        root: &settings.root,
+        path,
+    };
+    log.append(
+        project_root,
+        audit::AuditRecord {
+            at: chrono::Utc::now(),
+            check: check.name.clone(),
+            user,
+            host,
+            content_hash: content_hash.to_string(),
+            config_hash: config_hash.to_string(),
+            passed,
+            exit_code,
+            duration_ms,
+        },
+    )
+}
+
+/// Import verification state from a cache bundle.
+/// Only entries whose config and content hashes match the current tree are
+/// imported, so a bundle from a different revision can't mark changed checks
+/// as verified. Run history and cache stats are merged in regardless.
+/// Returns the number of imported checks.
+pub fn run_cache_import(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    bundle_path: &Path,
+    json: bool,
+) -> Result<usize> {
+    let ui = Ui::new(false);
+    let bundle = crate::bundle::read_bundle(bundle_path)?;
+
+    let (status_items, imported_count) = import_recursive(
+        project_root,
+        Path::new(""),
+        config,
+        cache,
+        &bundle,
+        &ui,
+        json,
+        0,
+    )?;
+
+    if json {
+        let output = StatusOutput::new(status_items);
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if imported_count == 0 {
+        eprintln!("No checks in the bundle matched the current files");
+    }
+
+    Ok(imported_count)
+}
+
+/// Import matching bundle entries for a project and its subprojects.
+/// Returns (status_items, imported_count).
+#[allow(clippy::too_many_arguments)]
+fn import_recursive(
+    project_root: &Path,
+    relative_dir: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    bundle: &crate::bundle::Bundle,
+    ui: &Ui,
+    json: bool,
+    indent: usize,
+) -> Result<(Vec<StatusItemJson>, usize)> {
+    let mut status_items: Vec<StatusItemJson> = Vec::new();
+    let mut imported_count = 0;
+
+    let key = crate::bundle::bundle_key(relative_dir);
+    if let Some(files) = bundle.state.get(&key) {
+        crate::bundle::import_state(project_root, files)?;
+    }
+
+    if let Some(bundled) = bundle.caches.get(&key) {
+        for check in config.verifications_only() {
+            // Aggregate and untracked checks have no state of their own
+            if check.command.is_none() || check.cache_paths.is_empty() {
+                continue;
+            }
+
+            let Some(entry) = bundled.get(&check.name) else {
+                continue;
+            };
+
+            let config_hash = check.config_hash();
+            let hash_result = check.compute_hash(project_root)?;
+            let status =
+                bundled.check_staleness(&check.name, &hash_result.combined_hash, &config_hash);
+            if !matches!(status, VerificationStatus::Verified) {
+                continue;
+            }
+
+            cache.insert(&check.name, entry.clone());
+            imported_count += 1;
+
+            // Bring back the outputs the imported result vouches for, so checks that
+            // read them see the files they were verified against
+            if let Some(files) = bundle.artifacts.get(&key)
+                && crate::bundle::restore_artifacts(project_root, files, &check.artifacts)? > 0
+            {
+                hasher::files_may_have_changed();
+            }
+
+            if json {
+                let json_item = CheckStatusJson::from_status(&check.name, &status, Some(entry));
+                status_items.push(StatusItemJson::Check(json_item));
+            } else {
+                ui.print_status(&check.name, &status, &entry.metadata, indent);
+            }
+        }
+    }
+
+    if imported_count > 0 {
+        cache.save(project_root)?;
+    }
+
+    for subproject in config.subprojects() {
+        let subproject_dir = project_root.join(&subproject.path);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        let mut sub_cache = CacheState::load(&subproject_dir, cache.settings())?;
+
+        if !json {
+            ui.print_subproject_header(&subproject.name, indent, false);
+        }
+
+        let (sub_items, sub_count) = import_recursive(
+            &subproject_dir,
+            &relative_dir.join(&subproject.path),
+            &sub_config,
+            &mut sub_cache,
+            bundle,
+            ui,
+            json,
+            indent + 1,
+        )?;
+        imported_count += sub_count;
+
+        if json {
+            status_items.push(StatusItemJson::Subproject(SubprojectStatusJson::new(
+                &subproject.name,
+                subproject.path.to_string_lossy().as_ref(),
+                &subproject.prefix(),
+                sub_items,
+            )));
+        }
+    }
+
+    Ok((status_items, imported_count))
+}
+
+/// Which checks of a run `--force` re-runs even when their cache is fresh
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Force {
+    #[default]
+    None,
+    All,
+    /// Only the requested checks; their dependencies still honor the cache
+    Named,
+}
+
+impl Force {
+    /// `--force` for a run of `names`: just those checks, unless none are named (the
+    /// whole config) or `force_deps` forces their dependencies too
+    pub fn new(force: bool, names: &[String], force_deps: bool) -> Self {
+        match force {
+            false => Force::None,
+            true if names.is_empty() || force_deps => Force::All,
+            true => Force::Named,
+        }
+    }
+
+    fn applies_to(self, name: &str, names: &[String]) -> bool {
+        match self {
+            Force::None => false,
+            Force::All => true,
+            Force::Named => names.iter().any(|n| n == name),
+        }
     }
+}
 
-    if json {
-        let output = StatusOutput {
-            checks: status_items,
-        };
-        println!("{}", serde_json::to_string_pretty(&output)?);
-    } else if synced_count == 0 {
-        eprintln!("No checks matched the trailer");
-    }
+/// How `run_checks` runs the checks and reports the results. The default runs the
+/// checks whose cache is stale and prints their results.
+#[derive(Default)]
+pub struct RunOptions<'a> {
+    /// Which checks run even when their cache is fresh
+    pub force: Force,
+    /// Skip checks that already passed in an interrupted run (per the run journal) if
+    /// their files and config are unchanged
+    pub resume: bool,
+    /// Print JSON instead of progress and a summary
+    pub json: bool,
+    /// Also write the JSON output to this file
+    pub json_file: Option<&'a Path>,
+    /// Stream every command's output
+    pub verbose: bool,
+    /// Annotation recorded with the run in the history
+    pub message: Option<String>,
+    /// When only one check's command failed, exit with its exit code
+    pub passthrough_exit_code: bool,
+    /// Say why each check ran or was skipped
+    pub explain_cache: bool,
+    /// Print the end of passing checks' output
+    pub show_output: bool,
+}
 
-    Ok(synced_count > 0)
+impl RunOptions<'_> {
+    fn ui(&self) -> Ui {
+        Ui::new(self.verbose)
+            .with_cache_explanations(self.explain_cache)
+            .with_passing_output(self.show_output)
+    }
 }
 
-/// Run verification checks
+/// Run verification checks.
+/// Returns (exit_code, subproject_dirs) where subproject_dirs are the directories
+/// (relative to project_root) of subprojects whose lock files the run wrote.
 pub fn run_checks(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    names: Vec<String>,
+    options: RunOptions,
+) -> Result<(i32, Vec<PathBuf>)> {
+    let start_time = Instant::now();
+    let ui = options.ui();
+    let final_results = execute_run(project_root, config, cache, &names, &options, &ui)?;
+
+    let subproject_dirs = final_results.subproject_paths();
+    let passthrough = passthrough_code(config, &final_results, options.passthrough_exit_code);
+    let duration_ms = record_history(
+        project_root,
+        config,
+        &final_results,
+        names,
+        options.message.clone(),
+        start_time,
+    );
+    let exit_code = report_run(config, final_results, duration_ms, &options, &ui)?;
+    Ok((passthrough.unwrap_or(exit_code), subproject_dirs))
+}
+
+/// Run checks without printing anything, returning the results as `verify --json run`
+/// would (for `verify serve`)
+pub fn run_checks_collect(
     project_root: &Path,
     config: &Config,
     cache: &mut CacheState,
     names: Vec<String>,
     force: bool,
-    json: bool,
-    verbose: bool,
-) -> Result<i32> {
+) -> Result<RunOutput> {
     let start_time = Instant::now();
-    let ui = Ui::new(verbose);
-    let final_results =
-        run_checks_recursive(project_root, config, cache, &names, force, json, &ui, 0)?;
+    let ui = Ui::new(false);
+    let options = RunOptions {
+        force: Force::new(force, &names, false),
+        json: true,
+        ..Default::default()
+    };
+    let final_results = execute_run(project_root, config, cache, &names, &options, &ui)?;
+
+    let metadata_totals = final_results.metadata_totals(&config.metadata_totals);
+    record_history(
+        project_root,
+        config,
+        &final_results,
+        names,
+        None,
+        start_time,
+    );
+    Ok(final_results.into_output(metadata_totals, None))
+}
+
+/// Run the checks under a run journal, then drop cache entries for checks no longer
+/// in the config and save the root cache
+fn execute_run(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    names: &[String],
+    options: &RunOptions,
+    ui: &Ui,
+) -> Result<RunResults> {
+    let mut journal = RunJournal::start(project_root, options.resume)?;
+    if config.record_user {
+        cache.attribute_to(Some(current_attribution(project_root)));
+    }
+
+    let final_results = run_with_progress(
+        project_root,
+        config,
+        cache,
+        &mut journal,
+        names,
+        options.force,
+        options.json,
+        ui,
+    )?;
 
     // Clean up orphaned cache entries (checks no longer in config)
-    let valid_names: std::collections::HashSet<String> = config
-        .verifications
-        .iter()
-        .map(|item| item.name().to_string())
-        .collect();
-    cache.cleanup_orphaned(&valid_names);
+    if statedir::writes_enabled() {
+        let valid_names: std::collections::HashSet<String> = config
+            .verifications
+            .iter()
+            .map(|item| item.name().to_string())
+            .collect();
+        cache.cleanup_orphaned(&valid_names);
+    }
 
     // Save cache for root project
     cache.save(project_root)?;
+    journal.finish()?;
+    Ok(final_results)
+}
 
-    let failed_count = final_results.failed;
-    let total_duration_ms = start_time.elapsed().as_millis() as u64;
+/// Exit code to pass through instead of the generic 1: the command's own exit code when
+/// exactly one check's command failed, and either `--passthrough-exit-code` was given or
+/// that check has `propagate_exit_code: true`
+fn passthrough_code(config: &Config, results: &RunResults, passthrough: bool) -> Option<i32> {
+    match results.command_failures().as_slice() {
+        [(name, code)] if *code != 0 => {
+            let propagate = config.get(name).is_some_and(|c| c.propagate_exit_code);
+            (passthrough || propagate).then_some(*code)
+        }
+        _ => None,
+    }
+}
 
-    if json {
-        let output = final_results.into_output();
-        println!("{}", serde_json::to_string_pretty(&output)?);
-    } else {
+/// Run every project in a workspace (or the named ones) with combined output.
+/// Each project is run like a subproject, keeping its own verify.lock. There's no run
+/// journal to resume, so `options.resume` doesn't apply, nor does
+/// `options.passthrough_exit_code`. Returns 1 if any check in any project failed.
+pub fn run_workspace(
+    workspace_root: &Path,
+    workspace: &Workspace,
+    names: Vec<String>,
+    options: RunOptions,
+) -> Result<i32> {
+    let start_time = Instant::now();
+    let ui = options.ui();
+
+    let config = Config {
+        verifications: workspace
+            .projects
+            .iter()
+            .filter(|project| names.is_empty() || names.contains(&project.name))
+            .cloned()
+            .map(VerificationItem::Subproject)
+            .collect(),
+        ..Default::default()
+    };
+
+    // The workspace root isn't a project, so its cache is never saved
+    let mut cache = CacheState::new();
+    let final_results = run_with_progress(
+        workspace_root,
+        &config,
+        &mut cache,
+        &mut RunJournal::disabled(),
+        &[],
+        options.force,
+        options.json,
+        &ui,
+    )?;
+
+    // The workspace root isn't a project either, so it keeps no history or stats. As in
+    // a run of a project with subprojects, the member projects don't record the run.
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    report_run(&config, final_results, duration_ms, &options, &ui)
+}
+
+/// Run checks with the run-level progress bar shown while they execute
+#[allow(clippy::too_many_arguments)]
+fn run_with_progress(
+    project_root: &Path,
+    config: &Config,
+    cache: &mut CacheState,
+    journal: &mut RunJournal,
+    names: &[String],
+    force: Force,
+    json: bool,
+    ui: &Ui,
+) -> Result<RunResults> {
+    let mut subprojects = Subprojects::load(project_root, config, cache.settings())?;
+    // The progress bar is only a display aid, so skip it if planning fails
+    if !json && let Ok(estimates) = plan_run(config, cache, &subprojects, names) {
+        ui.start_run_progress(&estimates);
+    }
+    let run_result = run_checks_recursive(
+        project_root,
+        config,
+        cache,
+        &mut subprojects,
+        journal,
+        names,
+        force,
+        json,
+        ui,
+        0,
+    );
+    ui.finish_run_progress();
+    run_result
+}
+
+/// Print a finished run's summary (or JSON output). Returns the exit code.
+fn report_run(
+    config: &Config,
+    final_results: RunResults,
+    total_duration_ms: u64,
+    options: &RunOptions,
+    ui: &Ui,
+) -> Result<i32> {
+    let message = &options.message;
+    let failed = final_results.has_failures();
+    let metadata_totals = final_results.metadata_totals(&config.metadata_totals);
+
+    if !options.json {
         ui.print_summary(
             final_results.passed,
             final_results.failed,
             final_results.skipped,
+            final_results.blocked,
             total_duration_ms,
         );
+        if let Some(message) = message {
+            ui.print_run_message(message);
+        }
+        ui.print_metadata_totals(&metadata_totals);
+    }
+    if options.json || options.json_file.is_some() {
+        let output = final_results.into_output(metadata_totals, message.clone());
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        if let Some(path) = options.json_file {
+            write_json_file(path, &output)?;
+        }
     }
 
     // Return exit code
-    if failed_count > 0 { Ok(1) } else { Ok(0) }
+    let exit_code = if failed { 1 } else { 0 };
+    Ok(exit_code)
+}
+
+/// Append a finished run to the history and its cache statistics to the stats,
+/// returning its duration
+fn record_history(
+    project_root: &Path,
+    config: &Config,
+    final_results: &RunResults,
+    names: Vec<String>,
+    message: Option<String>,
+    start_time: Instant,
+) -> u64 {
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    let attribution = config
+        .record_user
+        .then(|| current_attribution(project_root));
+    let recorded = history::append(
+        project_root,
+        &RunRecord {
+            timestamp: chrono::Utc::now(),
+            message,
+            checks: names,
+            total: final_results.total(),
+            passed: final_results.passed,
+            failed: final_results.failed,
+            skipped: final_results.skipped,
+            blocked: final_results.blocked,
+            duration_ms,
+            marked_by: None,
+            user: attribution.as_ref().map(|a| a.user.clone()),
+            host: attribution.and_then(|a| a.host),
+        },
+    );
+    warn_unrecorded("run history", recorded);
+    let recorded = stats::append(
+        project_root,
+        &StatsRecord::from_results(final_results, hasher::bytes_hashed(), duration_ms),
+    );
+    warn_unrecorded("cache statistics", recorded);
+    duration_ms
+}
+
+/// The history and stats are only logs, so failing to write them (a read-only `.verify/`,
+/// a full disk) is a warning rather than a failure of checks that already ran
+fn warn_unrecorded(log: &str, result: Result<()>) {
+    if let Err(e) = result {
+        Ui::new(false).print_warning(&format!("Failed to record the {}: {:#}", log, e));
+    }
+}
+
+/// Work out which checks a run will visit (mirroring `run_checks_recursive`, including
+/// dependencies and subprojects) and return the last recorded duration of each.
+fn plan_run(
+    config: &Config,
+    cache: &CacheState,
+    subprojects: &Subprojects,
+    names: &[String],
+) -> Result<Vec<Option<u64>>> {
+    let mut visited = std::collections::HashSet::new();
+    let mut estimates = Vec::new();
+    for item in &config.verifications {
+        if !names.is_empty() && !names.contains(&item.name().to_string()) {
+            continue;
+        }
+        plan_item(
+            config,
+            cache,
+            subprojects,
+            item.name(),
+            names,
+            &mut visited,
+            &mut estimates,
+        )?;
+    }
+    Ok(estimates)
+}
+
+/// Plan a single item and its dependencies for `plan_run`
+fn plan_item(
+    config: &Config,
+    cache: &CacheState,
+    subprojects: &Subprojects,
+    name: &str,
+    names: &[String],
+    visited: &mut std::collections::HashSet<String>,
+    estimates: &mut Vec<Option<u64>>,
+) -> Result<()> {
+    if !visited.insert(name.to_string()) {
+        return Ok(());
+    }
+
+    if config.get_subproject(name).is_some() {
+        let tree = subprojects.get(name)?;
+        // Named subprojects pass the name filter down; dependencies run in full
+        let sub_names = if names.iter().any(|n| n == name) {
+            names
+        } else {
+            &[]
+        };
+        estimates.extend(plan_run(
+            &tree.config,
+            &tree.cache,
+            &tree.subprojects,
+            sub_names,
+        )?);
+    } else if let Some(check) = config.get(name) {
+        for dep in &check.depends_on {
+            plan_item(config, cache, subprojects, dep, &[], visited, estimates)?;
+        }
+        estimates.push(cache.get(name).and_then(|c| c.duration_ms));
+    }
+
+    Ok(())
 }
 
 /// Recursively run checks for config and all subprojects
@@ -691,8 +2458,10 @@ fn run_checks_recursive(
     project_root: &Path,
     config: &Config,
     cache: &mut CacheState,
+    subprojects: &mut Subprojects,
+    journal: &mut RunJournal,
     names: &[String],
-    force: bool,
+    force: Force,
     json: bool,
     ui: &Ui,
     indent: usize,
@@ -709,6 +2478,8 @@ fn run_checks_recursive(
             project_root,
             config,
             cache,
+            subprojects,
+            journal,
             item,
             names,
             force,
@@ -730,9 +2501,11 @@ fn execute_item_with_deps(
     project_root: &Path,
     config: &Config,
     cache: &mut CacheState,
+    subprojects: &mut Subprojects,
+    journal: &mut RunJournal,
     item: &VerificationItem,
     names: &[String],
-    force: bool,
+    force: Force,
     json: bool,
     ui: &Ui,
     indent: usize,
@@ -756,8 +2529,20 @@ fn execute_item_with_deps(
     if let VerificationItem::Verification(v) = item {
         for dep_name in &v.depends_on {
             resolve_and_execute_dep(
-                project_root, config, cache, dep_name, force, json, ui, indent, executed,
-                was_stale, results,
+                project_root,
+                config,
+                cache,
+                subprojects,
+                journal,
+                dep_name,
+                names,
+                force,
+                json,
+                ui,
+                indent,
+                executed,
+                was_stale,
+                results,
             )?;
         }
     }
@@ -773,7 +2558,8 @@ fn execute_item_with_deps(
                 project_root,
                 v,
                 cache,
-                force,
+                journal,
+                force.applies_to(&v.name, names),
                 json,
                 ui,
                 indent,
@@ -788,10 +2574,19 @@ fn execute_item_with_deps(
                 return Ok(());
             }
             if !executed.contains_key(&s.name) {
-                let sub_results =
-                    run_checks_subproject(project_root, s, names, force, json, ui, indent)?;
-                let had_failures = sub_results.failed > 0;
-                let had_stale = sub_results.passed > 0 || sub_results.failed > 0;
+                let sub_results = run_checks_subproject(
+                    s,
+                    subprojects.get_mut(&s.name)?,
+                    names,
+                    force.applies_to(&s.name, names),
+                    json,
+                    ui,
+                    indent,
+                    journal,
+                    cache.attribution(),
+                )?;
+                let had_failures = sub_results.has_failures();
+                let had_stale = sub_results.passed > 0 || had_failures;
                 executed.insert(s.name.clone(), had_failures);
                 was_stale.insert(s.name.clone(), had_stale);
                 results.add_subproject(&s.name, s.path.to_string_lossy().as_ref(), sub_results);
@@ -810,8 +2605,11 @@ fn resolve_and_execute_dep(
     project_root: &Path,
     config: &Config,
     cache: &mut CacheState,
+    subprojects: &mut Subprojects,
+    journal: &mut RunJournal,
     dep_name: &str,
-    force: bool,
+    names: &[String],
+    force: Force,
     json: bool,
     ui: &Ui,
     indent: usize,
@@ -824,10 +2622,19 @@ fn resolve_and_execute_dep(
     }
 
     if let Some(sub) = config.get_subproject(dep_name) {
-        let sub_results =
-            run_checks_subproject(project_root, sub, &[], force, json, ui, indent)?;
-        let had_failures = sub_results.failed > 0;
-        let had_stale = sub_results.passed > 0 || sub_results.failed > 0;
+        let sub_results = run_checks_subproject(
+            sub,
+            subprojects.get_mut(dep_name)?,
+            &[],
+            force.applies_to(dep_name, names),
+            json,
+            ui,
+            indent,
+            journal,
+            cache.attribution(),
+        )?;
+        let had_failures = sub_results.has_failures();
+        let had_stale = sub_results.passed > 0 || had_failures;
         executed.insert(dep_name.to_string(), had_failures);
         was_stale.insert(dep_name.to_string(), had_stale);
         results.add_subproject(dep_name, sub.path.to_string_lossy().as_ref(), sub_results);
@@ -838,7 +2645,10 @@ fn resolve_and_execute_dep(
                 project_root,
                 config,
                 cache,
+                subprojects,
+                journal,
                 transitive_dep,
+                names,
                 force,
                 json,
                 ui,
@@ -849,19 +2659,30 @@ fn resolve_and_execute_dep(
             )?;
         }
         execute_verification(
-            project_root, dep_v, cache, force, json, ui, indent, executed, was_stale, results,
+            project_root,
+            dep_v,
+            cache,
+            journal,
+            force.applies_to(dep_name, names),
+            json,
+            ui,
+            indent,
+            executed,
+            was_stale,
+            results,
         )?;
     }
 
     Ok(())
 }
 
-/// Execute a single verification
+/// Execute a single verification and count it in the run progress
 #[allow(clippy::too_many_arguments)]
 fn execute_verification(
     project_root: &Path,
     check: &Verification,
     cache: &mut CacheState,
+    journal: &mut RunJournal,
     force: bool,
     json: bool,
     ui: &Ui,
@@ -875,6 +2696,74 @@ fn execute_verification(
         return Ok(());
     }
 
+    let estimate_ms = cache.get(&check.name).and_then(|c| c.duration_ms);
+    let skipped_before = results.skipped;
+    run_verification(
+        project_root,
+        check,
+        cache,
+        journal,
+        force,
+        json,
+        ui,
+        indent,
+        executed,
+        was_stale,
+        results,
+    )?;
+    let failed = executed.get(&check.name).copied().unwrap_or(false);
+    let outcome = if failed {
+        CheckOutcome::Failed
+    } else if results.skipped > skipped_before {
+        CheckOutcome::Cached
+    } else {
+        CheckOutcome::Passed
+    };
+    ui.advance_run_progress(&check.name, estimate_ms, outcome);
+
+    // Journal checks that passed in this run, so `--resume` can skip them after a crash
+    let ran = was_stale.get(&check.name).copied().unwrap_or(false);
+    if ran {
+        cache.record_config_components(&check.name, check.config_components());
+    }
+    if check.command.is_some()
+        && ran
+        && !failed
+        && let Some(entry) = cache.get(&check.name)
+        && let (Some(content_hash), Some(config_hash)) = (&entry.content_hash, &entry.config_hash)
+    {
+        journal.record(project_root, &check.name, content_hash, config_hash)?;
+    }
+
+    Ok(())
+}
+
+/// Execute a single verification (status check, then cached/run/per-file handling)
+#[allow(clippy::too_many_arguments)]
+fn run_verification(
+    project_root: &Path,
+    check: &Verification,
+    cache: &mut CacheState,
+    journal: &mut RunJournal,
+    force: bool,
+    json: bool,
+    ui: &Ui,
+    indent: usize,
+    executed: &mut HashMap<String, bool>,
+    was_stale: &mut HashMap<String, bool>,
+    results: &mut RunResults,
+) -> Result<()> {
+    // Nothing to run on this platform, which doesn't hold up its dependents
+    if !check.is_supported() {
+        if !json {
+            ui.print_unsupported(&check.name, indent);
+        }
+        results.add_unsupported(&check.name);
+        executed.insert(check.name.clone(), false);
+        was_stale.insert(check.name.clone(), false);
+        return Ok(());
+    }
+
     // Check if any dependency failed
     let dep_failed = check
         .depends_on
@@ -882,7 +2771,7 @@ fn execute_verification(
         .any(|dep| executed.get(dep).copied().unwrap_or(false));
 
     // Compute staleness
-    let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
+    let hash_result = check.compute_hash(project_root)?;
 
     // Build staleness map: a dependency is stale if it actually ran (was_stale),
     // not just if it failed. This ensures dependent checks re-run when their
@@ -926,9 +2815,39 @@ fn execute_verification(
                     indent,
                 );
             }
-            results.add_fail(&check.name, 0, None, None, &BTreeMap::new(), None);
+            results.add_blocked(&check.name, &failed_dep);
             executed.insert(check.name.clone(), true);
             was_stale.insert(check.name.clone(), true);
+        } else if guards_changed(check, cache, &hash_result, force) {
+            // Its dependencies passed, so record the guard files as verified
+            let config_hash = check.config_hash();
+            audit_execution(
+                cache.settings(),
+                project_root,
+                check,
+                &hash_result.combined_hash,
+                &config_hash,
+                true,
+                None,
+                0,
+            )?;
+            cache.update(
+                &check.name,
+                true,
+                config_hash,
+                Some(hash_result.combined_hash.clone()),
+                hash_result.file_hashes,
+                BTreeMap::new(),
+                false,
+            );
+            cache.save(project_root)?;
+            if !json {
+                let pb = create_running_indicator(&check.name, indent);
+                finish_pass_with_metadata(&pb, &check.name, 0, &BTreeMap::new(), None, indent);
+            }
+            results.add_pass(&check.name, 0, false, &BTreeMap::new(), None);
+            executed.insert(check.name.clone(), false);
+            was_stale.insert(check.name.clone(), true);
         } else {
             let any_dep_stale = check
                 .depends_on
@@ -945,7 +2864,47 @@ fn execute_verification(
         return Ok(());
     }
 
+    // Passed earlier in the interrupted run being resumed. It counts as having run,
+    // so dependents still re-run against it.
+    let resumed = journal.is_completed(
+        project_root,
+        &check.name,
+        &hash_result.combined_hash,
+        &check.config_hash(),
+    );
     let should_run = force || !matches!(status, VerificationStatus::Verified);
+    if ui.explains_cache() {
+        let decision = explain_cache(
+            check,
+            cache,
+            &hash_result,
+            &status,
+            force,
+            resumed,
+            should_run,
+        );
+        if !json {
+            ui.print_cache_decision(&check.name, &decision, indent);
+        }
+        results.explain_next(decision);
+    }
+
+    if resumed {
+        if !json {
+            let pb = create_running_indicator(&check.name, indent);
+            let cached_metadata = cache.get(&check.name).map(|c| &c.metadata);
+            finish_cached(
+                &pb,
+                &check.name,
+                cached_metadata.unwrap_or(&BTreeMap::new()),
+                indent,
+            );
+        }
+        results.add_skipped(&check.name);
+        executed.insert(check.name.clone(), false);
+        was_stale.insert(check.name.clone(), true);
+        return Ok(());
+    }
 
     if !should_run {
         // Skip - cache fresh, show with in-place green indicator
@@ -960,12 +2919,57 @@ fn execute_verification(
                 indent,
             );
         }
-        results.add_skipped(&check.name);
+        results.add_cached(&check.name, cached.and_then(|c| c.duration_ms));
         executed.insert(check.name.clone(), false);
         was_stale.insert(check.name.clone(), false);
         return Ok(());
     }
 
+    // Hold this check's lock while it runs, so another verify process (e.g. watch mode)
+    // can't run the same check at the same time. Unrelated checks aren't blocked.
+    let _check_lock = match FileLock::try_check(project_root, &check.name)? {
+        Some(lock) => lock,
+        None => {
+            if !json {
+                ui.print_waiting(&check.name, indent);
+            }
+            let lock = FileLock::check(project_root, &check.name)?;
+
+            // The other process may have just verified the check against the same files.
+            // Only reuse its result when our own dependencies didn't change underneath it.
+            let dependency_changed = matches!(
+                status,
+                VerificationStatus::Unverified {
+                    reason: UnverifiedReason::DependencyUnverified { .. }
+                }
+            );
+            if !force && !dependency_changed {
+                let latest = CacheState::load(project_root, cache.settings())?;
+                let latest_status = latest.check_staleness(
+                    &check.name,
+                    &hash_result.combined_hash,
+                    &check.config_hash(),
+                );
+                if let (VerificationStatus::Verified, Some(entry)) =
+                    (latest_status, latest.get(&check.name))
+                {
+                    if !json {
+                        let pb = create_running_indicator(&check.name, indent);
+                        finish_cached(&pb, &check.name, &entry.metadata, indent);
+                    }
+                    cache.insert(&check.name, entry.clone());
+                    results.add_cached(&check.name, entry.duration_ms);
+                    executed.insert(check.name.clone(), false);
+                    // It did run (in the other process), so dependents re-check
+                    was_stale.insert(check.name.clone(), true);
+                    return Ok(());
+                }
+            }
+            lock
+        }
+    };
+    ui.start_check_progress(&check.name);
+
     // Get previous cache for metadata deltas
     let prev_cache = cache.get(&check.name);
     let prev_metadata = prev_cache.map(|c| c.metadata.clone());
@@ -990,7 +2994,8 @@ fn execute_verification(
 
     // In verbose mode or non-TTY, print start indicator instead of using progress bar
     // (progress bar redraws interfere with streamed output or don't work in non-TTY)
-    let pb = if !json && ui.use_progress_bars() {
+    let stream = streams_output(check, ui, json);
+    let pb = if !json && ui.use_progress_bars() && !stream {
         Some(create_running_indicator(&check.name, indent))
     } else {
         if !json {
@@ -1001,26 +3006,70 @@ fn execute_verification(
 
     // Execute the check (command is guaranteed Some here — aggregate checks returned early)
     let command = check.command.as_ref().unwrap();
+    let executor = Executor::for_check(check, project_root);
     let start = Instant::now();
-    let (success, exit_code, output) = execute_command(
+    executor.prepare(project_root, check, &hash_result)?;
+    let group = stream && !json;
+    if group {
+        ui.start_output_group(&check.name);
+    }
+    let output_stream = stream.then_some(OutputStream {
+        ui,
+        label: &check.name,
+    });
+    let (success, exit_code, output, timed_out) = executor.execute(
+        &check.name,
         command,
         project_root,
         check.timeout_secs,
-        ui.is_verbose(),
+        output_stream,
         &[],
     );
+    if group {
+        ui.end_output_group(&check.name);
+    }
+    let (success, exit_code, output, unmet) = if timed_out {
+        fail_with_reason(output, timeout_message(check))
+    } else {
+        apply_output_expectations(check, success, exit_code, output)
+    };
+    executor.finish(project_root, check, success)?;
     let duration = start.elapsed();
+
+    // A command that rewrites its own cache_paths leaves the check stale after every run
+    let self_modified = if success && !check.allow_self_modification {
+        self_modified_files(project_root, check, &hash_result)?
+    } else {
+        Vec::new()
+    };
+    let (success, exit_code, output, unmet) =
+        if !self_modified.is_empty() && check.fail_on_self_modification {
+            let reason = self_modification_message(&check.name, &self_modified);
+            fail_with_reason(output, reason)
+        } else {
+            (success, exit_code, output, unmet)
+        };
     let duration_ms = duration.as_millis() as u64;
 
     // Extract metadata from output (only on success)
-    let metadata = if success && !check.metadata.is_empty() {
-        extract_metadata(&output, &check.metadata)
+    let metadata = if success {
+        check_metadata(project_root, check, &output, json, ui)
     } else {
         BTreeMap::new()
     };
 
     // Update cache
     let config_hash = check.config_hash();
+    audit_execution(
+        cache.settings(),
+        project_root,
+        check,
+        &hash_result.combined_hash,
+        &config_hash,
+        success,
+        exit_code,
+        duration_ms,
+    )?;
     cache.update(
         &check.name,
         success,
@@ -1030,6 +3079,15 @@ fn execute_verification(
         metadata.clone(),
         check.per_file,
     );
+    if !success {
+        cache.record_failure(
+            &check.name,
+            exit_code,
+            timed_out,
+            hash_result.combined_hash.clone(),
+        );
+    }
+    cache.record_duration(&check.name, duration_ms);
 
     // Record result
     executed.insert(check.name.clone(), !success);
@@ -1049,6 +3107,9 @@ fn execute_verification(
             // Verbose mode: print completion line
             ui.print_pass_indented(&check.name, duration_ms, indent);
         }
+        if !json {
+            print_command_output(ui, check, stream, true, &output, None, indent);
+        }
         results.add_pass(
             &check.name,
             duration_ms,
@@ -1056,6 +3117,12 @@ fn execute_verification(
             &metadata,
             prev_metadata.as_ref(),
         );
+        if !json && !self_modified.is_empty() {
+            ui.print_warning(&format!(
+                "{} (set allow_self_modification: true if this is intended)",
+                self_modification_message(&check.name, &self_modified)
+            ));
+        }
     } else {
         if let Some(pb) = pb {
             finish_fail_with_metadata(
@@ -1072,18 +3139,23 @@ fn execute_verification(
             ui.print_fail_indented(&check.name, duration_ms, None, indent);
         }
         // Print error output separately (can't be part of progress bar)
-        // In verbose mode, output was already streamed, so skip
-        if !json && !ui.is_verbose() {
-            ui.print_fail_output(Some(&output), indent);
+        if !json {
+            print_command_output(ui, check, stream, false, &output, unmet.as_deref(), indent);
         }
         results.add_fail(
             &check.name,
             duration_ms,
             exit_code,
-            Some(output),
+            Some(retained_output(check, &output)),
             &metadata,
             prev_metadata.as_ref(),
         );
+        if timed_out {
+            results.mark_timed_out();
+        }
+    }
+    if matches!(status, VerificationStatus::Untracked) {
+        results.mark_untracked();
     }
 
     // Save cache immediately after check completes
@@ -1092,6 +3164,223 @@ fn execute_verification(
     Ok(())
 }
 
+/// The cache comparison behind running or skipping a check, for `run --explain-cache`
+#[allow(clippy::too_many_arguments)]
+fn explain_cache(
+    check: &Verification,
+    cache: &CacheState,
+    hash_result: &HashResult,
+    status: &VerificationStatus,
+    force: bool,
+    resumed: bool,
+    should_run: bool,
+) -> CacheDecisionJson {
+    let cached = cache.get(&check.name);
+    let decision = if resumed {
+        "resumed"
+    } else if should_run {
+        "run"
+    } else {
+        "cached"
+    };
+    let invalidated_by = match status {
+        VerificationStatus::Unverified {
+            reason: UnverifiedReason::DependencyUnverified { dependency },
+        } => Some(dependency.clone()),
+        _ => None,
+    };
+    CacheDecisionJson {
+        decision: decision.to_string(),
+        config_hash: check.config_hash(),
+        cached_config_hash: cached.and_then(|c| c.config_hash.clone()),
+        content_hash: hash_result.combined_hash.clone(),
+        cached_content_hash: cached.and_then(|c| c.content_hash.clone()),
+        invalidated_by,
+        forced: force,
+    }
+}
+
+/// Whether an aggregate check's own cache_paths changed since it was last recorded as
+/// verified (always false for aggregates without cache_paths)
+fn guards_changed(
+    check: &Verification,
+    cache: &CacheState,
+    hash_result: &HashResult,
+    force: bool,
+) -> bool {
+    if check.cache_paths.is_empty() {
+        return false;
+    }
+    let status = cache.check_staleness(
+        &check.name,
+        &hash_result.combined_hash,
+        &check.config_hash(),
+    );
+    force || !matches!(status, VerificationStatus::Verified)
+}
+
+/// Whether a check's output streams while it runs: `--verbose` (outside JSON mode for a
+/// check's own `verbose`), unless the check is `quiet`
+fn streams_output(check: &Verification, ui: &Ui, json: bool) -> bool {
+    !check.quiet && (ui.is_verbose() || (check.verbose && !json))
+}
+
+/// Print a command's output under its status line per the check's `show_output`: only
+/// when it failed by default, also when it passed with `always` (or `run --show-output`).
+/// Streamed output was already shown, so, as with `never`, only an unmet output
+/// expectation is printed for it.
+fn print_command_output(
+    ui: &Ui,
+    check: &Verification,
+    streamed: bool,
+    success: bool,
+    output: &str,
+    unmet: Option<&str>,
+    indent: usize,
+) {
+    let show = check.show_output.unwrap_or(if ui.shows_passing_output() {
+        ShowOutput::Always
+    } else {
+        ShowOutput::Failure
+    });
+    if success && show != ShowOutput::Always {
+        return;
+    }
+    let output = retained_output(check, output);
+    if streamed || show == ShowOutput::Never {
+        ui.print_command_output(unmet, indent);
+    } else if check.quiet {
+        ui.print_quiet_command_output(Some(&output), indent);
+    } else {
+        ui.print_command_output(Some(&output), indent);
+    }
+}
+
+/// Fail a command that exited 0 when its output doesn't meet the check's `expect_output`
+/// or `expect_not_output`, appending the reason to the output (also returned, for verbose
+/// mode where the output was already streamed). The exit code is dropped so
+/// `propagate_exit_code` doesn't pass on the 0.
+fn apply_output_expectations(
+    check: &Verification,
+    success: bool,
+    exit_code: Option<i32>,
+    output: String,
+) -> (bool, Option<i32>, String, Option<String>) {
+    if !success {
+        return (success, exit_code, output, None);
+    }
+    // Patterns were validated when the config was loaded
+    let expect = check
+        .expect_output
+        .as_deref()
+        .and_then(|p| Regex::new(p).ok());
+    let expect_not = check
+        .expect_not_output
+        .as_deref()
+        .and_then(|p| Regex::new(p).ok());
+
+    let unmet = if let Some(re) = expect.filter(|re| !re.is_match(&output)) {
+        format!("expect_output: no match for /{}/", re.as_str())
+    } else if let Some(re) = expect_not
+        && let Some(m) = re.find(&output)
+    {
+        let line_start = output[..m.start()].rfind('\n').map_or(0, |i| i + 1);
+        let line = output[line_start..].lines().next().unwrap_or_default();
+        format!(
+            "expect_not_output: /{}/ matched \"{}\"",
+            re.as_str(),
+            line.trim()
+        )
+    } else {
+        return (success, exit_code, output, None);
+    };
+    fail_with_reason(output, unmet)
+}
+
+/// Fail a command that exited 0, appending the reason to its output
+fn fail_with_reason(
+    mut output: String,
+    reason: String,
+) -> (bool, Option<i32>, String, Option<String>) {
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&reason);
+    output.push('\n');
+    (false, None, output, Some(reason))
+}
+
+/// Why a command that ran past the check's `timeout_secs` failed
+fn timeout_message(check: &Verification) -> String {
+    format!(
+        "timed out after {}s (timeout_secs)",
+        check.timeout_secs.unwrap_or_default()
+    )
+}
+
+/// Files in the check's cache_paths that its command added, changed or removed, found by
+/// hashing them again and comparing with the hashes from before it ran
+fn self_modified_files(
+    project_root: &Path,
+    check: &Verification,
+    before: &HashResult,
+) -> Result<Vec<String>> {
+    if check.cache_paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let after = check.compute_hash(project_root)?;
+    if after.combined_hash == before.combined_hash {
+        return Ok(Vec::new());
+    }
+    let files: BTreeSet<&String> = before
+        .file_hashes
+        .keys()
+        .chain(after.file_hashes.keys())
+        .filter(|file| before.file_hashes.get(*file) != after.file_hashes.get(*file))
+        .collect();
+    Ok(files.into_iter().cloned().collect())
+}
+
+/// Explain which of its own files a check's command modified, listing the first few
+fn self_modification_message(name: &str, files: &[String]) -> String {
+    const SHOWN: usize = 5;
+    let mut list = files[..files.len().min(SHOWN)].join(", ");
+    if files.len() > SHOWN {
+        list.push_str(&format!(" and {} more", files.len() - SHOWN));
+    }
+    format!(
+        "{} modified files in its own cache_paths, so it will be stale on the next run: {}",
+        name, list
+    )
+}
+
+/// Extract a check's metadata from its output, leaving out (with a warning) values over
+/// its `max_metadata_length`
+fn check_metadata(
+    project_root: &Path,
+    check: &Verification,
+    output: &str,
+    json: bool,
+    ui: &Ui,
+) -> BTreeMap<String, MetadataValue> {
+    if check.metadata.is_empty() {
+        return BTreeMap::new();
+    }
+    let mut metadata = extract_metadata(project_root, output, &check.metadata);
+    let max_length = check
+        .max_metadata_length
+        .unwrap_or(crate::config::DEFAULT_MAX_METADATA_LENGTH);
+    for (key, length) in drop_oversized(&mut metadata, max_length) {
+        if !json {
+            ui.print_warning(&format!(
+                "{}: metadata '{}' is {} bytes, over max_metadata_length ({}), so it wasn't stored",
+                check.name, key, length, max_length
+            ));
+        }
+    }
+    metadata
+}
+
 /// Execute a verification in per_file mode
 #[allow(clippy::too_many_arguments)]
 fn execute_per_file(
@@ -1126,15 +3415,36 @@ fn execute_per_file(
     } else {
         cached.map(|c| &c.file_hashes).cloned().unwrap_or_default()
     };
-    let stale_files = get_stale_files_from_cache(&cached_file_hashes, hash_result);
+    let mut stale_files = get_stale_files_from_cache(&cached_file_hashes, hash_result);
+    let previously_failed = cached
+        .map(|c| c.failed_files.as_slice())
+        .unwrap_or_default();
+    order_stale_files(
+        project_root,
+        &mut stale_files,
+        check.per_file_order,
+        previously_failed,
+    );
     let total_files = hash_result.file_hashes.len();
     let fresh_count = total_files.saturating_sub(stale_files.len());
+    // Files skipped as cached, followed by those run below, for the JSON results
+    let mut file_results: Vec<FileRunJson> = hash_result
+        .file_hashes
+        .iter()
+        .filter(|(file, hash)| cached_file_hashes.get(*file) == Some(*hash))
+        .map(|(file, _)| FileRunJson::cached(file))
+        .collect();
+
     // If no stale files - show cached count and return early
     if stale_files.is_empty() {
         if !json {
             ui.print_per_file_cached(&check.name, total_files, indent);
         }
-        results.add_skipped(&check.name);
+        results.files_of_next(file_results);
+        results.add_cached(
+            &check.name,
+            cache.get(&check.name).and_then(|c| c.duration_ms),
+        );
         executed.insert(check.name.clone(), false);
         was_stale.insert(check.name.clone(), false);
         return Ok(());
@@ -1145,15 +3455,18 @@ fn execute_per_file(
         ui.print_per_file_cached(&check.name, fresh_count, indent);
     }
 
+    let executor = Executor::for_check(check, project_root);
     let start = Instant::now();
+    executor.prepare(project_root, check, hash_result)?;
     let mut last_output = String::new();
-    let mut failed_files: Vec<(String, Option<i32>, String)> = Vec::new();
+    let mut failed_files: Vec<(String, Option<i32>, bool, String)> = Vec::new();
+    let stream = streams_output(check, ui, json);
 
     // Run command for each stale file
     for file_path in &stale_files {
         // Create progress bar showing "check_name: file_path"
         let display_name = format!("{}: {}", check.name, file_path);
-        let file_pb = if !json && ui.use_progress_bars() {
+        let file_pb = if !json && ui.use_progress_bars() && !stream {
             Some(create_running_indicator(&display_name, indent))
         } else {
             if !json {
@@ -1166,14 +3479,32 @@ fn execute_per_file(
 
         let command = check.command.as_ref().unwrap();
         let file_start = Instant::now();
-        let (success, exit_code, output) = execute_command(
+        let group = stream && !json;
+        if group {
+            ui.start_output_group(&display_name);
+        }
+        let label = format!("{}:{}", check.name, file_path);
+        let output_stream = stream.then_some(OutputStream { ui, label: &label });
+        let (success, exit_code, output, timed_out) = executor.execute(
+            &check.name,
             command,
             project_root,
             check.timeout_secs,
-            ui.is_verbose(),
+            output_stream,
             &env_vars,
         );
+        if group {
+            ui.end_output_group(&display_name);
+        }
+        let (success, exit_code, output, unmet) = if timed_out {
+            fail_with_reason(output, timeout_message(check))
+        } else {
+            apply_output_expectations(check, success, exit_code, output)
+        };
         let file_duration_ms = file_start.elapsed().as_millis() as u64;
+        let mut file_result = FileRunJson::ran(file_path, success, file_duration_ms, exit_code);
+        file_result.timed_out = timed_out;
+        file_results.push(file_result);
 
         if success {
             // Finish file progress bar as passed
@@ -1191,6 +3522,9 @@ fn execute_per_file(
                 // Verbose mode: print completion line
                 ui.print_pass_indented(&display_name, file_duration_ms, indent);
             }
+            if !json {
+                print_command_output(ui, check, stream, true, &output, None, indent);
+            }
 
             // Update the file hash in cache (partial progress) and save immediately
             // so progress is preserved if process is interrupted
@@ -1215,41 +3549,68 @@ fn execute_per_file(
                 ui.print_fail_indented(&display_name, file_duration_ms, None, indent);
             }
 
-            // Print failure output (in verbose mode, output was already streamed)
-            if !json && !ui.is_verbose() {
-                ui.print_fail_output(Some(&output), indent);
+            if !json {
+                print_command_output(ui, check, stream, false, &output, unmet.as_deref(), indent);
             }
 
             // Track the failure but continue processing other files
-            failed_files.push((file_path.clone(), exit_code, output.clone()));
+            failed_files.push((file_path.clone(), exit_code, timed_out, output.clone()));
         }
 
         last_output = output;
     }
 
+    executor.finish(project_root, check, failed_files.is_empty())?;
+
     // If any files failed, mark check as failed
     if !failed_files.is_empty() {
         let total_duration_ms = start.elapsed().as_millis() as u64;
-        cache.mark_per_file_failed(&check.name, &config_hash);
+        let failed_paths = failed_files
+            .iter()
+            .map(|(file, _, _, _)| file.clone())
+            .collect();
+        cache.mark_per_file_failed(&check.name, &config_hash, failed_paths);
+        let (_, exit_code, timed_out, _) = &failed_files[0];
+        audit_execution(
+            cache.settings(),
+            project_root,
+            check,
+            &hash_result.combined_hash,
+            &config_hash,
+            false,
+            *exit_code,
+            total_duration_ms,
+        )?;
+        cache.record_failure(
+            &check.name,
+            *exit_code,
+            *timed_out,
+            hash_result.combined_hash.clone(),
+        );
+        cache.record_duration(&check.name, total_duration_ms);
         executed.insert(check.name.clone(), true);
         was_stale.insert(check.name.clone(), true);
 
         // Combine all failure outputs
         let combined_output = failed_files
             .iter()
-            .map(|(file, _, output)| format!("=== {} ===\n{}", file, output))
+            .map(|(file, _, _, output)| format!("=== {} ===\n{}", file, output))
             .collect::<Vec<_>>()
             .join("\n");
 
         let empty_metadata = BTreeMap::new();
+        results.files_of_next(file_results);
         results.add_fail(
             &check.name,
             total_duration_ms,
-            failed_files.first().and_then(|(_, code, _)| *code),
-            Some(combined_output),
+            *exit_code,
+            Some(retained_output(check, &combined_output)),
             &empty_metadata,
             prev_metadata.as_ref(),
         );
+        if *timed_out {
+            results.mark_timed_out();
+        }
 
         // Save cache immediately after per_file check fails
         cache.save(project_root)?;
@@ -1258,14 +3619,20 @@ fn execute_per_file(
     }
 
     // Extract metadata from last output (if configured)
-    let metadata = if !check.metadata.is_empty() {
-        extract_metadata(&last_output, &check.metadata)
-    } else {
-        BTreeMap::new()
-    };
+    let metadata = check_metadata(project_root, check, &last_output, json, ui);
 
     // Finalize cache - all files passed
     let total_duration_ms = start.elapsed().as_millis() as u64;
+    audit_execution(
+        cache.settings(),
+        project_root,
+        check,
+        &hash_result.combined_hash,
+        &config_hash,
+        true,
+        Some(0),
+        total_duration_ms,
+    )?;
     cache.finalize_per_file(
         &check.name,
         &config_hash,
@@ -1273,9 +3640,11 @@ fn execute_per_file(
         hash_result.file_hashes.clone(),
         metadata.clone(),
     );
+    cache.record_duration(&check.name, total_duration_ms);
 
     executed.insert(check.name.clone(), false);
     was_stale.insert(check.name.clone(), true);
+    results.files_of_next(file_results);
     results.add_pass(
         &check.name,
         total_duration_ms,
@@ -1291,20 +3660,26 @@ fn execute_per_file(
 }
 
 /// Run checks for a subproject
+#[allow(clippy::too_many_arguments)]
 fn run_checks_subproject(
-    parent_root: &Path,
     subproject: &Subproject,
+    tree: &mut ProjectTree,
     names: &[String],
     force: bool,
     json: bool,
     ui: &Ui,
     indent: usize,
+    journal: &mut RunJournal,
+    attribution: Option<&Attribution>,
 ) -> Result<RunResults> {
-    let subproject_dir = parent_root.join(&subproject.path);
-    let subproject_config_path = subproject_dir.join("verify.yaml");
-
-    let sub_config = Config::load_with_base(&subproject_config_path, &subproject_dir)?;
-    let mut sub_cache = CacheState::load(&subproject_dir)?;
+    let ProjectTree {
+        dir: subproject_dir,
+        config: sub_config,
+        cache: sub_cache,
+        subprojects,
+        ..
+    } = tree;
+    sub_cache.attribute_to(attribution.cloned());
 
     // For human output, print subproject header
     if !json {
@@ -1313,26 +3688,30 @@ fn run_checks_subproject(
 
     // Recursively run checks with the same name filter
     let sub_results = run_checks_recursive(
-        &subproject_dir,
-        &sub_config,
-        &mut sub_cache,
+        subproject_dir,
+        sub_config,
+        sub_cache,
+        subprojects,
+        journal,
         names,
-        force,
+        if force { Force::All } else { Force::None },
         json,
         ui,
         indent + 1,
     )?;
 
     // Clean up orphaned cache entries
-    let valid_names: std::collections::HashSet<String> = sub_config
-        .verifications
-        .iter()
-        .map(|item| item.name().to_string())
-        .collect();
-    sub_cache.cleanup_orphaned(&valid_names);
+    if statedir::writes_enabled() {
+        let valid_names: std::collections::HashSet<String> = sub_config
+            .verifications
+            .iter()
+            .map(|item| item.name().to_string())
+            .collect();
+        sub_cache.cleanup_orphaned(&valid_names);
+    }
 
     // Save subproject cache
-    sub_cache.save(&subproject_dir)?;
+    sub_cache.save(subproject_dir)?;
 
     Ok(sub_results)
 }
@@ -1351,12 +3730,10 @@ mod tests {
     ) -> Verification {
         Verification {
             name: name.to_string(),
-            command: Some("echo test".to_string()),
+            command: Some("echo test".into()),
             cache_paths: cache_paths.into_iter().map(|s| s.to_string()).collect(),
             depends_on: depends_on.into_iter().map(|s| s.to_string()).collect(),
-            timeout_secs: None,
-            metadata: HashMap::new(),
-            per_file: false,
+            ..Default::default()
         }
     }
 
@@ -1446,6 +3823,41 @@ mod tests {
         assert_eq!(stale[0], "file1.txt");
     }
 
+    // ==================== order_stale_files tests ====================
+
+    #[test]
+    fn test_order_stale_files_failed_first() {
+        let mut files = vec![
+            "a.txt".to_string(),
+            "b.txt".to_string(),
+            "c.txt".to_string(),
+        ];
+        let previously_failed = vec!["c.txt".to_string()];
+
+        order_stale_files(
+            Path::new("."),
+            &mut files,
+            PerFileOrder::FailedFirst,
+            &previously_failed,
+        );
+        assert_eq!(files, vec!["c.txt", "a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_order_stale_files_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("big.txt"), "xxxxxxxxxx").unwrap();
+        std::fs::write(temp_dir.path().join("small.txt"), "x").unwrap();
+        let mut files = vec![
+            "big.txt".to_string(),
+            "missing.txt".to_string(),
+            "small.txt".to_string(),
+        ];
+
+        order_stale_files(temp_dir.path(), &mut files, PerFileOrder::Size, &[]);
+        assert_eq!(files, vec!["small.txt", "big.txt", "missing.txt"]);
+    }
+
     // ==================== compute_staleness tests ====================
 
     #[test]
@@ -1573,6 +3985,31 @@ mod tests {
         assert_eq!(result, VerificationStatus::Verified);
     }
 
+    #[test]
+    fn test_compute_staleness_aggregate_with_guard_cache_paths() {
+        // An aggregate with its own cache_paths also needs them recorded as verified
+        let mut check = make_verification("release", vec!["NOTES.md"], vec!["build"]);
+        check.command = None;
+        let hash_result = make_hash_result("hash123", vec![]);
+        let mut cache = CacheState::new();
+        let dep_staleness = HashMap::from([("build".to_string(), false)]);
+
+        let result = compute_status(&check, &hash_result, &cache, &dep_staleness);
+        assert!(matches!(result, VerificationStatus::Unverified { .. }));
+
+        cache.update(
+            "release",
+            true,
+            check.config_hash(),
+            Some("hash123".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        let result = compute_status(&check, &hash_result, &cache, &dep_staleness);
+        assert_eq!(result, VerificationStatus::Verified);
+    }
+
     #[test]
     fn test_compute_staleness_aggregate_stale_when_dep_stale() {
         // Aggregate check (no command) is stale when a dep is stale
@@ -1693,12 +4130,56 @@ mod tests {
 
         match result {
             VerificationStatus::Unverified {
-                reason: UnverifiedReason::ConfigChanged,
-            } => {}
+                reason: UnverifiedReason::ConfigChanged { changed },
+            } => assert!(changed.is_empty(), "no components recorded: {:?}", changed),
             other => panic!("Expected ConfigChanged, got {:?}", other),
         }
     }
 
+    #[test]
+    fn test_compute_status_explains_config_change() {
+        let old = make_verification("test", vec!["src/**/*.rs"], vec![]);
+        let hash_result = make_hash_result("hash123", vec![("src/main.rs", "abc")]);
+
+        let mut cache = CacheState::new();
+        cache.update(
+            "test",
+            true,
+            old.config_hash(),
+            Some("hash123".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.record_config_components("test", old.config_components());
+
+        let mut edited = old.clone();
+        edited.command = Some("echo edited".into());
+        edited.timeout_secs = Some(60);
+        let result = compute_status(&edited, &hash_result, &cache, &HashMap::new());
+        assert_eq!(
+            result,
+            VerificationStatus::Unverified {
+                reason: UnverifiedReason::ConfigChanged {
+                    changed: vec!["command".to_string(), "timeout".to_string()],
+                },
+            }
+        );
+
+        // Settings only hashed when set count as changed when added
+        let mut salted = old.clone();
+        salted.cache_salt = Some("v2".to_string());
+        let result = compute_status(&salted, &hash_result, &cache, &HashMap::new());
+        assert_eq!(
+            result,
+            VerificationStatus::Unverified {
+                reason: UnverifiedReason::ConfigChanged {
+                    changed: vec!["cache_salt".to_string()],
+                },
+            }
+        );
+    }
+
     #[test]
     fn test_compute_staleness_after_failure() {
         // After a failed run, check should need to run again
@@ -1859,8 +4340,8 @@ mod tests {
     #[test]
     fn test_execute_command_success() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, exit_code, output) =
-            execute_command("echo 'hello world'", temp_dir.path(), None, false, &[]);
+        let (success, exit_code, output, _) =
+            execute_command("echo 'hello world'", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert_eq!(exit_code, Some(0));
@@ -1870,8 +4351,8 @@ mod tests {
     #[test]
     fn test_execute_command_failure() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, exit_code, _output) =
-            execute_command("exit 1", temp_dir.path(), None, false, &[]);
+        let (success, exit_code, _output, _) =
+            execute_command("exit 1", temp_dir.path(), None, None, &[]);
 
         assert!(!success);
         assert_eq!(exit_code, Some(1));
@@ -1880,18 +4361,89 @@ mod tests {
     #[test]
     fn test_execute_command_nonzero_exit_code() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, exit_code, _output) =
-            execute_command("exit 42", temp_dir.path(), None, false, &[]);
+        let (success, exit_code, _output, _) =
+            execute_command("exit 42", temp_dir.path(), None, None, &[]);
 
         assert!(!success);
         assert_eq!(exit_code, Some(42));
     }
 
+    #[test]
+    fn test_execute_command_timeout_stops_process_group() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let start = Instant::now();
+        // The background sleep holds the output pipe open, so it has to be stopped too
+        let (success, exit_code, output, timed_out) = execute_command(
+            "sleep 30 & echo started; sleep 30; echo never",
+            temp_dir.path(),
+            Some(1),
+            None,
+            &[],
+        );
+
+        assert!(!success);
+        assert!(timed_out);
+        assert_eq!(exit_code, None);
+        assert!(output.contains("started"), "{}", output);
+        assert!(!output.contains("never"), "{}", output);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // The command's own process is gone, but what it left running is still stopped
+        let start = Instant::now();
+        let (success, _, _, timed_out) =
+            execute_command("sleep 30 & exit 0", temp_dir.path(), Some(1), None, &[]);
+        assert!(!success);
+        assert!(timed_out);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let (success, _, _, timed_out) =
+            execute_command("true", temp_dir.path(), Some(5), None, &[]);
+        assert!(success);
+        assert!(!timed_out);
+    }
+
+    #[test]
+    fn test_apply_output_expectations() {
+        let mut check = make_verification("lint", vec![], vec![]);
+        check.expect_output = Some(r"\d+ files checked".to_string());
+        check.expect_not_output = Some("(?m)^error:".to_string());
+
+        let (success, exit_code, _, unmet) =
+            apply_output_expectations(&check, true, Some(0), "12 files checked\n".to_string());
+        assert!(success);
+        assert_eq!(exit_code, Some(0));
+        assert_eq!(unmet, None);
+
+        let (success, exit_code, output, unmet) =
+            apply_output_expectations(&check, true, Some(0), "nothing to do".to_string());
+        assert!(!success);
+        assert_eq!(exit_code, None);
+        let unmet = unmet.unwrap();
+        assert_eq!(unmet, r"expect_output: no match for /\d+ files checked/");
+        assert_eq!(output, format!("nothing to do\n{}\n", unmet));
+
+        let output = "3 files checked\nerror: unused import\n".to_string();
+        let (success, _, _, unmet) = apply_output_expectations(&check, true, Some(0), output);
+        assert!(!success);
+        assert_eq!(
+            unmet.unwrap(),
+            r#"expect_not_output: /(?m)^error:/ matched "error: unused import""#
+        );
+
+        // A failing command keeps its own exit code and output
+        let (success, exit_code, output, unmet) =
+            apply_output_expectations(&check, false, Some(2), "crashed".to_string());
+        assert!(!success);
+        assert_eq!(exit_code, Some(2));
+        assert_eq!(output, "crashed");
+        assert_eq!(unmet, None);
+    }
+
     #[test]
     fn test_execute_command_captures_stdout() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) =
-            execute_command("echo 'stdout test'", temp_dir.path(), None, false, &[]);
+        let (success, _, output, _) =
+            execute_command("echo 'stdout test'", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert!(output.contains("stdout test"));
@@ -1900,8 +4452,8 @@ mod tests {
     #[test]
     fn test_execute_command_captures_stderr() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) =
-            execute_command("echo 'stderr test' >&2", temp_dir.path(), None, false, &[]);
+        let (success, _, output, _) =
+            execute_command("echo 'stderr test' >&2", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert!(output.contains("stderr test"));
@@ -1910,11 +4462,11 @@ mod tests {
     #[test]
     fn test_execute_command_captures_both_stdout_stderr() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) = execute_command(
+        let (success, _, output, _) = execute_command(
             "echo 'stdout'; echo 'stderr' >&2",
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -1927,8 +4479,8 @@ mod tests {
     fn test_execute_command_with_env_var() {
         let temp_dir = tempfile::tempdir().unwrap();
         let env_vars = [("MY_TEST_VAR", "test_value")];
-        let (success, _, output) =
-            execute_command("echo $MY_TEST_VAR", temp_dir.path(), None, false, &env_vars);
+        let (success, _, output, _) =
+            execute_command("echo $MY_TEST_VAR", temp_dir.path(), None, None, &env_vars);
 
         assert!(success);
         assert!(output.contains("test_value"));
@@ -1939,8 +4491,8 @@ mod tests {
         // Test the specific VERIFY_FILE env var used in per_file mode
         let temp_dir = tempfile::tempdir().unwrap();
         let env_vars = [("VERIFY_FILE", "src/main.rs")];
-        let (success, _, output) =
-            execute_command("echo $VERIFY_FILE", temp_dir.path(), None, false, &env_vars);
+        let (success, _, output, _) =
+            execute_command("echo $VERIFY_FILE", temp_dir.path(), None, None, &env_vars);
 
         assert!(success);
         assert!(output.contains("src/main.rs"));
@@ -1950,8 +4502,8 @@ mod tests {
     fn test_execute_command_multiple_env_vars() {
         let temp_dir = tempfile::tempdir().unwrap();
         let env_vars = [("VAR1", "value1"), ("VAR2", "value2")];
-        let (success, _, output) =
-            execute_command("echo $VAR1 $VAR2", temp_dir.path(), None, false, &env_vars);
+        let (success, _, output, _) =
+            execute_command("echo $VAR1 $VAR2", temp_dir.path(), None, None, &env_vars);
 
         assert!(success);
         assert!(output.contains("value1"));
@@ -1964,8 +4516,8 @@ mod tests {
         // Create a file in the temp directory
         std::fs::write(temp_dir.path().join("test.txt"), "content").unwrap();
 
-        let (success, _, output) =
-            execute_command("ls test.txt", temp_dir.path(), None, false, &[]);
+        let (success, _, output, _) =
+            execute_command("ls test.txt", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert!(output.contains("test.txt"));
@@ -1974,11 +4526,11 @@ mod tests {
     #[test]
     fn test_execute_command_multiline_output() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) = execute_command(
+        let (success, _, output, _) = execute_command(
             "echo 'line1'; echo 'line2'; echo 'line3'",
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -1992,8 +4544,13 @@ mod tests {
     fn test_execute_command_verbose_mode() {
         let temp_dir = tempfile::tempdir().unwrap();
         // In verbose mode, output should still be captured
-        let (success, exit_code, output) =
-            execute_command("echo 'verbose test'", temp_dir.path(), None, true, &[]);
+        let ui = Ui::new(true);
+        let stream = Some(OutputStream {
+            ui: &ui,
+            label: "test",
+        });
+        let (success, exit_code, output, _) =
+            execute_command("echo 'verbose test'", temp_dir.path(), None, stream, &[]);
 
         assert!(success);
         assert_eq!(exit_code, Some(0));
@@ -2003,7 +4560,7 @@ mod tests {
     #[test]
     fn test_execute_command_empty_output() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) = execute_command("true", temp_dir.path(), None, false, &[]);
+        let (success, _, output, _) = execute_command("true", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert!(output.is_empty() || output.trim().is_empty());
@@ -2012,11 +4569,11 @@ mod tests {
     #[test]
     fn test_execute_command_special_characters_in_output() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) = execute_command(
+        let (success, _, output, _) = execute_command(
             r#"echo 'special: $VAR "quoted" `backticks`'"#,
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -2024,14 +4581,54 @@ mod tests {
         assert!(output.contains("special:"));
     }
 
+    #[test]
+    fn test_execute_command_binary_output() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let command = r"printf 'ok\033[1mbold\033[0m\r\n\000\377\033]0;title\007\n'";
+        let ui = Ui::new(true);
+        let streamed = OutputStream {
+            ui: &ui,
+            label: "test",
+        };
+        for stream in [None, Some(streamed)] {
+            let (success, _, output, _) =
+                execute_command(command, temp_dir.path(), None, stream, &[]);
+            assert!(success);
+            assert_eq!(
+                output,
+                "ok\x1b[1mbold\x1b[0m\n\u{FFFD}\u{FFFD}\u{FFFD}]0;title\u{FFFD}\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_retained_output() {
+        let mut check = make_verification("render", vec![], vec![]);
+        assert_eq!(
+            retained_output(&check, "line 1\nline 2\n"),
+            "line 1\nline 2\n"
+        );
+
+        check.max_output = Some(7);
+        assert_eq!(
+            retained_output(&check, "line 1\nline 2\n"),
+            "[7 bytes of output omitted]\nline 2\n"
+        );
+        // Never cuts a character in half
+        assert_eq!(
+            retained_output(&check, "ééééé"),
+            "[4 bytes of output omitted]\nééé"
+        );
+    }
+
     #[test]
     fn test_execute_command_piped_commands() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, _, output) = execute_command(
+        let (success, _, output, _) = execute_command(
             "echo 'abc\ndef\nghi' | grep 'def'",
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -2043,11 +4640,11 @@ mod tests {
     #[test]
     fn test_execute_command_command_not_found() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let (success, exit_code, _output) = execute_command(
+        let (success, exit_code, _output, _) = execute_command(
             "nonexistent_command_12345",
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -2062,8 +4659,8 @@ mod tests {
         let file_path = temp_dir.path().join("input.txt");
         std::fs::write(&file_path, "file contents here").unwrap();
 
-        let (success, _, output) =
-            execute_command("cat input.txt", temp_dir.path(), None, false, &[]);
+        let (success, _, output, _) =
+            execute_command("cat input.txt", temp_dir.path(), None, None, &[]);
 
         assert!(success);
         assert!(output.contains("file contents here"));
@@ -2073,11 +4670,11 @@ mod tests {
     fn test_execute_command_writes_file_in_workdir() {
         let temp_dir = tempfile::tempdir().unwrap();
 
-        let (success, _, _output) = execute_command(
+        let (success, _, _output, _) = execute_command(
             "echo 'written content' > output.txt",
             temp_dir.path(),
             None,
-            false,
+            None,
             &[],
         );
 
@@ -2094,8 +4691,8 @@ mod tests {
         std::fs::write(&file_path, "test content").unwrap();
 
         let env_vars = [("VERIFY_FILE", "test_file.txt")];
-        let (success, _, output) =
-            execute_command("cat $VERIFY_FILE", temp_dir.path(), None, false, &env_vars);
+        let (success, _, output, _) =
+            execute_command("cat $VERIFY_FILE", temp_dir.path(), None, None, &env_vars);
 
         assert!(success);
         assert!(output.contains("test content"));
@@ -2126,47 +4723,79 @@ mod tests {
             verifications: vec![
                 VerificationItem::Verification(Verification {
                     name: "build".to_string(),
-                    command: Some("echo build-ok".to_string()),
+                    command: Some("echo build-ok".into()),
                     cache_paths: vec!["lib/**/*".to_string()],
-                    depends_on: vec![],
-                    timeout_secs: None,
-                    metadata: HashMap::new(),
-                    per_file: false,
+                    ..Default::default()
                 }),
                 VerificationItem::Verification(Verification {
                     name: "app".to_string(),
-                    command: Some("echo app-ok".to_string()),
+                    command: Some("echo app-ok".into()),
                     cache_paths: vec!["app/**/*".to_string()],
                     depends_on: vec!["build".to_string()],
-                    timeout_secs: None,
-                    metadata: HashMap::new(),
-                    per_file: false,
+                    ..Default::default()
                 }),
             ],
+            ..Default::default()
         };
 
         let ui = Ui::new(false);
         let mut cache = CacheState::new();
+        let mut journal = RunJournal::disabled();
 
         // First run: both checks should execute
-        let results =
-            run_checks_recursive(root, &config, &mut cache, &[], false, true, &ui, 0).unwrap();
+        let results = run_checks_recursive(
+            root,
+            &config,
+            &mut cache,
+            &mut Subprojects::default(),
+            &mut journal,
+            &[],
+            Force::None,
+            true,
+            &ui,
+            0,
+        )
+        .unwrap();
         assert_eq!(results.passed, 2, "First run: both checks should pass");
         assert_eq!(results.skipped, 0, "First run: nothing should be skipped");
 
         // Second run with no changes: both should be cached
-        let results =
-            run_checks_recursive(root, &config, &mut cache, &[], false, true, &ui, 0).unwrap();
+        let results = run_checks_recursive(
+            root,
+            &config,
+            &mut cache,
+            &mut Subprojects::default(),
+            &mut journal,
+            &[],
+            Force::None,
+            true,
+            &ui,
+            0,
+        )
+        .unwrap();
         assert_eq!(results.skipped, 2, "Second run: both should be cached");
         assert_eq!(results.passed, 0, "Second run: nothing should re-run");
 
-        // Now change only lib/code.rs (build's files)
+        // Now change only lib/code.rs (build's files). Runs are separate invocations,
+        // which don't remember hashes from before the edit.
         std::fs::write(root.join("lib/code.rs"), "fn lib_v2() {}").unwrap();
+        hasher::files_may_have_changed();
 
         // Third run: build should re-run (files changed),
         // AND app should also re-run (dependency was stale)
-        let results =
-            run_checks_recursive(root, &config, &mut cache, &[], false, true, &ui, 0).unwrap();
+        let results = run_checks_recursive(
+            root,
+            &config,
+            &mut cache,
+            &mut Subprojects::default(),
+            &mut journal,
+            &[],
+            Force::None,
+            true,
+            &ui,
+            0,
+        )
+        .unwrap();
 
         assert_eq!(
             results.passed, 2,
@@ -2177,4 +4806,46 @@ mod tests {
             "Third run: app should NOT be cached when its dependency re-ran"
         );
     }
+
+    #[test]
+    fn test_plan_run_follows_names_and_dependencies() {
+        let config: Config = serde_yml::from_str(
+            r#"
+verifications:
+  - name: lint
+    command: echo lint
+  - name: build
+    command: echo build
+  - name: test
+    command: echo test
+    depends_on: [build]
+"#,
+        )
+        .unwrap();
+
+        let mut cache = CacheState::new();
+        cache.update(
+            "build",
+            true,
+            "config".to_string(),
+            None,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.record_duration("build", 2000);
+
+        let all = plan_run(&config, &cache, &Subprojects::default(), &[]).unwrap();
+        assert_eq!(all, vec![None, Some(2000), None]);
+
+        // Filtering to test still visits its dependency, once
+        let filtered = plan_run(
+            &config,
+            &cache,
+            &Subprojects::default(),
+            &["test".to_string()],
+        )
+        .unwrap();
+        assert_eq!(filtered, vec![Some(2000), None]);
+    }
 }
@@ -0,0 +1,354 @@
+use crate::cache::CacheState;
+use crate::config::Config;
+use crate::hashcache;
+use crate::hasher;
+use crate::runner;
+use crate::watch::FileWatcher;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// How often the watch thread checks whether its connection closed
+const WATCH_POLL: Duration = Duration::from_millis(500);
+
+/// Serve JSON-RPC 2.0 requests on stdin/stdout, one message per line, until stdin closes
+pub fn serve_stdio(project_root: &Path, config_path: &Path) -> Result<()> {
+    let stdin = std::io::stdin();
+    Connection::new(project_root, config_path, Box::new(std::io::stdout())).serve(stdin.lock())
+}
+
+/// Serve JSON-RPC 2.0 on a Unix socket, one client connection at a time
+#[cfg(unix)]
+pub fn serve_socket(project_root: &Path, config_path: &Path, socket: &Path) -> Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous server would make bind fail
+    if socket.exists() {
+        std::fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove stale socket: {}", socket.display()))?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    eprintln!("verify serve: listening on {}", socket.display());
+
+    for stream in listener.incoming() {
+        let stream = stream.context("Failed to accept connection")?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let connection = Connection::new(project_root, config_path, Box::new(stream));
+        // A client that misbehaves or disconnects shouldn't stop the server
+        if let Err(e) = connection.serve(reader) {
+            eprintln!("verify serve: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve_socket(_project_root: &Path, _config_path: &Path, _socket: &Path) -> Result<()> {
+    anyhow::bail!("--socket is only supported on Unix");
+}
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// A client session. Config and cache are reloaded for every request so changes made
+/// outside the server are seen, while file hashes stay cached in memory between requests.
+struct Connection {
+    project_root: PathBuf,
+    config_path: PathBuf,
+    writer: SharedWriter,
+    /// Set when the client goes away, to stop the watch thread
+    closed: Arc<AtomicBool>,
+    watching: bool,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// An error response: (code, message)
+type RpcError = (i64, String);
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct StatusParams {
+    name: Option<String>,
+    only_unverified: bool,
+    flat: bool,
+}
+
+#[derive(Deserialize)]
+struct ExplainParams {
+    name: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RunParams {
+    names: Vec<String>,
+    force: bool,
+}
+
+impl Connection {
+    fn new(project_root: &Path, config_path: &Path, writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            config_path: config_path.to_path_buf(),
+            writer: Arc::new(Mutex::new(writer)),
+            closed: Arc::new(AtomicBool::new(false)),
+            watching: false,
+        }
+    }
+
+    fn serve(mut self, reader: impl BufRead) -> Result<()> {
+        let result = self.serve_lines(reader);
+        self.closed.store(true, Ordering::Relaxed);
+        result
+    }
+
+    fn serve_lines(&mut self, reader: impl BufRead) -> Result<()> {
+        for line in reader.lines() {
+            let line = line.context("Failed to read request")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let request: Request = match serde_json::from_str::<Value>(&line) {
+                Err(e) => {
+                    self.respond(Value::Null, Err((PARSE_ERROR, e.to_string())))?;
+                    continue;
+                }
+                Ok(value) => match serde_json::from_value(value) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        self.respond(Value::Null, Err((INVALID_REQUEST, e.to_string())))?;
+                        continue;
+                    }
+                },
+            };
+
+            let result = self.handle(&request.method, request.params.unwrap_or(Value::Null));
+            hashcache::save_all();
+            // Requests without an id are notifications, which get no response
+            if let Some(id) = request.id {
+                self.respond(id, result)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        // Files may have been edited since the last request
+        hasher::files_may_have_changed();
+        match method {
+            "status" => {
+                let params: StatusParams = parse_params(params)?;
+                let (config, cache) = self.load().map_err(internal)?;
+                if let Some(ref name) = params.name
+                    && config.get(name).is_none()
+                {
+                    return Err((INVALID_PARAMS, format!("Unknown check: {}", name)));
+                }
+                let mut output =
+                    runner::collect_status(&self.project_root, &config, &cache, params.name)
+                        .map_err(internal)?;
+                if params.only_unverified {
+                    output.retain_unverified();
+                }
+                if params.flat {
+                    output.flatten();
+                }
+                serde_json::to_value(output).map_err(internal)
+            }
+            "explain" => {
+                let params: ExplainParams =
+                    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                let (config, cache) = self.load().map_err(internal)?;
+                if config.get(&params.name).is_none() {
+                    return Err((INVALID_PARAMS, format!("Unknown check: {}", params.name)));
+                }
+                let output =
+                    runner::collect_status(&self.project_root, &config, &cache, Some(params.name))
+                        .map_err(internal)?;
+                serde_json::to_value(output).map_err(internal)
+            }
+            "run" => {
+                let params: RunParams = parse_params(params)?;
+                let (config, mut cache) = self.load().map_err(internal)?;
+                for name in &params.names {
+                    if config.get(name).is_none() {
+                        return Err((INVALID_PARAMS, format!("Unknown check: {}", name)));
+                    }
+                }
+                let output = runner::run_checks_collect(
+                    &self.project_root,
+                    &config,
+                    &mut cache,
+                    params.names,
+                    params.force,
+                )
+                .map_err(internal)?;
+                serde_json::to_value(output).map_err(internal)
+            }
+            "watchEvents" => {
+                if !self.watching {
+                    self.start_watching().map_err(internal)?;
+                    self.watching = true;
+                }
+                Ok(json!({ "subscribed": true }))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+
+    fn load(&self) -> Result<(Config, CacheState)> {
+        let config = Config::load(&self.config_path)?;
+        let settings = Arc::new(config.settings(&self.project_root));
+        let cache = CacheState::load(&self.project_root, &settings)?;
+        Ok((config, cache))
+    }
+
+    /// Send a `filesChanged` notification, with project-relative paths, whenever files
+    /// that can affect check status change
+    fn start_watching(&self) -> Result<()> {
+        let watcher = FileWatcher::new(&self.project_root)?;
+        let root = self.project_root.canonicalize()?;
+        let writer = Arc::clone(&self.writer);
+        let closed = Arc::clone(&self.closed);
+        std::thread::spawn(move || {
+            while !closed.load(Ordering::Relaxed) {
+                let changed = watcher.wait(WATCH_POLL);
+                if changed.is_empty() {
+                    continue;
+                }
+                let paths: Vec<String> = changed
+                    .iter()
+                    .filter_map(|path| path.strip_prefix(&root).ok())
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+                    .collect();
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "filesChanged",
+                    "params": { "paths": paths },
+                });
+                if write_message(&writer, &notification).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn respond(&self, id: Value, result: Result<Value, RpcError>) -> Result<()> {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&self.writer, &message)
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de> + Default>(params: Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+fn internal(e: impl std::fmt::Display) -> RpcError {
+    (INTERNAL_ERROR, format!("{:#}", e))
+}
+
+fn write_message(writer: &SharedWriter, message: &Value) -> Result<()> {
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    writeln!(writer, "{}", message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Collects everything the server writes
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn serve(project_root: &Path, requests: &str) -> Vec<Value> {
+        let output = Output::default();
+        let connection = Connection::new(
+            project_root,
+            &project_root.join("verify.yaml"),
+            Box::new(output.clone()),
+        );
+        connection.serve(requests.as_bytes()).unwrap();
+        let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_status_run_and_errors() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("verify.yaml"),
+            "verifications:\n  - name: build\n    command: echo ok\n    cache_paths: [\"*.txt\"]\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+
+        let responses = serve(
+            dir.path(),
+            r#"{"jsonrpc":"2.0","id":1,"method":"status"}
+{"jsonrpc":"2.0","id":2,"method":"run","params":{"names":["build"]}}
+{"jsonrpc":"2.0","id":3,"method":"explain","params":{"name":"build"}}
+{"jsonrpc":"2.0","method":"status"}
+{"jsonrpc":"2.0","id":4,"method":"explain","params":{"name":"nope"}}
+{"jsonrpc":"2.0","id":5,"method":"deploy"}
+not json
+"#,
+        );
+
+        assert_eq!(responses.len(), 6, "{:?}", responses);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["checks"][0]["status"], "unverified");
+        assert_eq!(responses[1]["result"]["summary"]["passed"], 1);
+        assert_eq!(responses[2]["result"]["checks"][0]["status"], "verified");
+        assert_eq!(responses[3]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[4]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[5]["error"]["code"], PARSE_ERROR);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::bundle;
+use crate::cache::{self, CacheState};
+use crate::config::Config;
+use crate::statedir;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+const LOCK_FILE: &str = "verify.lock";
+
+/// Save the lock files of the project and every subproject as snapshot `name`, replacing
+/// an earlier snapshot of that name. Returns the number of lock files saved.
+pub fn save(project_root: &Path, config: &Config, name: &str) -> Result<usize> {
+    let dir = snapshot_dir(project_root, name)?;
+    let locks = bundle::collect_lock_files(project_root, config)?;
+    statedir::create(project_root)?;
+
+    // Write into a staging directory and swap it in, so a failed save keeps the old snapshot
+    let staging = dir.with_file_name(format!(".{}.saving", name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("Failed to remove {}", staging.display()))?;
+    }
+    for (key, contents) in &locks {
+        let lock_dir = staging.join(key);
+        fs::create_dir_all(&lock_dir)
+            .with_context(|| format!("Failed to create {}", lock_dir.display()))?;
+        let path = lock_dir.join(LOCK_FILE);
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write snapshot file: {}", path.display()))?;
+    }
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    }
+    fs::rename(&staging, &dir)
+        .with_context(|| format!("Failed to save snapshot: {}", dir.display()))?;
+    Ok(locks.len())
+}
+
+/// Restore snapshot `name`: every saved lock file replaces the current one, and projects
+/// that had no lock file when the snapshot was taken lose theirs. Returns the number of
+/// lock files restored.
+pub fn restore(project_root: &Path, config: &Config, name: &str) -> Result<usize> {
+    let dir = snapshot_dir(project_root, name)?;
+    if !dir.is_dir() {
+        let saved = list(project_root)?;
+        let hint = if saved.is_empty() {
+            " (no snapshots saved)".to_string()
+        } else {
+            format!(" (saved snapshots: {})", saved.join(", "))
+        };
+        anyhow::bail!("No snapshot named '{}'{}", name, hint);
+    }
+
+    let mut saved = BTreeMap::new();
+    read_lock_files(&dir, Path::new(""), &mut saved)?;
+    // Don't touch anything unless every saved lock file is readable
+    for (key, contents) in &saved {
+        serde_json::from_slice::<CacheState>(contents).with_context(|| {
+            format!(
+                "Snapshot '{}' has an invalid lock file: {}",
+                name,
+                Path::new(key).join(LOCK_FILE).display()
+            )
+        })?;
+    }
+
+    let current = bundle::collect_lock_files(project_root, config)?;
+    let mut restored = 0;
+    for (key, contents) in &saved {
+        let project_dir = project_root.join(key);
+        // The subproject may have been removed since the snapshot was taken
+        if !project_dir.is_dir() {
+            continue;
+        }
+        cache::write_lock_file(&project_dir, contents)?;
+        restored += 1;
+    }
+    for key in current.keys().filter(|key| !saved.contains_key(*key)) {
+        cache::remove_lock_file(&project_root.join(key))?;
+    }
+    Ok(restored)
+}
+
+/// Names of the saved snapshots, sorted
+pub fn list(project_root: &Path) -> Result<Vec<String>> {
+    let dir = statedir::state_dir(project_root).join(SNAPSHOTS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() && !name.starts_with('.') {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Directory of snapshot `name`. Names are used as directory names, so they're limited to
+/// letters, digits, `-`, `_` and `.`, and can't start with `.`.
+fn snapshot_dir(project_root: &Path, name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid snapshot name '{}': use letters, digits, '-', '_' and '.', not starting with '.'",
+            name
+        );
+    }
+    Ok(statedir::state_dir(project_root)
+        .join(SNAPSHOTS_DIR)
+        .join(name))
+}
+
+/// Collect the lock files under a snapshot directory, keyed by project directory
+fn read_lock_files(
+    snapshot: &Path,
+    relative_dir: &Path,
+    locks: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<()> {
+    let dir = snapshot.join(relative_dir);
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let relative = relative_dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            read_lock_files(snapshot, &relative, locks)?;
+        } else if entry.file_name() == LOCK_FILE {
+            let contents = fs::read(entry.path())
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            locks.insert(bundle::bundle_key(relative_dir), contents);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    fn record(project_dir: &Path, name: &str) {
+        let mut cache = CacheState::load(project_dir, &Default::default()).unwrap();
+        cache.update(
+            name,
+            true,
+            "config".to_string(),
+            Some("hash".to_string()),
+            BTreeMap::new(),
+            BTreeMap::new(),
+            false,
+        );
+        cache.save(project_dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("verify.yaml"),
+            "verifications:\n  - name: web\n    path: web\n",
+        )
+        .unwrap();
+        fs::create_dir(root.join("web")).unwrap();
+        fs::write(root.join("web/verify.yaml"), "verifications: []\n").unwrap();
+        let config = Config::load(&root.join("verify.yaml")).unwrap();
+
+        record(root, "build");
+        assert_eq!(save(root, &config, "base").unwrap(), 1);
+        assert_eq!(list(root).unwrap(), vec!["base"]);
+
+        // Experiment: the root result is cleared and the subproject gains a lock file
+        cache::clean_cache(root, &Default::default(), vec![]).unwrap();
+        record(&root.join("web"), "lint");
+
+        assert_eq!(restore(root, &config, "base").unwrap(), 1);
+        assert!(
+            CacheState::load(root, &Default::default())
+                .unwrap()
+                .get("build")
+                .is_some()
+        );
+        assert!(!root.join("web/verify.lock").exists());
+    }
+
+    #[test]
+    fn test_restore_unknown_snapshot() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("verify.yaml"), "verifications: []\n").unwrap();
+        let config = Config::load(&dir.path().join("verify.yaml")).unwrap();
+        save(dir.path(), &config, "base").unwrap();
+
+        let err = restore(dir.path(), &config, "other").unwrap_err();
+        assert!(err.to_string().contains("saved snapshots: base"), "{}", err);
+        assert!(save(dir.path(), &config, "../escape").is_err());
+    }
+}
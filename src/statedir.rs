@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Directory inside each project holding verify's non-lock state (run history, hash
+/// cache, check locks, snapshots, ...)
+const STATE_DIR: &str = ".verify";
+
+/// Environment variable that relocates state directories, overriding `cache_dir`
+pub const CACHE_DIR_ENV: &str = "VERIFY_CACHE_DIR";
+
+/// File in a relocated state directory naming the project it belongs to
+const PROJECT_PATH_FILE: &str = "project-path";
+
+/// Where state directories are relocated to for this process, if anywhere. Set once,
+/// before any state is read, since the hash and config caches of every project use it.
+static RELOCATED: OnceLock<PathBuf> = OnceLock::new();
+
+/// Set when this process must not write verify.lock files or state directories
+static WRITES_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Keep the state of every project touched by this process under `base` instead of
+/// in each project's `.verify/`. Only the first call has any effect.
+pub fn relocate(base: PathBuf) {
+    let _ = RELOCATED.set(base);
+}
+
+/// Leave every project's verify.lock and state directory untouched for the rest of this
+/// process: caches, run history, journals and hash caches are only kept in memory
+pub fn disable_writes() {
+    WRITES_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether this process may write verify.lock files and state directories
+pub fn writes_enabled() -> bool {
+    !WRITES_DISABLED.load(Ordering::Relaxed)
+}
+
+/// The state directory of a project: its `.verify/`, or when relocated, a directory
+/// under the base named after the project's absolute path, so separate worktrees of
+/// the same repository never share one
+pub fn state_dir(project_root: &Path) -> PathBuf {
+    match RELOCATED.get() {
+        Some(base) => base.join(project_key(project_root)),
+        None => project_root.join(STATE_DIR),
+    }
+}
+
+/// Create the project's state directory if needed and return it. A relocated
+/// directory records the project path it belongs to.
+pub fn create(project_root: &Path) -> Result<PathBuf> {
+    let dir = state_dir(project_root);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+
+    if is_relocated() {
+        let record = dir.join(PROJECT_PATH_FILE);
+        if !record.exists() {
+            let project = absolute(project_root);
+            fs::write(&record, format!("{}\n", project.display()))
+                .with_context(|| format!("Failed to write {}", record.display()))?;
+        }
+    }
+    Ok(dir)
+}
+
+fn is_relocated() -> bool {
+    RELOCATED.get().is_some()
+}
+
+/// Relocated directory name: the project directory's name (for finding it by hand)
+/// and a hash of its absolute path
+fn project_key(project_root: &Path) -> String {
+    let project = absolute(project_root);
+    let name = project
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string());
+    let hash = blake3::hash(project.to_string_lossy().as_bytes()).to_hex();
+    format!("{}-{}", name, &hash[..16])
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    // A config path without a directory has an empty parent, meaning the current one
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_project_key_is_per_worktree() {
+        let dir = tempdir().unwrap();
+        let main = dir.path().join("repo");
+        let worktree = dir.path().join("worktrees/repo");
+        fs::create_dir_all(&main).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+
+        let main_key = project_key(&main);
+        assert!(main_key.starts_with("repo-"), "{}", main_key);
+        assert_ne!(main_key, project_key(&worktree));
+        // The same project always maps to the same directory
+        assert_eq!(main_key, project_key(&main.join("../repo")));
+    }
+}
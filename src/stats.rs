@@ -0,0 +1,205 @@
+use crate::output::{RunItemJson, RunResults};
+use crate::statedir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const STATS_FILE: &str = "stats.jsonl";
+
+/// How the cache did in one `verify run`, appended to `.verify/stats.jsonl`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatsRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Checks skipped because their cache was fresh, subprojects' as `subproject/name`
+    #[serde(default)]
+    pub cached: Vec<String>,
+    /// Checks that ran (blocked checks, which didn't run because a dependency failed,
+    /// count as neither)
+    #[serde(default)]
+    pub ran: Vec<String>,
+    /// How long the cached checks took when they last ran
+    pub saved_ms: u64,
+    /// Bytes of file contents read to hash them (files in the hash cache aren't read)
+    pub bytes_hashed: u64,
+    pub duration_ms: u64,
+}
+
+impl StatsRecord {
+    /// The cache statistics of a finished run
+    pub fn from_results(results: &RunResults, bytes_hashed: u64, duration_ms: u64) -> Self {
+        let mut record = StatsRecord {
+            timestamp: Utc::now(),
+            cached: Vec::new(),
+            ran: Vec::new(),
+            saved_ms: 0,
+            bytes_hashed,
+            duration_ms,
+        };
+        record.add_items(&results.results, "");
+        record
+    }
+
+    fn add_items(&mut self, items: &[RunItemJson], prefix: &str) {
+        for item in items {
+            match item {
+                RunItemJson::Check(check) => {
+                    let name = format!("{}{}", prefix, check.name);
+                    if let Some(saved_ms) = check.cache_hit {
+                        self.cached.push(name);
+                        self.saved_ms += saved_ms;
+                    } else if check.result != "skipped" && check.result != "blocked" {
+                        self.ran.push(name);
+                    }
+                }
+                RunItemJson::Subproject(sub) => {
+                    self.add_items(&sub.results, &format!("{}{}/", prefix, sub.name));
+                }
+            }
+        }
+    }
+}
+
+/// Append a run's statistics to the project's stats
+pub fn append(project_root: &Path, record: &StatsRecord) -> Result<()> {
+    if !statedir::writes_enabled() {
+        return Ok(());
+    }
+    statedir::create(project_root)?;
+    let path = stats_path(project_root);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open cache stats: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+        .with_context(|| format!("Failed to write cache stats: {}", path.display()))?;
+    Ok(())
+}
+
+/// Load every recorded run, oldest first. Lines that can't be parsed are skipped.
+pub fn load(project_root: &Path) -> Result<Vec<StatsRecord>> {
+    let path = stats_path(project_root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read cache stats: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn stats_path(project_root: &Path) -> PathBuf {
+    statedir::state_dir(project_root).join(STATS_FILE)
+}
+
+/// Cumulative cache effectiveness over recorded runs (`verify stats`)
+#[derive(Debug, Default, Serialize)]
+pub struct StatsSummary {
+    pub runs: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    pub cached: usize,
+    pub ran: usize,
+    /// Share of checks skipped as cached, from 0 to 1
+    pub hit_rate: f64,
+    pub saved_ms: u64,
+    pub bytes_hashed: u64,
+    /// Per check, most often run first
+    pub checks: Vec<CheckStats>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CheckStats {
+    pub name: String,
+    pub cached: usize,
+    pub ran: usize,
+    pub hit_rate: f64,
+}
+
+fn hit_rate(cached: usize, ran: usize) -> f64 {
+    if cached + ran == 0 {
+        0.0
+    } else {
+        cached as f64 / (cached + ran) as f64
+    }
+}
+
+impl StatsSummary {
+    pub fn new(records: &[StatsRecord]) -> Self {
+        let mut summary = StatsSummary {
+            runs: records.len(),
+            since: records.first().map(|record| record.timestamp),
+            ..Default::default()
+        };
+        let mut checks: BTreeMap<&str, CheckStats> = BTreeMap::new();
+        for record in records {
+            summary.cached += record.cached.len();
+            summary.ran += record.ran.len();
+            summary.saved_ms += record.saved_ms;
+            summary.bytes_hashed += record.bytes_hashed;
+            for name in &record.cached {
+                checks.entry(name).or_default().cached += 1;
+            }
+            for name in &record.ran {
+                checks.entry(name).or_default().ran += 1;
+            }
+        }
+        summary.hit_rate = hit_rate(summary.cached, summary.ran);
+        summary.checks = checks
+            .into_iter()
+            .map(|(name, stats)| CheckStats {
+                name: name.to_string(),
+                hit_rate: hit_rate(stats.cached, stats.ran),
+                ..stats
+            })
+            .collect();
+        // Stable, so ties stay in name order
+        summary.checks.sort_by_key(|check| std::cmp::Reverse(check.ran));
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(cached: &[&str], ran: &[&str], saved_ms: u64) -> StatsRecord {
+        StatsRecord {
+            timestamp: Utc::now(),
+            cached: cached.iter().map(|name| name.to_string()).collect(),
+            ran: ran.iter().map(|name| name.to_string()).collect(),
+            saved_ms,
+            bytes_hashed: 100,
+            duration_ms: 10,
+        }
+    }
+
+    #[test]
+    fn test_append_load_and_summarize() {
+        let dir = tempdir().unwrap();
+        assert!(load(dir.path()).unwrap().is_empty());
+
+        append(dir.path(), &record(&[], &["build", "lint"], 0)).unwrap();
+        append(dir.path(), &record(&["build"], &["lint"], 1500)).unwrap();
+        append(dir.path(), &record(&["build", "lint"], &[], 2000)).unwrap();
+        let records = load(dir.path()).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let summary = StatsSummary::new(&records);
+        assert_eq!(summary.runs, 3);
+        assert_eq!((summary.cached, summary.ran), (3, 3));
+        assert_eq!(summary.hit_rate, 0.5);
+        assert_eq!(summary.saved_ms, 3500);
+        assert_eq!(summary.bytes_hashed, 300);
+        let names: Vec<_> = summary.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["lint", "build"]);
+        assert_eq!((summary.checks[0].cached, summary.checks[0].ran), (1, 2));
+    }
+}
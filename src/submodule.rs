@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A git submodule under a project directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submodule {
+    /// Path relative to the project directory, with forward slashes
+    pub path: String,
+    /// Commit the superproject records for it
+    pub recorded: String,
+}
+
+impl Submodule {
+    /// Whether the submodule is checked out (`git submodule update --init` has run)
+    pub fn is_initialized(&self, dir: &Path) -> bool {
+        dir.join(&self.path).join(".git").exists()
+    }
+
+    /// The commit checked out in the submodule, or the recorded one if it isn't initialized
+    pub fn commit(&self, dir: &Path) -> String {
+        if !self.is_initialized(dir) {
+            return self.recorded.clone();
+        }
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(dir.join(&self.path))
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_else(|| self.recorded.clone())
+    }
+}
+
+/// The submodules under `dir` (limited to `paths` if given). Empty outside a git repository.
+pub fn list(dir: &Path, paths: &[String]) -> Vec<Submodule> {
+    // The directory of a config given as a bare file name
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let output = Command::new("git")
+        .args(["ls-files", "--stage", "--"])
+        .args(paths)
+        .current_dir(dir)
+        .output();
+    let Ok(output) = output.map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    else {
+        return Vec::new();
+    };
+    parse_stage(&output)
+}
+
+/// Gitlink entries (mode 160000) of `git ls-files --stage` output
+fn parse_stage(output: &str) -> Vec<Submodule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (info, path) = line.split_once('\t')?;
+            let mut fields = info.split_whitespace();
+            if fields.next()? != "160000" {
+                return None;
+            }
+            Some(Submodule {
+                path: path.to_string(),
+                recorded: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The commit of each of `paths` (a check's `submodules`), keyed by path, for hashing
+/// alongside its files
+pub fn commits(dir: &Path, paths: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| {
+            path.to_string_lossy()
+                .replace('\\', "/")
+                .trim_end_matches('/')
+                .to_string()
+        })
+        .collect();
+    let submodules = list(dir, &paths);
+    paths
+        .iter()
+        .map(|path| {
+            let submodule = submodules
+                .iter()
+                .find(|submodule| &submodule.path == path)
+                .with_context(|| format!("'{}' is not a git submodule", path))?;
+            Ok((path.clone(), submodule.commit(dir)))
+        })
+        .collect()
+}
+
+/// Submodules under `dir` that have their own verify.yaml, to run as implicit subprojects
+pub fn with_configs(dir: &Path) -> Vec<Submodule> {
+    list(dir, &[])
+        .into_iter()
+        .filter(|submodule| dir.join(&submodule.path).join("verify.yaml").is_file())
+        .collect()
+}
+
+/// Whether a cache_paths pattern can match files inside the directory `path`
+pub fn pattern_reaches(pattern: &str, path: &str) -> bool {
+    let mut pattern_parts = pattern
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".");
+    for part in path.split('/') {
+        match pattern_parts.next() {
+            Some("**") => return true,
+            Some(pattern_part) => {
+                let matches = glob::Pattern::new(pattern_part)
+                    .is_ok_and(|pattern_part| pattern_part.matches(part));
+                if !matches {
+                    return false;
+                }
+            }
+            // The pattern stops at (or above) the submodule itself
+            None => return false,
+        }
+    }
+    pattern_parts.next().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stage() {
+        let output = "100644 0a1b2c3d4e5f60718293a4b5c6d7e8f901234567 0\tREADME.md\n\
+                      160000 1111111111111111111111111111111111111111 0\tvendor/lib\n";
+        assert_eq!(
+            parse_stage(output),
+            vec![Submodule {
+                path: "vendor/lib".to_string(),
+                recorded: "1111111111111111111111111111111111111111".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pattern_reaches() {
+        assert!(pattern_reaches("vendor/lib/**/*.c", "vendor/lib"));
+        assert!(pattern_reaches("./vendor/*/src/*.c", "vendor/lib"));
+        assert!(pattern_reaches("**/*.c", "vendor/lib"));
+        assert!(!pattern_reaches("src/**/*.c", "vendor/lib"));
+        assert!(!pattern_reaches("*.c", "vendor/lib"));
+        assert!(!pattern_reaches("vendor/lib", "vendor/lib"));
+    }
+}
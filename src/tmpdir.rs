@@ -0,0 +1,176 @@
+use crate::statedir;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use tempfile::{NamedTempFile, TempDir};
+
+/// Environment variable naming a check command's temporary directory
+pub const TMPDIR_ENV: &str = "VERIFY_TMPDIR";
+
+const TMP_DIR: &str = "tmp";
+
+/// Projects whose leftover temporary directories this process has already removed
+static SWEPT: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// A fresh directory for one invocation of a check's command, in `.verify/tmp/`, removed
+/// with everything in it when dropped. Its lock file (`<dir>.lock` next to it) stays locked
+/// while it's in use, so when verify is killed before dropping it, the next verify to
+/// create one in the project sees the lock was released and removes the directory.
+/// When writes are disabled the directory is in the system temp dir instead.
+pub struct CheckTmpDir {
+    path: PathBuf,
+    /// Held while the directory is in use (None in the system temp dir)
+    lock: Option<NamedTempFile>,
+    /// Removes the directory when it's in the system temp dir
+    _system: Option<TempDir>,
+}
+
+impl CheckTmpDir {
+    pub fn create(project_root: &Path, check_name: &str) -> Result<Self> {
+        let prefix = format!("{}-", safe_name(check_name));
+        if !statedir::writes_enabled() {
+            let dir = tempfile::Builder::new()
+                .prefix(&format!("verify-{}", prefix))
+                .tempdir()
+                .context("Failed to create temp directory")?;
+            return Ok(Self {
+                path: dir.path().to_path_buf(),
+                lock: None,
+                _system: Some(dir),
+            });
+        }
+
+        let parent = statedir::create(project_root)?.join(TMP_DIR);
+        fs::create_dir_all(&parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        sweep_once(&parent);
+
+        // The lock comes first, so a directory is never seen without its lock held
+        let lock = tempfile::Builder::new()
+            .prefix(&prefix)
+            .suffix(".lock")
+            .tempfile_in(&parent)
+            .context("Failed to create temp directory lock")?;
+        lock.as_file()
+            .lock()
+            .context("Failed to lock temp directory")?;
+        let path = lock.path().with_extension("");
+        fs::create_dir(&path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        Ok(Self {
+            path,
+            lock: Some(lock),
+            _system: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CheckTmpDir {
+    /// Remove the directory before its lock file is released and deleted
+    fn drop(&mut self) {
+        if self.lock.is_some() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Remove the directories in `parent` whose owner is gone, once per process
+fn sweep_once(parent: &Path) {
+    if !SWEPT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(parent.to_path_buf())
+    {
+        return;
+    }
+    sweep(parent);
+}
+
+/// Remove every directory in `parent` whose lock file isn't locked. Failing to remove one
+/// isn't an error; a later sweep tries again.
+fn sweep(parent: &Path) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let lock_path = entry.path();
+        if lock_path.extension().is_none_or(|ext| ext != "lock") {
+            continue;
+        }
+        // Not created if missing: another sweep removed it
+        let Ok(file) = File::options().write(true).open(&lock_path) else {
+            continue;
+        };
+        if file.try_lock().is_ok() {
+            let _ = fs::remove_dir_all(lock_path.with_extension(""));
+            let _ = fs::remove_file(&lock_path);
+        }
+    }
+}
+
+/// A check name as part of a directory name
+fn safe_name(check_name: &str) -> String {
+    check_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_removed_when_dropped() {
+        let project = tempdir().unwrap();
+        let tmp = CheckTmpDir::create(project.path(), "web/build").unwrap();
+        let path = tmp.path().to_path_buf();
+        assert!(path.starts_with(project.path().join(".verify/tmp")));
+        assert!(
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("web_build-")
+        );
+        fs::write(path.join("scratch"), "data").unwrap();
+
+        let other = CheckTmpDir::create(project.path(), "web/build").unwrap();
+        assert_ne!(other.path(), path);
+
+        drop(tmp);
+        assert!(!path.exists());
+        assert!(!path.with_extension("lock").exists());
+        assert!(other.path().exists());
+    }
+
+    #[test]
+    fn test_sweep_removes_only_abandoned_dirs() {
+        let project = tempdir().unwrap();
+        let live = CheckTmpDir::create(project.path(), "build").unwrap();
+        let parent = live.path().parent().unwrap().to_path_buf();
+
+        // Left behind by a verify that was killed: its lock file is no longer locked
+        let abandoned = parent.join("build-abandoned");
+        fs::create_dir(&abandoned).unwrap();
+        fs::write(abandoned.join("scratch"), "data").unwrap();
+        fs::write(parent.join("build-abandoned.lock"), "").unwrap();
+
+        sweep(&parent);
+        assert!(!abandoned.exists());
+        assert!(!parent.join("build-abandoned.lock").exists());
+        assert!(live.path().exists());
+    }
+}
@@ -1,15 +1,24 @@
 use anyhow::{Context, Result};
 use blake3::Hasher;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
 use std::process::Command;
 
 use crate::cache::{CacheState, VerificationStatus};
 use crate::config::Config;
 use crate::graph::DependencyGraph;
-use crate::hasher::compute_check_hash;
 
-const TRAILER_HASH_LENGTH: usize = 8;
+/// Hex digits of each hash written to the trailer, unless `trailer_hash_length` says otherwise
+pub const DEFAULT_TRAILER_HASH_LENGTH: usize = 16;
+
+/// Shortest trailer hash accepted as a match, the length earlier versions wrote
+pub const MIN_TRAILER_HASH_LENGTH: usize = 8;
+
+/// Manifest of per-check hashes committed next to verify.yaml with `trailer: compact`
+pub const MANIFEST_FILE: &str = "verify.manifest.json";
+
+/// Compact trailer values are this prefix followed by the manifest's truncated root hash
+const MANIFEST_ROOT_PREFIX: &str = "@";
 
 /// Compute combined hash for a regular check from its config_hash and content_hash.
 /// Returns full 64-char blake3 hex string.
@@ -21,23 +30,106 @@ pub fn compute_combined_hash(config_hash: &str, content_hash: &str) -> String {
     hasher.finalize().to_hex().to_string()
 }
 
-/// Truncate a full hash to the trailer length (8 chars).
-pub fn truncate_hash(hash: &str) -> &str {
-    &hash[..TRAILER_HASH_LENGTH.min(hash.len())]
+/// Truncate a full hash to `length` chars for the trailer.
+pub fn truncate_hash(hash: &str, length: usize) -> &str {
+    &hash[..length.min(hash.len())]
+}
+
+/// Whether a hash read from a trailer matches a full hash. Trailers may have been written
+/// with any `trailer_hash_length`, so it's a prefix match, but never on fewer than
+/// `MIN_TRAILER_HASH_LENGTH` chars.
+pub fn hash_matches(trailer_hash: &str, full_hash: &str) -> bool {
+    trailer_hash.len() >= MIN_TRAILER_HASH_LENGTH && full_hash.starts_with(trailer_hash)
+}
+
+/// Compute the derived hash of an aggregate check from its dependencies' hashes: the hash
+/// of the sorted `name:hash` lines of every signed check it covers.
+pub fn compute_aggregate_hash(dependency_hashes: &BTreeMap<String, String>) -> String {
+    let mut hasher = Hasher::new();
+    for (name, hash) in dependency_hashes {
+        hasher.update(name.as_bytes());
+        hasher.update(b":");
+        hasher.update(hash.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// With `hash_aggregates`, add a derived hash for each aggregate check whose dependencies
+/// all have hashes (untracked dependencies don't count). A subproject dependency covers
+/// all of its checks and only counts when `complete_subprojects` contains it.
+fn add_aggregate_hashes(
+    config: &Config,
+    hashes: &mut BTreeMap<String, String>,
+    complete_subprojects: &HashSet<String>,
+) -> Result<()> {
+    if !config.hash_aggregates {
+        return Ok(());
+    }
+
+    let graph = DependencyGraph::from_config(config)?;
+    for wave in graph.execution_waves() {
+        for name in wave {
+            let Some(check) = config.get(&name) else {
+                continue;
+            };
+            if check.command.is_some() {
+                continue;
+            }
+
+            let mut dependency_hashes = BTreeMap::new();
+            let mut complete = true;
+            for dep in &check.depends_on {
+                if let Some(subproject) = config.get_subproject(dep) {
+                    complete &= complete_subprojects.contains(dep);
+                    let prefix = format!("{}/", subproject.prefix());
+                    dependency_hashes.extend(
+                        hashes
+                            .iter()
+                            .filter(|(key, _)| key.starts_with(&prefix))
+                            .map(|(key, hash)| (key.clone(), hash.clone())),
+                    );
+                } else if let Some(hash) = hashes.get(dep) {
+                    dependency_hashes.insert(dep.clone(), hash.clone());
+                } else if !config.get(dep).is_some_and(|dep| {
+                    (dep.command.is_some() && dep.cache_paths.is_empty()) || !dep.is_supported()
+                }) {
+                    complete = false;
+                }
+            }
+
+            if complete {
+                hashes.insert(name, compute_aggregate_hash(&dependency_hashes));
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Compute combined hashes for all currently fresh checks, respecting dependency order.
-/// Returns a map of check name -> full combined hash.
-/// Skips aggregate checks (implicit from their dependencies).
+/// Returns a map of check name -> full combined hash, with subproject checks named
+/// `prefix/name`.
+/// Skips aggregate checks (implicit from their dependencies) unless `hash_aggregates` is
+/// set, in which case fresh aggregates get a derived hash.
 /// Skips stale checks (files changed, config changed, never run).
 pub fn compute_all_hashes(
     project_root: &Path,
     config: &Config,
     cache: &CacheState,
 ) -> Result<BTreeMap<String, String>> {
+    Ok(fresh_hashes(project_root, config, cache)?.0)
+}
+
+/// Combined hashes of the fresh checks, and whether every tracked check is fresh
+fn fresh_hashes(
+    project_root: &Path,
+    config: &Config,
+    cache: &CacheState,
+) -> Result<(BTreeMap<String, String>, bool)> {
     let graph = DependencyGraph::from_config(config)?;
     let waves = graph.execution_waves();
     let mut combined_hashes: BTreeMap<String, String> = BTreeMap::new();
+    let mut all_fresh = true;
 
     for wave in waves {
         for name in wave {
@@ -51,36 +143,114 @@ pub fn compute_all_hashes(
                 continue;
             }
 
-            // Skip untracked checks (no cache_paths)
-            if check.cache_paths.is_empty() {
+            // Skip untracked checks (no cache_paths), and those that don't run here
+            if check.cache_paths.is_empty() || !check.is_supported() {
                 continue;
             }
 
             // Compute current hashes and check freshness
             let current_config_hash = check.config_hash();
-            let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
-            let status = cache.check_staleness(&name, &hash_result.combined_hash, &current_config_hash);
+            let hash_result = check.compute_hash(project_root)?;
+            let status =
+                cache.check_staleness(&name, &hash_result.combined_hash, &current_config_hash);
 
             if matches!(status, VerificationStatus::Verified) {
                 let hash = compute_combined_hash(&current_config_hash, &hash_result.combined_hash);
                 combined_hashes.insert(name, hash);
+            } else {
+                all_fresh = false;
             }
         }
     }
 
-    Ok(combined_hashes)
+    let mut complete_subprojects = HashSet::new();
+    for subproject in config.subprojects() {
+        let subproject_dir = project_root.join(&subproject.path);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        let sub_cache = CacheState::load(&subproject_dir, cache.settings())?;
+        let (sub_hashes, sub_fresh) = fresh_hashes(&subproject_dir, &sub_config, &sub_cache)?;
+        for (name, hash) in sub_hashes {
+            combined_hashes.insert(subproject.qualify(&name), hash);
+        }
+        if sub_fresh {
+            complete_subprojects.insert(subproject.name.clone());
+        } else {
+            all_fresh = false;
+        }
+    }
+
+    add_aggregate_hashes(config, &mut combined_hashes, &complete_subprojects)?;
+    Ok((combined_hashes, all_fresh))
+}
+
+/// Limit hashes to the checks being signed: `names` if given (`sign --checks`), otherwise
+/// the config's `sign_checks`, otherwise all. An aggregate check stands for its dependencies
+/// (and itself, when it has a derived hash).
+pub fn select_signed_checks(
+    config: &Config,
+    hashes: BTreeMap<String, String>,
+    names: &[String],
+) -> Result<BTreeMap<String, String>> {
+    let names = if names.is_empty() {
+        &config.sign_checks
+    } else {
+        names
+    };
+    if names.is_empty() {
+        return Ok(hashes);
+    }
+
+    let graph = DependencyGraph::from_config(config)?;
+    let mut selected = HashSet::new();
+    for name in names {
+        // Subproject checks are signed by their qualified name
+        let is_aggregate = config
+            .get(name)
+            .is_some_and(|check| check.command.is_none());
+        if !is_aggregate && (hashes.contains_key(name) || config.get_subproject(name).is_some()) {
+            selected.insert(name.clone());
+            continue;
+        }
+        let Some(check) = config.get(name) else {
+            anyhow::bail!("Unknown check: {}", name);
+        };
+        if check.command.is_none() {
+            selected.extend(graph.transitive_dependencies(name));
+        }
+        selected.insert(name.clone());
+    }
+    // A selected subproject stands for all of its checks
+    for subproject in config.subprojects() {
+        if selected.contains(&subproject.name) {
+            let prefix = format!("{}/", subproject.prefix());
+            selected.extend(
+                hashes
+                    .keys()
+                    .filter(|name| name.starts_with(&prefix))
+                    .cloned(),
+            );
+        }
+    }
+
+    Ok(hashes
+        .into_iter()
+        .filter(|(name, _)| selected.contains(name))
+        .collect())
 }
 
 /// Compute the expected combined hash for a regular check from current files.
 pub fn compute_expected_hash(project_root: &Path, check: &crate::config::Verification) -> Result<String> {
     let config_hash = check.config_hash();
-    let hash_result = compute_check_hash(project_root, &check.cache_paths)?;
+    let hash_result = check.compute_hash(project_root)?;
     Ok(compute_combined_hash(&config_hash, &hash_result.combined_hash))
 }
 
 /// Compute expected hashes for all checks from current files, respecting dependency order.
-/// Returns a map of check name -> full combined hash.
-/// Skips aggregate checks (implicit from their dependencies).
+/// Returns a map of check name -> full combined hash, with subproject checks named
+/// `prefix/name`.
+/// Skips aggregate checks (implicit from their dependencies) unless `hash_aggregates` is
+/// set, in which case they get a derived hash.
 pub fn compute_all_expected_hashes(
     project_root: &Path,
     config: &Config,
@@ -101,8 +271,8 @@ pub fn compute_all_expected_hashes(
                 continue;
             }
 
-            // Skip untracked checks (no cache_paths)
-            if check.cache_paths.is_empty() {
+            // Skip untracked checks (no cache_paths), and those that don't run here
+            if check.cache_paths.is_empty() || !check.is_supported() {
                 continue;
             }
 
@@ -110,6 +280,18 @@ pub fn compute_all_expected_hashes(
         }
     }
 
+    let mut subprojects = HashSet::new();
+    for subproject in config.subprojects() {
+        let subproject_dir = project_root.join(&subproject.path);
+        let sub_config =
+            Config::load_with_base(&subproject_dir.join("verify.yaml"), &subproject_dir)?;
+        for (name, hash) in compute_all_expected_hashes(&subproject_dir, &sub_config)? {
+            expected_hashes.insert(subproject.qualify(&name), hash);
+        }
+        subprojects.insert(subproject.name.clone());
+    }
+
+    add_aggregate_hashes(config, &mut expected_hashes, &subprojects)?;
     Ok(expected_hashes)
 }
 
@@ -130,9 +312,10 @@ pub fn read_trailer(project_root: &Path) -> Result<Option<BTreeMap<String, Strin
         );
     }
 
+    let load_manifest = || read_committed_manifest(project_root, "HEAD");
     let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
     if !value.is_empty() {
-        return Ok(Some(parse_trailer_value(&value)));
+        return resolve_trailer_value(&value, load_manifest).map(Some);
     }
 
     // Fallback: parse commit body directly for "Verified:" line.
@@ -152,21 +335,31 @@ pub fn read_trailer(project_root: &Path) -> Result<Option<BTreeMap<String, Strin
     }
 
     let body = String::from_utf8_lossy(&output.stdout);
-    Ok(parse_verified_from_body(&body))
+    find_verified_value(&body)
+        .map(|value| resolve_trailer_value(&value, load_manifest))
+        .transpose()
 }
 
-/// Search recent git history for the most recent commit with a Verified trailer.
-/// Returns None if no trailer is found within max_depth commits.
+/// Search git history for commits with a Verified trailer, newest first, looking at
+/// max_depth commits reachable from `revisions` (HEAD when empty; git log arguments such
+/// as a branch name or `--branches`).
 ///
 /// Uses direct body parsing rather than git's built-in trailer parser, because
 /// GitHub squash-merge can reformat commit messages in ways that break git's
 /// trailer detection.
-pub fn read_trailer_from_history(
+pub fn read_trailers_from_history(
     project_root: &Path,
     max_depth: usize,
-) -> Result<Option<BTreeMap<String, String>>> {
+    revisions: &[&str],
+) -> Result<Vec<BTreeMap<String, String>>> {
     let output = Command::new("git")
-        .args(["log", &format!("-{}", max_depth), "--format=%B%x00"])
+        .args([
+            "log",
+            "--date-order",
+            &format!("-{}", max_depth),
+            "--format=%H%x1f%B%x00",
+        ])
+        .args(revisions)
         .current_dir(project_root)
         .output()
         .context("Failed to run git log. Is this a git repository?")?;
@@ -179,32 +372,182 @@ pub fn read_trailer_from_history(
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    for commit_body in stdout.split('\0') {
-        if let Some(map) = parse_verified_from_body(commit_body) {
-            return Ok(Some(map));
+    let mut trailers = Vec::new();
+    for entry in stdout.split('\0') {
+        let Some((commit, body)) = entry.trim_start().split_once('\x1f') else {
+            continue;
+        };
+        if let Some(value) = find_verified_value(body) {
+            trailers.push(resolve_trailer_value(&value, || {
+                read_committed_manifest(project_root, commit)
+            })?);
         }
     }
 
-    Ok(None)
+    Ok(trailers)
+}
+
+/// List the first-parent history of HEAD, newest first.
+/// Returns (commit, subject) pairs for at most max_depth commits.
+pub fn read_history(project_root: &Path, max_depth: usize) -> Result<Vec<(String, String)>> {
+    let output = Command::new("git")
+        .args([
+            "log",
+            "--first-parent",
+            &format!("-{}", max_depth),
+            "--format=%H%x00%s",
+        ])
+        .current_dir(project_root)
+        .output()
+        .context("Failed to run git log. Is this a git repository?")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (commit, subject) = line.split_once('\0')?;
+            Some((commit.to_string(), subject.to_string()))
+        })
+        .collect())
 }
 
 /// Parse a commit message body for a "Verified: name:hash,..." line.
 /// Returns the last match, since squash-merge commits may concatenate
 /// multiple commit messages each with their own Verified trailer.
-fn parse_verified_from_body(body: &str) -> Option<BTreeMap<String, String>> {
-    let mut last_match: Option<BTreeMap<String, String>> = None;
+#[allow(dead_code)]
+pub fn parse_verified_from_body(body: &str) -> Option<BTreeMap<String, String>> {
+    find_verified_value(body).map(|value| parse_trailer_value(&value))
+}
+
+/// Find the raw value of the last "Verified:" line in a commit message body
+pub fn find_verified_value(body: &str) -> Option<String> {
+    let mut last_match = None;
     for line in body.lines() {
         let trimmed = line.trim();
         if let Some(value) = trimmed.strip_prefix("Verified:") {
             let value = value.trim();
             if !value.is_empty() {
-                last_match = Some(parse_trailer_value(value));
+                last_match = Some(value.to_string());
             }
         }
     }
     last_match
 }
 
+/// Parse a trailer value into a map of check name -> hash (truncated, except for the
+/// full hashes of a manifest). A compact value ("@root") is expanded from the manifest
+/// returned by `load_manifest`, which must hash to that root.
+pub fn resolve_trailer_value(
+    value: &str,
+    load_manifest: impl FnOnce() -> Result<Option<String>>,
+) -> Result<BTreeMap<String, String>> {
+    let Some(root) = value.trim().strip_prefix(MANIFEST_ROOT_PREFIX) else {
+        return Ok(parse_trailer_value(value));
+    };
+
+    let manifest = load_manifest()?.with_context(|| {
+        format!(
+            "Verified trailer refers to a manifest, but {} was not found",
+            MANIFEST_FILE
+        )
+    })?;
+    if !hash_matches(root, &manifest_root(&manifest)) {
+        anyhow::bail!(
+            "{} does not match the root hash in the Verified trailer ({})",
+            MANIFEST_FILE,
+            root
+        );
+    }
+    serde_json::from_str(&manifest).with_context(|| format!("Failed to parse {}", MANIFEST_FILE))
+}
+
+/// Canonical manifest content for a set of check hashes
+pub fn format_manifest(hashes: &BTreeMap<String, String>) -> String {
+    let mut manifest = serde_json::to_string_pretty(hashes).expect("string map serializes");
+    manifest.push('\n');
+    manifest
+}
+
+/// Root hash of a manifest, committed to by compact trailers
+fn manifest_root(manifest: &str) -> String {
+    blake3::hash(manifest.as_bytes()).to_hex().to_string()
+}
+
+/// Format a compact trailer value ("@root") for a manifest, with the root truncated to
+/// `hash_length`
+pub fn format_compact_trailer_value(manifest: &str, hash_length: usize) -> String {
+    format!(
+        "{}{}",
+        MANIFEST_ROOT_PREFIX,
+        truncate_hash(&manifest_root(manifest), hash_length)
+    )
+}
+
+/// Read the manifest next to verify.yaml from the working tree
+pub fn read_manifest_file(project_root: &Path) -> Result<Option<String>> {
+    let path = project_root.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Read the manifest as committed in `revision` (or staged, for revision "")
+fn read_committed_manifest(project_root: &Path, revision: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["show", &format!("{}:./{}", revision, MANIFEST_FILE)])
+        .current_dir(project_root)
+        .output()
+        .context("Failed to run git show")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Write the manifest next to verify.yaml and stage it for the next commit
+pub fn stage_manifest(project_root: &Path, manifest: &str) -> Result<()> {
+    let path = project_root.join(MANIFEST_FILE);
+    std::fs::write(&path, manifest)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let output = Command::new("git")
+        .args(["add", "--", MANIFEST_FILE])
+        .current_dir(project_root)
+        .output()
+        .context("Failed to run git add")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to stage {}: {}",
+            MANIFEST_FILE,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Check that the manifest staged for the next commit matches the given hashes, so a
+/// compact trailer signed for them can be resolved from the commit
+pub fn check_staged_manifest(project_root: &Path, manifest: &str) -> Result<()> {
+    if read_committed_manifest(project_root, "")?.as_deref() != Some(manifest) {
+        anyhow::bail!(
+            "{} is missing or out of date in the commit. Run `verify run --stage` \
+             (e.g. in the pre-commit hook) before signing",
+            MANIFEST_FILE
+        );
+    }
+    Ok(())
+}
+
 /// Parse a trailer value string "name:hash,name:hash,..." into a map.
 pub fn parse_trailer_value(value: &str) -> BTreeMap<String, String> {
     let mut map = BTreeMap::new();
@@ -218,22 +561,17 @@ pub fn parse_trailer_value(value: &str) -> BTreeMap<String, String> {
 }
 
 /// Format hashes as a trailer value string "name:hash,name:hash,...".
-/// Truncates hashes to 8 chars for compact trailer output.
-pub fn format_trailer_value(hashes: &BTreeMap<String, String>) -> String {
+/// Truncates hashes to `hash_length` chars for compact trailer output.
+pub fn format_trailer_value(hashes: &BTreeMap<String, String>, hash_length: usize) -> String {
     hashes
         .iter()
-        .map(|(name, hash)| format!("{}:{}", name, truncate_hash(hash)))
+        .map(|(name, hash)| format!("{}:{}", name, truncate_hash(hash, hash_length)))
         .collect::<Vec<_>>()
         .join(",")
 }
 
 /// Write the Verified trailer to a commit message file using git interpret-trailers.
-pub fn write_trailer(commit_msg_file: &Path, hashes: &BTreeMap<String, String>) -> Result<()> {
-    if hashes.is_empty() {
-        return Ok(());
-    }
-
-    let trailer_value = format_trailer_value(hashes);
+pub fn write_trailer(commit_msg_file: &Path, trailer_value: &str) -> Result<()> {
     let trailer = format!("Verified: {}", trailer_value);
 
     let output = Command::new("git")
@@ -271,7 +609,7 @@ impl Drop for FileGuard {
 /// Temporarily removes MERGE_HEAD if present so `git commit --amend`
 /// doesn't fail during post-merge hooks (where git hasn't cleaned up
 /// merge state yet). Restores it afterward.
-pub fn resign_head(project_root: &Path, hashes: &BTreeMap<String, String>) -> Result<()> {
+pub fn resign_head(project_root: &Path, trailer_value: &str) -> Result<()> {
     // Read HEAD's commit message
     let output = Command::new("git")
         .args(["log", "-1", "--format=%B", "HEAD"])
@@ -292,7 +630,7 @@ pub fn resign_head(project_root: &Path, hashes: &BTreeMap<String, String>) -> Re
     let temp_path = std::env::temp_dir().join(format!("verify-resign-msg-{}", std::process::id()));
     let _cleanup = FileGuard(temp_path.clone());
     std::fs::write(&temp_path, &message).context("Failed to write temp commit message file")?;
-    write_trailer(&temp_path, hashes)?;
+    write_trailer(&temp_path, trailer_value)?;
 
     // Temporarily remove MERGE_HEAD if present — git commit --amend refuses
     // to run while it exists, but during post-merge hooks the merge is already
@@ -334,6 +672,25 @@ pub fn resign_head(project_root: &Path, hashes: &BTreeMap<String, String>) -> Re
     Ok(())
 }
 
+/// Run a git command in `dir` and return its trimmed stdout
+pub fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -365,15 +722,113 @@ mod tests {
         assert_ne!(h1, h2);
     }
 
+    #[test]
+    fn test_select_signed_checks_expands_aggregates() {
+        let yaml = r#"
+sign_checks: [ci]
+verifications:
+  - name: build
+    command: cargo build
+  - name: test
+    command: cargo test
+  - name: ci
+    depends_on: [build, test]
+  - name: experimental
+    command: ./try.sh
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let hashes: BTreeMap<String, String> = ["build", "test", "experimental"]
+            .iter()
+            .map(|name| (name.to_string(), format!("{}hash", name)))
+            .collect();
+
+        let signed = select_signed_checks(&config, hashes.clone(), &[]).unwrap();
+        assert_eq!(signed.keys().collect::<Vec<_>>(), vec!["build", "test"]);
+
+        let signed = select_signed_checks(&config, hashes.clone(), &["build".to_string()]).unwrap();
+        assert_eq!(signed.keys().collect::<Vec<_>>(), vec!["build"]);
+
+        assert!(select_signed_checks(&config, hashes, &["nope".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_hashes() {
+        let yaml = r#"
+hash_aggregates: true
+verifications:
+  - name: build
+    command: cargo build
+    cache_paths: ["src/**"]
+  - name: test
+    command: cargo test
+    cache_paths: ["tests/**"]
+  - name: notify
+    command: ./notify.sh
+  - name: ci
+    depends_on: [build, test, notify]
+  - name: all
+    depends_on: [ci]
+"#;
+        let config: Config = serde_yml::from_str(yaml).unwrap();
+        let mut hashes: BTreeMap<String, String> = [("build", "b1"), ("test", "t1")]
+            .iter()
+            .map(|(name, hash)| (name.to_string(), hash.to_string()))
+            .collect();
+        add_aggregate_hashes(&config, &mut hashes, &HashSet::new()).unwrap();
+
+        // Untracked dependencies don't count, and aggregates can depend on aggregates
+        let ci = compute_aggregate_hash(&BTreeMap::from([
+            ("build".to_string(), "b1".to_string()),
+            ("test".to_string(), "t1".to_string()),
+        ]));
+        assert_eq!(hashes["ci"], ci);
+        let all = compute_aggregate_hash(&[("ci".to_string(), ci)].into_iter().collect());
+        assert_eq!(hashes["all"], all);
+
+        let mut changed = BTreeMap::from([
+            ("build".to_string(), "b2".to_string()),
+            ("test".to_string(), "t1".to_string()),
+        ]);
+        add_aggregate_hashes(&config, &mut changed, &HashSet::new()).unwrap();
+        assert_ne!(changed["all"], hashes["all"]);
+
+        // A stale dependency leaves the aggregates without a hash
+        let mut stale = BTreeMap::from([("build".to_string(), "b1".to_string())]);
+        add_aggregate_hashes(&config, &mut stale, &HashSet::new()).unwrap();
+        assert_eq!(stale.keys().collect::<Vec<_>>(), vec!["build"]);
+
+        let signed = select_signed_checks(&config, hashes, &["ci".to_string()]).unwrap();
+        assert_eq!(
+            signed.keys().collect::<Vec<_>>(),
+            vec!["build", "ci", "test"]
+        );
+    }
+
     #[test]
     fn test_truncate_hash() {
         let full = "a1b2c3d4e5f6a7b8c9d0e1f23a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2";
-        assert_eq!(truncate_hash(full), "a1b2c3d4");
+        assert_eq!(truncate_hash(full, 8), "a1b2c3d4");
+        assert_eq!(
+            truncate_hash(full, DEFAULT_TRAILER_HASH_LENGTH),
+            "a1b2c3d4e5f6a7b8"
+        );
     }
 
     #[test]
     fn test_truncate_hash_short_input() {
-        assert_eq!(truncate_hash("abc"), "abc");
+        assert_eq!(truncate_hash("abc", 8), "abc");
+    }
+
+    #[test]
+    fn test_hash_matches() {
+        let full = "a1b2c3d4e5f6a7b8c9d0e1f23a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2";
+        assert!(hash_matches("a1b2c3d4", full));
+        assert!(hash_matches("a1b2c3d4e5f6a7b8", full));
+        assert!(hash_matches(full, full));
+        assert!(!hash_matches("a1b2c3d5", full));
+        // Too short to trust, even as a prefix
+        assert!(!hash_matches("a1b2c3", full));
+        assert!(!hash_matches("", full));
     }
 
     #[test]
@@ -382,14 +837,14 @@ mod tests {
         hashes.insert("build".to_string(), "a1b2c3d4e5f6a7b8".to_string());
         hashes.insert("lint".to_string(), "c9d0e1f23a4b5c6d".to_string());
 
-        let output = format_trailer_value(&hashes);
+        let output = format_trailer_value(&hashes, 8);
         assert_eq!(output, "build:a1b2c3d4,lint:c9d0e1f2");
     }
 
     #[test]
     fn test_format_trailer_value_empty() {
         let hashes = BTreeMap::new();
-        assert_eq!(format_trailer_value(&hashes), "");
+        assert_eq!(format_trailer_value(&hashes, 8), "");
     }
 
     #[test]
@@ -500,14 +955,55 @@ Co-authored-by: Claude Opus 4.6 <noreply@anthropic.com>";
     #[test]
     fn test_format_parse_roundtrip() {
         let mut hashes = BTreeMap::new();
-        hashes.insert("build".to_string(), "a1b2c3d4e5f6a7b8c9d0e1f23a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2".to_string());
-        hashes.insert("lint".to_string(), "1122334455667788aabbccddeeff00112233445566778899aabbccddeeff001122".to_string());
+        hashes.insert(
+            "build".to_string(),
+            "a1b2c3d4e5f6a7b8c9d0e1f23a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2".to_string(),
+        );
+        hashes.insert(
+            "lint".to_string(),
+            "1122334455667788aabbccddeeff00112233445566778899aabbccddeeff001122".to_string(),
+        );
 
-        let formatted = format_trailer_value(&hashes);
+        let formatted = format_trailer_value(&hashes, DEFAULT_TRAILER_HASH_LENGTH);
         let parsed = parse_trailer_value(&formatted);
 
         // Parsed values should be truncated versions
-        assert_eq!(parsed["build"], "a1b2c3d4");
-        assert_eq!(parsed["lint"], "11223344");
+        assert_eq!(parsed["build"], "a1b2c3d4e5f6a7b8");
+        assert_eq!(parsed["lint"], "1122334455667788");
+    }
+
+    #[test]
+    fn test_resolve_full_trailer_value() {
+        let map = resolve_trailer_value("build:a1b2c3d4", || panic!("no manifest needed")).unwrap();
+        assert_eq!(map.get("build").map(String::as_str), Some("a1b2c3d4"));
+    }
+
+    #[test]
+    fn test_resolve_compact_trailer_value() {
+        let mut hashes = BTreeMap::new();
+        hashes.insert("build".to_string(), "a1b2c3d4e5f6a7b8".to_string());
+        hashes.insert("lint".to_string(), "c9d0e1f23a4b5c6d".to_string());
+        let manifest = format_manifest(&hashes);
+        let value = format_compact_trailer_value(&manifest, DEFAULT_TRAILER_HASH_LENGTH);
+        assert!(value.starts_with('@'));
+        assert_eq!(value.len(), 1 + DEFAULT_TRAILER_HASH_LENGTH);
+
+        let map = resolve_trailer_value(&value, || Ok(Some(manifest.clone()))).unwrap();
+        assert_eq!(
+            map.get("build").map(String::as_str),
+            Some("a1b2c3d4e5f6a7b8")
+        );
+        assert_eq!(
+            map.get("lint").map(String::as_str),
+            Some("c9d0e1f23a4b5c6d")
+        );
+
+        // A compact value signed with a shorter hash still resolves
+        let short = format_compact_trailer_value(&manifest, 8);
+        assert!(resolve_trailer_value(&short, || Ok(Some(manifest.clone()))).is_ok());
+
+        let tampered = manifest.replace("a1b2c3d4", "00000000");
+        assert!(resolve_trailer_value(&value, || Ok(Some(tampered))).is_err());
+        assert!(resolve_trailer_value(&value, || Ok(None)).is_err());
     }
 }
@@ -1,20 +1,126 @@
-use crate::cache::{UnverifiedReason, VerificationStatus};
+use crate::audit::ChainReport;
+use crate::cache::{UnverifiedReason, VerificationStatus, VerifiedBy};
+use crate::ci::CiProvider;
+use crate::dashboard::{DashboardRow, DashboardState};
+use crate::history::RunRecord;
 use crate::metadata::{MetadataValue, compute_delta};
-use crate::output::format_duration;
-use console::{Term, style};
-use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::BTreeMap;
-use std::time::Duration;
+use crate::output::{
+    BisectOutput, CacheDecisionJson, CoverageOutput, ExplainOutput, format_age, format_duration,
+    format_size,
+};
+use crate::stats::StatsSummary;
+use console::{Color, Style, Term, style};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, HashSet};
+use std::io::IsTerminal;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
 
 /// Circle icon used for all states (colored differently)
 pub const ICON_CIRCLE: &str = "\u{25CF}"; // ●
 
+/// Draw target shared by check spinners and the run progress bar.
+/// Everything printed while bars are on screen goes through `outln!`, which suspends
+/// the bars so lines land above them instead of being drawn over.
+static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Run-level progress bar, pinned below the check spinners while `verify run` is in progress
+static RUN_PROGRESS: Mutex<Option<RunProgress>> = Mutex::new(None);
+
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        PROGRESS.suspend(|| println!($($arg)*))
+    };
+}
+
+/// How a check ended, for the run-level progress bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckOutcome {
+    Passed,
+    Cached,
+    Failed,
+}
+
+/// State behind the run-level progress bar
+struct RunProgress {
+    bar: ProgressBar,
+    total: usize,
+    passed: usize,
+    cached: usize,
+    failed: usize,
+    /// Checks whose command has started but that haven't completed
+    running: HashSet<String>,
+    /// Estimated time left, from the historical durations of checks not yet completed
+    remaining_ms: Option<u64>,
+    /// Estimate used for checks with no recorded duration
+    default_estimate_ms: u64,
+}
+
+impl RunProgress {
+    fn completed(&self) -> usize {
+        self.passed + self.cached + self.failed
+    }
+
+    /// "3 passed · 1 failed · 1 running · 17 queued", leaving out counts that are zero
+    fn message(&self) -> String {
+        let queued = self
+            .total
+            .saturating_sub(self.completed() + self.running.len());
+        let counts = [
+            (self.passed, style("passed").green()),
+            (self.cached, style("cached").dim()),
+            (self.failed, style("failed").red()),
+            (self.running.len(), style("running").yellow()),
+            (queued, style("queued")),
+        ];
+        let mut message = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect::<Vec<_>>()
+            .join(" · ");
+        if let Some(remaining_ms) = self.remaining_ms
+            && remaining_ms > 0
+        {
+            message.push_str(&format!(", ~{} remaining", format_duration(remaining_ms)));
+        }
+        message
+    }
+}
+
+/// Colors for the labels of streamed output, picked per check
+const LABEL_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+    Color::Yellow,
+    Color::Color256(208),
+];
+
+/// The label color of a check, the same on every run. per_file labels (`check:file`) take
+/// the color of their check.
+fn label_style(label: &str) -> Style {
+    let check = label.split(':').next().unwrap_or(label);
+    let hash = check.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+    Style::new()
+        .fg(LABEL_COLORS[hash % LABEL_COLORS.len()])
+        .bold()
+}
+
 /// Terminal UI helper
 pub struct Ui {
     #[allow(dead_code)]
     term: Term,
     verbose: bool,
     is_tty: bool,
+    /// CI service to fold streamed check output for
+    ci: Option<CiProvider>,
+    /// Print the cache decision for each check in a run (`run --explain-cache`)
+    explain_cache: bool,
+    /// Print passing checks' output too, unless they set `show_output` (`run --show-output`)
+    show_output: bool,
 }
 
 impl Ui {
@@ -25,13 +131,36 @@ impl Ui {
             term,
             verbose,
             is_tty,
+            ci: CiProvider::detect(),
+            explain_cache: false,
+            show_output: false,
         }
     }
 
+    /// Explain why each check in a run is run or skipped
+    pub fn with_cache_explanations(mut self, explain: bool) -> Self {
+        self.explain_cache = explain;
+        self
+    }
+
     pub fn is_verbose(&self) -> bool {
         self.verbose
     }
 
+    pub fn explains_cache(&self) -> bool {
+        self.explain_cache
+    }
+
+    /// Print the output of passing checks after their status line
+    pub fn with_passing_output(mut self, show: bool) -> Self {
+        self.show_output = show;
+        self
+    }
+
+    pub fn shows_passing_output(&self) -> bool {
+        self.show_output
+    }
+
     /// Returns true if we should use progress bars (TTY and not verbose)
     pub fn use_progress_bars(&self) -> bool {
         self.is_tty && !self.verbose
@@ -50,7 +179,7 @@ impl Ui {
         } else {
             style(ICON_CIRCLE).green().bold()
         };
-        println!("{}{} {}", prefix, icon_style, style(name).bold());
+        outln!("{}{} {}", prefix, icon_style, style(name).bold());
     }
 
     /// Print status for a check
@@ -64,7 +193,7 @@ impl Ui {
         let prefix = Self::indent_str(indent);
         match status {
             VerificationStatus::Verified => {
-                println!(
+                outln!(
                     "{}{} {} - {}",
                     prefix,
                     style(ICON_CIRCLE).green().bold(),
@@ -73,37 +202,45 @@ impl Ui {
                 );
             }
             VerificationStatus::Unverified { reason } => {
-                let reason_str = match reason {
-                    UnverifiedReason::FilesChanged { changed_files } => {
-                        if changed_files.is_empty() {
-                            "files changed".to_string()
-                        } else {
-                            format!("{} file(s) changed", changed_files.len())
-                        }
-                    }
-                    UnverifiedReason::DependencyUnverified { dependency } => {
-                        format!("depends on: {}", dependency)
-                    }
-                    UnverifiedReason::ConfigChanged => "config changed".to_string(),
-                    UnverifiedReason::NeverRun => "never run".to_string(),
-                };
-
-                println!(
+                outln!(
                     "{}{} {} - {} ({})",
                     prefix,
                     style(ICON_CIRCLE).yellow().bold(),
                     style(name).bold(),
                     style("unverified").yellow(),
-                    reason_str
+                    reason_text(reason)
                 );
             }
             VerificationStatus::Untracked => {
-                println!(
+                outln!(
+                    "{}{} {} - {}",
+                    prefix,
+                    style(ICON_CIRCLE).dim(),
+                    style(name).bold(),
+                    style("untracked (no cache_paths)").dim()
+                );
+            }
+            VerificationStatus::Unsupported => {
+                outln!(
                     "{}{} {} - {}",
                     prefix,
                     style(ICON_CIRCLE).dim(),
                     style(name).bold(),
-                    style("untracked").dim()
+                    style(unsupported_text()).dim()
+                );
+            }
+            VerificationStatus::Failed {
+                exit_code,
+                at,
+                timed_out,
+            } => {
+                outln!(
+                    "{}{} {} - {} ({})",
+                    prefix,
+                    style(ICON_CIRCLE).red().bold(),
+                    style(name).bold(),
+                    style("failed").red(),
+                    failure_text(*exit_code, *timed_out, at)
                 );
             }
         }
@@ -113,6 +250,113 @@ impl Ui {
         }
     }
 
+    /// Print who ran a verified check, below its status line (`status --detailed`)
+    pub fn print_verified_by(&self, verified_by: &VerifiedBy, indent: usize) {
+        let by = &verified_by.attribution;
+        let mut text = format!("verified by {}", by.user);
+        if let Some(ref host) = by.host {
+            text.push_str(&format!(" on {}", host));
+        }
+        let at = verified_by.at.with_timezone(&chrono::Local);
+        text.push_str(&format!(" at {}", at.format("%Y-%m-%d %H:%M")));
+        outln!("{}  {}", Self::indent_str(indent), style(text).dim());
+    }
+
+    /// Note under a check's status that its trailer hash matched on only `length` chars
+    pub fn print_short_trailer_hash(&self, length: usize, indent: usize) {
+        let text = format!(
+            "matched on only {} hash characters (possible collision; re-sign for more)",
+            length
+        );
+        outln!("{}  {}", Self::indent_str(indent), style(text).yellow());
+    }
+
+    /// Print a check's description and owner under its status line
+    pub fn print_check_docs(&self, description: Option<&str>, owner: Option<&str>, indent: usize) {
+        let prefix = Self::indent_str(indent);
+        if let Some(description) = description {
+            outln!("{}  {}", prefix, style(description).dim());
+        }
+        if let Some(owner) = owner {
+            outln!("{}  {}", prefix, style(format!("owner: {}", owner)).dim());
+        }
+    }
+
+    /// Print what `verify explain` knows about a check beyond its status and docs
+    pub fn print_explain(&self, explain: &ExplainOutput) {
+        let field = |label: &str, value: String| {
+            outln!("  {} {}", style(format!("{}:", label)).dim(), value);
+        };
+        match explain.command {
+            Some(ref command) => field("command", command.clone()),
+            None => field(
+                "command",
+                "none (aggregate of its dependencies)".to_string(),
+            ),
+        }
+        if explain.cache_paths.is_empty() {
+            field("cache_paths", "none (runs every time)".to_string());
+        } else {
+            field("cache_paths", explain.cache_paths.join(", "));
+        }
+        if !explain.depends_on.is_empty() {
+            field("depends_on", explain.depends_on.join(", "));
+        }
+    }
+
+    /// Print the result of `verify bisect`
+    pub fn print_bisect(&self, result: &BisectOutput, max_depth: usize) {
+        let name = style(&result.check).bold();
+        let Some(ref last_verified) = result.last_verified else {
+            outln!(
+                "{} {} - {}",
+                style(ICON_CIRCLE).yellow().bold(),
+                name,
+                style(format!(
+                    "no verified commit in the last {} commit(s)",
+                    max_depth
+                ))
+                .yellow()
+            );
+            return;
+        };
+
+        let short = |commit: &str| commit.chars().take(7).collect::<String>();
+        match result.invalidated_by {
+            Some(ref invalidated_by) => {
+                outln!(
+                    "{} {} - {} {} {}",
+                    style(ICON_CIRCLE).red().bold(),
+                    name,
+                    style("invalidated by").red(),
+                    style(short(&invalidated_by.commit)).bold(),
+                    invalidated_by.subject
+                );
+                outln!(
+                    "  {} {}",
+                    style("changed:").dim(),
+                    result.changed_checks.join(", ")
+                );
+                outln!(
+                    "  {} {} {}",
+                    style("last verified:").dim(),
+                    short(&last_verified.commit),
+                    last_verified.subject
+                );
+            }
+            None => {
+                outln!(
+                    "{} {} - {} {} {}",
+                    style(ICON_CIRCLE).green().bold(),
+                    name,
+                    style("verified since").green(),
+                    style(short(&last_verified.commit)).bold(),
+                    last_verified.subject
+                );
+            }
+        }
+    }
+
     /// Print when a check is skipped (cache fresh)
     #[allow(dead_code)]
     pub fn print_skipped(&self, name: &str) {
@@ -123,7 +367,7 @@ impl Ui {
     #[allow(dead_code)]
     pub fn print_skipped_indented(&self, name: &str, indent: usize) {
         let prefix = Self::indent_str(indent);
-        println!(
+        outln!(
             "{}{} {} {}",
             prefix,
             style(ICON_CIRCLE).dim(),
@@ -141,7 +385,7 @@ impl Ui {
     /// Print when a check passes with indentation
     pub fn print_pass_indented(&self, name: &str, duration_ms: u64, indent: usize) {
         let prefix = Self::indent_str(indent);
-        println!(
+        outln!(
             "{}{} {} {}",
             prefix,
             style(ICON_CIRCLE).green().bold(),
@@ -160,7 +404,7 @@ impl Ui {
     #[allow(dead_code)]
     pub fn print_cached_indented(&self, name: &str, indent: usize) {
         let prefix = Self::indent_str(indent);
-        println!(
+        outln!(
             "{}{} {} {}",
             prefix,
             style(ICON_CIRCLE).green().bold(),
@@ -169,7 +413,7 @@ impl Ui {
         );
     }
 
-    /// Print cached count for per_file mode (using progress bar for consistent newline handling)
+    /// Print cached count for per_file mode
     pub fn print_per_file_cached(&self, name: &str, count: usize, indent: usize) {
         let prefix = Self::indent_str(indent);
         let message = format!(
@@ -179,17 +423,7 @@ impl Ui {
             style(format!("({} cached)", count)).dim()
         );
 
-        if self.use_progress_bars() {
-            let pb = crate::ui::create_running_indicator(name, indent);
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template(&format!("{}{{msg}}", prefix))
-                    .unwrap(),
-            );
-            pb.finish_with_message(message);
-        } else {
-            println!("{}{}", prefix, message);
-        }
+        outln!("{}{}", prefix, message);
     }
 
     /// Print when a check fails
@@ -207,7 +441,7 @@ impl Ui {
         indent: usize,
     ) {
         let prefix = Self::indent_str(indent);
-        println!(
+        outln!(
             "{}{} {} {}",
             prefix,
             style(ICON_CIRCLE).red().bold(),
@@ -215,31 +449,48 @@ impl Ui {
             style(format!("({})", format_duration(duration_ms))).dim()
         );
 
-        self.print_fail_output(output, indent);
+        self.print_command_output(output, indent);
     }
 
-    /// Print the output from a failed check (separate from the status line)
-    pub fn print_fail_output(&self, output: Option<&str>, indent: usize) {
+    /// Print a command's output under its status line
+    pub fn print_command_output(&self, output: Option<&str>, indent: usize) {
+        self.print_output_tail(output, self.verbose, "use --verbose to see all", indent);
+    }
+
+    /// Print the last lines of a `quiet` check's output, even in verbose mode
+    pub fn print_quiet_command_output(&self, output: Option<&str>, indent: usize) {
+        self.print_output_tail(output, false, "check is quiet", indent);
+    }
+
+    fn print_output_tail(
+        &self,
+        output: Option<&str>,
+        all: bool,
+        omitted_hint: &str,
+        indent: usize,
+    ) {
         let prefix = Self::indent_str(indent);
         if let Some(output) = output {
             // Print indented output, limited lines (show last N lines)
             let lines: Vec<&str> = output.lines().collect();
-            let max_lines = if self.verbose { lines.len() } else { 10 };
+            let max_lines = if all { lines.len() } else { 10 };
             let output_prefix = format!("{}  ", prefix);
 
             let skip_count = lines.len().saturating_sub(max_lines);
             if skip_count > 0 {
-                println!("{}{}", output_prefix, style("...").dim());
+                outln!("{}{}", output_prefix, style("...").dim());
             }
 
             for line in lines.iter().skip(skip_count) {
-                println!("{}{}", output_prefix, style(line).dim());
+                outln!("{}{}", output_prefix, style(line).dim());
             }
 
             if skip_count > 0 {
-                println!(
-                    "{}{} lines omitted (use --verbose to see all)",
-                    output_prefix, skip_count
+                outln!(
+                    "{}{} lines omitted ({})",
+                    output_prefix,
+                    skip_count,
+                    omitted_hint
                 );
             }
         }
@@ -256,14 +507,14 @@ impl Ui {
     pub fn print_wave_start_indented(&self, names: &[String], indent: usize) {
         let prefix = Self::indent_str(indent);
         if names.len() == 1 {
-            println!(
+            outln!(
                 "{}{} {}",
                 prefix,
                 style(ICON_CIRCLE).yellow().bold(),
                 style(&names[0]).bold()
             );
         } else {
-            println!(
+            outln!(
                 "{}{} {} {}",
                 prefix,
                 style(ICON_CIRCLE).yellow().bold(),
@@ -273,34 +524,243 @@ impl Ui {
         }
     }
 
+    /// Show the run-level progress bar below the check spinners.
+    /// Takes the historical duration of each check the run will visit (None if never recorded).
+    /// Does nothing when progress bars are disabled (non-TTY or verbose).
+    pub fn start_run_progress(&self, estimates: &[Option<u64>]) {
+        if !self.use_progress_bars() || estimates.is_empty() {
+            return;
+        }
+
+        let known: Vec<u64> = estimates.iter().flatten().copied().collect();
+        let default_estimate_ms = if known.is_empty() {
+            0
+        } else {
+            known.iter().sum::<u64>() / known.len() as u64
+        };
+        let remaining_ms = (!known.is_empty()).then(|| {
+            estimates
+                .iter()
+                .map(|e| e.unwrap_or(default_estimate_ms))
+                .sum()
+        });
+
+        let bar = PROGRESS.add(ProgressBar::new(estimates.len() as u64));
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{bar:30.green/dim} {msg}")
+                .unwrap()
+                .progress_chars("━━─"),
+        );
+        let progress = RunProgress {
+            bar,
+            total: estimates.len(),
+            passed: 0,
+            cached: 0,
+            failed: 0,
+            running: HashSet::new(),
+            remaining_ms,
+            default_estimate_ms,
+        };
+        progress.bar.set_message(progress.message());
+        progress.bar.enable_steady_tick(Duration::from_millis(100));
+        *RUN_PROGRESS.lock().unwrap() = Some(progress);
+    }
+
+    /// Count a check as running in the run-level progress bar
+    pub fn start_check_progress(&self, name: &str) {
+        let mut guard = RUN_PROGRESS.lock().unwrap();
+        if let Some(progress) = guard.as_mut() {
+            progress.running.insert(name.to_string());
+            progress.bar.set_message(progress.message());
+        }
+    }
+
+    /// Count a check as completed in the run-level progress bar
+    pub fn advance_run_progress(
+        &self,
+        name: &str,
+        estimate_ms: Option<u64>,
+        outcome: CheckOutcome,
+    ) {
+        let mut guard = RUN_PROGRESS.lock().unwrap();
+        let Some(progress) = guard.as_mut() else {
+            return;
+        };
+
+        progress.running.remove(name);
+        match outcome {
+            CheckOutcome::Passed => progress.passed += 1,
+            CheckOutcome::Cached => progress.cached += 1,
+            CheckOutcome::Failed => progress.failed += 1,
+        }
+        let estimate_ms = estimate_ms.unwrap_or(progress.default_estimate_ms);
+        progress.remaining_ms = progress
+            .remaining_ms
+            .map(|remaining| remaining.saturating_sub(estimate_ms));
+        progress.bar.set_position(progress.completed() as u64);
+        progress.bar.set_message(progress.message());
+    }
+
+    /// Remove the run-level progress bar
+    pub fn finish_run_progress(&self) {
+        if let Some(progress) = RUN_PROGRESS.lock().unwrap().take() {
+            progress.bar.finish_and_clear();
+            PROGRESS.remove(&progress.bar);
+        }
+    }
+
     /// Print summary at end of run
-    pub fn print_summary(&self, passed: usize, failed: usize, skipped: usize, duration_ms: u64) {
-        println!();
+    pub fn print_summary(
+        &self,
+        passed: usize,
+        failed: usize,
+        skipped: usize,
+        blocked: usize,
+        duration_ms: u64,
+    ) {
+        outln!();
 
         // Treat cached as passed
         let total_passed = passed + skipped;
-        let duration_str = format!("({})", format_duration(duration_ms));
+        let mut counts = style(format!("{} verified", total_passed))
+            .green()
+            .to_string();
+        if failed > 0 {
+            counts.push_str(&format!(", {}", style(format!("{} failed", failed)).red()));
+        }
+        if blocked > 0 {
+            counts.push_str(&format!(
+                ", {}",
+                style(format!("{} blocked", blocked)).yellow()
+            ));
+        }
+        outln!(
+            "{} {}",
+            counts,
+            style(format!("({})", format_duration(duration_ms))).dim()
+        );
+    }
 
-        if failed == 0 {
-            println!(
-                "{} {}",
-                style(format!("{} verified", total_passed)).green(),
-                style(duration_str).dim()
+    /// Print the run's `--message` annotation below the summary
+    pub fn print_run_message(&self, message: &str) {
+        outln!("{}", style(message).dim().italic());
+    }
+
+    /// Print recent runs from the run history, newest first
+    pub fn print_history(&self, runs: &[RunRecord]) {
+        if runs.is_empty() {
+            outln!("{}", style("No runs recorded yet").dim());
+            return;
+        }
+
+        for run in runs {
+            let (icon, counts) = if let Some(ref by) = run.marked_by {
+                (
+                    style(ICON_CIRCLE).green().bold(),
+                    style(format!("marked verified by {}", by)).green(),
+                )
+            } else if run.failed == 0 && run.blocked == 0 {
+                (
+                    style(ICON_CIRCLE).green().bold(),
+                    style(format!("{} verified", run.passed + run.skipped)).green(),
+                )
+            } else {
+                (
+                    style(ICON_CIRCLE).red().bold(),
+                    style(if run.blocked > 0 {
+                        format!(
+                            "{} verified, {} failed, {} blocked",
+                            run.passed + run.skipped,
+                            run.failed,
+                            run.blocked
+                        )
+                    } else {
+                        format!(
+                            "{} verified, {} failed",
+                            run.passed + run.skipped,
+                            run.failed
+                        )
+                    })
+                    .red(),
+                )
+            };
+            let timestamp = run.timestamp.with_timezone(&chrono::Local);
+            let mut line = format!(
+                "{} {} {} {}",
+                icon,
+                style(timestamp.format("%Y-%m-%d %H:%M")).dim(),
+                counts,
+                style(format!("({})", format_duration(run.duration_ms))).dim()
             );
-        } else {
-            println!(
-                "{}, {} {}",
-                style(format!("{} verified", total_passed)).green(),
-                style(format!("{} failed", failed)).red(),
-                style(duration_str).dim()
+            if !run.checks.is_empty() {
+                line.push_str(&format!(" {}", run.checks.join(", ")));
+            }
+            if let Some(ref user) = run.user {
+                let by = match run.host {
+                    Some(ref host) => format!("by {} on {}", user, host),
+                    None => format!("by {}", user),
+                };
+                line.push_str(&format!(" {}", style(by).dim()));
+            }
+            if let Some(ref message) = run.message {
+                line.push_str(&format!(" - {}", style(message).italic()));
+            }
+            outln!("{}", line);
+        }
+    }
+
+    /// Print cumulative cache statistics, then each check's, most often run first
+    pub fn print_stats(&self, summary: &StatsSummary) {
+        let Some(since) = summary.since else {
+            outln!("{}", style("No runs recorded yet").dim());
+            return;
+        };
+        let since = since.with_timezone(&chrono::Local);
+        outln!(
+            "{} runs since {}",
+            summary.runs,
+            style(since.format("%Y-%m-%d %H:%M")).dim()
+        );
+        outln!(
+            "  {} of {} checks cached ({:.0}%)",
+            summary.cached,
+            summary.cached + summary.ran,
+            summary.hit_rate * 100.0
+        );
+        outln!("  {} saved", format_duration(summary.saved_ms));
+        outln!("  {} hashed", format_size(summary.bytes_hashed));
+
+        let width = summary
+            .checks
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0);
+        if width > 0 {
+            outln!();
+        }
+        for check in &summary.checks {
+            outln!(
+                "{:width$}  ran {}, cached {} {}",
+                check.name,
+                check.ran,
+                check.cached,
+                style(format!("({:.0}% cached)", check.hit_rate * 100.0)).dim(),
+                width = width
             );
         }
     }
 
+    /// Print metadata aggregated across the run, below the summary
+    pub fn print_metadata_totals(&self, totals: &BTreeMap<String, MetadataValue>) {
+        print_metadata(totals, None, 0);
+    }
+
     /// Print when all checks are fresh
     #[allow(dead_code)]
     pub fn print_all_fresh(&self) {
-        println!("{}", style("All checks are fresh, nothing to run").green());
+        outln!("{}", style("All checks are fresh, nothing to run").green());
     }
 
     /// Print error message
@@ -314,29 +774,34 @@ impl Ui {
         eprintln!("{} {}", style("hint:").yellow(), msg);
     }
 
+    /// Print a warning message
+    pub fn print_warning(&self, msg: &str) {
+        eprintln!("{} {}", style("warning:").yellow().bold(), msg);
+    }
+
     /// Print success message for init
     pub fn print_init_success(&self, path: &str) {
-        println!(
+        outln!(
             "{} Created {}",
             style(ICON_CIRCLE).green().bold(),
             style(path).bold()
         );
-        println!(
+        outln!(
             "  Run {} to see check status",
             style("verify status").cyan()
         );
-        println!("  Run {} to execute checks", style("verify").cyan());
+        outln!("  Run {} to execute checks", style("verify").cyan());
     }
 
     /// Print cache cleaned message
     pub fn print_cache_cleaned(&self, names: &[String]) {
         if names.is_empty() {
-            println!(
+            outln!(
                 "{} Cleared all cached results",
                 style(ICON_CIRCLE).green().bold()
             );
         } else {
-            println!(
+            outln!(
                 "{} Cleared cache for: {}",
                 style(ICON_CIRCLE).green().bold(),
                 names.join(", ")
@@ -344,22 +809,453 @@ impl Ui {
         }
     }
 
+    /// Print the stale dependencies `verify run NAME` runs before the named checks, with
+    /// how long each took last time
+    pub fn print_stale_dependencies(
+        &self,
+        names: &[String],
+        dependencies: &[(String, Option<u64>)],
+    ) {
+        let list = dependencies
+            .iter()
+            .map(|(name, estimate)| match estimate {
+                Some(ms) => format!("{} (~{})", name, format_duration(*ms)),
+                None => name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        outln!(
+            "{} {} {} on stale checks that will run first: {}",
+            style(ICON_CIRCLE).yellow().bold(),
+            names.join(", "),
+            if names.len() == 1 {
+                "depends"
+            } else {
+                "depend"
+            },
+            list
+        );
+    }
+
+    /// Ask a yes/no question (default yes). None when stdin or stderr isn't a terminal.
+    pub fn confirm(&self, question: &str) -> Option<bool> {
+        if !Term::stderr().is_term() || !std::io::stdin().is_terminal() {
+            return None;
+        }
+        eprint!("{} [Y/n] ", question);
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).ok()?;
+        Some(!matches!(answer.trim().to_lowercase().as_str(), "n" | "no"))
+    }
+
+    /// Print `verify diff`'s message for a check whose files match verify.lock
+    pub fn print_no_changes(&self, name: &str) {
+        outln!(
+            "{} No files changed since {} was verified",
+            style(ICON_CIRCLE).green().bold(),
+            name
+        );
+    }
+
+    /// Print a changed file without its diff, as "M path" with an optional note
+    pub fn print_file_change(&self, marker: &str, path: &str, note: Option<&str>) {
+        match note {
+            Some(note) => outln!("{} {} {}", marker, path, style(format!("({})", note)).dim()),
+            None => outln!("{} {}", marker, path),
+        }
+    }
+
+    /// Print the result of `verify audit`
+    pub fn print_audit_report(&self, report: &ChainReport) {
+        if report.intact {
+            outln!(
+                "{} Audit log intact: {} entries{}",
+                style(ICON_CIRCLE).green().bold(),
+                report.entries,
+                report
+                    .head
+                    .as_ref()
+                    .map(|head| format!(", head {}", head))
+                    .unwrap_or_default()
+            );
+        } else {
+            outln!(
+                "{} Audit log tampered with ({})",
+                style(ICON_CIRCLE).red().bold(),
+                report.path
+            );
+            if let Some(error) = &report.error {
+                outln!("  {}", error);
+            }
+            outln!("  {} entries before it are intact", report.entries);
+        }
+    }
+
+    /// Print `verify coverage`: a line per directory, then the files no check covers
+    pub fn print_coverage(&self, report: &CoverageOutput) {
+        let width = report
+            .directories
+            .iter()
+            .map(|dir| dir.path.len())
+            .max()
+            .unwrap_or(0);
+        for dir in &report.directories {
+            let percent = format!("{:>5.1}%", dir.percent);
+            let percent = if dir.covered == dir.total {
+                style(percent).green()
+            } else if dir.covered == 0 {
+                style(percent).red()
+            } else {
+                style(percent).yellow()
+            };
+            outln!(
+                "  {:<width$}  {} {}",
+                dir.path,
+                percent,
+                style(format!("({}/{})", dir.covered, dir.total)).dim(),
+                width = width
+            );
+        }
+
+        let icon = if report.uncovered.is_empty() {
+            style(ICON_CIRCLE).green().bold()
+        } else {
+            style(ICON_CIRCLE).yellow().bold()
+        };
+        outln!(
+            "{} {} of {} files covered by cache_paths ({:.1}%)",
+            icon,
+            report.covered,
+            report.total,
+            report.percent
+        );
+        if !report.uncovered.is_empty() {
+            outln!("Not covered by any check:");
+            for file in &report.uncovered {
+                outln!("  {}", file);
+            }
+        }
+    }
+
+    /// Clear the terminal between `verify watch` rounds (not when output is redirected)
+    pub fn clear_screen(&self) {
+        let term = Term::stdout();
+        if term.is_term() {
+            let _ = term.clear_screen();
+        }
+    }
+
+    /// Print the files whose change `verify watch` is reacting to
+    pub fn print_files_changed(&self, paths: &[String]) {
+        const SHOWN: usize = 5;
+        let mut list = paths
+            .iter()
+            .take(SHOWN)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if paths.len() > SHOWN {
+            list.push_str(&format!(" and {} more", paths.len() - SHOWN));
+        }
+        outln!("{} Changed: {}", style(ICON_CIRCLE).cyan().bold(), list);
+    }
+
+    pub fn print_watching(&self) {
+        outln!("\n{}", style("Watching for changes (Ctrl-C to stop)").dim());
+    }
+
+    pub fn print_marked_verified(&self, name: &str, by: &str) {
+        outln!(
+            "{} Marked {} verified (by {})",
+            style(ICON_CIRCLE).green().bold(),
+            name,
+            by
+        );
+    }
+
+    /// Print the `verify why` heading for a check with blockers
+    pub fn print_why_header(&self, name: &str) {
+        outln!(
+            "{} is unverified. These have to run first, in order:",
+            style(name).bold()
+        );
+    }
+
+    /// Print a `verify run` command suggestion
+    pub fn print_run_suggestion(&self, command: &str) {
+        outln!();
+        outln!("{} {}", style("run:").dim(), command);
+    }
+
+    pub fn print_orphans_removed(&self, paths: &[String]) {
+        if paths.is_empty() {
+            outln!(
+                "{} No orphaned subproject state found",
+                style(ICON_CIRCLE).green().bold()
+            );
+        } else {
+            outln!(
+                "{} Removed state of orphaned subprojects: {}",
+                style(ICON_CIRCLE).green().bold(),
+                paths.join(", ")
+            );
+        }
+    }
+
     /// Print when a check starts running (for verbose mode)
     pub fn print_running(&self, name: &str, indent: usize) {
         let prefix = Self::indent_str(indent);
-        println!(
+        outln!(
             "{}{} {}",
             prefix,
             style(ICON_CIRCLE).yellow().bold(),
             style(name).bold()
         );
     }
+
+    /// Print a line of a check's streamed output, prefixed with a colored `[label]` so
+    /// lines from different checks can't be mixed up. Inside CI sections, which already
+    /// name the check, lines are printed as they are.
+    pub fn print_streamed_line(&self, label: &str, line: &str, stderr: bool) {
+        let line = if self.ci.is_some() {
+            line.to_string()
+        } else {
+            format!(
+                "{} {}",
+                label_style(label).apply_to(format!("[{}]", label)),
+                line
+            )
+        };
+        if stderr {
+            PROGRESS.suspend(|| eprintln!("{}", line));
+        } else {
+            outln!("{}", line);
+        }
+    }
+
+    /// Open a collapsible section for a check's streamed output when running in CI
+    pub fn start_output_group(&self, name: &str) {
+        if let Some(ci) = self.ci {
+            outln!("{}", ci.section_start(name));
+        }
+    }
+
+    /// Close the section opened by `start_output_group`
+    pub fn end_output_group(&self, name: &str) {
+        if let Some(end) = self.ci.and_then(|ci| ci.section_end(name)) {
+            outln!("{}", end);
+        }
+    }
+
+    /// Print the cache comparison behind running or skipping a check (`run --explain-cache`)
+    pub fn print_cache_decision(&self, name: &str, decision: &CacheDecisionJson, indent: usize) {
+        let prefix = Self::indent_str(indent);
+        fn compare(current: &str, cached: Option<&str>) -> String {
+            let short = |hash: &str| hash.chars().take(12).collect::<String>();
+            match cached {
+                Some(cached) if cached == current => format!("{} (unchanged)", short(current)),
+                Some(cached) => format!("{} (was {})", short(current), short(cached)),
+                None => format!("{} (not cached)", short(current)),
+            }
+        }
+
+        outln!(
+            "{}{} {}",
+            prefix,
+            style(format!("{} cache:", name)).dim(),
+            style(&decision.decision).bold()
+        );
+        let mut lines = vec![
+            format!(
+                "config:  {}",
+                compare(
+                    &decision.config_hash,
+                    decision.cached_config_hash.as_deref()
+                )
+            ),
+            format!(
+                "content: {}",
+                compare(
+                    &decision.content_hash,
+                    decision.cached_content_hash.as_deref()
+                )
+            ),
+        ];
+        if let Some(ref dependency) = decision.invalidated_by {
+            lines.push(format!("invalidated by dependency '{}'", dependency));
+        }
+        if decision.forced {
+            lines.push("forced with --force".to_string());
+        }
+        for line in lines {
+            outln!("{}  {}", prefix, style(line).dim());
+        }
+    }
+
+    /// Print that a check is waiting for another verify process that is running it
+    pub fn print_waiting(&self, name: &str, indent: usize) {
+        let prefix = Self::indent_str(indent);
+        outln!(
+            "{}{} {} {}",
+            prefix,
+            style(ICON_CIRCLE).dim(),
+            style(name).bold(),
+            style("waiting for another verify process").dim()
+        );
+    }
+
+    /// Print that a check was skipped for having no command for this platform
+    pub fn print_unsupported(&self, name: &str, indent: usize) {
+        let prefix = Self::indent_str(indent);
+        outln!(
+            "{}{} {} {}",
+            prefix,
+            style(ICON_CIRCLE).dim(),
+            style(name).bold(),
+            style(unsupported_text()).dim()
+        );
+    }
+}
+
+/// Short description of why a check is unverified
+fn reason_text(reason: &UnverifiedReason) -> String {
+    match reason {
+        UnverifiedReason::FilesChanged { changed_files } => {
+            if changed_files.is_empty() {
+                "files changed".to_string()
+            } else {
+                format!("{} file(s) changed", changed_files.len())
+            }
+        }
+        UnverifiedReason::DependencyUnverified { dependency } => {
+            format!("depends on: {}", dependency)
+        }
+        UnverifiedReason::ConfigChanged { changed } => {
+            if changed.is_empty() {
+                "config changed".to_string()
+            } else {
+                format!("{} changed", changed.join(", "))
+            }
+        }
+        UnverifiedReason::NeverRun => "never run".to_string(),
+        UnverifiedReason::ToolUpgraded { from } => match from {
+            Some(version) => format!("recorded by verify {}", version),
+            None => "recorded by an older verify".to_string(),
+        },
+    }
+}
+
+/// Status of a check with no command for this platform
+fn unsupported_text() -> String {
+    format!("unsupported (no command for {})", std::env::consts::OS)
+}
+
+/// Exit code (or timeout) and local time of a check's last failed run
+fn failure_text(
+    exit_code: Option<i32>,
+    timed_out: bool,
+    at: &chrono::DateTime<chrono::Utc>,
+) -> String {
+    let code = match exit_code {
+        Some(code) => format!("exit code {}", code),
+        None if timed_out => "timed out".to_string(),
+        None => "killed".to_string(),
+    };
+    let at = at.with_timezone(&chrono::Local);
+    format!("{} at {}", code, at.format("%Y-%m-%d %H:%M"))
 }
 
-/// Create a running indicator that shows a yellow circle and can be updated in-place
+/// Lines of the `verify status --watch` dashboard: one row per check or subproject,
+/// with the selected row marked and unverified rows aged from when they went stale
+pub fn dashboard_lines(
+    rows: &[DashboardRow],
+    selected: usize,
+    message: Option<&str>,
+    now: SystemTime,
+) -> Vec<String> {
+    let verified = rows.iter().filter(|row| row.is_verified()).count();
+    let width = rows.iter().map(|row| row.name.len()).max().unwrap_or(0);
+
+    let mut lines = vec![
+        format!(
+            "{} {}",
+            style("verify status").bold(),
+            style(format!("({}/{} verified)", verified, rows.len())).dim()
+        ),
+        String::new(),
+    ];
+
+    for (index, row) in rows.iter().enumerate() {
+        let marker = if index == selected { ">" } else { " " };
+        let name = format!("{:width$}", row.name, width = width);
+        let age = row
+            .stale_since
+            .and_then(|since| now.duration_since(since).ok())
+            .map(|age| format!("stale for {}, ", format_age(age)))
+            .unwrap_or_default();
+        let (icon, detail) = match &row.state {
+            DashboardState::Check(VerificationStatus::Verified)
+            | DashboardState::Subproject { has_stale: false } => (
+                style(ICON_CIRCLE).green().bold(),
+                style("verified".to_string()).green(),
+            ),
+            DashboardState::Check(VerificationStatus::Unverified { reason }) => (
+                style(ICON_CIRCLE).yellow().bold(),
+                style(format!("unverified ({}{})", age, reason_text(reason))).yellow(),
+            ),
+            DashboardState::Check(VerificationStatus::Untracked) => (
+                style(ICON_CIRCLE).dim(),
+                style("untracked (no cache_paths)".to_string()).dim(),
+            ),
+            DashboardState::Check(VerificationStatus::Unsupported) => {
+                (style(ICON_CIRCLE).dim(), style(unsupported_text()).dim())
+            }
+            DashboardState::Check(VerificationStatus::Failed {
+                exit_code,
+                at,
+                timed_out,
+            }) => (
+                style(ICON_CIRCLE).red().bold(),
+                style(format!(
+                    "failed ({})",
+                    failure_text(*exit_code, *timed_out, at)
+                ))
+                .red(),
+            ),
+            DashboardState::Subproject { has_stale: true } => (
+                style(ICON_CIRCLE).yellow().bold(),
+                style("subproject has unverified checks".to_string()).yellow(),
+            ),
+        };
+        let name = if index == selected {
+            style(name).bold().reverse()
+        } else {
+            style(name).bold()
+        };
+        lines.push(format!("{} {} {}  {}", marker, icon, name, detail));
+    }
+
+    lines.push(String::new());
+    lines.push(
+        style("up/down select · enter run selected · a run all unverified · q quit")
+            .dim()
+            .to_string(),
+    );
+    if let Some(message) = message {
+        lines.push(message.to_string());
+    }
+    lines
+}
+
+/// Create a running indicator that shows a yellow circle and can be updated in-place.
+/// While a run progress bar is shown, the indicator is placed above it.
 pub fn create_running_indicator(name: &str, indent: usize) -> ProgressBar {
     let prefix = "    ".repeat(indent);
     let pb = ProgressBar::new_spinner();
+    let pb = match RUN_PROGRESS.lock().unwrap().as_ref() {
+        Some(progress) => PROGRESS.insert_before(&progress.bar, pb),
+        None => PROGRESS.add(pb),
+    };
     pb.set_style(
         ProgressStyle::default_spinner()
             .template(&format!("{}{{spinner:.yellow.bold}} {{msg}}", prefix))
@@ -382,16 +1278,8 @@ pub fn finish_pass(pb: &ProgressBar, name: &str, duration_ms: u64, indent: usize
         style(format!("({})", format_duration(duration_ms))).dim()
     );
 
-    if pb.is_hidden() {
-        println!("{}{}", prefix, message);
-    } else {
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template(&format!("{}{{msg}}", prefix))
-                .unwrap(),
-        );
-        pb.finish_with_message(message);
-    }
+    clear_indicator(pb);
+    outln!("{}{}", prefix, message);
 }
 
 /// Finish a running indicator with cached state (green circle) + metadata display
@@ -409,17 +1297,8 @@ pub fn finish_cached(
         style("(cached)").dim()
     );
 
-    if pb.is_hidden() {
-        // Non-TTY: print directly
-        println!("{}{}", prefix, message);
-    } else {
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template(&format!("{}{{msg}}", prefix))
-                .unwrap(),
-        );
-        pb.finish_with_message(message);
-    }
+    clear_indicator(pb);
+    outln!("{}{}", prefix, message);
 
     // Print metadata below (if any)
     if !metadata.is_empty() {
@@ -431,10 +1310,8 @@ pub fn finish_cached(
 #[allow(dead_code)]
 pub fn finish_fail(pb: &ProgressBar, name: &str, command: &str, duration_ms: u64, indent: usize) {
     let prefix = "    ".repeat(indent);
-    if !pb.is_hidden() {
-        pb.finish_and_clear();
-    }
-    println!(
+    clear_indicator(pb);
+    outln!(
         "{}{} {} {}",
         prefix,
         style(ICON_CIRCLE).red().bold(),
@@ -442,7 +1319,13 @@ pub fn finish_fail(pb: &ProgressBar, name: &str, command: &str, duration_ms: u64
         style(format!("({})", format_duration(duration_ms))).dim()
     );
     // Print the command in red
-    println!("{}  {}", prefix, style(command).red());
+    outln!("{}  {}", prefix, style(command).red());
+}
+
+/// Remove a running indicator from the terminal so its final line can be printed in its place
+fn clear_indicator(pb: &ProgressBar) {
+    pb.finish_and_clear();
+    PROGRESS.remove(pb);
 }
 
 /// Format duration for display
@@ -471,7 +1354,7 @@ fn print_metadata(
 
         match delta {
             Some(d) if d > 0.0 => {
-                println!(
+                outln!(
                     "{}  {}: {} {}",
                     prefix,
                     style(key).dim(),
@@ -480,7 +1363,7 @@ fn print_metadata(
                 )
             }
             Some(d) if d < 0.0 => {
-                println!(
+                outln!(
                     "{}  {}: {} {}",
                     prefix,
                     style(key).dim(),
@@ -488,7 +1371,7 @@ fn print_metadata(
                     style(format!("({})", format_delta(d))).red()
                 )
             }
-            _ => println!("{}  {}: {}", prefix, style(key).dim(), value),
+            _ => outln!("{}  {}: {}", prefix, style(key).dim(), value),
         }
     }
 }
@@ -511,17 +1394,8 @@ pub fn finish_pass_with_metadata(
         style(duration_str).dim()
     );
 
-    if pb.is_hidden() {
-        // Non-TTY: print directly
-        println!("{}{}", prefix, message);
-    } else {
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template(&format!("{}{{msg}}", prefix))
-                .unwrap(),
-        );
-        pb.finish_with_message(message);
-    }
+    clear_indicator(pb);
+    outln!("{}{}", prefix, message);
 
     // Print metadata below (if any)
     if !metadata.is_empty() {
@@ -542,10 +1416,8 @@ pub fn finish_fail_with_metadata(
     let prefix = "    ".repeat(indent);
     let duration_str = format_duration_display(duration_ms);
 
-    if !pb.is_hidden() {
-        pb.finish_and_clear();
-    }
-    println!(
+    clear_indicator(pb);
+    outln!(
         "{}{} {} {}",
         prefix,
         style(ICON_CIRCLE).red().bold(),
@@ -554,10 +1426,39 @@ pub fn finish_fail_with_metadata(
     );
 
     // Print the command in red
-    println!("{}  {}", prefix, style(command).red());
+    outln!("{}  {}", prefix, style(command).red());
 
     // Print metadata below (if any)
     if !metadata.is_empty() {
         print_metadata(metadata, prev_metadata, indent);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_progress_message() {
+        console::set_colors_enabled(false);
+        let mut progress = RunProgress {
+            bar: ProgressBar::hidden(),
+            total: 23,
+            passed: 3,
+            cached: 0,
+            failed: 1,
+            running: HashSet::from(["test".to_string(), "lint".to_string()]),
+            remaining_ms: None,
+            default_estimate_ms: 0,
+        };
+        assert_eq!(
+            progress.message(),
+            "3 passed · 1 failed · 2 running · 17 queued"
+        );
+
+        progress.running.clear();
+        progress.cached = 19;
+        progress.remaining_ms = Some(0);
+        assert_eq!(progress.message(), "3 passed · 19 cached · 1 failed");
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Version of this binary
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const REPO: &str = "yonaskolb/verify";
+
+/// Error unless this binary satisfies a config's `required_version`
+pub fn check_required_version(required: &str, config_path: &Path) -> Result<()> {
+    let req = VersionReq::parse(required).with_context(|| {
+        format!(
+            "Invalid required_version '{}' in {}",
+            required,
+            config_path.display()
+        )
+    })?;
+    let current = Version::parse(CURRENT_VERSION)?;
+    if !req.matches(&current) {
+        anyhow::bail!(
+            "{} requires verify {}, but this is verify {}. Run `verify self-update` to upgrade.",
+            config_path.display(),
+            required,
+            CURRENT_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Result of `verify self-update`
+pub enum UpdateOutcome {
+    UpToDate,
+    Updated { version: Version },
+}
+
+/// Replace the running binary with the latest release, after checking the download
+/// against the release's published SHA-256 checksum
+pub fn self_update() -> Result<UpdateOutcome> {
+    let target = release_target()?;
+    let latest = latest_version()?;
+    let current = Version::parse(CURRENT_VERSION)?;
+    if latest <= current {
+        return Ok(UpdateOutcome::UpToDate);
+    }
+
+    let asset = format!("verify-{}.tar.gz", target);
+    let base = format!("https://github.com/{}/releases/download/v{}", REPO, latest);
+    let dir = tempfile::tempdir()?;
+    let archive = dir.path().join(&asset);
+    download(&format!("{}/{}", base, asset), &archive)?;
+    let checksums = dir.path().join(format!("{}.sha256", asset));
+    download(&format!("{}/{}.sha256", base, asset), &checksums)?;
+
+    let expected = parse_checksum(&fs::read_to_string(&checksums)?)
+        .with_context(|| format!("Malformed checksum file for {}", asset))?;
+    let actual = format!("{:x}", Sha256::digest(fs::read(&archive)?));
+    if actual != expected {
+        anyhow::bail!(
+            "Checksum mismatch for {} (expected {}, got {}); not updating",
+            asset,
+            expected,
+            actual
+        );
+    }
+
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(dir.path())
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}", asset);
+    }
+
+    replace_current_exe(&dir.path().join("verify"))?;
+    Ok(UpdateOutcome::Updated { version: latest })
+}
+
+/// Release asset target for this platform (matching install.sh)
+fn release_target() -> Result<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("x86_64-unknown-linux-gnu"),
+        ("macos", "x86_64") => Ok("x86_64-apple-darwin"),
+        ("macos", "aarch64") => Ok("aarch64-apple-darwin"),
+        (os, arch) => anyhow::bail!("No release binaries for {}-{}", os, arch),
+    }
+}
+
+fn latest_version() -> Result<Version> {
+    let url = format!("https://api.github.com/repos/{}/releases/latest", REPO);
+    let output = Command::new("curl")
+        .args(["-fsSL", "-H", "Accept: application/vnd.github+json", &url])
+        .output()
+        .context("Failed to run curl")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to fetch the latest release: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let release: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("Failed to parse the latest release")?;
+    let tag = release["tag_name"]
+        .as_str()
+        .context("Latest release has no tag")?;
+    parse_tag(tag)
+}
+
+/// Parse a release tag like "v0.8.0"
+fn parse_tag(tag: &str) -> Result<Version> {
+    Version::parse(tag.trim_start_matches('v'))
+        .with_context(|| format!("Unexpected release tag: {}", tag))
+}
+
+/// The hash from a `shasum -a 256` line ("<hash>  <file>")
+fn parse_checksum(content: &str) -> Option<String> {
+    let hash = content.split_whitespace().next()?.to_lowercase();
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then_some(hash)
+}
+
+fn download(url: &str, dest: &Path) -> Result<()> {
+    let status = Command::new("curl")
+        .args(["-fsSL", "-o"])
+        .arg(dest)
+        .arg(url)
+        .status()
+        .context("Failed to run curl")?;
+    if !status.success() {
+        anyhow::bail!("Failed to download {}", url);
+    }
+    Ok(())
+}
+
+/// Swap in the new binary with a rename next to the current one, so a failure midway
+/// never leaves a partially written executable
+fn replace_current_exe(new_binary: &Path) -> Result<()> {
+    let current = std::env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .context("Failed to locate the running binary")?;
+    let staged = current.with_file_name(".verify-update");
+    let no_permission = || {
+        format!(
+            "Failed to write {} (try again with sudo)",
+            current.display()
+        )
+    };
+
+    fs::copy(new_binary, &staged).with_context(no_permission)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    if let Err(e) = fs::rename(&staged, &current) {
+        let _ = fs::remove_file(&staged);
+        return Err(e).with_context(no_permission);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_required_version() {
+        let path = Path::new("verify.yaml");
+        assert!(check_required_version(&format!(">={}", CURRENT_VERSION), path).is_ok());
+        assert!(check_required_version(">=0.1", path).is_ok());
+
+        let err = check_required_version(">=999.0", path)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("requires verify >=999.0"), "{}", err);
+        assert!(err.contains("verify self-update"), "{}", err);
+
+        assert!(check_required_version("not a version", path).is_err());
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag("v0.8.0").unwrap(), Version::new(0, 8, 0));
+        assert_eq!(parse_tag("1.2.3").unwrap(), Version::new(1, 2, 3));
+        assert!(parse_tag("latest").is_err());
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let hash = "a".repeat(64);
+        assert_eq!(
+            parse_checksum(&format!("{}  verify-x86_64-apple-darwin.tar.gz\n", hash)),
+            Some(hash.clone())
+        );
+        assert_eq!(parse_checksum(&hash.to_uppercase()), Some(hash));
+        assert_eq!(parse_checksum("not-a-hash  file"), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+}
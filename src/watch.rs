@@ -0,0 +1,119 @@
+use crate::hasher::{self, is_verify_state};
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Quiet period after a change before reporting it, so a burst of writes
+/// (saving several files, a git checkout) is seen as one change
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Recursively watches a project for file changes, ignoring verify's own
+/// `.verify/` state and lock files, and `.git/`
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new(project_root: &Path) -> Result<Self> {
+        let root = project_root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve: {}", project_root.display()))?;
+        let (tx, changes) = mpsc::channel();
+
+        let filter_root = root.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if event.kind.is_access() {
+                    return;
+                }
+                for path in event.paths {
+                    if is_watched(&filter_root, &path) {
+                        let _ = tx.send(path);
+                    }
+                }
+            })
+            .context("Failed to start file watcher")?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch: {}", root.display()))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait up to `timeout` for files to change. Returns the changed paths once
+    /// changes have settled, or an empty list if nothing changed.
+    pub fn wait(&self, timeout: Duration) -> Vec<PathBuf> {
+        self.wait_settled(timeout, SETTLE)
+    }
+
+    /// `wait`, with changes settled once nothing has changed for `settle`
+    pub fn wait_settled(&self, timeout: Duration, settle: Duration) -> Vec<PathBuf> {
+        let mut changed = match self.changes.recv_timeout(timeout) {
+            Ok(path) => vec![path],
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return Vec::new(),
+        };
+        while let Ok(path) = self.changes.recv_timeout(settle) {
+            changed.push(path);
+        }
+        changed.sort();
+        changed.dedup();
+        // Hashes of the changed files may be remembered from before the change
+        hasher::files_may_have_changed();
+        changed
+    }
+}
+
+/// Whether a change at `path` can affect check status
+fn is_watched(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    relative != ".git" && !relative.starts_with(".git/") && !is_verify_state(&relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_is_watched_ignores_internal_dirs() {
+        let root = Path::new("/project");
+        assert!(is_watched(root, Path::new("/project/src/main.rs")));
+        assert!(is_watched(root, Path::new("/project/verify.yaml")));
+        assert!(!is_watched(root, Path::new("/project/verify.lock")));
+        assert!(!is_watched(root, Path::new("/project/sub/verify.lock")));
+        assert!(!is_watched(root, Path::new("/project/.verify/hashcache")));
+        assert!(!is_watched(
+            root,
+            Path::new("/project/sub/.verify/hashcache")
+        ));
+        assert!(!is_watched(root, Path::new("/project/.git/index")));
+        assert!(!is_watched(root, Path::new("/elsewhere/file")));
+    }
+
+    #[test]
+    fn test_wait_reports_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let watcher = FileWatcher::new(dir.path()).unwrap();
+        assert!(watcher.wait(Duration::from_millis(50)).is_empty());
+
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let changed = watcher.wait(Duration::from_secs(5));
+        assert!(
+            changed.iter().any(|p| p.ends_with("a.txt")),
+            "Expected a.txt in {:?}",
+            changed
+        );
+    }
+}
@@ -2,13 +2,13 @@
 /// Tests for cache persistence, atomicity, version handling, and edge cases
 mod common;
 
-use common::TestProject;
+use common::{TestProject, verify_binary};
 use std::fs;
 
 // ==================== Cache Format Tests ====================
 
 #[test]
-fn test_cache_version_is_4() {
+fn test_cache_version_is_5() {
     let project = TestProject::new(
         r#"
 verifications:
@@ -21,7 +21,7 @@ verifications:
     project.run(&["run"]);
 
     let lock = project.read_lock().expect("Lock file should exist");
-    assert_eq!(lock["version"], 4, "Cache version should be 4");
+    assert_eq!(lock["version"], 5, "Cache version should be 5");
 }
 
 #[test]
@@ -109,7 +109,7 @@ verifications:
 
     // Manually write an old version lock file
     let old_lock = r#"{
-        "version": 3,
+        "version": 4,
         "checks": {
             "test": {
                 "config_hash": "old_hash",
@@ -119,13 +119,94 @@ verifications:
     }"#;
     fs::write(project.path().join("verify.lock"), old_lock).unwrap();
 
-    // Run again - should treat as fresh start due to version mismatch
+    // Run again - the migrated entry has no hashes, so it re-runs
     let (success, _stdout, _) = project.run(&["run"]);
     assert!(success);
 
     // Should have re-run (not cached)
     let lock = project.read_lock().expect("Lock file should exist");
-    assert_eq!(lock["version"], 4, "Version should be updated to 4");
+    assert_eq!(lock["version"], 5, "Version should be updated to 5");
+}
+
+#[test]
+fn test_old_cache_version_is_migrated_with_backup() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.txt"
+  - name: other
+    command: echo "other"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+    project.create_file("file.txt", "content");
+
+    let old_lock = r#"{
+        "version": 3,
+        "checks": {
+            "other": {
+                "config_hash": "old_hash",
+                "content_hash": "old_content",
+                "duration_ms": 4200
+            }
+        }
+    }"#;
+    fs::write(project.path().join("verify.lock"), old_lock).unwrap();
+
+    // Migrated entries lose their hashes, so they show as unverified instead of vanishing
+    let (_, stdout, _) = project.run(&["status", "other"]);
+    assert!(stdout.contains("unverified"), "stdout: {}", stdout);
+
+    let (success, _, stderr) = project.run(&["run", "test"]);
+    assert!(success);
+    assert!(
+        stderr.contains("Migrated verify.lock from format 3 to 5"),
+        "stderr: {}",
+        stderr
+    );
+
+    let lock = project.read_lock().unwrap();
+    assert_eq!(lock["version"], 5);
+    assert_eq!(lock["checks"]["other"]["duration_ms"], 4200);
+    assert!(lock["checks"]["other"].get("content_hash").is_none());
+    assert!(lock["checks"]["test"]["content_hash"].is_string());
+
+    let backup = project.path().join(".verify/verify.lock.v3");
+    assert_eq!(fs::read_to_string(backup).unwrap(), old_lock);
+
+    // Only the first save after migrating announces it
+    let (_, _, stderr) = project.run(&["run", "other"]);
+    assert!(!stderr.contains("Migrated"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_newer_cache_version_is_refused() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: test
+    command: echo "test"
+"#,
+    );
+    let future_lock = r#"{"version": 99, "checks": {}}"#;
+    fs::write(project.path().join("verify.lock"), future_lock).unwrap();
+
+    let (success, _, stderr) = project.run(&["run"]);
+    assert!(!success);
+    assert!(
+        stderr.contains("written by a newer version of verify"),
+        "stderr: {}",
+        stderr
+    );
+    // Left as it was rather than overwritten
+    assert_eq!(
+        fs::read_to_string(project.path().join("verify.lock")).unwrap(),
+        future_lock
+    );
 }
 
 // ==================== Cache Atomicity Tests ====================
@@ -215,10 +296,16 @@ verifications:
     let (success, stdout, _) = project.run(&["status"]);
     assert!(success);
     assert!(
-        stdout.contains("config") || stdout.contains("unverified"),
-        "Should indicate config changed: {}",
+        stdout.contains("test - unverified (command changed)"),
+        "Should indicate the command changed: {}",
         stdout
     );
+
+    let (_, stdout, _) = project.run(&["--json", "status"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let check = &json["checks"][0];
+    assert_eq!(check["reason"], "config_changed");
+    assert_eq!(check["changed_config"], serde_json::json!(["command"]));
 }
 
 #[test]
@@ -338,6 +425,58 @@ verifications:
     );
 }
 
+#[test]
+fn test_depends_on_change_triggers_rerun() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: lint
+    command: echo "lint"
+    cache_paths:
+      - "*.rs"
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    project.create_file("file.txt", "content");
+    project.create_file("file.rs", "content");
+    project.run(&["run"]);
+
+    // Add a new dependency - test must be re-verified with it in place
+    fs::write(
+        project.path().join("verify.yaml"),
+        r#"
+verifications:
+  - name: lint
+    command: echo "lint"
+    cache_paths:
+      - "*.rs"
+  - name: test
+    command: echo "test"
+    depends_on: [lint]
+    cache_paths:
+      - "*.txt"
+"#,
+    )
+    .unwrap();
+
+    let (success, stdout, _) = project.run(&["status"]);
+    assert!(success);
+    assert!(
+        stdout.contains("lint - verified"),
+        "lint should be unaffected: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("test - unverified (depends_on changed)"),
+        "Should indicate which part of the config changed: {}",
+        stdout
+    );
+}
+
 // ==================== Orphaned Cache Cleanup ====================
 
 #[test]
@@ -650,3 +789,301 @@ verifications:
         stdout
     );
 }
+
+/// Move a file's mtime into the past so the hash cache will record it
+fn backdate(path: &std::path::Path, secs: u64) {
+    let file = fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(secs))
+        .unwrap();
+}
+
+#[test]
+fn test_hash_cache_records_settled_files() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    project.create_file("settled.txt", "content");
+    backdate(&project.path().join("settled.txt"), 60);
+    project.create_file("fresh.txt", "content");
+    project.run(&["run"]);
+
+    let hash_cache = fs::read_to_string(project.path().join(".verify/hashcache"))
+        .expect("Hash cache should exist");
+    assert!(hash_cache.contains("settled.txt"));
+    assert!(
+        !hash_cache.contains("fresh.txt"),
+        "Recently modified files should not be cached"
+    );
+}
+
+#[test]
+fn test_hash_cache_detects_same_size_edit() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    let file = project.path().join("file.txt");
+    project.create_file("file.txt", "aaaa");
+    backdate(&file, 60);
+    project.run(&["run"]);
+
+    // Same size, different content and mtime
+    project.create_file("file.txt", "bbbb");
+    backdate(&file, 30);
+
+    let (_, stdout, _) = project.run(&["status"]);
+    assert!(
+        stdout.contains("unverified") || stdout.contains("changed"),
+        "Should be stale when file modified: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_hash_cache_never_tracked() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "file.txt"
+      - ".verify/*"
+"#,
+    );
+
+    project.create_file("file.txt", "content");
+    backdate(&project.path().join("file.txt"), 60);
+    project.run(&["run"]);
+    assert!(project.path().join(".verify/hashcache").exists());
+
+    let (_, stdout, _) = project.run(&["status"]);
+    assert!(
+        !stdout.contains("unverified"),
+        "Hash cache should not invalidate checks: {}",
+        stdout
+    );
+}
+
+// ==================== Concurrent Run Tests ====================
+
+/// Start `verify run` for the given checks in the background
+fn spawn_run(project: &TestProject, names: &[&str]) -> std::process::Child {
+    std::process::Command::new(verify_binary())
+        .arg("run")
+        .args(names)
+        .current_dir(project.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+#[test]
+fn test_concurrent_runs_of_unrelated_checks() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: slow
+    command: sleep 2
+    cache_paths:
+      - "slow.txt"
+  - name: lint
+    command: echo "lint"
+    cache_paths:
+      - "lint.txt"
+"#,
+    );
+    project.create_file("slow.txt", "slow");
+    project.create_file("lint.txt", "lint");
+
+    let mut slow = spawn_run(&project, &["slow"]);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    let start = std::time::Instant::now();
+    let (success, _, _) = project.run(&["run", "lint"]);
+    assert!(success);
+    assert!(
+        start.elapsed() < std::time::Duration::from_millis(1500),
+        "Unrelated check should not wait for the running one"
+    );
+
+    assert!(slow.wait().unwrap().success());
+    let lock = project.read_lock().expect("Lock file should exist");
+    assert!(lock["checks"]["slow"]["content_hash"].is_string());
+    assert!(
+        lock["checks"]["lint"]["content_hash"].is_string(),
+        "Neither run should clobber the other's result"
+    );
+}
+
+#[test]
+fn test_concurrent_runs_of_same_check_run_it_once() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: build
+    command: echo run >> runs.log && sleep 1
+    cache_paths:
+      - "src.txt"
+"#,
+    );
+    project.create_file("src.txt", "source");
+
+    let mut first = spawn_run(&project, &["build"]);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    let (success, stdout, _) = project.run(&["run", "build"]);
+    assert!(success);
+    assert!(first.wait().unwrap().success());
+
+    let runs = project.read_file("runs.log").unwrap();
+    assert_eq!(
+        runs.lines().count(),
+        1,
+        "Second run should reuse the first run's result: {}",
+        stdout
+    );
+}
+
+// ==================== Cache Export/Import Tests ====================
+
+#[test]
+fn test_cache_export_import_roundtrip() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: build
+    command: echo "build"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    project.create_file("file.txt", "content");
+    project.run(&["run"]);
+
+    let bundle = project.path().join("bundle.tar.zst");
+    let bundle_arg = bundle.to_string_lossy().to_string();
+    let (success, _, stderr) = project.run(&["cache", "export", &bundle_arg]);
+    assert!(success, "Export should succeed: {}", stderr);
+    assert!(bundle.exists(), "Bundle file should be written");
+
+    project.run(&["clean"]);
+    let (success, _, _) = project.run(&["cache", "import", &bundle_arg]);
+    assert!(success, "Import should succeed");
+
+    let lock = project.read_lock().expect("Lock file should exist");
+    assert!(
+        lock["checks"]["build"]["content_hash"].is_string(),
+        "Imported check should have a content_hash"
+    );
+
+    let (_, stdout, _) = project.run(&["status"]);
+    assert!(
+        stdout.contains("build - verified"),
+        "Imported check should be verified: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_cache_import_skips_checks_with_changed_files() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: build
+    command: echo "build"
+    cache_paths:
+      - "build.txt"
+  - name: lint
+    command: echo "lint"
+    cache_paths:
+      - "lint.txt"
+"#,
+    );
+
+    project.create_file("build.txt", "build");
+    project.create_file("lint.txt", "lint");
+    project.run(&["run"]);
+
+    let bundle = project.path().join("bundle.tar.zst");
+    let bundle_arg = bundle.to_string_lossy().to_string();
+    project.run(&["cache", "export", &bundle_arg]);
+
+    project.run(&["clean"]);
+    project.create_file("lint.txt", "changed");
+
+    let (success, _, _) = project.run(&["cache", "import", &bundle_arg]);
+    assert!(success, "Import should succeed");
+
+    let lock = project.read_lock().expect("Lock file should exist");
+    assert!(
+        lock["checks"]["build"].is_object(),
+        "Unchanged check should be imported"
+    );
+    assert!(
+        lock["checks"]["lint"].is_null(),
+        "Check with changed files should not be imported"
+    );
+}
+
+#[test]
+fn test_cache_export_import_carries_history_and_stats() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: build
+    command: echo "build"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    project.create_file("file.txt", "content");
+    project.run(&["run"]);
+
+    let bundle = project.path().join("bundle.tar.zst");
+    let bundle_arg = bundle.to_string_lossy().to_string();
+    project.run(&["cache", "export", &bundle_arg]);
+
+    let state_dir = project.path().join(".verify");
+    fs::remove_dir_all(&state_dir).unwrap();
+    project.run(&["cache", "import", &bundle_arg]);
+    let (success, _, _) = project.run(&["cache", "import", &bundle_arg]);
+    assert!(success, "Importing twice should succeed");
+
+    for file in ["history.jsonl", "stats.jsonl"] {
+        let content = fs::read_to_string(state_dir.join(file))
+            .unwrap_or_else(|_| panic!("{} should be imported", file));
+        assert_eq!(content.lines().count(), 1, "{}: {}", file, content);
+    }
+}
+
+#[test]
+fn test_cache_import_missing_bundle_fails() {
+    let project = TestProject::new(
+        r#"
+verifications:
+  - name: build
+    command: echo "build"
+    cache_paths: []
+"#,
+    );
+
+    let exit_code = project.run_exit_code(&["cache", "import", "missing.tar.zst"]);
+    assert_eq!(exit_code, 2, "Missing bundle should be an error");
+}
@@ -93,6 +93,37 @@ fn test_per_file_stores_file_hashes() {
     );
 }
 
+#[test]
+fn test_per_file_gets_own_tmpdir() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: test
+    command: echo "$VERIFY_TMPDIR" >> dirs.log && touch "$VERIFY_TMPDIR/scratch"
+    cache_paths:
+      - "*.txt"
+    per_file: true
+"#,
+    );
+
+    project.create_file("a.txt", "a");
+    project.create_file("b.txt", "b");
+
+    let (success, _stdout, stderr) = project.run(&["run"]);
+    assert!(success, "{}", stderr);
+
+    let log = project.read_file("dirs.log").unwrap();
+    let dirs: Vec<&str> = log.lines().collect();
+    assert_eq!(dirs.len(), 2, "{}", log);
+    assert_ne!(dirs[0], dirs[1], "Each file should get a fresh directory");
+    for dir in dirs {
+        assert!(
+            !std::path::Path::new(dir).exists(),
+            "{} was left behind",
+            dir
+        );
+    }
+}
+
 // ==================== Partial Progress Tests ====================
 
 #[test]
@@ -561,6 +592,48 @@ fn test_per_file_json_output() {
     assert!(json["results"].is_array(), "JSON should have results array");
 }
 
+#[test]
+fn test_per_file_json_reports_each_file() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: test
+    command: grep -q ok $VERIFY_FILE
+    cache_paths:
+      - "*.txt"
+    per_file: true
+"#,
+    );
+
+    project.create_file("a.txt", "ok");
+    project.create_file("b.txt", "ok");
+    project.run(&["run"]);
+
+    // a.txt is unchanged, b.txt now fails and c.txt is new
+    project.create_file("b.txt", "bad");
+    project.create_file("c.txt", "ok");
+    let (success, stdout, _) = project.run(&["--json", "run"]);
+    assert!(!success);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let files = json["results"][0]["files"].as_array().unwrap();
+    let results: Vec<(&str, &str)> = files
+        .iter()
+        .map(|file| {
+            (
+                file["path"].as_str().unwrap(),
+                file["result"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        results,
+        vec![("a.txt", "skipped"), ("b.txt", "fail"), ("c.txt", "pass")]
+    );
+    assert!(files[0].get("duration_ms").is_none());
+    assert_eq!(files[1]["exit_code"], 1);
+    assert!(files[2]["duration_ms"].is_u64());
+}
+
 // ==================== All Fresh Scenario ====================
 
 #[test]
@@ -592,3 +665,76 @@ fn test_per_file_all_fresh_shows_cached() {
         stdout
     );
 }
+
+// ==================== File Ordering ====================
+
+#[test]
+fn test_per_file_order_failed_first() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: test
+    command: 'echo "$VERIFY_FILE" >> order.log && ! grep -q bad "$VERIFY_FILE"'
+    cache_paths:
+      - "*.txt"
+    per_file: true
+    per_file_order: failed-first
+"#,
+    );
+
+    project.create_file("a.txt", "a");
+    project.create_file("b.txt", "b");
+    project.create_file("z.txt", "bad");
+
+    let (success, _, _) = project.run(&["run"]);
+    assert!(!success, "z.txt should fail");
+    assert_eq!(
+        project.read_file("order.log").unwrap(),
+        "a.txt\nb.txt\nz.txt\n"
+    );
+
+    let lock = project.read_lock().expect("Lock file should exist");
+    assert_eq!(
+        lock["checks"]["test"]["failed_files"],
+        serde_json::json!(["z.txt"])
+    );
+
+    // Fix the failing file and touch another; the previous failure runs first
+    project.create_file("a.txt", "a2");
+    project.create_file("z.txt", "fixed");
+    fs::remove_file(project.path().join("order.log")).unwrap();
+
+    let (success, stdout, stderr) = project.run(&["run"]);
+    assert!(success, "stdout: {}\nstderr: {}", stdout, stderr);
+    assert_eq!(project.read_file("order.log").unwrap(), "z.txt\na.txt\n");
+
+    let lock = project.read_lock().expect("Lock file should exist");
+    assert!(
+        lock["checks"]["test"].get("failed_files").is_none(),
+        "failed_files should be cleared once all files pass"
+    );
+}
+
+#[test]
+fn test_per_file_order_size() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: test
+    command: 'echo "$VERIFY_FILE" >> order.log'
+    cache_paths:
+      - "*.txt"
+    per_file: true
+    per_file_order: size
+"#,
+    );
+
+    project.create_file("a.txt", "largest file");
+    project.create_file("b.txt", "x");
+    project.create_file("c.txt", "medium");
+
+    let (success, _, _) = project.run(&["run"]);
+    assert!(success);
+    assert_eq!(
+        project.read_file("order.log").unwrap(),
+        "b.txt\nc.txt\na.txt\n"
+    );
+}
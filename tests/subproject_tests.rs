@@ -4,6 +4,7 @@ mod common;
 
 use common::TestProject;
 use std::fs;
+use std::process::Command;
 
 // ==================== Basic Subproject Tests ====================
 
@@ -367,6 +368,71 @@ fn test_status_json_includes_subprojects() {
     );
 }
 
+#[test]
+fn test_status_json_only_unverified_flat() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: lint
+    command: echo "lint"
+    cache_paths:
+      - "*.md"
+  - name: backend
+    path: backend
+"#,
+    );
+
+    project.add_subproject(
+        "backend",
+        r#"verifications:
+  - name: build
+    command: echo "build"
+    cache_paths:
+      - "*.txt"
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.rs"
+"#,
+    );
+
+    project.create_file("README.md", "readme");
+    project.create_subproject_file("backend", "file.txt", "content");
+    project.create_subproject_file("backend", "lib.rs", "code");
+    project.run(&["run"]);
+    project.create_subproject_file("backend", "lib.rs", "changed");
+
+    let (success, stdout, _) = project.run(&["--json", "status", "--only-unverified", "--flat"]);
+    assert!(success);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("Should be valid JSON");
+    let names: Vec<&str> = json["checks"]
+        .as_array()
+        .expect("checks should be array")
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["backend/test"], "Unexpected checks: {}", stdout);
+
+    // The summary still counts every check
+    let summary = &json["summary"];
+    assert_eq!(summary["verified"], 2, "{}", stdout);
+    assert_eq!(summary["unverified"], 1);
+    assert_eq!(summary["untracked"], 0);
+    assert_eq!(summary["total"], 3);
+    assert_eq!(summary["all_verified"], false);
+
+    let (_, stdout, _) = project.run(&["--json", "status"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("Should be valid JSON");
+    let backend = &json["checks"][1];
+    assert_eq!(backend["summary"]["total"], 2, "{}", stdout);
+    assert_eq!(backend["summary"]["unverified"], 1);
+
+    // The filters only apply to JSON output
+    let (success, _, stderr) = project.run(&["status", "--flat"]);
+    assert!(!success);
+    assert!(stderr.contains("--json"), "Unexpected error: {}", stderr);
+}
+
 // ==================== Subproject Status Propagation Tests ====================
 
 #[test]
@@ -456,6 +522,40 @@ fn test_status_verification_unverified_when_subproject_unverified() {
     );
 }
 
+#[test]
+fn test_why_includes_unverified_subproject_dependency() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: backend
+    path: packages/backend
+  - name: integration_test
+    command: echo "integration"
+    depends_on: [backend]
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+
+    project.add_subproject(
+        "packages/backend",
+        r#"verifications:
+  - name: build
+    command: echo "build"
+    cache_paths:
+      - "*.rs"
+"#,
+    );
+
+    project.create_file("test.txt", "root content");
+    project.create_subproject_file("packages/backend", "lib.rs", "fn main() {}");
+
+    let (_, stdout, _) = project.run(&["--json", "why", "integration_test"]);
+    let why: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(why["blockers"][0]["name"], "backend");
+    assert_eq!(why["blockers"][0]["reason"], "subproject_unverified");
+    assert_eq!(why["command"], "verify run integration_test");
+}
+
 #[test]
 fn test_status_aggregate_verified_when_subprojects_verified() {
     // An aggregate check (no command) depending on subprojects should be "verified"
@@ -782,6 +882,49 @@ fn test_run_specific_check_with_subprojects() {
     );
 }
 
+#[test]
+fn test_run_root_from_nested_subproject() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: packages
+    path: packages
+  - name: e2e
+    command: echo "e2e" > e2e.out
+    depends_on: [packages]
+"#,
+    );
+    fs::create_dir(project.path().join(".git")).unwrap();
+    project.add_subproject(
+        "packages",
+        r#"verifications:
+  - name: app
+    path: app
+"#,
+    );
+    project.add_subproject(
+        "packages/app",
+        r#"verifications:
+  - name: build
+    command: echo "build" > build.out
+"#,
+    );
+
+    // Without --root only the subproject's own checks run
+    let (success, _, stderr) = project.run_in_subproject("packages/app", &["run"]);
+    assert!(success, "{}", stderr);
+    assert!(project.file_exists("packages/app/build.out"));
+    assert!(!project.file_exists("e2e.out"));
+
+    // With --root the outermost config runs, including the root check that depends on it
+    let (success, stdout, stderr) = project.run_in_subproject("packages/app", &["run", "--root"]);
+    assert!(success, "stdout: {}\nstderr: {}", stdout, stderr);
+    assert!(project.file_exists("e2e.out"));
+
+    // From the root itself, --root runs the local config
+    let (success, _, stderr) = project.run(&["run", "--root", "e2e"]);
+    assert!(success, "{}", stderr);
+}
+
 // ==================== Clean with Subprojects ====================
 
 #[test]
@@ -869,3 +1012,458 @@ fn test_force_run_affects_subprojects() {
         stdout
     );
 }
+
+// ==================== Cache Bundle Tests ====================
+
+#[test]
+fn test_cache_export_import_includes_subproject_locks() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: sub
+    path: ./sub
+"#,
+    );
+
+    project.add_subproject(
+        "sub",
+        r#"verifications:
+  - name: test
+    command: echo "test"
+    cache_paths:
+      - "*.txt"
+"#,
+    );
+    project.create_subproject_file("sub", "file.txt", "content");
+    project.run(&["run"]);
+
+    let bundle = project.path().join("bundle.tar.zst");
+    let bundle_arg = bundle.to_string_lossy().to_string();
+    let (success, _, _) = project.run(&["cache", "export", &bundle_arg]);
+    assert!(success, "Export should succeed");
+
+    fs::remove_file(project.path().join("sub/verify.lock")).unwrap();
+
+    let (success, _, _) = project.run(&["cache", "import", &bundle_arg]);
+    assert!(success, "Import should succeed");
+
+    let lock = project
+        .read_subproject_lock("sub")
+        .expect("Subproject lock should be restored");
+    assert!(
+        lock["checks"]["test"]["content_hash"].is_string(),
+        "Subproject check should be imported"
+    );
+}
+
+// ==================== Stage Tests ====================
+
+#[test]
+fn test_stage_includes_subproject_lock_files() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: root_check
+    command: echo "root"
+    cache_paths: []
+  - name: sub
+    path: packages/sub
+"#,
+    );
+
+    project.add_subproject(
+        "packages/sub",
+        r#"verifications:
+  - name: test
+    command: echo "test"
+    cache_paths: []
+"#,
+    );
+
+    for args in [
+        vec!["init"],
+        vec!["config", "user.email", "test@test.com"],
+        vec!["config", "user.name", "Test"],
+    ] {
+        Command::new("git")
+            .args(&args)
+            .current_dir(project.path())
+            .output()
+            .unwrap();
+    }
+
+    let (success, _, _) = project.run(&["run", "--stage"]);
+    assert!(success, "Run should succeed");
+
+    let output = Command::new("git")
+        .args(["diff", "--cached", "--name-only"])
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    let staged = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        staged.lines().any(|l| l == "verify.lock"),
+        "Root lock should be staged: {}",
+        staged
+    );
+    assert!(
+        staged.lines().any(|l| l == "packages/sub/verify.lock"),
+        "Subproject lock should be staged: {}",
+        staged
+    );
+}
+
+// ==================== Metadata Totals Tests ====================
+
+#[test]
+fn test_metadata_totals_aggregate_across_subprojects() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: unit
+    command: "echo 'Tests: 10 passed, Coverage: 80%'"
+    metadata:
+      tests: "Tests: (\\d+) passed"
+      coverage: "Coverage: (\\d+)%"
+  - name: backend
+    path: packages/backend
+metadata_totals:
+  tests: sum
+  coverage: avg
+"#,
+    );
+
+    project.add_subproject(
+        "packages/backend",
+        r#"verifications:
+  - name: api
+    command: "echo 'Tests: 32 passed, Coverage: 90%'"
+    metadata:
+      tests: "Tests: (\\d+) passed"
+      coverage: "Coverage: (\\d+)%"
+"#,
+    );
+
+    let (success, stdout, stderr) = project.run(&["--json", "run"]);
+    assert!(success, "Run should succeed. Stderr: {}", stderr);
+
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(parsed["metadata_totals"]["tests"], serde_json::json!(42));
+    assert_eq!(
+        parsed["metadata_totals"]["coverage"],
+        serde_json::json!(85.0)
+    );
+
+    let (_, stdout, _) = project.run(&["run"]);
+    assert!(stdout.contains("tests: 42"), "{}", stdout);
+}
+
+#[test]
+fn test_metadata_totals_omitted_when_not_configured() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: unit
+    command: "echo 'Tests: 10 passed'"
+    metadata:
+      tests: "Tests: (\\d+) passed"
+"#,
+    );
+
+    let (success, stdout, _) = project.run(&["--json", "run"]);
+    assert!(success);
+
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(parsed.get("metadata_totals").is_none());
+}
+
+// ==================== Workspace Tests ====================
+
+/// Sibling projects `api` and `web` next to a `release` directory holding the workspace file
+fn workspace_project(web_command: &str) -> TestProject {
+    let project = TestProject::new("verifications: []\n");
+    project.add_subproject(
+        "api",
+        r#"
+verifications:
+  - name: build
+    command: echo "api build"
+    cache_paths: []
+"#,
+    );
+    project.add_subproject(
+        "web",
+        &format!(
+            r#"
+verifications:
+  - name: test
+    command: {}
+    cache_paths: []
+"#,
+            web_command
+        ),
+    );
+    project.create_file(
+        "release/verify-workspace.yaml",
+        r#"
+projects:
+  - name: api
+    path: ../api
+  - name: web
+    path: ../web
+"#,
+    );
+    project
+}
+
+#[test]
+fn test_workspace_runs_all_projects() {
+    let project = workspace_project("echo web");
+
+    let (success, stdout, _) = project.run_in_subproject("release", &["run", "--workspace"]);
+    assert!(success, "Workspace run should succeed: {}", stdout);
+    assert!(stdout.contains("api"));
+    assert!(stdout.contains("web"));
+
+    assert!(project.read_subproject_lock("api").is_some());
+    assert!(project.read_subproject_lock("web").is_some());
+    assert!(
+        !project.file_exists("release/verify.lock"),
+        "Workspace root should not get a lock file"
+    );
+    assert!(
+        !project.file_exists("release/.verify"),
+        "Workspace root should not get run history or stats"
+    );
+}
+
+#[test]
+fn test_workspace_combined_exit_code_and_json() {
+    let project = workspace_project("exit 1");
+
+    let (success, stdout, _) =
+        project.run_in_subproject("release", &["--json", "run", "--workspace"]);
+    assert!(
+        !success,
+        "Failure in one project should fail the workspace run"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["summary"]["passed"], 1);
+    assert_eq!(json["summary"]["failed"], 1);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["name"], "api");
+    assert_eq!(results[1]["path"], "../web");
+}
+
+#[test]
+fn test_workspace_selects_projects_by_name() {
+    let project = workspace_project("exit 1");
+
+    let (success, _, _) =
+        project.run_in_subproject("release", &["run", "--workspace", "-m", "x", "api"]);
+    assert!(success, "Only api should run");
+    assert!(project.read_subproject_lock("api").is_some());
+    assert!(project.read_subproject_lock("web").is_none());
+
+    let exit_code = project.run_exit_code(&[
+        "run",
+        "--workspace",
+        "release/verify-workspace.yaml",
+        "mobile",
+    ]);
+    assert_eq!(exit_code, 2, "Unknown project should be an error");
+}
+
+#[test]
+fn test_clean_orphaned_removes_state_of_removed_subproject() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: web
+    path: web
+"#,
+    );
+    project.add_subproject(
+        "web",
+        r#"verifications:
+  - name: build
+    command: echo "building web"
+    cache_paths: []
+"#,
+    );
+
+    let (success, _, _) = project.run(&["run"]);
+    assert!(success);
+    assert!(project.read_subproject_lock("web").is_some());
+
+    fs::write(project.path().join("verify.yaml"), "verifications: []\n").unwrap();
+    let (_, _, stderr) = project.run(&["status"]);
+    assert!(
+        stderr.contains("web: subproject is no longer in the config"),
+        "Should warn about orphaned state: {}",
+        stderr
+    );
+
+    let (success, stdout, _) = project.run(&["clean", "--orphaned"]);
+    assert!(success);
+    assert!(
+        stdout.contains("web"),
+        "Should list removed state: {}",
+        stdout
+    );
+    assert!(project.read_subproject_lock("web").is_none());
+    assert!(project.file_exists("web/verify.yaml"));
+
+    let (_, _, stderr) = project.run(&["status"]);
+    assert!(!stderr.contains("no longer in the config"), "{}", stderr);
+}
+
+// ==================== Trailer Namespacing Tests ====================
+
+fn git(project: &TestProject, args: &[&str]) {
+    Command::new("git")
+        .args(args)
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+}
+
+#[test]
+fn test_trailer_names_subproject_checks_by_prefix() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: lint
+    command: echo "lint"
+    cache_paths: ["*.txt"]
+  - name: api
+    path: packages/api
+    prefix: api
+  - name: web
+    path: packages/web
+"#,
+    );
+    project.create_file("root.txt", "root");
+    for sub in ["packages/api", "packages/web"] {
+        project.add_subproject(
+            sub,
+            r#"verifications:
+  - name: build
+    command: echo "build"
+    cache_paths: ["*.txt"]
+"#,
+        );
+        project.create_subproject_file(sub, "src.txt", sub);
+    }
+
+    let (success, _, stderr) = project.run(&["run"]);
+    assert!(success, "{}", stderr);
+
+    // Both builds are signed, told apart by prefix (the path when not configured)
+    let (success, stdout, _) = project.run(&["hash"]);
+    assert!(success);
+    let hashes: Vec<(&str, &str)> = stdout
+        .trim()
+        .split(',')
+        .filter_map(|pair| pair.split_once(':'))
+        .collect();
+    let names: Vec<&str> = hashes.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["api/build", "lint", "packages/web/build"]);
+    let (success, _, stderr) = project.run(&["hash", "api/build"]);
+    assert!(success, "{}", stderr);
+
+    let trailer: Vec<String> = hashes
+        .iter()
+        .map(|(name, hash)| format!("{}:{}", name, &hash[..8]))
+        .collect();
+    let trailer = trailer.join(",");
+    let (success, stdout, _) = project.run(&["check", "--trailer", &trailer]);
+    assert!(success, "{}", stdout);
+    // A changed subproject file only unverifies that subproject's check
+    project.create_subproject_file("packages/web", "src.txt", "changed");
+    let (success, stdout, _) = project.run(&["--json", "check", "--trailer", &trailer]);
+    assert!(!success);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let web = json["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["name"] == "web")
+        .unwrap();
+    assert_eq!(web["prefix"], "packages/web");
+    assert_eq!(web["checks"][0]["status"], "unverified");
+    project.create_subproject_file("packages/web", "src.txt", "packages/web");
+
+    // Sync seeds subproject lock files from the namespaced trailer
+    git(&project, &["init"]);
+    git(&project, &["config", "user.email", "test@test.com"]);
+    git(&project, &["config", "user.name", "Test"]);
+    git(&project, &["add", "."]);
+    let message = format!("Add projects\n\nVerified: {}\n", trailer);
+    git(&project, &["commit", "-m", &message]);
+    fs::remove_file(project.path().join("packages/api/verify.lock")).unwrap();
+
+    let (_, stdout, _) = project.run(&["--json", "sync"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(
+        json["summary"]["synced"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("api/build")),
+        "{}",
+        stdout
+    );
+    let lock = project.read_subproject_lock("packages/api").unwrap();
+    assert!(lock["checks"]["build"]["content_hash"].is_string());
+
+    let (_, stdout, _) = project.run(&["--json", "status", "--flat"]);
+    assert!(stdout.contains("\"api/build\""), "{}", stdout);
+}
+
+#[test]
+fn test_subproject_prefix_collisions_are_rejected() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: api
+    path: packages/api
+    prefix: svc
+  - name: web
+    path: packages/web
+    prefix: svc
+"#,
+    );
+    project.add_subproject("packages/api", "verifications: []\n");
+    project.add_subproject("packages/web", "verifications: []\n");
+
+    let (success, _, stderr) = project.run(&["status"]);
+    assert!(!success);
+    assert!(
+        stderr.contains("Subprojects 'api' and 'web' both name their checks 'svc/...'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_subproject_case_mismatch_warning() {
+    let project = TestProject::new(
+        r#"verifications:
+  - name: web
+    path: packages/web
+"#,
+    );
+    project.add_subproject(
+        "packages/web",
+        r#"verifications:
+  - name: assets
+    command: echo "assets"
+    cache_paths: ["assets/**/*.png"]
+"#,
+    );
+    project.create_file("packages/web/Assets/icons/logo.PNG", "png");
+
+    let (_, _, stderr) = project.run(&["status"]);
+    assert!(
+        stderr.contains(
+            "packages/web/assets: 'assets/**/*.png' matches no files, but would match ignoring case"
+        ),
+        "Expected case mismatch warning for the subproject check: {}",
+        stderr
+    );
+}